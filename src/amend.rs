@@ -0,0 +1,357 @@
+//! Implements `cargo spdx amend` subcommand
+//!
+//! Published SBOMs sometimes need correcting, such as once legal has concluded
+//! a license. Rather than being edited in place, the SBOM is amended: a new
+//! document copies it, applies the corrections, and refers back to it with an
+//! AMENDS relationship, with each corrected element annotated with what changed
+//! and why.
+
+use crate::cli::Args;
+use crate::convert::{output_path, read_referenced_document};
+use crate::document::{
+    self, AnnotationType, Document, DocumentPipeline, ExternalDocumentReference, FileAnnotation,
+    PackageAnnotation, Relationship, RelationshipType,
+};
+use crate::format::Format;
+use crate::identity::Identity;
+use crate::output::OutputManager;
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+
+/// A correction to a field of a package or file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Change {
+    /// What to correct, an SPDX ID or `pkg:NAME[@VERSION]`.
+    element: String,
+    /// The field to correct, as named in JSON documents, e.g. `licenseConcluded`.
+    field: String,
+    /// The corrected value.
+    value: String,
+}
+
+/// Writes a corrected copy of an SBOM which amends the original
+///
+/// # Arguments
+/// * `base` - Path to the SBOM being amended
+/// * `sets` - The corrections, as pairs of the element and `FIELD=VALUE`
+/// * `reason` - Why the SBOM is being amended
+/// * `from` - The format of the SBOM, guessed from its file extension if not given
+/// * `untrusted` - Whether to parse the SBOM defensively
+/// * `args` - The output settings
+pub fn amend(
+    base: &Path,
+    sets: &[String],
+    reason: Option<&str>,
+    from: Option<Format>,
+    untrusted: bool,
+    args: &Args,
+) -> Result<()> {
+    if args.sarif().is_some() || args.bundle().is_some() {
+        return Err(anyhow!("--sarif and --bundle can't be used with amend"));
+    }
+
+    let changes = parse_changes(sets)?;
+    let (original, reference) = read_referenced_document(base, from, untrusted)?;
+
+    let path = match args.output() {
+        Some(path) => path.to_owned(),
+        None => output_path(base, ".amended", args.format(), args.compression()),
+    };
+    let output_manager = OutputManager::new(
+        &path,
+        args.force(),
+        args.format(),
+        args.json_style(),
+        args.compression(),
+        args.checksums(),
+    );
+    // There's no workspace, so no configuration file to take the creators from.
+    let identity = Identity::configured(None, args.no_person(), args.creators())?;
+    identity.report();
+    let fresh = document::builder(
+        args.host_url()?.as_ref(),
+        &output_manager.output_file_name(),
+        &identity.creation_info(args.explain_creators())?,
+    )?
+    .build()?;
+
+    let mut doc = amend_document(original, fresh, reference, &changes, reason)?;
+
+    let mut pipeline = DocumentPipeline::default();
+    if let Some(version) = args.requested_spdx_version() {
+        pipeline.add_pass("conform to version", move |doc| {
+            doc.spdx_version = version;
+            doc.conform_to_version();
+            Ok(())
+        });
+    }
+    let profile = args.profile();
+    pipeline
+        .add_pass("profile", move |doc| profile.apply(doc))
+        .add_pass("sort", |doc| {
+            doc.sort_elements();
+            Ok(())
+        });
+    pipeline.run(&mut doc)?;
+
+    output_manager.write_document(&doc)
+}
+
+/// Parse the `--set` pairs into changes.
+fn parse_changes(sets: &[String]) -> Result<Vec<Change>> {
+    sets.chunks(2)
+        .map(|pair| match pair {
+            [element, assignment] => {
+                let (field, value) = assignment.split_once('=').ok_or_else(|| {
+                    anyhow!(
+                        "expected FIELD=VALUE after {}, found '{}'",
+                        element,
+                        assignment
+                    )
+                })?;
+                Ok(Change {
+                    element: element.clone(),
+                    field: field.trim().to_string(),
+                    value: value.trim().to_string(),
+                })
+            }
+            _ => Err(anyhow!("--set takes an element and FIELD=VALUE")),
+        })
+        .collect()
+}
+
+/// Make the amending document out of the original and a freshly built one.
+///
+/// The amendment keeps the elements of the original, but takes its name,
+/// namespace, and creation info from `fresh`.
+fn amend_document(
+    original: Document,
+    fresh: Document,
+    reference: ExternalDocumentReference,
+    changes: &[Change],
+    reason: Option<&str>,
+) -> Result<Document> {
+    let original_name = original.document_name.to_string();
+
+    let mut doc = original;
+    doc.document_name = fresh.document_name;
+    doc.document_namespace = fresh.document_namespace;
+    doc.creation_info = fresh.creation_info;
+    doc.document_comment = Some(match reason {
+        Some(reason) => format!("Amends {}: {}", original_name, reason),
+        None => format!("Amends {}.", original_name),
+    });
+
+    let reference_id = doc.add_external_reference(reference);
+    doc.relationships
+        .get_or_insert_with(Vec::new)
+        .push(Relationship {
+            comment: reason.map(ToOwned::to_owned),
+            related_spdx_element: format!("{}:{}", reference_id, doc.spdx_identifier),
+            relationship_type: RelationshipType::Amends,
+            spdx_element_id: doc.spdx_identifier.to_string(),
+        });
+
+    let annotator = doc.annotator();
+    let date = doc.creation_info.created.to_string();
+    let comment = |change: &Change, old: Option<String>| {
+        let amended = match old {
+            Some(old) => format!("Amended {} from {} to {}", change.field, old, change.value),
+            None => format!("Amended {} to {}", change.field, change.value),
+        };
+        match reason {
+            Some(reason) => format!("{}: {}", amended, reason),
+            None => format!("{}.", amended),
+        }
+    };
+
+    for change in changes {
+        let mut found = false;
+        for package in doc.packages.iter_mut().flatten() {
+            if selects(
+                change,
+                &package.spdxid,
+                &package.name,
+                package.version_info.as_deref(),
+            ) {
+                let old = set_field(package, change)?;
+                package
+                    .annotations
+                    .get_or_insert_with(Vec::new)
+                    .push(PackageAnnotation {
+                        annotation_date: date.clone(),
+                        annotation_type: AnnotationType::Review,
+                        annotator: annotator.clone(),
+                        comment: comment(change, old),
+                    });
+                found = true;
+            }
+        }
+        for file in doc.files.iter_mut().flatten() {
+            if file.spdxid == change.element {
+                let old = set_field(file, change)?;
+                file.annotations
+                    .get_or_insert_with(Vec::new)
+                    .push(FileAnnotation {
+                        annotation_date: date.clone(),
+                        annotation_type: AnnotationType::Review,
+                        annotator: annotator.clone(),
+                        comment: comment(change, old),
+                    });
+                found = true;
+            }
+        }
+        if !found {
+            return Err(anyhow!(
+                "no package or file in the SBOM matches {}",
+                change.element
+            ));
+        }
+    }
+
+    Ok(doc)
+}
+
+// Check whether a change is to the element with the given SPDX ID, name, and version
+fn selects(change: &Change, spdxid: &str, name: &str, version: Option<&str>) -> bool {
+    match change.element.strip_prefix("pkg:") {
+        Some(selector) => match selector.split_once('@') {
+            Some((selector_name, selector_version)) => {
+                selector_name == name && Some(selector_version) == version
+            }
+            None => selector == name,
+        },
+        None => change.element == spdxid,
+    }
+}
+
+// Set a field of an element by its JSON name, returning its previous value
+fn set_field<T: Serialize + DeserializeOwned>(
+    element: &mut T,
+    change: &Change,
+) -> Result<Option<String>> {
+    let invalid = || format!("can't set {} of {}", change.field, change.element);
+
+    let mut value = serde_json::to_value(&*element)?;
+    let fields = value.as_object_mut().ok_or_else(|| anyhow!(invalid()))?;
+    let old = match fields.insert(change.field.clone(), Value::String(change.value.clone())) {
+        None => None,
+        Some(Value::String(old)) => Some(old),
+        Some(_) => return Err(anyhow!("only fields holding text can be set").context(invalid())),
+    };
+
+    // Fields the element doesn't have are dropped when it's read back.
+    let amended: T = serde_json::from_value(value).with_context(invalid)?;
+    let stored = serde_json::to_value(&amended)?;
+    if stored.get(&change.field).and_then(Value::as_str) != Some(change.value.as_str()) {
+        return Err(anyhow!("it has no {} field", change.field).context(invalid()));
+    }
+
+    *element = amended;
+    Ok(old)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{amend_document, parse_changes, Change};
+    use crate::document::{
+        CreationInfoBuilder, Creator, Document, DocumentBuilder, ExternalDocumentReference,
+        Package, RelationshipType,
+    };
+
+    fn document(name: &str, packages: Vec<Package>) -> Document {
+        DocumentBuilder::default()
+            .document_name(name)
+            .try_document_namespace(format!("https://example.com/{}", name).as_str())
+            .unwrap()
+            .creation_info(
+                CreationInfoBuilder::default()
+                    .creators(vec![Creator::tool("cargo-spdx 0.1.0")])
+                    .build()
+                    .unwrap(),
+            )
+            .packages(packages)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_changes() {
+        let sets = [
+            "pkg:serde".to_string(),
+            "licenseConcluded = MIT".to_string(),
+        ];
+        assert_eq!(
+            parse_changes(&sets).unwrap(),
+            [Change {
+                element: "pkg:serde".to_string(),
+                field: "licenseConcluded".to_string(),
+                value: "MIT".to_string(),
+            }]
+        );
+        assert!(parse_changes(&["pkg:serde".to_string(), "MIT".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_amend_document() {
+        let mut serde = Package::build_tool("serde");
+        serde.version_info = Some("1.0.0".to_string());
+        let original = document("foo.spdx.json", vec![serde]);
+        let fresh = document("foo.amended.spdx.json", Vec::new());
+        let change = |element: &str, field: &str| Change {
+            element: element.to_string(),
+            field: field.to_string(),
+            value: "MIT".to_string(),
+        };
+
+        let doc = amend_document(
+            original.clone(),
+            fresh.clone(),
+            ExternalDocumentReference::to(
+                &original,
+                "da39a3ee5e6b4b0d3255bfef95601890afd80709".to_string(),
+            ),
+            &[change("pkg:serde@1.0.0", "licenseConcluded")],
+            Some("Reviewed by legal"),
+        )
+        .unwrap();
+        assert_eq!(doc.document_name.to_string(), "foo.amended.spdx.json");
+        let reference = &doc.external_document_references.as_ref().unwrap()[0];
+        assert_eq!(reference.id_string.0, "DocumentRef-foo.spdx.json");
+        assert_eq!(
+            reference.document_uri.as_str(),
+            "https://example.com/foo.spdx.json"
+        );
+        let relationship = &doc.relationships.as_ref().unwrap()[0];
+        assert!(matches!(
+            relationship.relationship_type,
+            RelationshipType::Amends
+        ));
+        assert_eq!(
+            relationship.related_spdx_element,
+            "DocumentRef-foo.spdx.json:SPDXRef-DOCUMENT"
+        );
+        let package = &doc.packages.as_ref().unwrap()[0];
+        assert_eq!(package.license_concluded.as_deref(), Some("MIT"));
+        assert_eq!(
+            package.annotations.as_ref().unwrap()[0].comment,
+            "Amended licenseConcluded from NOASSERTION to MIT: Reviewed by legal"
+        );
+
+        let amend = |change| {
+            amend_document(
+                original.clone(),
+                fresh.clone(),
+                ExternalDocumentReference::to(&original, String::new()),
+                &[change],
+                None,
+            )
+        };
+        assert!(amend(change("pkg:tokio", "licenseConcluded")).is_err());
+        assert!(amend(change("pkg:serde", "notAField")).is_err());
+        assert!(amend(change("pkg:serde", "externalRefs")).is_err());
+    }
+}
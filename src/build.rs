@@ -1,19 +1,49 @@
 //! Implements `cargo spdx build` subcommand
 
+use crate::cargo::{
+    dependency_chains, dependency_edges, host_packages, target_packages, CargoFlags, MetadataExt,
+};
+#[cfg(feature = "network")]
+use crate::checksums;
+use crate::checksums::ArchiveChecksums;
+use crate::config::{
+    AnnotationsConfig, BuildToolsConfig, ExtensionsConfig, NoneConfig, PolicyConfig,
+};
+use crate::copyright;
+#[cfg(feature = "network")]
+use crate::document::Algorithm;
 use crate::document::{
-    get_creation_info, DocumentBuilder, File, FileType, Package, Relationship, RelationshipType,
+    self, CreationInfo, Document, DocumentPipeline, ExternalDocumentReference, File, FileType,
+    HasExtractedLicensingInfo, Package, PathPolicy, Profile, Relationship, RelationshipType,
+    SpdxIdentifier, SpdxVersion,
 };
+use crate::document::{AnnotationType, PackageAnnotation};
+use crate::embeds;
+use crate::format::json::JsonStyle;
 use crate::format::Format;
-use crate::output::OutputManager;
+use crate::license::DeclaredLicense;
+use crate::licenses::{self, write_licenses_dir};
+use crate::output::{self, ChecksumAlgorithm, Compression, OutputManager, OutputPlan};
+use crate::policy::{self, Violation};
+#[cfg(feature = "network")]
+use crate::registry::{self, Downloader, SparseIndex};
+use crate::suppliers::Suppliers;
+use crate::telemetry;
+use crate::toolchain;
 use anyhow::Result;
 use cargo_metadata::camino::{Utf8Path, Utf8PathBuf};
-use cargo_metadata::{Artifact, Metadata, MetadataCommand, PackageId};
+use cargo_metadata::{BuildScript, Message, Metadata, MetadataCommand, PackageId};
 use clap::Parser;
-use std::collections::HashMap;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::OsString;
 use std::fs;
-use std::io::{BufRead, BufReader};
-use std::process::{ChildStdout, Command, Stdio};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{ChildStderr, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
 // Used for capturing the `cargo build` arguments we need to intercept
 #[derive(Debug, Parser)]
@@ -21,8 +51,20 @@ use std::process::{ChildStdout, Command, Stdio};
 struct CargoBuild {
     #[clap(long)]
     target: Option<String>,
+    #[clap(short, long)]
+    release: bool,
+    #[clap(long)]
+    profile: Option<String>,
     #[clap(long)]
     message_format: Option<String>,
+    #[clap(long)]
+    offline: bool,
+    #[clap(long)]
+    locked: bool,
+    #[clap(long)]
+    frozen: bool,
+    #[clap(long)]
+    target_dir: Option<PathBuf>,
     // clap_cargo doesn't support -F or comma separated features
     // https://github.com/crate-ci/clap-cargo/pull/33 fixes first
     // TODO fix second with custom parser
@@ -30,27 +72,140 @@ struct CargoBuild {
     features: clap_cargo::Features,
 }
 
+/// The kinds of build output that get their own SBOM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryKind {
+    /// An executable
+    Executable,
+    /// A static library (`--crate-type staticlib`), usually linked into non-Rust code
+    StaticLib,
+    /// A dynamic library (`--crate-type cdylib`), usually loaded by non-Rust code
+    DynamicLib,
+}
+
+/// What to do with packages only built for the host, such as build-dependencies
+/// and proc-macros, which don't end up in the binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildDeps {
+    /// List them in the SBOM of the binary
+    Include,
+    /// Leave them out
+    Exclude,
+    /// List them in a separate build environment SBOM alongside the binary's
+    Separate,
+}
+
+/// Settings applied to every SBOM `cargo spdx build` produces
+#[derive(Debug)]
+pub struct SbomOptions<'a> {
+    /// SPDX host URL
+    pub host_url: &'a str,
+    /// Who and what created each SBOM
+    pub creation_info: &'a CreationInfo,
+    /// SPDX format
+    pub format: Format,
+    /// Layout of JSON output
+    pub json_style: JsonStyle,
+    /// How to compress each SBOM
+    pub compression: Compression,
+    /// Algorithms to write checksum files of each SBOM with
+    pub checksums: &'a [ChecksumAlgorithm],
+    /// SPDX specification version
+    pub spdx_version: SpdxVersion,
+    /// The subset of the specification to restrict each SBOM to
+    pub profile: Profile,
+    /// What to do with local paths outside the workspace
+    pub scrub_paths: PathPolicy,
+    /// Vendor extension fields to add to each SBOM, if any
+    pub extensions: Option<&'a ExtensionsConfig>,
+    /// Notes to annotate packages with
+    pub annotations: &'a AnnotationsConfig,
+    /// The fields given as `NONE` where nothing was found
+    pub none: &'a NoneConfig,
+    /// The suppliers of packages
+    pub suppliers: &'a Suppliers,
+    /// Whether to take the copyright of packages from their sources
+    pub scan_copyrights: bool,
+    /// Whether to add a CPE for each crate
+    pub cpes: bool,
+    /// Whether to fill in suppliers and originators from crates.io owners
+    pub crates_io_owners: bool,
+    /// What to do with packages only built for the host
+    pub build_deps: BuildDeps,
+    /// System tools to look for in the output of build scripts
+    pub build_tools: &'a BuildToolsConfig,
+    /// The license policy each SBOM is checked against
+    pub policy: &'a PolicyConfig,
+    /// Where to write the policy violations found in the SBOMs, if anywhere
+    pub sarif: Option<&'a Path>,
+    /// Patterns of the names of packages to leave out of each SBOM
+    pub exclude: &'a [String],
+}
+
 // Stores packages and binaries identified from `cargo build`
 #[derive(Debug, Default)]
 struct CargoBuildInfo {
     /// packages identified from cargo json messages
     packages: HashMap<PackageId, Package>,
     /// binaries identifed from cargo json messages
-    binaries: Vec<(Utf8PathBuf, PackageId)>,
+    binaries: Vec<(Utf8PathBuf, PackageId, BinaryKind)>,
 
     source_files: Vec<File>,
     relationships: Vec<Relationship>,
+    /// SPDX IDs of the source files collected so far, as a package producing
+    /// several artifacts lists the same files for each of them
+    source_file_ids: HashSet<String>,
+    /// system tools build scripts were seen running, keyed by name
+    build_tools: BTreeMap<String, Package>,
+    /// native libraries declared by the `links` key of packages, keyed by name
+    native_libraries: BTreeMap<String, Package>,
+    /// packages built as Rust dylibs which binaries link dynamically
+    dylibs: HashSet<PackageId>,
+    /// the Rust compiler which built everything, if it could be identified
+    toolchain: Option<Package>,
+    /// texts of the licenses packages declare which aren't on the SPDX License
+    /// List, keyed by their LicenseRef
+    extracted_licenses: BTreeMap<String, HasExtractedLicensingInfo>,
+    /// why the lockfile checksums of packages don't match the crates.io index,
+    /// keyed by the SPDX IDs of the packages
+    checksum_mismatches: BTreeMap<String, String>,
+
+    /// everything cargo printed during the build, if it was captured
+    build_log: Option<Vec<u8>>,
+    /// the settings the build ran with, as `key=value` lines
+    environment: String,
 }
 
 /// Runs a `cargo build`, outputting an SBOM for each binary produced
 ///
 /// # Arguments
 /// * `build_args` - Arguments that will be passed to `cargo build`
+/// * `options` - Settings for the produced SBOMs
+/// * `verify_downloads` - Check crates.io dependencies against their published archives
+/// * `verify_index` - Check the lockfile checksums of crates.io dependencies against the index
+/// * `fetch_missing` - Download and hash the archives whose checksums are found nowhere else
+/// * `capture_log` - Save the build log alongside each SBOM, and list it in the SBOM
+/// * `licenses_dir` - Directory to copy the license files of the packages built into
 ///
-pub fn build(build_args: &[OsString], host_url: &str, format: Format) -> Result<()> {
+pub fn build(
+    build_args: &[OsString],
+    options: &SbomOptions<'_>,
+    verify_downloads: bool,
+    verify_index: bool,
+    fetch_missing: bool,
+    capture_log: bool,
+    licenses_dir: Option<&Path>,
+) -> Result<()> {
     // This function runs `cargo build` with json messages enabled, in order to detect produced binaries
     // and identify crates used in build.
 
+    if verify_downloads && !cfg!(feature = "network") {
+        anyhow::bail!("--verify-downloads needs cargo-spdx to be built with the `network` feature");
+    }
+    if verify_index && !cfg!(feature = "network") {
+        anyhow::bail!("--verify-index needs cargo-spdx to be built with the `network` feature");
+    }
+
     let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
     let mut cargo_build_args: Vec<OsString> = vec!["build".to_string().into()];
     cargo_build_args.extend(build_args.iter().cloned());
@@ -66,13 +221,39 @@ pub fn build(build_args: &[OsString], host_url: &str, format: Format) -> Result<
     let CargoBuild {
         features,
         target,
+        release,
+        profile,
         message_format,
+        offline,
+        locked,
+        frozen,
+        target_dir,
     } = CargoBuild::try_parse_from(&cargo_build_args)?;
     features.forward_metadata(&mut metadata_cmd);
-    if let Some(target) = target {
-        metadata_cmd.other_options(vec!["--filter-platform".to_string(), target]);
+    let cargo_flags = CargoFlags {
+        offline,
+        locked,
+        frozen,
+    };
+    let mut other_options = cargo_flags.args();
+    if let Some(target) = &target {
+        other_options.extend(["--filter-platform".to_string(), target.clone()]);
     }
-    let metadata = metadata_cmd.exec()?;
+    metadata_cmd.other_options(other_options);
+    let metadata = telemetry::in_span("resolve metadata", &[], || Ok(metadata_cmd.exec()?))?;
+    // cargo metadata accounts for CARGO_TARGET_DIR and the cargo configuration, but
+    // can't be passed `--target-dir`, so that has to be applied here.
+    let target_dir = resolve_target_dir(target_dir.as_deref(), &metadata.target_directory)?;
+    let mut archives = ArchiveChecksums::new(
+        metadata.workspace_root.as_std_path(),
+        target_dir
+            .join("cargo-spdx")
+            .join("downloads")
+            .as_std_path(),
+        cargo_flags.is_offline(),
+        fetch_missing,
+    )?;
+    log::info!(target: "cargo_spdx", "building into {}", target_dir);
 
     // If the user specified a non-json message format for cargo, then exit as we won't
     // be able to specify --message-format=json to cargo
@@ -87,35 +268,192 @@ pub fn build(build_args: &[OsString], host_url: &str, format: Format) -> Result<
     }
 
     // Run `cargo build`
-    let mut child = Command::new(cargo)
-        .stderr(Stdio::inherit())
+    let mut child = Command::new(&cargo)
+        .stderr(if capture_log {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        })
         .stdout(Stdio::piped())
         .args(cargo_build_args)
         .spawn()?;
 
+    // stderr and the json messages are interleaved into the log as they arrive
+    let log = capture_log.then(|| Arc::new(Mutex::new(Vec::new())));
+    let stderr_capture = match (&log, child.stderr.take()) {
+        (Some(log), Some(stderr)) => Some(capture_stderr(stderr, Arc::clone(log))),
+        _ => None,
+    };
+
+    // Source files are hashed as cargo reports building them.
     let stdout = child.stdout.take().unwrap();
-    let cargo_build_info = process_json_messages(stdout, message_format.is_some(), &metadata)?;
+    let mut cargo_build_info = telemetry::in_span("cargo build", &[], || {
+        process_json_messages(
+            stdout,
+            message_format.is_some(),
+            &metadata,
+            options.build_tools,
+            log.as_deref(),
+        )
+    })?;
+    if let Some(stderr_capture) = stderr_capture {
+        stderr_capture
+            .join()
+            .map_err(|_| anyhow::anyhow!("failed to capture the build log"))??;
+    }
+    if let Some(log) = log {
+        cargo_build_info.build_log = Some(std::mem::take(&mut *log.lock().unwrap()));
+    }
+    let toolchain = match toolchain::detect(metadata.workspace_root.as_std_path()) {
+        Ok(toolchain) => Some(toolchain),
+        Err(err) => {
+            log::warn!(target: "cargo_spdx", "not recording the Rust toolchain: {:#}", err);
+            None
+        }
+    };
+    let profile = profile.unwrap_or_else(|| if release { "release" } else { "dev" }.to_string());
+    // Without `--target`, cargo builds for the configured target, or else the host
+    let target = target
+        .or_else(|| std::env::var("CARGO_BUILD_TARGET").ok())
+        .or_else(|| toolchain.as_ref().map(|toolchain| toolchain.host.clone()));
+    cargo_build_info.environment = build_environment(
+        &profile,
+        target.as_deref(),
+        &rustflags(),
+        cargo_version(&cargo).as_deref(),
+    );
+    cargo_build_info.toolchain = toolchain.map(|toolchain| toolchain.package());
 
     // Verify cargo build succeeds. If it fails, exit with the same exit code
     let ecode = child.wait()?;
     if !ecode.success() {
         log::error!(target: "cargo_spdx", "cargo build failed");
+        telemetry::export(cargo_flags.is_offline());
         std::process::exit(ecode.code().unwrap_or(1));
     }
 
-    for (binary, package_id) in &cargo_build_info.binaries {
-        produce_sbom(binary, &cargo_build_info, package_id, host_url, format)?;
+    telemetry::in_span("package checksums", &[], || {
+        for (id, package) in &mut cargo_build_info.packages {
+            if let Some(checksum) = archives.checksum(&metadata[id])? {
+                package.set_archive(&checksum);
+            }
+        }
+        Ok(())
+    })?;
+    #[cfg(feature = "network")]
+    if verify_downloads {
+        telemetry::in_span("verify downloads", &[], || {
+            verify_registry_packages(&mut cargo_build_info, &metadata, &target_dir)
+        })?;
+    }
+    if options.scan_copyrights {
+        telemetry::in_span("scan copyrights", &[], || {
+            for (id, package) in &mut cargo_build_info.packages {
+                package.copyright_text = Some(copyright::scan_package(&metadata[id])?);
+            }
+            Ok(())
+        })?;
+    }
+    #[cfg(feature = "network")]
+    let mismatches = if verify_index {
+        telemetry::in_span("verify index checksums", &[], || {
+            verify_index_checksums(&mut cargo_build_info, &metadata)
+        })?
+    } else {
+        Vec::new()
+    };
+
+    // Fail before writing anything if outputs would replace each other
+    let index_path = sbom_index_path(&target_dir);
+    plan_outputs(
+        &cargo_build_info,
+        &metadata,
+        options,
+        licenses_dir,
+        &index_path,
+    )
+    .check()?;
+
+    if let Some(dir) = licenses_dir {
+        write_licenses_dir(
+            dir,
+            cargo_build_info.packages.keys().map(|id| &metadata[id]),
+        )?;
+    }
+
+    let mut violations = Vec::new();
+    let mut index = Vec::new();
+    for (binary, package_id, kind) in &cargo_build_info.binaries {
+        violations.extend(telemetry::in_span(
+            "produce sbom",
+            &[("spdx.binary", binary.as_str())],
+            || {
+                produce_sbom(
+                    binary,
+                    *kind,
+                    &cargo_build_info,
+                    package_id,
+                    &metadata,
+                    options,
+                    &mut index,
+                )
+            },
+        )?);
+    }
+
+    // List the SBOMs so release tooling can find them
+    index.sort_by(|a, b| a.sbom.cmp(&b.sbom));
+    fs::create_dir_all(index_path.parent().unwrap())?;
+    fs::write(&index_path, serde_json::to_string_pretty(&index)?)?;
+
+    if let Some(sarif_path) = options.sarif {
+        policy::write_sarif(
+            sarif_path,
+            &violations,
+            metadata.workspace_root.as_std_path(),
+        )?;
+    }
+
+    // Repeat the mismatches last, so they aren't lost among the build output
+    #[cfg(feature = "network")]
+    if !mismatches.is_empty() {
+        log::error!(
+            target: "cargo_spdx",
+            "{} package(s) don't match the checksums in the crates.io index, and may have been tampered with: {}",
+            mismatches.len(),
+            mismatches.join(", ")
+        );
     }
     Ok(())
 }
 
+// Pass cargo's stderr through, keeping a copy in the build log
+fn capture_stderr(
+    stderr: ChildStderr,
+    log: Arc<Mutex<Vec<u8>>>,
+) -> thread::JoinHandle<io::Result<()>> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stderr);
+        let mut line = Vec::new();
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            io::stderr().write_all(&line)?;
+            log.lock().unwrap().extend_from_slice(&line);
+            line.clear();
+        }
+        Ok(())
+    })
+}
+
 // Identify binaries and packages from cargo's json messages
 fn process_json_messages(
     stdout: ChildStdout,
     print_messages: bool,
     metadata: &Metadata,
+    build_tools: &BuildToolsConfig,
+    log: Option<&Mutex<Vec<u8>>>,
 ) -> Result<CargoBuildInfo, anyhow::Error> {
     let mut collector = CargoBuildInfo::default();
+    let rustflags = rustflags();
 
     let reader = BufReader::new(stdout);
     reader
@@ -127,26 +465,66 @@ fn process_json_messages(
                 if print_messages {
                     println!("{}", line);
                 }
+                if let Some(log) = log {
+                    let mut log = log.lock().unwrap();
+                    log.extend_from_slice(line.as_bytes());
+                    log.push(b'\n');
+                }
 
                 Ok(serde_json::from_str(&line)?)
             })
             .ok()
         })
-        .try_for_each::<_, Result<()>>(|artifact: Artifact| {
+        .try_for_each::<_, Result<()>>(|message: Message| {
+            let artifact = match message {
+                Message::CompilerArtifact(artifact) => artifact,
+                Message::BuildScriptExecuted(script) => {
+                    let output = build_script_output(&script);
+                    collect_build_tools(
+                        &script,
+                        output.as_deref(),
+                        metadata,
+                        build_tools,
+                        &mut collector,
+                    );
+                    collect_native_library(&script, output.as_deref(), metadata, &mut collector);
+                    return Ok(());
+                }
+                _ => return Ok(()),
+            };
+
             // Identify dependent packages
             let package = &metadata[&artifact.package_id];
             if !collector.packages.contains_key(&artifact.package_id) {
+                let mut spdx_package: Package = package.into();
+                if metadata.is_ambiguous(package) {
+                    spdx_package.disambiguate(package);
+                }
+                // The features it was actually compiled with, which may differ
+                // from those resolved for the whole workspace.
+                spdx_package.note_features(package, &artifact.features);
+                let license = DeclaredLicense::of(package)?;
+                spdx_package.license_declared = Some(license.expression);
+                for info in license.extracted {
+                    collector
+                        .extracted_licenses
+                        .insert(info.license_id.clone(), info);
+                }
                 collector
                     .packages
-                    .insert(artifact.package_id.clone(), package.into());
+                    .insert(artifact.package_id.clone(), spdx_package);
+            }
+
+            if links_dynamically(&artifact.target.crate_types, &rustflags) {
+                collector.dylibs.insert(artifact.package_id.clone());
             }
 
             // If this message has an rmeta file, then collect the corresponding source files
-            if let Some(rmeta) = artifact
+            let rmeta = artifact
                 .filenames
                 .iter()
-                .find(|f| f.extension() == Some("rmeta"))
-            {
+                .find(|f| f.extension() == Some("rmeta"));
+            if let Some(rmeta) = rmeta {
                 let dep_info = rmeta_to_dep_info(rmeta);
                 collect_source_files(
                     &dep_info,
@@ -155,6 +533,7 @@ fn process_json_messages(
                         // parent is directory containing Cargo.toml
                         .parent()
                         .unwrap(),
+                    &metadata.workspace_root,
                     &artifact.package_id,
                     &mut collector,
                     // Look for the dep_info entry itself as this lists source files
@@ -162,26 +541,105 @@ fn process_json_messages(
                 )?;
             }
 
+            // Identify static libraries
+            if artifact.target.crate_types.iter().any(|t| t == "staticlib") {
+                if let Some(staticlib) = artifact.filenames.iter().find(|f| is_staticlib(f)) {
+                    collector.binaries.push((
+                        staticlib.clone(),
+                        artifact.package_id.clone(),
+                        BinaryKind::StaticLib,
+                    ));
+
+                    // The source files were already collected if the crate also produced an rlib
+                    if rmeta.is_none() {
+                        if let Some((dep_info, entry)) = artifact_dep_info(staticlib)? {
+                            collect_source_files(
+                                &dep_info,
+                                package
+                                    .manifest_path
+                                    // parent is directory containing Cargo.toml
+                                    .parent()
+                                    .unwrap(),
+                                &metadata.workspace_root,
+                                &artifact.package_id,
+                                &mut collector,
+                                &entry,
+                            )?;
+                        } else {
+                            log::warn!(
+                                target: "cargo_spdx",
+                                "no dep-info file found for {}, its source files won't be listed",
+                                staticlib
+                            );
+                        }
+                    }
+                }
+            }
+
+            // Identify dynamic libraries
+            if artifact.target.crate_types.iter().any(|t| t == "cdylib") {
+                if let Some(cdylib) = artifact.filenames.iter().find(|f| is_dynamic_lib(f)) {
+                    collector.binaries.push((
+                        cdylib.clone(),
+                        artifact.package_id.clone(),
+                        BinaryKind::DynamicLib,
+                    ));
+
+                    if rmeta.is_none() {
+                        if let Some((dep_info, entry)) = artifact_dep_info(cdylib)? {
+                            collect_source_files(
+                                &dep_info,
+                                package
+                                    .manifest_path
+                                    // parent is directory containing Cargo.toml
+                                    .parent()
+                                    .unwrap(),
+                                &metadata.workspace_root,
+                                &artifact.package_id,
+                                &mut collector,
+                                &entry,
+                            )?;
+                        } else {
+                            log::warn!(
+                                target: "cargo_spdx",
+                                "no dep-info file found for {}, its source files won't be listed",
+                                cdylib
+                            );
+                        }
+                    }
+                }
+            }
+
             // Identify executables
-            // TODO also identify compiled libraries e.g dll/.so/.a
             if let Some(executable) = artifact.executable {
-                collector
-                    .binaries
-                    .push((executable.clone(), artifact.package_id.clone()));
+                collector.binaries.push((
+                    executable.clone(),
+                    artifact.package_id.clone(),
+                    BinaryKind::Executable,
+                ));
 
-                // Binaries have their own colocated dep-info file containing source files
-                let dep_info = Utf8PathBuf::from(format!("{}.d", executable));
-                collect_source_files(
-                    &dep_info,
-                    package
-                        .manifest_path
-                        // parent is directory containing Cargo.toml
-                        .parent()
-                        .unwrap(),
-                    &artifact.package_id,
-                    &mut collector,
-                    executable.as_str(),
-                )?;
+                // Binaries usually have their own colocated dep-info file containing
+                // source files, but it's only written once the build finishes
+                if let Some((dep_info, entry)) = artifact_dep_info(&executable)? {
+                    collect_source_files(
+                        &dep_info,
+                        package
+                            .manifest_path
+                            // parent is directory containing Cargo.toml
+                            .parent()
+                            .unwrap(),
+                        &metadata.workspace_root,
+                        &artifact.package_id,
+                        &mut collector,
+                        &entry,
+                    )?;
+                } else {
+                    log::warn!(
+                        target: "cargo_spdx",
+                        "no dep-info file found for {}, its source files won't be listed",
+                        executable
+                    );
+                }
             }
 
             Ok(())
@@ -190,36 +648,388 @@ fn process_json_messages(
     Ok(collector)
 }
 
+// Read what a build script printed, which cargo saves alongside its OUT_DIR
+fn build_script_output(script: &BuildScript) -> Option<String> {
+    let output_path = script.out_dir.parent()?.join("output");
+    match fs::read_to_string(&output_path) {
+        Ok(output) => Some(output),
+        Err(err) => {
+            log::warn!(
+                target: "cargo_spdx",
+                "can't read build script output {}, build tools and native library versions won't be found: {}",
+                output_path,
+                err
+            );
+            None
+        }
+    }
+}
+
+// Record the system tools a build script ran as prerequisites of its package
+fn collect_build_tools(
+    script: &BuildScript,
+    output: Option<&str>,
+    metadata: &Metadata,
+    build_tools: &BuildToolsConfig,
+    collector: &mut CargoBuildInfo,
+) {
+    let output = match output {
+        Some(output) => output,
+        None => return,
+    };
+
+    let package_spdxid = collector
+        .packages
+        .entry(script.package_id.clone())
+        .or_insert_with(|| (&metadata[&script.package_id]).into())
+        .spdxid
+        .clone();
+
+    for name in build_tools.detect(output) {
+        log::info!(target: "cargo_spdx", "{} runs {}", package_spdxid, name);
+        let tool_spdxid = collector
+            .build_tools
+            .entry(name.clone())
+            .or_insert_with(|| Package::build_tool(&name))
+            .spdxid
+            .clone();
+
+        for relationship_type in [
+            RelationshipType::PrerequisiteFor,
+            RelationshipType::BuildToolOf,
+        ] {
+            let relationship = Relationship {
+                comment: None,
+                related_spdx_element: package_spdxid.clone(),
+                relationship_type,
+                spdx_element_id: tool_spdxid.clone(),
+            };
+            // Build scripts may be reported more than once, e.g. for each target
+            if !collector.relationships.contains(&relationship) {
+                collector.relationships.push(relationship);
+            }
+        }
+    }
+}
+
+// Record the native library a package declares with its `links` key as linked by it
+fn collect_native_library(
+    script: &BuildScript,
+    output: Option<&str>,
+    metadata: &Metadata,
+    collector: &mut CargoBuildInfo,
+) {
+    let package = &metadata[&script.package_id];
+    let links = match &package.links {
+        Some(links) => links,
+        None => return,
+    };
+
+    let package_spdxid = collector
+        .packages
+        .entry(script.package_id.clone())
+        .or_insert_with(|| package.into())
+        .spdxid
+        .clone();
+    let library_spdxid = collector
+        .native_libraries
+        .entry(links.clone())
+        .or_insert_with(|| {
+            Package::native_library(links, package, output.and_then(native_library_version))
+        })
+        .spdxid
+        .clone();
+
+    // Libraries are linked dynamically unless the script asks for otherwise
+    let is_static = script.linked_libs.iter().any(|lib| {
+        matches!(lib.as_str().split_once('='), Some((kind, name))
+            if kind.starts_with("static") && name.split(':').next() == Some(links.as_str()))
+    });
+    let relationship = Relationship {
+        comment: None,
+        related_spdx_element: library_spdxid,
+        relationship_type: if is_static {
+            RelationshipType::StaticLink
+        } else {
+            RelationshipType::DynamicLink
+        },
+        spdx_element_id: package_spdxid,
+    };
+    // Build scripts may be reported more than once, e.g. for each target
+    if !collector.relationships.contains(&relationship) {
+        collector.relationships.push(relationship);
+    }
+}
+
+// Find the version a build script reports for the native library it links, e.g.
+// `cargo:version=1.2.13`, which dependents see as `DEP_Z_VERSION`
+fn native_library_version(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        line.strip_prefix("cargo::metadata=")
+            .or_else(|| line.strip_prefix("cargo:"))?
+            .strip_prefix("version=")
+            .map(|version| version.trim().to_string())
+    })
+}
+
+/// Check crates.io packages against the archives published on crates.io
+///
+/// Each archive is verified against the lockfile checksum before being unpacked
+/// and compared file-by-file with the sources used in the build, so local
+/// modifications to registry sources are called out in the SBOM.
+#[cfg(feature = "network")]
+fn verify_registry_packages(
+    collector: &mut CargoBuildInfo,
+    metadata: &Metadata,
+    target_dir: &Utf8Path,
+) -> Result<()> {
+    let checksums =
+        checksums::lockfile_checksums(metadata.workspace_root.join("Cargo.lock").as_std_path())?;
+    let work_dir = target_dir.join("cargo-spdx");
+    let mut downloader = Downloader::new(work_dir.join("downloads").as_std_path())?;
+
+    for (package_id, package) in &mut collector.packages {
+        let cargo_package = &metadata[package_id];
+        let source = match &cargo_package.source {
+            Some(source) if source.is_crates_io() => source,
+            _ => continue,
+        };
+
+        let name = cargo_package.name.as_str();
+        let version = cargo_package.version.to_string();
+        let key = (name.to_string(), version.clone(), source.repr.clone());
+        let checksum = match checksums.get(&key) {
+            Some(checksum) => checksum,
+            None => {
+                log::warn!(
+                    target: "cargo_spdx",
+                    "no lockfile checksum for {} {}, skipping verification",
+                    name,
+                    version
+                );
+                continue;
+            }
+        };
+
+        let archive = downloader.fetch(name, &version, checksum)?;
+        package.set_archive(checksum);
+
+        let pristine =
+            registry::unpack(&archive, name, &version, work_dir.join("src").as_std_path())?;
+        let package_files = collector
+            .relationships
+            .iter()
+            .filter(|r| {
+                matches!(r.relationship_type, RelationshipType::Contains)
+                    && r.spdx_element_id == package.spdxid
+            })
+            .map(|r| r.related_spdx_element.as_str())
+            .collect::<Vec<_>>();
+
+        for file in collector
+            .source_files
+            .iter_mut()
+            .filter(|f| package_files.contains(&f.spdxid.as_str()))
+        {
+            let original = pristine.join(&file.file_name);
+            let matches = original.is_file()
+                && file
+                    .checksums
+                    .iter()
+                    .flatten()
+                    .filter(|c| matches!(c.algorithm, Algorithm::Sha256))
+                    .any(|c| {
+                        checksums::sha256_file(&original).ok().as_ref() == Some(&c.checksum_value)
+                    });
+            if !matches {
+                log::warn!(
+                    target: "cargo_spdx",
+                    "{} differs from the published {} {}",
+                    file.file_name,
+                    name,
+                    version
+                );
+                file.comment = Some(format!(
+                    "Differs from the file published in {}-{}.crate.",
+                    name, version
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the lockfile checksums of crates.io packages against the crates.io index
+///
+/// A lockfile checksum which differs from the one crates.io publishes means the
+/// lockfile, or the registry it was resolved against, has been tampered with.
+/// Each mismatch is noted for annotating the package in each SBOM listing it,
+/// and the mismatched packages are returned, e.g. `foo 1.0.0`.
+#[cfg(feature = "network")]
+fn verify_index_checksums(
+    collector: &mut CargoBuildInfo,
+    metadata: &Metadata,
+) -> Result<Vec<String>> {
+    let checksums =
+        checksums::lockfile_checksums(metadata.workspace_root.join("Cargo.lock").as_std_path())?;
+    let mut index = SparseIndex::default();
+    let mut mismatches = Vec::new();
+
+    for (package_id, package) in &collector.packages {
+        let cargo_package = &metadata[package_id];
+        let source = match &cargo_package.source {
+            Some(source) if source.is_crates_io() => source,
+            _ => continue,
+        };
+
+        let name = cargo_package.name.as_str();
+        let version = cargo_package.version.to_string();
+        let key = (name.to_string(), version.clone(), source.repr.clone());
+        let locked = match checksums.get(&key) {
+            Some(checksum) => checksum,
+            None => {
+                log::warn!(
+                    target: "cargo_spdx",
+                    "no lockfile checksum for {} {}, skipping verification",
+                    name,
+                    version
+                );
+                continue;
+            }
+        };
+
+        let comment = match index.checksum(name, &version)? {
+            Some(published) if published == locked => continue,
+            Some(published) => format!(
+                "The lockfile checksum of {} {} is {}, but the crates.io index has {}.",
+                name, version, locked, published
+            ),
+            None => format!(
+                "{} {} is locked to crates.io, but isn't in the crates.io index.",
+                name, version
+            ),
+        };
+        log::error!(target: "cargo_spdx", "{}", comment);
+        collector
+            .checksum_mismatches
+            .insert(package.spdxid.clone(), comment);
+        mismatches.push(format!("{} {}", name, version));
+    }
+
+    mismatches.sort();
+    Ok(mismatches)
+}
+
 /// Create an SBOM for the binary
 ///
 /// # Arguments
 /// * `binary` - Path to the binary
+/// * `kind` - The kind of binary
 /// * `cargo_build_info` - CargoBuildInfo
 /// * `package_id` - Cargo Package ID of the package that generates the binary
-/// * `host_url` - SPDX host URL
-/// * `format` - SPDX format
+/// * `metadata` - Metadata of the workspace, whose root local paths are made relative to
+/// * `options` - Settings for the SBOM
+/// * `index` - The index of SBOMs written, which the SBOM is added to
+///
+/// Returns the policy violations found in the SBOM, if asked to look for them.
 fn produce_sbom(
     binary: &Utf8Path,
+    kind: BinaryKind,
     cargo_build_info: &CargoBuildInfo,
     package_id: &PackageId,
-    host_url: &str,
-    format: Format,
-) -> Result<()> {
+    metadata: &Metadata,
+    options: &SbomOptions<'_>,
+    index: &mut Vec<SbomIndexEntry>,
+) -> Result<Vec<Violation>> {
     let mut relationships = cargo_build_info.relationships.clone();
     let mut files = cargo_build_info.source_files.clone();
-    let packages = cargo_build_info.packages.clone();
+    let mut packages = cargo_build_info.packages.clone();
+
+    // Explain why each dependency is in the binary, e.g. `via foo -> tokio -> mio`
+    let chains = dependency_chains(metadata, package_id);
+    for (id, package) in &mut packages {
+        if let Some(chain) = chains.get(id) {
+            let chain = std::iter::once(package_id)
+                .chain(chain.iter().copied())
+                .map(|id| metadata[id].name.as_str())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            let introduced = format!("Introduced via {}.", chain);
+            package.comment = Some(match package.comment.take() {
+                Some(comment) => format!("{} {}", comment, introduced),
+                None => introduced,
+            });
+        }
+    }
+
+    // Keep packages only built for the host out of the binary's SBOM, if asked to
+    let mut build_env = match options.build_deps {
+        BuildDeps::Include => None,
+        BuildDeps::Exclude | BuildDeps::Separate => Some(take_host_packages(
+            &mut packages,
+            &mut files,
+            &mut relationships,
+            metadata,
+            package_id,
+        )),
+    };
 
     // Create file information for the binary
-    let file = File::try_from_file(
+    let mut file = File::try_from_file(
         binary,
         binary.parent().unwrap(),
         FileType::Binary,
         None,
         None,
     )?;
+    match kind {
+        BinaryKind::Executable => {}
+        BinaryKind::StaticLib => {
+            file.comment = Some(
+                "Static library. Anything linking it statically also contains the packages it statically links."
+                    .to_string(),
+            );
+        }
+        BinaryKind::DynamicLib => {
+            file.comment = Some("Dynamic library.".to_string());
+        }
+    }
     let binary_spdxid = file.spdxid.clone();
     files.push(file);
 
+    // Map the parts of the binary the embed step put assets into
+    let snippets = embeds::binary_snippets(binary, &binary_spdxid, &mut files, &mut relationships)?;
+
+    // Record the build that produced the binary
+    if let Some(build_log) = &cargo_build_info.build_log {
+        let log_path = build_log_path(binary);
+        fs::write(&log_path, build_log)?;
+        let mut log_file = File::try_from_file(
+            &log_path,
+            binary.parent().unwrap(),
+            FileType::Text,
+            None,
+            None,
+        )?;
+        log_file.comment = Some(format!(
+            "Log of the cargo build which produced {}.",
+            binary.file_name().unwrap_or_default()
+        ));
+        relationships.push(Relationship {
+            comment: None,
+            related_spdx_element: cargo_build_info
+                .packages
+                .get(package_id)
+                .unwrap()
+                .spdxid
+                .clone(),
+            relationship_type: RelationshipType::GeneratedFrom,
+            spdx_element_id: log_file.spdxid.clone(),
+        });
+        files.push(log_file);
+    }
+
     // Indicate the crate the binary was generated from
     relationships.push(Relationship {
         comment: None,
@@ -233,45 +1043,646 @@ fn produce_sbom(
         spdx_element_id: binary_spdxid.clone(),
     });
 
-    // Add all crates as dependencies of the binary
+    // Record the compiler which built the binary
+    if let Some(rustc) = &cargo_build_info.toolchain {
+        relationships.push(Relationship {
+            comment: None,
+            related_spdx_element: binary_spdxid.clone(),
+            relationship_type: RelationshipType::BuildToolOf,
+            spdx_element_id: rustc.spdxid.clone(),
+        });
+    }
+
+    // The document is the SBOM of the binary
+    relationships.push(Relationship {
+        comment: None,
+        related_spdx_element: binary_spdxid.clone(),
+        relationship_type: RelationshipType::Describes,
+        spdx_element_id: SpdxIdentifier.to_string(),
+    });
+
+    // Relate each crate to its dependencies as cargo resolved them
     // (May include unused dependencies e.g as part of a workspace build that produces
     // multiple binaries. Not obvious how to refine this outside of cargo
     // without the user doing a build per binary)
+    // Dev-dependencies are only in the SBOM if they were built, e.g. for tests.
+    // Edges between the binary's packages and those only built for the host are
+    // kept in the build environment SBOM, which refers to the binary's.
+    for edge in dependency_edges(metadata, &[package_id], true) {
+        let host = |id| {
+            build_env
+                .as_ref()
+                .and_then(|build_env| build_env.packages.get(id))
+        };
+        match (packages.get(edge.from), packages.get(edge.to)) {
+            (Some(package), Some(dependency)) => {
+                relationships.push(edge.relationship(&package.spdxid, &dependency.spdxid))
+            }
+            (package, dependency) => {
+                if let (Some(package), Some(dependency)) = (
+                    package.or_else(|| host(edge.from)),
+                    dependency.or_else(|| host(edge.to)),
+                ) {
+                    let relationship = edge.relationship(&package.spdxid, &dependency.spdxid);
+                    if let Some(build_env) = &mut build_env {
+                        build_env.relationships.push(relationship);
+                    }
+                }
+            }
+        }
+    }
+
+    // Rust binaries statically link the rlib of every crate built for the target,
+    // other than Rust dylibs they link dynamically, while crates only built for
+    // the host just helped build them. Anything linking a static library
+    // statically links those crates too.
+    let target = target_packages(metadata, package_id);
     relationships.extend(
-        cargo_build_info
-            .packages
-            .values()
-            .map(|package| Relationship {
+        packages
+            .iter()
+            .filter(|(id, _)| *id != package_id && target.contains(id))
+            .map(|(id, package)| Relationship {
                 comment: None,
                 related_spdx_element: package.spdxid.clone(),
-                // Is this the best fit? Should the file indicate that it statically links the crate?
-                relationship_type: RelationshipType::DependsOn,
+                relationship_type: if cargo_build_info.dylibs.contains(id) {
+                    RelationshipType::DynamicLink
+                } else {
+                    RelationshipType::StaticLink
+                },
                 spdx_element_id: binary_spdxid.clone(),
             }),
     );
 
     // Create the SBOM and write it out
+    let path = sbom_path(binary, options.format, options.compression).into_std_path_buf();
+    let output_manager = OutputManager::new(
+        &path,
+        true,
+        options.format,
+        options.json_style,
+        options.compression,
+        options.checksums,
+    );
+
+    let mut doc = document::builder(
+        &sbom_namespace(
+            options.host_url,
+            binary.file_name().unwrap_or_default(),
+            Utf8Path::new(&output_manager.output_file_name()),
+        ),
+        &output_manager.output_file_name(),
+        options.creation_info,
+    )?
+    .spdx_version(options.spdx_version)
+    .files(files)
+    .packages(
+        packages
+            .values()
+            .chain(cargo_build_info.build_tools.values())
+            .chain(cargo_build_info.native_libraries.values())
+            .chain(cargo_build_info.toolchain.iter())
+            .cloned()
+            .collect(),
+    )
+    .relationships(relationships)
+    .build()?;
+    if !snippets.is_empty() {
+        doc.snippets = Some(snippets);
+    }
+    finish_document(&mut doc, metadata, options, cargo_build_info)?;
+    output_manager.write_document(&doc)?;
+    index.push(SbomIndexEntry::new(&path, binary, &doc, false)?);
+
+    // List what was only built for the host in its own SBOM, if asked to and
+    // there is anything
+    let build_env = build_env.filter(|build_env| !build_env.packages.is_empty());
+    if let (Some(build_env), BuildDeps::Separate) = (build_env, options.build_deps) {
+        let contents = fs::read(&path)?;
+        let binary_doc = ExternalDocumentReference::to(&doc, hex::encode(Sha1::digest(&contents)));
+        let described: Vec<String> = build_env
+            .packages
+            .values()
+            .map(|package| package.spdxid.clone())
+            .collect();
+
+        let path = build_env_path(binary, options.format, options.compression);
+        let output_manager = OutputManager::new(
+            path.as_std_path(),
+            true,
+            options.format,
+            options.json_style,
+            options.compression,
+            options.checksums,
+        );
+        let mut doc = document::builder(
+            &sbom_namespace(
+                options.host_url,
+                &build_env_name(binary),
+                Utf8Path::new(&output_manager.output_file_name()),
+            ),
+            &output_manager.output_file_name(),
+            options.creation_info,
+        )?
+        .spdx_version(options.spdx_version)
+        .files(build_env.files)
+        .packages(
+            build_env
+                .packages
+                .into_values()
+                .chain(cargo_build_info.build_tools.values().cloned())
+                .collect(),
+        )
+        .build()?;
+        doc.document_comment = Some(format!(
+            "Packages built for the host to build {}, which aren't part of it.",
+            binary.file_name().unwrap_or_default()
+        ));
+
+        // The host packages are what's described, and anything they're related
+        // to which isn't listed here is in the binary's SBOM.
+        let doc_ref = doc.add_external_reference(binary_doc);
+        let listed: HashSet<String> = doc
+            .packages
+            .iter()
+            .flatten()
+            .map(|package| package.spdxid.clone())
+            .chain(doc.files.iter().flatten().map(|file| file.spdxid.clone()))
+            .collect();
+        let refer = |spdxid: String| {
+            if listed.contains(&spdxid) {
+                spdxid
+            } else {
+                format!("{}:{}", doc_ref, spdxid)
+            }
+        };
+        let mut relationships: Vec<Relationship> = described
+            .iter()
+            .map(|spdxid| Relationship {
+                comment: None,
+                related_spdx_element: spdxid.clone(),
+                relationship_type: RelationshipType::Describes,
+                spdx_element_id: SpdxIdentifier.to_string(),
+            })
+            .collect();
+        relationships.extend(build_env.relationships.into_iter().map(|relationship| {
+            Relationship {
+                spdx_element_id: refer(relationship.spdx_element_id),
+                related_spdx_element: refer(relationship.related_spdx_element),
+                ..relationship
+            }
+        }));
+        doc.relationships = Some(relationships);
+        finish_document(&mut doc, metadata, options, cargo_build_info)?;
+        output_manager.write_document(&doc)?;
+        index.push(SbomIndexEntry::new(path.as_std_path(), binary, &doc, true)?);
+    }
+
+    Ok(match options.sarif {
+        Some(_) => policy::check(&doc, &path, options.policy),
+        None => Vec::new(),
+    })
+}
+
+// Add the texts of the LicenseRefs the document's packages declare
+fn add_extracted_licenses(
+    doc: &mut Document,
+    extracted_licenses: &BTreeMap<String, HasExtractedLicensingInfo>,
+) {
+    let referenced: HashSet<&str> = doc
+        .packages
+        .iter()
+        .flatten()
+        .filter_map(|package| package.license_declared.as_deref())
+        .flat_map(|expression| {
+            expression.split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        })
+        .collect();
+    let infos: Vec<HasExtractedLicensingInfo> = extracted_licenses
+        .values()
+        .filter(|info| referenced.contains(info.license_id.as_str()))
+        .cloned()
+        .collect();
+    if !infos.is_empty() {
+        doc.has_extracted_licensing_infos = Some(infos);
+    }
+}
+
+// Apply the settings shared by every SBOM to a document, ready for writing it out
+fn finish_document(
+    doc: &mut Document,
+    metadata: &Metadata,
+    options: &SbomOptions<'_>,
+    cargo_build_info: &CargoBuildInfo,
+) -> Result<()> {
+    DocumentPipeline::standard(
+        options.extensions,
+        options.suppliers,
+        options.profile,
+        options.scrub_paths,
+        metadata.workspace_root.as_std_path(),
+        options.cpes,
+        options.crates_io_owners,
+    )
+    .add_pass_before("describes", "exclude", |doc| {
+        doc.exclude_packages(options.exclude)
+    })
+    // Added once the licenses from files are known, which may be `NONE`.
+    .add_pass_before("extensions", "none", |doc| {
+        options.none.apply(doc);
+        Ok(())
+    })
+    // Added before the profile is applied, so it restricts them too.
+    .add_pass_before("profile", "extracted licenses", |doc| {
+        add_extracted_licenses(doc, &cargo_build_info.extracted_licenses);
+        Ok(())
+    })
+    .add_pass_before("profile", "build environment", |doc| {
+        let environment = cargo_build_info.environment.clone();
+        doc.creation_info.comment = Some(match doc.creation_info.comment.take() {
+            Some(comment) => format!("{}\n{}", comment, environment),
+            None => environment,
+        });
+        Ok(())
+    })
+    .add_pass_before("profile", "annotations", |doc| {
+        options.annotations.apply(doc);
+        Ok(())
+    })
+    .add_pass_before("profile", "checksum mismatches", |doc| {
+        annotate_checksum_mismatches(doc, &cargo_build_info.checksum_mismatches);
+        Ok(())
+    })
+    .run(doc)
+}
+
+/// Annotate the packages whose lockfile checksums don't match the crates.io
+/// index, as the document's first creator, when it was created.
+fn annotate_checksum_mismatches(doc: &mut Document, mismatches: &BTreeMap<String, String>) {
+    let annotator = doc.annotator();
+    let date = doc.creation_info.created.to_string();
+    for package in doc.packages.iter_mut().flatten() {
+        if let Some(comment) = mismatches.get(&package.spdxid) {
+            package
+                .annotations
+                .get_or_insert_with(Vec::new)
+                .push(PackageAnnotation {
+                    annotation_date: date.clone(),
+                    annotation_type: AnnotationType::Review,
+                    annotator: annotator.clone(),
+                    comment: format!("Checksum mismatch: {}", comment),
+                });
+        }
+    }
+}
+
+/// An entry in the index of SBOMs written by a build
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SbomIndexEntry {
+    /// Path of the SBOM
+    sbom: Utf8PathBuf,
+    /// Path of the binary the SBOM is for
+    binary: Utf8PathBuf,
+    /// SHA-256 checksum of the SBOM, as written
+    sha256: String,
+    /// Namespace of the SBOM
+    document_namespace: String,
+    /// Whether the SBOM lists the packages only built for the host, rather
+    /// than what's in the binary
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    build_environment: bool,
+}
+
+impl SbomIndexEntry {
+    // Describe an SBOM which has been written
+    fn new(
+        path: &Path,
+        binary: &Utf8Path,
+        doc: &Document,
+        build_environment: bool,
+    ) -> Result<Self> {
+        Ok(SbomIndexEntry {
+            sbom: Utf8PathBuf::try_from(path.to_owned())?,
+            binary: binary.to_owned(),
+            sha256: output::file_digest(path, ChecksumAlgorithm::Sha256)?,
+            document_namespace: doc.document_namespace.to_string(),
+            build_environment,
+        })
+    }
+}
+
+/// The packages only built for the host, with the files they contain and
+/// their relationships
+#[derive(Debug, Default)]
+struct BuildEnvironment {
+    packages: HashMap<PackageId, Package>,
+    files: Vec<File>,
+    relationships: Vec<Relationship>,
+}
+
+// Move the packages only built for the host as part of a binary's package out
+// of those listed for the binary, along with the files they contain and any
+// relationships involving either
+fn take_host_packages(
+    packages: &mut HashMap<PackageId, Package>,
+    files: &mut Vec<File>,
+    relationships: &mut Vec<Relationship>,
+    metadata: &Metadata,
+    package_id: &PackageId,
+) -> BuildEnvironment {
+    let host_ids: Vec<PackageId> = host_packages(metadata, package_id)
+        .into_iter()
+        .cloned()
+        .collect();
+
+    let mut build_env = BuildEnvironment::default();
+    for id in host_ids {
+        if let Some(package) = packages.remove(&id) {
+            log::info!(target: "cargo_spdx", "{} is only built for the host", package.spdxid);
+            build_env.packages.insert(id, package);
+        }
+    }
+
+    let mut moved: HashSet<String> = build_env
+        .packages
+        .values()
+        .map(|package| package.spdxid.clone())
+        .collect();
+    moved.extend(
+        relationships
+            .iter()
+            .filter(|relationship| {
+                relationship.relationship_type == RelationshipType::Contains
+                    && moved.contains(&relationship.spdx_element_id)
+            })
+            .map(|relationship| relationship.related_spdx_element.clone())
+            .collect::<Vec<_>>(),
+    );
+
+    let (moved_files, kept_files) = files
+        .drain(..)
+        .partition(|file| moved.contains(&file.spdxid));
+    *files = kept_files;
+    build_env.files = moved_files;
+
+    let (moved_relationships, kept_relationships) =
+        relationships.drain(..).partition(|relationship| {
+            moved.contains(&relationship.spdx_element_id)
+                || moved.contains(&relationship.related_spdx_element)
+        });
+    *relationships = kept_relationships;
+    build_env.relationships = moved_relationships;
+
+    build_env
+}
+
+// Plan the files written for each binary, so clashes are found before any are written
+fn plan_outputs(
+    cargo_build_info: &CargoBuildInfo,
+    metadata: &Metadata,
+    options: &SbomOptions<'_>,
+    licenses_dir: Option<&Path>,
+    index_path: &Utf8Path,
+) -> OutputPlan {
+    let mut plan = OutputPlan::default();
+    let rename = "give the binaries distinct names with `name` in their [[bin]] or [lib] \
+        section, or build one package at a time with `-- -p <package>`";
+    let renamespace = "put `{binary}` in --host-url where each binary's name should go";
+
+    for (binary, package_id, _) in &cargo_build_info.binaries {
+        let package = &metadata[package_id];
+        let from = format!("{} {}", package.name, package.version);
+        let path = sbom_path(binary, options.format, options.compression);
+        plan.add(
+            path.as_std_path(),
+            format!("SBOM for {} from {}", binary, from),
+            rename.to_string(),
+        );
+        plan.add_namespace(
+            &sbom_namespace(
+                options.host_url,
+                binary.file_name().unwrap_or_default(),
+                &path,
+            ),
+            format!("SBOM for {} from {}", binary, from),
+            renamespace.to_string(),
+        );
+        for algorithm in options.checksums {
+            plan.add(
+                &output::checksum_path(path.as_std_path(), *algorithm),
+                format!(
+                    "{} checksum of the SBOM for {} from {}",
+                    algorithm, binary, from
+                ),
+                rename.to_string(),
+            );
+        }
+        if options.build_deps == BuildDeps::Separate {
+            let path = build_env_path(binary, options.format, options.compression);
+            plan.add(
+                path.as_std_path(),
+                format!("build environment SBOM for {} from {}", binary, from),
+                rename.to_string(),
+            );
+            plan.add_namespace(
+                &sbom_namespace(options.host_url, &build_env_name(binary), &path),
+                format!("build environment SBOM for {} from {}", binary, from),
+                renamespace.to_string(),
+            );
+            for algorithm in options.checksums {
+                plan.add(
+                    &output::checksum_path(path.as_std_path(), *algorithm),
+                    format!(
+                        "{} checksum of the build environment SBOM for {} from {}",
+                        algorithm, binary, from
+                    ),
+                    rename.to_string(),
+                );
+            }
+        }
+        if cargo_build_info.build_log.is_some() {
+            plan.add(
+                build_log_path(binary).as_std_path(),
+                format!("build log for {} from {}", binary, from),
+                rename.to_string(),
+            );
+        }
+    }
+
+    plan.add(
+        index_path.as_std_path(),
+        "index of the SBOMs written".to_string(),
+        "pass a different directory to `-- --target-dir`".to_string(),
+    );
+
+    if let Some(dir) = licenses_dir {
+        plan.add(
+            &licenses::index_path(dir),
+            "license file index".to_string(),
+            "pass a different directory to --licenses-dir".to_string(),
+        );
+    }
+
+    if let Some(sarif_path) = options.sarif {
+        plan.add(
+            sarif_path,
+            "policy violations for --sarif".to_string(),
+            "pass a different path to --sarif".to_string(),
+        );
+    }
+
+    plan
+}
+
+// Get the target directory the build writes to, which is `--target-dir` if it was
+// passed to `cargo build`, and otherwise whatever cargo metadata found
+fn resolve_target_dir(target_dir: Option<&Path>, default: &Utf8Path) -> Result<Utf8PathBuf> {
+    match target_dir {
+        // cargo takes a relative `--target-dir` as relative to the working directory
+        Some(dir) => {
+            Utf8PathBuf::from_path_buf(std::env::current_dir()?.join(dir)).map_err(|dir| {
+                anyhow::anyhow!("target directory isn't valid UTF-8: {}", dir.display())
+            })
+        }
+        None => Ok(default.to_owned()),
+    }
+}
+
+// Get the path of the SBOM for a binary, e.g. `target/debug/foo.spdx.json`
+fn sbom_path(binary: &Utf8Path, format: Format, compression: Compression) -> Utf8PathBuf {
     let mut spdx_path = Utf8PathBuf::from(binary);
     spdx_path.set_extension(
         format!(
             "{}{}",
             spdx_path.extension().unwrap_or_default(),
-            format.extension()
+            format.extension(compression)
         )
         .trim_start_matches('.'),
     );
-    let output_manager = OutputManager::new(&spdx_path.into_std_path_buf(), true, format);
-
-    let doc = DocumentBuilder::default()
-        .document_name(output_manager.output_file_name())
-        .try_document_namespace(host_url)?
-        .creation_info(get_creation_info()?)
-        .files(files)
-        .packages(packages.values().cloned().collect())
-        .relationships(relationships)
-        .build()?;
-    output_manager.write_document(&doc)?;
-    Ok(())
+    spdx_path
+}
+
+// Get the namespace of the SBOM of an artifact, e.g. the binary `foo` or its
+// build environment `foo.build-env`, so each SBOM a build writes has its own.
+// `{binary}` in the host URL is replaced by the artifact, and otherwise the
+// file name of the SBOM is appended to it.
+fn sbom_namespace(host_url: &str, artifact: &str, sbom: &Utf8Path) -> String {
+    if host_url.contains("{binary}") {
+        host_url.replace("{binary}", artifact)
+    } else {
+        format!(
+            "{}/{}",
+            host_url.trim_end_matches('/'),
+            sbom.file_name().unwrap_or_default()
+        )
+    }
+}
+
+// Get the name of the build environment of a binary, e.g. `foo.build-env`
+fn build_env_name(binary: &Utf8Path) -> String {
+    format!("{}.build-env", binary.file_name().unwrap_or_default())
+}
+
+// Get the path of the build environment SBOM for a binary, e.g.
+// `target/debug/foo.build-env.spdx.json`
+fn build_env_path(binary: &Utf8Path, format: Format, compression: Compression) -> Utf8PathBuf {
+    sbom_path(
+        &Utf8PathBuf::from(format!("{}.build-env", binary)),
+        format,
+        compression,
+    )
+}
+
+// Get the path of the index of SBOMs written by a build
+fn sbom_index_path(target_dir: &Utf8Path) -> Utf8PathBuf {
+    target_dir.join("cargo-spdx").join("sboms.json")
+}
+
+// Get the path of the build log saved alongside a binary
+fn build_log_path(binary: &Utf8Path) -> Utf8PathBuf {
+    Utf8PathBuf::from(format!("{}.build.log", binary))
+}
+
+// Check if a file produced for a staticlib crate is the static library itself
+fn is_staticlib(path: &Utf8Path) -> bool {
+    // On MSVC targets a cdylib also produces a `.dll.lib` import library, which
+    // isn't a static library.
+    let file_name = path.file_name().unwrap_or_default();
+    matches!(path.extension(), Some("a" | "lib")) && !file_name.ends_with(".dll.lib")
+}
+
+// Check if a file produced for a cdylib crate is the dynamic library itself
+fn is_dynamic_lib(path: &Utf8Path) -> bool {
+    matches!(path.extension(), Some("so" | "dylib" | "dll"))
+}
+
+// Find the dep-info file for an executable or library, along with the entry in
+// it listing the artifact's source files
+//
+// cargo doesn't always place a dep-info file next to libraries, and only places
+// one next to executables at the end of the build, so fall back to the most
+// recent matching one in `deps/`.
+fn artifact_dep_info(artifact: &Utf8Path) -> Result<Option<(Utf8PathBuf, String)>> {
+    let mut colocated = Utf8PathBuf::from(artifact);
+    colocated.set_extension("d");
+    if colocated.exists() {
+        return Ok(Some((colocated, artifact.to_string())));
+    }
+
+    let (parent, stem) = match (artifact.parent(), artifact.file_stem()) {
+        (Some(parent), Some(stem)) => (parent, stem),
+        _ => return Ok(None),
+    };
+    let deps = parent.join("deps");
+    if !deps.is_dir() {
+        return Ok(None);
+    }
+
+    // Entries look like `target/debug/deps/libfoo-0123456789abcdef.a: src/lib.rs`,
+    // or have no extension at all for executables on most platforms
+    let prefix = format!("{}-", deps.join(stem));
+    let suffix = artifact
+        .extension()
+        .map(|extension| format!(".{}", extension))
+        .unwrap_or_default();
+    let mut newest = None;
+    for entry in deps.read_dir_utf8()? {
+        let path = entry?.path().to_owned();
+        if path.extension() != Some("d") {
+            continue;
+        }
+
+        let target = BufReader::new(fs::File::open(&path)?)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| line.split_once(':').map(|(target, _)| target.to_string()))
+            .find(|target| is_deps_entry(target, &prefix, &suffix));
+        let target = match target {
+            Some(target) => target,
+            None => continue,
+        };
+
+        let modified = fs::metadata(&path)?.modified()?;
+        if newest
+            .as_ref()
+            .map_or(true, |(newest, _, _)| modified > *newest)
+        {
+            newest = Some((modified, path, target));
+        }
+    }
+
+    Ok(newest.map(|(_, path, target)| (path, target)))
+}
+
+// Check if a dep-info entry is for the artifact with the given prefix and suffix,
+// with only cargo's hash between them
+fn is_deps_entry(target: &str, prefix: &str, suffix: &str) -> bool {
+    target
+        .strip_prefix(prefix)
+        .and_then(|rest| rest.strip_suffix(suffix))
+        .map_or(false, |hash| {
+            !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit())
+        })
 }
 
 // Return the dep-info (*.d) file for a given rmeta file
@@ -292,6 +1703,8 @@ fn rmeta_to_dep_info(rmeta_path: &Utf8Path) -> Utf8PathBuf {
 /// # Arguments
 /// * `dep_info` - Path to the dep-info file
 /// * `package_root` - Path to the root of the owning package. SPDX File names will be relative to this
+/// * `workspace_root` - Path to the root of the workspace, which relative paths in the dep-info file
+///   are relative to
 /// * `package_id` - Cargo Package ID of the owning package
 /// * `collector` - CargoBuildInfo that will have files/relationships added to it.
 /// * `dep_info_entry` - The dep_info_entry to extract source files for
@@ -300,26 +1713,29 @@ fn rmeta_to_dep_info(rmeta_path: &Utf8Path) -> Utf8PathBuf {
 fn collect_source_files(
     dep_info: &Utf8Path,
     package_root: &Utf8Path,
+    workspace_root: &Utf8Path,
     package_id: &PackageId,
     collector: &mut CargoBuildInfo,
     dep_info_entry: &str,
 ) -> Result<Vec<File>> {
     let package = collector.packages.get(package_id).unwrap();
-    let file = fs::File::open(&dep_info)?;
+    let file = fs::File::open(dep_info)?;
     let mut files = if let Some(line) = BufReader::new(file)
         .lines()
-        .filter_map(Result::ok)
+        .map_while(Result::ok)
         .find(|line| line.starts_with(dep_info_entry))
     {
         line.split_whitespace()
             // First entry is the dep info file
             .skip(1)
             .map(|file| {
-                let path = Utf8PathBuf::from(file);
+                // cargo runs rustc from the workspace root, so relative paths are
+                // relative to it (joining leaves absolute paths as they are)
+                let path = workspace_root.join(file);
                 File::try_from_file(
                     &path,
                     package_root,
-                    FileType::Source,
+                    FileType::of(&path),
                     Some(&package.name),
                     package.version_info.as_deref(),
                 )
@@ -330,6 +1746,9 @@ fn collect_source_files(
         vec![]
     };
 
+    // Packages producing several artifacts list the same files for each
+    files.retain(|file| collector.source_file_ids.insert(file.spdxid.clone()));
+
     let package_spdxid = &collector.packages.get(package_id).unwrap().spdxid;
 
     for file in &files {
@@ -345,11 +1764,66 @@ fn collect_source_files(
     Ok(files)
 }
 
+// Describe what a build ran with as `key=value` lines, so SBOMs of builds of
+// the same code with different settings can be told apart
+fn build_environment(
+    profile: &str,
+    target: Option<&str>,
+    rustflags: &str,
+    cargo_version: Option<&str>,
+) -> String {
+    let mut lines = vec![format!("profile={}", profile)];
+    if let Some(target) = target {
+        lines.push(format!("target={}", target));
+    }
+    lines.push(format!("rustflags={}", rustflags));
+    if let Some(cargo_version) = cargo_version {
+        lines.push(format!("cargo={}", cargo_version));
+    }
+    lines.join("\n")
+}
+
+// Ask cargo for its version, e.g. `cargo 1.75.0 (1d8b05cdd 2023-11-20)`
+fn cargo_version(cargo: &str) -> Option<String> {
+    let output = Command::new(cargo).arg("--version").output().ok()?;
+    let version = String::from_utf8(output.stdout).ok()?;
+    output.status.success().then(|| version.trim().to_string())
+}
+
+// Get the flags cargo passes to rustc, which it takes from the environment
+fn rustflags() -> String {
+    std::env::var("CARGO_ENCODED_RUSTFLAGS")
+        .map(|flags| flags.replace('\x1f', " "))
+        .or_else(|_| std::env::var("RUSTFLAGS"))
+        .unwrap_or_default()
+}
+
+// Check whether a crate with the given crate types is linked dynamically
+//
+// rustc links a Rust dylib statically when it also has an rlib, unless it's
+// asked to prefer dynamic linking.
+fn links_dynamically(crate_types: &[String], rustflags: &str) -> bool {
+    let prefer_dynamic =
+        rustflags.contains("prefer-dynamic") && !rustflags.contains("prefer-dynamic=no");
+    crate_types.iter().any(|t| t == "dylib")
+        && (prefer_dynamic || !crate_types.iter().any(|t| t == "lib" || t == "rlib"))
+}
+
 #[cfg(test)]
 mod tests {
     use clap::Parser;
 
-    use super::CargoBuild;
+    use super::{
+        annotate_checksum_mismatches, build_environment, is_deps_entry, is_dynamic_lib,
+        is_staticlib, links_dynamically, native_library_version, resolve_target_dir,
+        sbom_namespace, take_host_packages, CargoBuild,
+    };
+    use crate::document::{Document, Package};
+    use cargo_metadata::camino::Utf8Path;
+    use cargo_metadata::{Metadata, PackageId};
+    use serde_json::json;
+    use std::collections::{BTreeMap, HashMap};
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn test_cargo_build_arg_parsing() {
@@ -361,6 +1835,8 @@ mod tests {
             "foo bar",
             "--message-format=json",
             "--target=x86_64-unknown-linux-musl",
+            "--target-dir",
+            "/tmp/target",
             "--release",
         ])
         .unwrap();
@@ -371,5 +1847,241 @@ mod tests {
         );
         assert_eq!(cargs.message_format, Some("json".to_string()));
         assert_eq!(cargs.target, Some("x86_64-unknown-linux-musl".to_string()));
+        assert_eq!(cargs.target_dir, Some(PathBuf::from("/tmp/target")));
+        assert!(cargs.release);
+    }
+
+    #[test]
+    fn test_build_environment() {
+        assert_eq!(
+            build_environment(
+                "release",
+                Some("x86_64-unknown-linux-musl"),
+                "-C target-cpu=native",
+                Some("cargo 1.75.0 (1d8b05cdd 2023-11-20)"),
+            ),
+            "profile=release\n\
+             target=x86_64-unknown-linux-musl\n\
+             rustflags=-C target-cpu=native\n\
+             cargo=cargo 1.75.0 (1d8b05cdd 2023-11-20)"
+        );
+        assert_eq!(
+            build_environment("dev", None, "", None),
+            "profile=dev\nrustflags="
+        );
+    }
+
+    #[test]
+    fn test_sbom_namespace() {
+        assert_eq!(
+            sbom_namespace(
+                "https://x/m/",
+                "m",
+                Utf8Path::new("target/debug/m.spdx.json")
+            ),
+            "https://x/m/m.spdx.json"
+        );
+        assert_eq!(
+            sbom_namespace(
+                "https://x/{binary}",
+                "m.build-env",
+                Utf8Path::new("target/debug/m.build-env.spdx.json")
+            ),
+            "https://x/m.build-env"
+        );
+    }
+
+    #[test]
+    fn test_annotate_checksum_mismatches() {
+        let mut doc: Document = serde_json::from_value(serde_json::json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": "foo.spdx",
+            "documentNamespace": "https://example.com/foo",
+            "creationInfo": {
+                "created": "2022-06-01T12:00:00Z",
+                "creators": ["Person: Jane Doe", "Tool: cargo-spdx"],
+            },
+            "packages": [
+                { "name": "foo", "SPDXID": "SPDXRef-foo", "downloadLocation": "NONE" },
+                { "name": "bar", "SPDXID": "SPDXRef-bar", "downloadLocation": "NONE" },
+            ],
+        }))
+        .unwrap();
+        let mismatches = BTreeMap::from([("SPDXRef-foo".to_string(), "tampered".to_string())]);
+
+        annotate_checksum_mismatches(&mut doc, &mismatches);
+
+        let packages = doc.packages.unwrap();
+        let annotations = packages[0].annotations.as_ref().unwrap();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].annotator, "Person: Jane Doe");
+        assert_eq!(annotations[0].annotation_date, "2022-06-01T12:00:00Z");
+        assert_eq!(annotations[0].comment, "Checksum mismatch: tampered");
+        assert!(packages[1].annotations.is_none());
+    }
+
+    #[test]
+    fn test_take_host_packages() {
+        // Two binaries, `a` and `b`, built together, with `a` using `gen` in its build script.
+        let node = |name: &str, deps: &[(&str, &str)]| {
+            let deps: Vec<_> = deps
+                .iter()
+                .map(|(name, kind)| {
+                    let kind = if kind.is_empty() { None } else { Some(kind) };
+                    json!({ "name": name, "pkg": name, "dep_kinds": [{ "kind": kind, "target": null }] })
+                })
+                .collect();
+            json!({ "id": name, "dependencies": [], "deps": deps, "features": [] })
+        };
+        let metadata: Metadata = serde_json::from_value(json!({
+            "packages": [],
+            "workspace_members": ["a", "b"],
+            "resolve": {
+                "root": null,
+                "nodes": [
+                    node("a", &[("la", ""), ("gen", "build")]),
+                    node("b", &[("lb", "")]),
+                    node("la", &[]),
+                    node("lb", &[]),
+                    node("gen", &[]),
+                ],
+            },
+            "target_directory": "/work/target",
+            "version": 1,
+            "workspace_root": "/work",
+        }))
+        .unwrap();
+        let mut packages: HashMap<PackageId, Package> = ["a", "b", "la", "lb", "gen"]
+            .iter()
+            .map(|name| {
+                let package = json!({
+                    "name": name,
+                    "SPDXID": format!("SPDXRef-{}", name),
+                    "downloadLocation": "NONE",
+                });
+                let id = PackageId {
+                    repr: name.to_string(),
+                };
+                (id, serde_json::from_value(package).unwrap())
+            })
+            .collect();
+        let mut files = vec![serde_json::from_value(json!({
+            "fileName": "gen/src/lib.rs",
+            "SPDXID": "SPDXRef-File-gen",
+        }))
+        .unwrap()];
+        let mut relationships = vec![serde_json::from_value(json!({
+            "spdxElementId": "SPDXRef-gen",
+            "relationshipType": "CONTAINS",
+            "relatedSpdxElement": "SPDXRef-File-gen",
+        }))
+        .unwrap()];
+
+        let build_env = take_host_packages(
+            &mut packages,
+            &mut files,
+            &mut relationships,
+            &metadata,
+            &PackageId {
+                repr: "a".to_string(),
+            },
+        );
+
+        let mut host: Vec<_> = build_env
+            .packages
+            .keys()
+            .map(|id| id.repr.as_str())
+            .collect();
+        host.sort_unstable();
+        assert_eq!(host, ["gen"]);
+        let mut kept: Vec<_> = packages.keys().map(|id| id.repr.as_str()).collect();
+        kept.sort_unstable();
+        assert_eq!(kept, ["a", "b", "la", "lb"]);
+        assert_eq!(build_env.files.len(), 1);
+        assert!(files.is_empty());
+        assert_eq!(build_env.relationships.len(), 1);
+        assert!(relationships.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_target_dir() {
+        let default = Utf8Path::new("/work/target");
+        assert_eq!(resolve_target_dir(None, default).unwrap(), default);
+        assert_eq!(
+            resolve_target_dir(Some(Path::new("/tmp/target")), default).unwrap(),
+            "/tmp/target"
+        );
+        assert_eq!(
+            resolve_target_dir(Some(Path::new("out")), default).unwrap(),
+            std::env::current_dir().unwrap().join("out")
+        );
+    }
+
+    #[test]
+    fn test_is_deps_entry() {
+        let prefix = "target/debug/deps/foo-";
+        assert!(is_deps_entry("target/debug/deps/foo-0123abcd", prefix, ""));
+        assert!(!is_deps_entry(
+            "target/debug/deps/foo-0123abcd.d",
+            prefix,
+            ""
+        ));
+        assert!(is_deps_entry(
+            "target/debug/deps/foo-0123abcd.exe",
+            prefix,
+            ".exe"
+        ));
+        assert!(!is_deps_entry(
+            "target/debug/deps/foo-bar-0123abcd",
+            prefix,
+            ""
+        ));
+    }
+
+    #[test]
+    fn test_is_staticlib() {
+        assert!(is_staticlib(Utf8Path::new("target/debug/libfoo.a")));
+        assert!(is_staticlib(Utf8Path::new("target/debug/foo.lib")));
+        assert!(!is_staticlib(Utf8Path::new("target/debug/foo.dll.lib")));
+        assert!(!is_staticlib(Utf8Path::new("target/debug/libfoo.so")));
+    }
+
+    #[test]
+    fn test_links_dynamically() {
+        let types = |types: &[&str]| types.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert!(links_dynamically(&types(&["dylib"]), ""));
+        assert!(!links_dynamically(&types(&["dylib", "rlib"]), ""));
+        assert!(links_dynamically(
+            &types(&["dylib", "rlib"]),
+            "-C prefer-dynamic"
+        ));
+        assert!(!links_dynamically(
+            &types(&["dylib", "rlib"]),
+            "-Cprefer-dynamic=no"
+        ));
+        assert!(!links_dynamically(&types(&["lib"]), "-C prefer-dynamic"));
+    }
+
+    #[test]
+    fn test_is_dynamic_lib() {
+        assert!(is_dynamic_lib(Utf8Path::new("target/debug/libfoo.so")));
+        assert!(is_dynamic_lib(Utf8Path::new("target/debug/libfoo.dylib")));
+        assert!(is_dynamic_lib(Utf8Path::new("target/debug/foo.dll")));
+        assert!(!is_dynamic_lib(Utf8Path::new("target/debug/foo.dll.lib")));
+        assert!(!is_dynamic_lib(Utf8Path::new("target/debug/foo.pdb")));
+    }
+
+    #[test]
+    fn test_native_library_version() {
+        let output = "cargo:rustc-link-lib=static=z\ncargo:version=1.2.13\n";
+        assert_eq!(native_library_version(output).as_deref(), Some("1.2.13"));
+        let output = "cargo::metadata=version=3.0.8\n";
+        assert_eq!(native_library_version(output).as_deref(), Some("3.0.8"));
+        assert_eq!(
+            native_library_version("cargo:version_number=30000080\n"),
+            None
+        );
     }
 }
@@ -0,0 +1,111 @@
+//! Bundles an SBOM with the license texts of the packages it describes.
+//!
+//! The bundle is a gzipped tarball holding the SBOM at its root, and the
+//! license files of each package under `licenses/`. The SBOM lists each license
+//! file, so the texts can be traced back to their packages.
+
+use crate::document::{
+    spdx_id_safe, Document, File, FileType, Package, Relationship, RelationshipType,
+};
+use crate::licenses::{self, PackageLicenses};
+use anyhow::{anyhow, Context, Result};
+use cargo_metadata::Metadata;
+use flate2::write::GzEncoder;
+use std::collections::HashSet;
+use std::ops::Not as _;
+use std::path::Path;
+
+/// The directory of the bundle the license files are put in.
+const LICENSES_DIR: &str = "licenses";
+
+/// Find the license files of the packages the document describes.
+pub fn find_described_licenses(
+    doc: &Document,
+    metadata: &Metadata,
+) -> Result<Vec<PackageLicenses>> {
+    let described: HashSet<&str> = doc
+        .packages
+        .iter()
+        .flatten()
+        .map(|package| package.spdxid.as_str())
+        .collect();
+    licenses::find_licenses(
+        metadata
+            .packages
+            .iter()
+            .filter(|package| described.contains(Package::from(*package).spdxid.as_str())),
+    )
+}
+
+/// Add the license files to the document, as files which are metafiles of their packages.
+pub fn add_license_files(doc: &mut Document, licenses: &[PackageLicenses]) -> Result<()> {
+    let mut files = Vec::new();
+    let mut relationships = Vec::new();
+    for package in licenses {
+        for (path, source) in package.collected_paths() {
+            let mut file = File::try_from_file(
+                source,
+                source.parent().unwrap(),
+                FileType::Text,
+                Some(&package.name),
+                Some(&package.version),
+            )?;
+            // The same file may also be listed as a source file of the package.
+            file.spdxid = spdx_id_safe(&format!("SPDXRef-LicenseText-{}", path));
+            file.file_name = format!("{}/{}", LICENSES_DIR, path);
+            file.comment = Some(format!(
+                "License text of {} {}, included in the bundle.",
+                package.name, package.version
+            ));
+            relationships.push(Relationship {
+                comment: None,
+                related_spdx_element: package.spdxid.clone(),
+                relationship_type: RelationshipType::MetafileOf,
+                spdx_element_id: file.spdxid.clone(),
+            });
+            files.push(file);
+        }
+    }
+
+    doc.files.get_or_insert_with(Vec::new).extend(files);
+    doc.relationships
+        .get_or_insert_with(Vec::new)
+        .extend(relationships);
+    Ok(())
+}
+
+/// Write the bundle: the SBOM at `sbom`, and the license files.
+///
+/// Returns an error if the bundle already exists and the user hasn't set output
+/// to be forced.
+pub fn write_bundle(
+    path: &Path,
+    force: bool,
+    sbom: &Path,
+    licenses: &[PackageLicenses],
+) -> Result<()> {
+    log::info!(target: "cargo_spdx", "writing bundle to {}", path.display());
+
+    if force.not() && path.exists() {
+        return Err(anyhow!("bundle already exists: {}", path.display()));
+    }
+
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("failed to create bundle {}", path.display()))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, flate2::Compression::default()));
+
+    let sbom_name = sbom
+        .file_name()
+        .ok_or_else(|| anyhow!("missing output file name"))?;
+    builder.append_path_with_name(sbom, sbom_name)?;
+    for package in licenses {
+        for (name, source) in package.collected_paths() {
+            builder
+                .append_path_with_name(source, format!("{}/{}", LICENSES_DIR, name))
+                .with_context(|| format!("failed to add license file {}", source))?;
+        }
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
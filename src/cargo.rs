@@ -1,10 +1,16 @@
 //! Functions for interacting with `cargo-metadata`.
 
+use crate::document::{Relationship, RelationshipType};
 use anyhow::{anyhow, Result};
-use cargo_metadata::{Metadata, Package};
+use cargo_metadata::{DependencyKind, Metadata, Node, NodeDep, Package, PackageId};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 pub trait MetadataExt<'a> {
     fn root(&'a self) -> Result<&'a Package>;
+    fn current_member(&'a self) -> Option<&'a Package>;
+    fn find_member(&'a self, spec: &str) -> Result<&'a Package>;
+    fn is_ambiguous(&'a self, package: &Package) -> bool;
+    fn enabled_features(&'a self, id: &PackageId) -> &'a [String];
 }
 
 impl<'a> MetadataExt<'a> for Metadata {
@@ -13,7 +19,399 @@ impl<'a> MetadataExt<'a> for Metadata {
         self.resolve
             .as_ref()
             .and_then(|r| r.root.as_ref().map(|r| &self[r]))
-            .ok_or_else(|| anyhow!("no root found"))
+            .ok_or_else(|| {
+                anyhow!("no root package found, pass --package to choose a workspace member")
+            })
+    }
+
+    /// Find the workspace member a package spec names, e.g. `foo` or `foo@1.0.0`.
+    fn find_member(&'a self, spec: &str) -> Result<&'a Package> {
+        let (name, version) = match spec.split_once('@') {
+            Some((name, version)) => (name, Some(version)),
+            None => (spec, None),
+        };
+        // cargo requires the names of workspace members to be unique.
+        self.workspace_packages()
+            .into_iter()
+            .find(|package| {
+                package.name == name
+                    && version.map_or(true, |version| package.version.to_string() == version)
+            })
+            .ok_or_else(|| anyhow!("no workspace member matches --package {}", spec))
+    }
+
+    /// Find the workspace member cargo was run for, the one whose directory
+    /// most closely holds the current directory, as the resolve's root would
+    /// be, but also with `--no-deps`. There's none at the root of a virtual
+    /// workspace.
+    fn current_member(&'a self) -> Option<&'a Package> {
+        let current_dir = std::env::current_dir().ok()?;
+        self.workspace_packages()
+            .into_iter()
+            .filter(|package| {
+                package
+                    .manifest_path
+                    .parent()
+                    .map_or(false, |dir| current_dir.starts_with(dir))
+            })
+            .max_by_key(|package| package.manifest_path.components().count())
+    }
+
+    /// Check if another package has the same name and version, e.g. a git fork
+    /// of a crate alongside the crate from crates.io.
+    fn is_ambiguous(&'a self, package: &Package) -> bool {
+        self.packages.iter().any(|other| {
+            other.id != package.id && other.name == package.name && other.version == package.version
+        })
+    }
+
+    /// Find the features cargo resolved the package to be built with.
+    fn enabled_features(&'a self, id: &PackageId) -> &'a [String] {
+        self.resolve
+            .iter()
+            .flat_map(|resolve| &resolve.nodes)
+            .find(|node| &node.id == id)
+            .map_or(&[], |node| &node.features)
+    }
+}
+
+/// Find one shortest chain of dependencies from `root` to each package it depends on.
+///
+/// Each chain leads from a direct dependency of `root` to the package itself,
+/// like the reversed paths `cargo tree -i` prints. Dev-dependencies are skipped,
+/// as they don't end up in what's built.
+pub fn dependency_chains<'a>(
+    metadata: &'a Metadata,
+    root: &'a PackageId,
+) -> HashMap<&'a PackageId, Vec<&'a PackageId>> {
+    // The walk is breadth first, so the first chain found to each package is a shortest one.
+    let mut chains: HashMap<&PackageId, Vec<&PackageId>> = HashMap::new();
+    chains.insert(root, Vec::new());
+    DependencyGraph::new(metadata).walk(&[root], |id, dep| {
+        let shipped = dep.dep_kinds.is_empty()
+            || dep
+                .dep_kinds
+                .iter()
+                .any(|info| info.kind != DependencyKind::Development);
+        if shipped && !chains.contains_key(&dep.pkg) {
+            let mut chain = chains[id].clone();
+            chain.push(&dep.pkg);
+            chains.insert(&dep.pkg, chain);
+        }
+        shipped
+    });
+
+    chains.remove(root);
+    chains
+}
+
+/// A package depending on one of its dependencies, as cargo resolved it.
+#[derive(Debug, Clone)]
+pub struct DependencyEdge<'a> {
+    /// The package depending on the dependency.
+    pub from: &'a PackageId,
+    /// The dependency.
+    pub to: &'a PackageId,
+    /// How the dependency is depended on. One declared as more than one kind,
+    /// e.g. as both a dependency and a dev-dependency, takes the kind bringing
+    /// it closest to what's shipped, and one only built for build scripts
+    /// counts as a build-dependency.
+    pub kind: DependencyKind,
+    /// Whether the dependency is a proc-macro, which is only run by the compiler.
+    pub proc_macro: bool,
+    /// The features enabling the dependency, if it's optional.
+    pub features: Option<Vec<String>>,
+}
+
+impl DependencyEdge<'_> {
+    /// Relate the SPDX elements of the package and its dependency.
+    ///
+    /// Optional dependencies say which features enabled them in the comment.
+    pub fn relationship(&self, from: &str, to: &str) -> Relationship {
+        let (relationship_type, spdx_element_id, related_spdx_element) =
+            match (self.kind, self.proc_macro, &self.features) {
+                (DependencyKind::Development, _, _) => {
+                    (RelationshipType::DevDependencyOf, to, from)
+                }
+                (_, true, _) => (RelationshipType::BuildToolOf, to, from),
+                (DependencyKind::Build, _, _) => (RelationshipType::BuildDependencyOf, to, from),
+                (_, _, Some(_)) => (RelationshipType::OptionalDependencyOf, to, from),
+                _ => (RelationshipType::DependsOn, from, to),
+            };
+        let comment = self.features.as_deref().map(|features| match features {
+            [] => "Optional.".to_string(),
+            [feature] => format!("Optional, enabled by the `{}` feature.", feature),
+            features => format!(
+                "Optional, enabled by the {} features.",
+                features
+                    .iter()
+                    .map(|feature| format!("`{}`", feature))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        });
+        Relationship {
+            comment,
+            related_spdx_element: related_spdx_element.to_string(),
+            relationship_type,
+            spdx_element_id: spdx_element_id.to_string(),
+        }
+    }
+}
+
+/// Find the dependencies of the `roots`, and of everything they depend on.
+///
+/// Edges are listed in the order they're found walking out from the roots.
+/// Dev-dependencies, and anything only they depend on, are only walked if
+/// `include_dev` is set. Proc-macros are only run by the compiler, so anything
+/// only they depend on counts as a build-dependency.
+pub fn dependency_edges<'a>(
+    metadata: &'a Metadata,
+    roots: &[&'a PackageId],
+    include_dev: bool,
+) -> Vec<DependencyEdge<'a>> {
+    let graph = DependencyGraph::new(metadata);
+    let packages: HashMap<_, _> = metadata
+        .packages
+        .iter()
+        .map(|package| (&package.id, package))
+        .collect();
+
+    let mut edges = Vec::new();
+    graph.walk(roots, |id, dep| {
+        let kinds = || dep.dep_kinds.iter().map(|info| info.kind);
+        // Dependency kinds are only listed by cargo 1.41 and later.
+        let kind = if dep.dep_kinds.is_empty()
+            || kinds()
+                .any(|kind| kind != DependencyKind::Build && kind != DependencyKind::Development)
+        {
+            DependencyKind::Normal
+        } else if kinds().any(|kind| kind == DependencyKind::Build) {
+            DependencyKind::Build
+        } else {
+            DependencyKind::Development
+        };
+        if kind == DependencyKind::Development && !include_dev {
+            return false;
+        }
+        let features = match (packages.get(id), packages.get(&dep.pkg)) {
+            (Some(package), Some(dependency)) => {
+                optional_features(package, &graph.nodes[id].features, dependency, kind)
+            }
+            _ => None,
+        };
+        edges.push(DependencyEdge {
+            from: id,
+            to: &dep.pkg,
+            kind,
+            proc_macro: graph.proc_macros.contains(&dep.pkg),
+            features,
+        });
+        true
+    });
+
+    // Anything only reachable through a build-dependency or proc-macro is only
+    // built for the host, so is a build-dependency too.
+    let mut dependencies: HashMap<&PackageId, Vec<&PackageId>> = HashMap::new();
+    for edge in edges
+        .iter()
+        .filter(|edge| edge.kind != DependencyKind::Build && !edge.proc_macro)
+    {
+        dependencies.entry(edge.from).or_default().push(edge.to);
+    }
+    let mut built: HashSet<&PackageId> = roots.iter().copied().collect();
+    let mut queue: VecDeque<&PackageId> = roots.iter().copied().collect();
+    while let Some(id) = queue.pop_front() {
+        for dep in dependencies.get(id).into_iter().flatten() {
+            if built.insert(dep) {
+                queue.push_back(dep);
+            }
+        }
+    }
+    for edge in &mut edges {
+        if edge.kind == DependencyKind::Normal && !built.contains(edge.to) {
+            edge.kind = DependencyKind::Build;
+        }
+    }
+
+    edges
+}
+
+// Find the enabled features of `package` which enable its dependency on
+// `dependency`, or None if it's not an optional dependency
+fn optional_features(
+    package: &Package,
+    enabled: &[String],
+    dependency: &Package,
+    kind: DependencyKind,
+) -> Option<Vec<String>> {
+    let declared: Vec<_> = package
+        .dependencies
+        .iter()
+        .filter(|declared| declared.name == dependency.name && declared.kind == kind)
+        .collect();
+    if declared.is_empty() || declared.iter().any(|declared| !declared.optional) {
+        return None;
+    }
+
+    // Features refer to the dependency by the name it's imported as.
+    let keys: Vec<&str> = declared
+        .iter()
+        .map(|declared| declared.rename.as_deref().unwrap_or(&declared.name))
+        .collect();
+    let enables = |value: &String| {
+        keys.iter().any(|key| {
+            value.strip_prefix("dep:") == Some(key)
+                || value == key
+                || value.starts_with(&format!("{}/", key))
+        })
+    };
+    let features: Vec<String> = enabled
+        .iter()
+        .filter(|feature| {
+            !keys.contains(&feature.as_str())
+                && package
+                    .features
+                    .get(*feature)
+                    .map_or(false, |values| values.iter().any(enables))
+        })
+        .cloned()
+        .collect();
+
+    // Otherwise it was enabled by the implicit feature named after it.
+    if features.is_empty() {
+        Some(
+            enabled
+                .iter()
+                .filter(|feature| keys.contains(&feature.as_str()))
+                .cloned()
+                .collect(),
+        )
+    } else {
+        Some(features)
+    }
+}
+
+/// Find the packages built for the target platform as part of `root`.
+///
+/// Build-dependencies and proc-macros, along with anything only they depend on,
+/// are built for and run on the host instead, so don't end up in what's shipped.
+pub fn target_packages<'a>(metadata: &'a Metadata, root: &'a PackageId) -> HashSet<&'a PackageId> {
+    let graph = DependencyGraph::new(metadata);
+    graph.walk(&[root], |_, dep| {
+        let normal = dep.dep_kinds.is_empty()
+            || dep
+                .dep_kinds
+                .iter()
+                .any(|info| info.kind == DependencyKind::Normal);
+        normal && !graph.proc_macros.contains(&dep.pkg)
+    })
+}
+
+/// Find the packages only built for the host as part of `root`.
+///
+/// These are the build-dependencies and proc-macros `root` reaches, along with
+/// anything only they depend on. Packages `root` doesn't reach at all, e.g.
+/// those of other binaries built alongside it, aren't included.
+pub fn host_packages<'a>(metadata: &'a Metadata, root: &'a PackageId) -> HashSet<&'a PackageId> {
+    let target = target_packages(metadata, root);
+    DependencyGraph::new(metadata)
+        .walk(&[root], |_, dep| {
+            dep.dep_kinds.is_empty()
+                || dep
+                    .dep_kinds
+                    .iter()
+                    .any(|info| info.kind != DependencyKind::Development)
+        })
+        .into_iter()
+        .filter(|id| !target.contains(id))
+        .collect()
+}
+
+// The dependency graph cargo resolved, indexed for walking
+struct DependencyGraph<'a> {
+    // The resolved node of each package
+    nodes: HashMap<&'a PackageId, &'a Node>,
+    // The packages which are proc-macros, so only run by the compiler
+    proc_macros: HashSet<&'a PackageId>,
+}
+
+impl<'a> DependencyGraph<'a> {
+    fn new(metadata: &'a Metadata) -> DependencyGraph<'a> {
+        let nodes = metadata
+            .resolve
+            .iter()
+            .flat_map(|resolve| &resolve.nodes)
+            .map(|node| (&node.id, node))
+            .collect();
+        let proc_macros = metadata
+            .packages
+            .iter()
+            .filter(|package| {
+                package
+                    .targets
+                    .iter()
+                    .any(|target| target.kind.iter().any(|kind| kind == "proc-macro"))
+            })
+            .map(|package| &package.id)
+            .collect();
+        DependencyGraph { nodes, proc_macros }
+    }
+
+    // Walk breadth first from the `roots`, calling `follow` with each package
+    // reached and each of its dependencies, and walking on to those it returns
+    // true for. Returns the packages reached, including the roots.
+    fn walk(
+        &self,
+        roots: &[&'a PackageId],
+        mut follow: impl FnMut(&'a PackageId, &'a NodeDep) -> bool,
+    ) -> HashSet<&'a PackageId> {
+        let mut found: HashSet<&PackageId> = roots.iter().copied().collect();
+        let mut queue: VecDeque<&PackageId> = roots.iter().copied().collect();
+        while let Some(id) = queue.pop_front() {
+            let node = match self.nodes.get(id) {
+                Some(node) => node,
+                None => continue,
+            };
+            for dep in &node.deps {
+                if follow(id, dep) && found.insert(&dep.pkg) {
+                    queue.push_back(&dep.pkg);
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Flags passed to every cargo command run, so it can be kept off the network
+/// and from changing `Cargo.lock`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CargoFlags {
+    /// Run without accessing the network.
+    pub offline: bool,
+    /// Require `Cargo.lock` to be up to date.
+    pub locked: bool,
+    /// Require `Cargo.lock` to be up to date, without accessing the network.
+    pub frozen: bool,
+}
+
+impl CargoFlags {
+    /// The flags, as passed to cargo.
+    pub fn args(&self) -> Vec<String> {
+        [
+            (self.offline, "--offline"),
+            (self.locked, "--locked"),
+            (self.frozen, "--frozen"),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, flag)| flag.to_string())
+        .collect()
+    }
+
+    /// Whether cargo is kept off the network.
+    pub fn is_offline(&self) -> bool {
+        self.offline || self.frozen
     }
 }
 
@@ -21,3 +419,318 @@ pub fn cargo_exec() -> String {
     // cargo sets this for cargo subcommands, so use that when invoking cargo, if present
     std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        dependency_chains, dependency_edges, host_packages, target_packages, CargoFlags,
+        MetadataExt,
+    };
+    use crate::document::RelationshipType;
+    use cargo_metadata::{DependencyKind, Metadata, PackageId};
+    use serde_json::{json, Value};
+
+    fn id(name: &str) -> PackageId {
+        PackageId {
+            repr: name.to_string(),
+        }
+    }
+
+    fn dep(name: &str, kind: Option<&str>) -> Value {
+        json!({ "name": name, "pkg": name, "dep_kinds": [{ "kind": kind, "target": null }] })
+    }
+
+    fn node(name: &str, deps: Vec<Value>) -> Value {
+        json!({ "id": name, "dependencies": [], "deps": deps, "features": [] })
+    }
+
+    #[test]
+    fn test_dependency_chains() {
+        let metadata: Metadata = serde_json::from_value(json!({
+            "packages": [],
+            "workspace_members": ["root"],
+            "resolve": {
+                "root": "root",
+                "nodes": [
+                    node("root", vec![dep("a", None), dep("c", Some("dev"))]),
+                    node("a", vec![dep("b", Some("build"))]),
+                    node("b", vec![]),
+                    node("c", vec![dep("d", None)]),
+                    node("d", vec![]),
+                ],
+            },
+            "target_directory": "/work/target",
+            "version": 1,
+            "workspace_root": "/work",
+        }))
+        .unwrap();
+
+        let root = id("root");
+        let chains = dependency_chains(&metadata, &root);
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[&id("a")], vec![&id("a")]);
+        assert_eq!(chains[&id("b")], vec![&id("a"), &id("b")]);
+    }
+
+    #[test]
+    fn test_target_packages() {
+        let metadata: Metadata = serde_json::from_value(json!({
+            "packages": [{
+                "name": "derive",
+                "version": "1.0.0",
+                "id": "derive",
+                "dependencies": [],
+                "targets": [{
+                    "name": "derive",
+                    "kind": ["proc-macro"],
+                    "crate_types": ["proc-macro"],
+                    "src_path": "/derive/src/lib.rs",
+                }],
+                "features": {},
+                "manifest_path": "/derive/Cargo.toml",
+            }],
+            "workspace_members": ["root"],
+            "resolve": {
+                "root": "root",
+                "nodes": [
+                    node("root", vec![dep("a", None), dep("gen", Some("build")), dep("derive", None)]),
+                    node("a", vec![dep("shared", None)]),
+                    node("gen", vec![dep("shared", None), dep("codegen", None)]),
+                    node("derive", vec![dep("syn", None)]),
+                    node("shared", vec![]),
+                    node("codegen", vec![]),
+                    node("syn", vec![]),
+                ],
+            },
+            "target_directory": "/work/target",
+            "version": 1,
+            "workspace_root": "/work",
+        }))
+        .unwrap();
+
+        let root = id("root");
+        let mut target: Vec<_> = target_packages(&metadata, &root)
+            .into_iter()
+            .map(|id| id.repr.as_str())
+            .collect();
+        target.sort_unstable();
+        assert_eq!(target, ["a", "root", "shared"]);
+    }
+
+    #[test]
+    fn test_host_packages() {
+        let metadata: Metadata = serde_json::from_value(json!({
+            "packages": [{
+                "name": "derive",
+                "version": "1.0.0",
+                "id": "derive",
+                "dependencies": [],
+                "targets": [{
+                    "name": "derive",
+                    "kind": ["proc-macro"],
+                    "crate_types": ["proc-macro"],
+                    "src_path": "/derive/src/lib.rs",
+                }],
+                "features": {},
+                "manifest_path": "/derive/Cargo.toml",
+            }],
+            "workspace_members": ["root", "other"],
+            "resolve": {
+                "root": null,
+                "nodes": [
+                    node("root", vec![dep("a", None), dep("gen", Some("build")), dep("derive", None), dep("test", Some("dev"))]),
+                    node("a", vec![dep("shared", None)]),
+                    node("gen", vec![dep("shared", None), dep("codegen", None)]),
+                    node("derive", vec![dep("syn", None)]),
+                    node("other", vec![dep("lo", None)]),
+                    node("shared", vec![]),
+                    node("codegen", vec![]),
+                    node("syn", vec![]),
+                    node("test", vec![]),
+                    node("lo", vec![]),
+                ],
+            },
+            "target_directory": "/work/target",
+            "version": 1,
+            "workspace_root": "/work",
+        }))
+        .unwrap();
+
+        let root = id("root");
+        let mut host: Vec<_> = host_packages(&metadata, &root)
+            .into_iter()
+            .map(|id| id.repr.as_str())
+            .collect();
+        host.sort_unstable();
+        assert_eq!(host, ["codegen", "derive", "gen", "syn"]);
+    }
+
+    #[test]
+    fn test_dependency_edges() {
+        let metadata: Metadata = serde_json::from_value(json!({
+            "packages": [{
+                "name": "derive",
+                "version": "1.0.0",
+                "id": "derive",
+                "dependencies": [],
+                "targets": [{
+                    "name": "derive",
+                    "kind": ["proc-macro"],
+                    "crate_types": ["proc-macro"],
+                    "src_path": "/derive/src/lib.rs",
+                }],
+                "features": {},
+                "manifest_path": "/derive/Cargo.toml",
+            }, {
+                "name": "a",
+                "version": "1.0.0",
+                "id": "a",
+                "dependencies": [{
+                    "name": "shared",
+                    "source": null,
+                    "req": "^1",
+                    "kind": null,
+                    "optional": true,
+                    "uses_default_features": true,
+                    "features": [],
+                    "target": null,
+                    "rename": null,
+                    "registry": null,
+                }],
+                "targets": [],
+                "features": { "default": ["fast"], "fast": ["dep:shared"] },
+                "manifest_path": "/a/Cargo.toml",
+            }, {
+                "name": "shared",
+                "version": "1.0.0",
+                "id": "shared",
+                "dependencies": [],
+                "targets": [],
+                "features": {},
+                "manifest_path": "/shared/Cargo.toml",
+            }],
+            "workspace_members": ["root", "member"],
+            "resolve": {
+                "root": "root",
+                "nodes": [
+                    node("root", vec![dep("member", None), dep("a", None), dep("test", Some("dev"))]),
+                    node("member", vec![dep("gen", Some("build"))]),
+                    json!({ "id": "a", "dependencies": [], "deps": [dep("shared", None), dep("derive", None)], "features": ["default", "fast"] }),
+                    node("derive", vec![dep("syn", None)]),
+                    node("syn", vec![]),
+                    node("gen", vec![dep("shared", None), dep("codegen", None)]),
+                    node("test", vec![dep("other", None)]),
+                    node("shared", vec![]),
+                    node("codegen", vec![]),
+                    node("other", vec![]),
+                ],
+            },
+            "target_directory": "/work/target",
+            "version": 1,
+            "workspace_root": "/work",
+        }))
+        .unwrap();
+
+        let members: Vec<_> = metadata.workspace_members.iter().collect();
+        let edges = |include_dev| -> Vec<_> {
+            dependency_edges(&metadata, &members, include_dev)
+                .into_iter()
+                .map(|edge| (edge.from.repr.as_str(), edge.to.repr.as_str(), edge.kind))
+                .collect()
+        };
+        assert_eq!(
+            edges(false),
+            [
+                ("root", "member", DependencyKind::Normal),
+                ("root", "a", DependencyKind::Normal),
+                ("member", "gen", DependencyKind::Build),
+                ("a", "shared", DependencyKind::Normal),
+                ("a", "derive", DependencyKind::Build),
+                ("gen", "shared", DependencyKind::Normal),
+                ("gen", "codegen", DependencyKind::Build),
+                ("derive", "syn", DependencyKind::Build),
+            ]
+        );
+        assert_eq!(
+            edges(true),
+            [
+                ("root", "member", DependencyKind::Normal),
+                ("root", "a", DependencyKind::Normal),
+                ("root", "test", DependencyKind::Development),
+                ("member", "gen", DependencyKind::Build),
+                ("a", "shared", DependencyKind::Normal),
+                ("a", "derive", DependencyKind::Build),
+                ("test", "other", DependencyKind::Normal),
+                ("gen", "shared", DependencyKind::Normal),
+                ("gen", "codegen", DependencyKind::Build),
+                ("derive", "syn", DependencyKind::Build),
+            ]
+        );
+
+        let edge = dependency_edges(&metadata, &members, true).remove(2);
+        let relationship = edge.relationship("SPDXRef-root", "SPDXRef-test");
+        assert!(matches!(
+            relationship.relationship_type,
+            RelationshipType::DevDependencyOf
+        ));
+        assert_eq!(relationship.spdx_element_id, "SPDXRef-test");
+        assert_eq!(relationship.related_spdx_element, "SPDXRef-root");
+
+        let edge = dependency_edges(&metadata, &members, false).remove(4);
+        assert!(edge.proc_macro);
+        let relationship = edge.relationship("SPDXRef-a", "SPDXRef-derive");
+        assert!(matches!(
+            relationship.relationship_type,
+            RelationshipType::BuildToolOf
+        ));
+        assert_eq!(relationship.spdx_element_id, "SPDXRef-derive");
+
+        let edge = dependency_edges(&metadata, &members, false).remove(3);
+        assert_eq!(edge.features.as_deref(), Some(&["fast".to_string()][..]));
+        let relationship = edge.relationship("SPDXRef-a", "SPDXRef-shared");
+        assert!(matches!(
+            relationship.relationship_type,
+            RelationshipType::OptionalDependencyOf
+        ));
+        assert_eq!(
+            relationship.comment.as_deref(),
+            Some("Optional, enabled by the `fast` feature.")
+        );
+    }
+
+    #[test]
+    fn test_find_member() {
+        let package = |name: &str, version: &str| json!({ "name": name, "version": version, "id": name, "dependencies": [], "targets": [], "features": {}, "manifest_path": format!("/work/{}/Cargo.toml", name) });
+        let metadata: Metadata = serde_json::from_value(json!({
+            "packages": [package("foo", "1.0.0"), package("bar", "2.0.0"), package("dep", "1.0.0")],
+            "workspace_members": ["foo", "bar"],
+            "resolve": null,
+            "target_directory": "/work/target",
+            "version": 1,
+            "workspace_root": "/work",
+        }))
+        .unwrap();
+
+        assert_eq!(metadata.find_member("bar").unwrap().name, "bar");
+        assert_eq!(metadata.find_member("foo@1.0.0").unwrap().name, "foo");
+        assert!(metadata.find_member("foo@2.0.0").is_err());
+        assert!(metadata.find_member("dep").is_err());
+    }
+
+    #[test]
+    fn test_cargo_flags() {
+        assert!(CargoFlags::default().args().is_empty());
+        let flags = CargoFlags {
+            offline: true,
+            locked: true,
+            frozen: false,
+        };
+        assert_eq!(flags.args(), ["--offline", "--locked"]);
+        assert!(flags.is_offline());
+        assert!(CargoFlags {
+            frozen: true,
+            ..CargoFlags::default()
+        }
+        .is_offline());
+    }
+}
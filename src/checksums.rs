@@ -0,0 +1,363 @@
+//! Finds the checksums of the `.crate` archives registry packages come from.
+//!
+//! Cargo records the SHA256 of each registry package's archive in the
+//! lockfile. Where the lockfile doesn't have it, the archive in cargo's cache
+//! is hashed instead, and failing that, the checksum the registry's sparse
+//! index publishes is used, unless running offline. With `--fetch-missing`,
+//! archives found nowhere else are downloaded and hashed too, so packages have
+//! checksums even on fresh CI machines where nothing has been downloaded yet.
+
+#[cfg(feature = "network")]
+use crate::registry::{Downloader, SparseIndex, INDEX_URL};
+use anyhow::{anyhow, Context, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::ops::Not as _;
+use std::path::{Path, PathBuf};
+
+/// Checksums of registry packages recorded in a lockfile, keyed by name,
+/// version, and source, e.g. `registry+https://github.com/rust-lang/crates.io-index`.
+pub type LockfileChecksums = HashMap<(String, String, String), String>;
+
+/// Finds the checksums of registry packages' archives.
+#[derive(Debug)]
+pub struct ArchiveChecksums {
+    /// The checksums the workspace's lockfile records.
+    lockfile: LockfileChecksums,
+    /// Cargo's registry caches, where archives already downloaded are kept.
+    cache_dirs: Vec<PathBuf>,
+    /// The sparse indexes of the registries asked so far, keyed by their URL,
+    /// unless offline or one couldn't be reached.
+    #[cfg(feature = "network")]
+    indexes: Option<HashMap<String, SparseIndex>>,
+    /// Downloads the archives found nowhere else, with `--fetch-missing`.
+    #[cfg(feature = "network")]
+    downloader: Option<Downloader>,
+}
+
+impl ArchiveChecksums {
+    /// Look for checksums for the packages of a workspace.
+    ///
+    /// # Arguments
+    /// * `workspace_root` - The root of the workspace, holding its lockfile
+    /// * `download_dir` - Where archives downloaded with `fetch_missing` are kept
+    /// * `offline` - Whether to keep off the network, leaving registry indexes unasked
+    /// * `fetch_missing` - Whether to download the archives found nowhere else
+    // Nothing is asked or downloaded without the `network` feature.
+    #[cfg_attr(not(feature = "network"), allow(unused_variables))]
+    pub fn new(
+        workspace_root: &Path,
+        download_dir: &Path,
+        offline: bool,
+        fetch_missing: bool,
+    ) -> Result<ArchiveChecksums> {
+        if fetch_missing && cfg!(feature = "network").not() {
+            return Err(anyhow!(
+                "--fetch-missing needs cargo-spdx to be built with the `network` feature"
+            ));
+        }
+
+        // A workspace without a lockfile just has no checksums in it.
+        let lockfile = workspace_root.join("Cargo.lock");
+        let lockfile = if lockfile.exists() {
+            lockfile_checksums(&lockfile)?
+        } else {
+            LockfileChecksums::new()
+        };
+
+        Ok(ArchiveChecksums {
+            lockfile,
+            cache_dirs: registry_cache_dirs(),
+            #[cfg(feature = "network")]
+            indexes: offline.not().then(HashMap::new),
+            #[cfg(feature = "network")]
+            downloader: fetch_missing
+                .then(|| Downloader::new(download_dir))
+                .transpose()?,
+        })
+    }
+
+    /// Get the SHA256 of the archive a package comes from.
+    ///
+    /// Returns `None` for packages which don't come from a registry, and those
+    /// whose checksum can't be found.
+    pub fn checksum(&mut self, package: &cargo_metadata::Package) -> Result<Option<String>> {
+        let source = match &package.source {
+            Some(source)
+                if source.repr.starts_with("registry+") || source.repr.starts_with("sparse+") =>
+            {
+                source
+            }
+            _ => return Ok(None),
+        };
+        let name = package.name.as_str();
+        let version = package.version.to_string();
+
+        let key = (name.to_string(), version.clone(), source.repr.clone());
+        if let Some(checksum) = self.lockfile.get(&key) {
+            return Ok(Some(checksum.clone()));
+        }
+
+        // Other registries may have a package of the same name and version, so
+        // only the package's own registry's cache is looked in.
+        let file_name = format!("{}-{}.crate", name, version);
+        for cache_dir in self
+            .cache_dirs
+            .iter()
+            .filter(|cache_dir| is_cache_dir_of(cache_dir, source))
+        {
+            let cached = cache_dir.join(&file_name);
+            if cached.is_file() {
+                log::debug!(target: "cargo_spdx", "hashing cached archive {}", cached.display());
+                return sha256_file(&cached).map(Some);
+            }
+        }
+
+        #[cfg(feature = "network")]
+        if let Some(checksum) = self.index_checksum(source, name, &version) {
+            return Ok(Some(checksum));
+        }
+
+        // Only crates.io's download URL is known without reading the registry's configuration.
+        #[cfg(feature = "network")]
+        if let Some(downloader) = self.downloader.as_mut().filter(|_| source.is_crates_io()) {
+            let archive = downloader.fetch_unverified(name, &version)?;
+            log::debug!(target: "cargo_spdx", "hashing downloaded archive {}", archive.display());
+            return sha256_file(&archive).map(Some);
+        }
+
+        log::warn!(
+            target: "cargo_spdx",
+            "no checksum for {} {} from {}, as it isn't in the lockfile, the cargo cache, or the registry index",
+            name,
+            version,
+            source
+        );
+        Ok(None)
+    }
+}
+
+#[cfg(feature = "network")]
+impl ArchiveChecksums {
+    // Ask the sparse index of the package's registry for the checksum of its
+    // archive. An index which can't be reached is warned about once, and no
+    // index is asked after that, as the network is likely unavailable.
+    fn index_checksum(
+        &mut self,
+        source: &cargo_metadata::Source,
+        name: &str,
+        version: &str,
+    ) -> Option<String> {
+        let url = index_url(source)?;
+        let indexes = self.indexes.as_mut()?;
+        let index = indexes
+            .entry(url.to_string())
+            .or_insert_with(|| SparseIndex::new(url));
+        match index.checksum(name, version) {
+            Ok(checksum) => checksum.map(ToOwned::to_owned),
+            Err(err) => {
+                log::warn!(
+                    target: "cargo_spdx",
+                    "not asking registry indexes for checksums, as {} can't be read: {:#}",
+                    url,
+                    err
+                );
+                self.indexes = None;
+                None
+            }
+        }
+    }
+}
+
+// Get the URL of the sparse index of a registry, if it has one. crates.io
+// has one whether or not it's used through its git index.
+#[cfg(feature = "network")]
+fn index_url(source: &cargo_metadata::Source) -> Option<&str> {
+    if source.is_crates_io() {
+        return Some(INDEX_URL);
+    }
+    source
+        .repr
+        .strip_prefix("sparse+")
+        .map(|url| url.trim_end_matches('/'))
+}
+
+/// Read the registry package checksums from a `Cargo.lock` file.
+pub fn lockfile_checksums(lockfile: &Path) -> Result<LockfileChecksums> {
+    let contents = fs::read_to_string(lockfile)
+        .with_context(|| format!("failed to read {}", lockfile.display()))?;
+    parse_lockfile_checksums(&contents)
+        .with_context(|| format!("invalid lockfile {}", lockfile.display()))
+}
+
+/// Parse the registry package checksums out of lockfile contents.
+fn parse_lockfile_checksums(contents: &str) -> Result<LockfileChecksums> {
+    let lockfile: toml::Value = toml::from_str(contents)?;
+    let packages = lockfile
+        .get("package")
+        .and_then(toml::Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    let mut checksums = LockfileChecksums::new();
+    for package in packages {
+        let field = |key| package.get(key).and_then(toml::Value::as_str);
+        if let (Some(name), Some(version), Some(source), Some(checksum)) = (
+            field("name"),
+            field("version"),
+            field("source"),
+            field("checksum"),
+        ) {
+            checksums.insert(
+                (name.to_string(), version.to_string(), source.to_string()),
+                checksum.to_string(),
+            );
+        }
+    }
+
+    Ok(checksums)
+}
+
+/// Compute the hex-encoded SHA256 of a file.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("failed to calculate checksum for {}", path.display()))?;
+    let mut sha256 = Sha256::new();
+    io::copy(&mut file, &mut sha256)?;
+    Ok(hex::encode(sha256.finalize()))
+}
+
+/// Find the directories cargo caches registry archives in.
+pub fn registry_cache_dirs() -> Vec<PathBuf> {
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .or_else(|| std::env::var_os("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(".cargo"))
+        });
+
+    // Each registry gets its own directory, e.g. `index.crates.io-6f17d22bba15001f`.
+    cargo_home
+        .map(|home| home.join("registry").join("cache"))
+        .and_then(|cache| fs::read_dir(cache).ok())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Check whether a registry cache directory holds the archives of a source.
+///
+/// Cargo names each registry's directory after the host of its index URL, then
+/// a hash of the source, e.g. `index.crates.io-6f17d22bba15001f`. The hash
+/// differs between cargo versions, so only the host is compared. crates.io is
+/// cached under `github.com` when used through its git index.
+fn is_cache_dir_of(cache_dir: &Path, source: &cargo_metadata::Source) -> bool {
+    let dir_host = match cache_dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.rsplit_once('-'))
+    {
+        Some((host, _)) => host,
+        None => return false,
+    };
+
+    if source.is_crates_io() {
+        return dir_host == "index.crates.io" || dir_host == "github.com";
+    }
+
+    source_host(&source.repr) == Some(dir_host)
+}
+
+/// Get the host of a registry source, e.g. `example.com` for
+/// `sparse+https://example.com:8080/index/`.
+fn source_host(source: &str) -> Option<&str> {
+    let url = source.split_once('+').map_or(source, |(_, url)| url);
+    let (_, rest) = url.split_once("://")?;
+    rest.split(['/', ':', '?', '#'])
+        .next()
+        .filter(|host| host.is_empty().not())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_lockfile_checksums, source_host};
+
+    #[test]
+    fn test_lockfile_checksums() {
+        let checksums = parse_lockfile_checksums(
+            r#"
+version = 3
+
+[[package]]
+name = "anyhow"
+version = "1.0.57"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "08f9b8508dccb7687a1d6c4ce66b2b0ecef467c94667de27d8d7fe1f8d2a9cdc"
+
+[[package]]
+name = "cargo-spdx"
+version = "0.1.0"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(checksums.len(), 1);
+        assert_eq!(
+            checksums[&(
+                "anyhow".to_string(),
+                "1.0.57".to_string(),
+                "registry+https://github.com/rust-lang/crates.io-index".to_string()
+            )],
+            "08f9b8508dccb7687a1d6c4ce66b2b0ecef467c94667de27d8d7fe1f8d2a9cdc"
+        );
+    }
+
+    #[test]
+    fn test_source_host() {
+        assert_eq!(
+            source_host("registry+https://github.com/rust-lang/crates.io-index"),
+            Some("github.com")
+        );
+        assert_eq!(
+            source_host("sparse+https://example.com:8080/index/"),
+            Some("example.com")
+        );
+        assert_eq!(source_host("not a url"), None);
+    }
+
+    #[cfg(feature = "network")]
+    #[test]
+    fn test_index_url() {
+        use super::index_url;
+        use cargo_metadata::Source;
+
+        let source = |repr: &str| Source {
+            repr: repr.to_string(),
+        };
+        assert_eq!(
+            index_url(&source(
+                "registry+https://github.com/rust-lang/crates.io-index"
+            )),
+            Some("https://index.crates.io")
+        );
+        assert_eq!(
+            index_url(&source("sparse+https://index.crates.io/")),
+            Some("https://index.crates.io")
+        );
+        assert_eq!(
+            index_url(&source("sparse+https://example.com/index/")),
+            Some("https://example.com/index")
+        );
+        assert_eq!(
+            index_url(&source("registry+https://example.com/index")),
+            None
+        );
+    }
+}
@@ -1,19 +1,25 @@
 //! Defines the CLI for `cargo-spdx`.
 
+use crate::cargo::CargoFlags;
+use crate::config::{fill_host_url, Config};
+use crate::document::{AnnotationType, Creator, PathPolicy, Profile, SpdxVersion};
+use crate::format::json::JsonStyle;
 use crate::format::Format;
+use crate::identity::parse_creator;
+use crate::output::{ChecksumAlgorithm, Compression};
+use crate::prompt::Prompter;
 use anyhow::{anyhow, Result};
-use clap::Parser;
 use clap::Subcommand;
-use dialoguer::Input;
+use clap::{CommandFactory, ErrorKind, FromArgMatches, Parser};
 use std::borrow::Cow;
 use std::ffi::OsString;
-use std::ops::Deref;
 use std::ops::Not as _;
+use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 #[allow(missing_docs)]
-#[derive(Parser)]
+#[derive(Clone, Parser)]
 #[clap(bin_name = "cargo")]
 pub enum Args {
     /// Generate an SPDX SBOM for a crate.
@@ -32,23 +38,86 @@ impl Deref for Args {
     }
 }
 
+impl DerefMut for Args {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Args::Spdx(inner) => inner,
+        }
+    }
+}
+
+/// The options which only apply when describing the workspace, so can't be used
+/// with the subcommands which don't, as clap can't declare conflicts with those.
+const NOT_WITH_SUBCOMMANDS: &[&str] = &[
+    "emit-ir",
+    "from-ir",
+    "bundle",
+    "exclude-dev-deps",
+    "previous-sbom",
+    "version-from-git",
+    "package",
+    "workspace",
+    "merged",
+    "features",
+    "all-features",
+    "no-default-features",
+    "target",
+    "no-deps",
+    "annotate",
+    "external-ref",
+];
+
+/// The subcommands which the options in `NOT_WITH_SUBCOMMANDS` can't be used with.
+const WORKSPACE_SUBCOMMANDS: &[&str] = &["build", "tree"];
+
 /// The inner argument type.
-#[derive(Parser)]
+#[derive(Clone, Parser)]
 #[clap(version, about, long_about = None)]
 pub struct SpdxArgs {
-    /// The output format to use: 'kv' (default), 'json', 'yaml', 'rdf'.
+    /// The output format to use: 'kv' (default), 'json', 'yaml', 'rdf', 'cyclonedx', 'github', 'markdown', 'html'.
     #[clap(short, long)]
     #[clap(parse(try_from_str = parse_format))]
     format: Option<Format>,
 
-    /// The URL where the SBOM will be hosted. Must be unique for each SBOM.
+    /// How to lay out JSON, CycloneDX, and GitHub output: 'pretty' (default), 'compact'.
+    #[clap(long)]
+    #[clap(parse(try_from_str = JsonStyle::from_str))]
+    json_style: Option<JsonStyle>,
+
+    /// The version of the SPDX specification to follow: '2.2' (default), '2.3', '3.0'.
+    #[clap(long)]
+    #[clap(parse(try_from_str = parse_spdx_version))]
+    spdx_version: Option<SpdxVersion>,
+
+    /// The subset of the specification to restrict the document to: 'full' (default), 'spdx-lite'.
+    #[clap(long)]
+    #[clap(parse(try_from_str = Profile::from_str))]
+    profile: Option<Profile>,
+
+    /// What to do with local paths outside the workspace in file names and comments:
+    /// 'keep' (default), 'relative', 'hash', 'placeholder'.
+    #[clap(long, value_name = "POLICY")]
+    #[clap(parse(try_from_str = PathPolicy::from_str))]
+    scrub_paths: Option<PathPolicy>,
+
+    /// The URL where the SBOM will be hosted, with `{name}` and `{version}` replaced by the root package's. Must be unique for each SBOM.
     #[clap(short = 'H', long)]
     host_url: Option<String>,
 
-    /// The path of the desired output file.
+    /// The path of the desired output file, or the directory to write them to with --workspace.
     #[clap(short, long)]
     output: Option<PathBuf>,
 
+    /// Compress the output: 'gzip', 'zstd' [default: from the extension of --output, if any].
+    #[clap(long, value_name = "ALGORITHM")]
+    #[clap(parse(try_from_str = Compression::from_str))]
+    compress: Option<Compression>,
+
+    /// Write a checksum file alongside each SBOM, e.g. foo.spdx.json.sha256: 'sha256', 'sha512'.
+    #[clap(long, value_name = "ALGORITHM", multiple_occurrences = true)]
+    #[clap(parse(try_from_str = ChecksumAlgorithm::from_str))]
+    emit_checksum: Vec<ChecksumAlgorithm>,
+
     /// Force the output, replacing any existing file with the same name.
     #[clap(short = 'F', long)]
     force: bool,
@@ -57,60 +126,426 @@ pub struct SpdxArgs {
     #[clap(short = 'n', long = "no-interact")]
     no_interact: bool,
 
+    /// The path of the configuration file [default: cargo-spdx.toml in the workspace root].
+    #[clap(short, long)]
+    config: Option<PathBuf>,
+
+    /// The path of the supplier mapping [default: suppliers.toml in the workspace root].
+    #[clap(long, value_name = "PATH")]
+    suppliers: Option<PathBuf>,
+
+    /// The supplier of packages nothing else gives one, e.g. 'Organization: crates.io'.
+    #[clap(long, value_name = "SUPPLIER")]
+    default_supplier: Option<String>,
+
+    /// Only emit fields defined by the SPDX specification, omitting vendor extensions.
+    #[clap(long)]
+    strict: bool,
+
+    /// Note in each SBOM's creation info comment where its creators were looked for.
+    #[clap(long)]
+    explain_creators: bool,
+
+    /// Another creator to list, e.g. 'Organization: Acme Corp'. Can be repeated.
+    #[clap(long, value_name = "CREATOR", multiple_occurrences = true)]
+    #[clap(parse(try_from_str = parse_creator))]
+    creator: Vec<Creator>,
+
+    /// Don't look for the person creating the SBOM, listing only the creators given with --creator or in the configuration file.
+    #[clap(long)]
+    no_person: bool,
+
+    /// Leave dev-dependencies out of the workspace SBOM, rather than marking them DEV_DEPENDENCY_OF.
+    #[clap(long, alias = "exclude-dev")]
+    exclude_dev_deps: bool,
+
+    /// List only the workspace members, with their manifests, leaving out what they depend on.
+    #[clap(long)]
+    no_deps: bool,
+
+    /// Leave packages with this name out of each SBOM, where `*` matches anything, e.g. 'xtask' or '*-harness'. Can be repeated.
+    #[clap(long, value_name = "PATTERN", multiple_occurrences = true)]
+    exclude: Vec<String>,
+
+    /// Take the copyright of each package from the statements in its sources. Slow for large workspaces.
+    #[clap(long)]
+    scan_copyrights: bool,
+
+    /// Add a CPE for each crate from a registry or git, for scanners which match vulnerabilities by CPE.
+    #[clap(long)]
+    cpes: bool,
+
+    /// Fill in the supplier and originator of crates.io packages from their owners. Makes a request a second.
+    #[clap(long)]
+    crates_io_owners: bool,
+
+    /// Download and hash the archives of crates whose checksums aren't in the lockfile, the cargo cache, or the index.
+    #[clap(long)]
+    fetch_missing: bool,
+
+    /// Append the git commit checked out to the root package's version, e.g. `1.0.0+g1a2b3c4`.
+    #[clap(long)]
+    version_from_git: bool,
+
+    /// The workspace member to describe, e.g. 'foo' or 'foo@1.0.0' [default: the one in the current directory].
+    #[clap(short, long, value_name = "SPEC")]
+    package: Option<String>,
+
+    /// Write an SBOM for each workspace member, describing it and what it depends on.
+    #[clap(long, conflicts_with_all = &["package", "emit-ir", "from-ir", "previous-sbom", "bundle", "sarif"])]
+    workspace: bool,
+
+    /// Describe the whole workspace in one SBOM, as a package containing every member.
+    #[clap(long, conflicts_with_all = &["package", "workspace", "version-from-git"])]
+    merged: bool,
+
+    // The features to resolve the dependencies with, as for `cargo build`
+    #[clap(flatten)]
+    features: clap_cargo::Features,
+
+    /// Only list packages built for this platform, e.g. 'x86_64-unknown-linux-gnu' [default: all platforms].
+    #[clap(long, value_name = "TRIPLE")]
+    target: Option<String>,
+
+    /// Run cargo without accessing the network.
+    #[clap(long)]
+    offline: bool,
+
+    /// Run cargo requiring Cargo.lock to be up to date.
+    #[clap(long)]
+    locked: bool,
+
+    /// Run cargo requiring Cargo.lock to be up to date, without accessing the network.
+    #[clap(long)]
+    frozen: bool,
+
+    /// Annotate the workspace SBOM as a whole, e.g. with a sign-off note. Can be repeated.
+    #[clap(long, value_name = "COMMENT", multiple_occurrences = true)]
+    annotate: Vec<String>,
+
+    /// The type of the annotations from --annotate: 'other' (default), 'review'.
+    #[clap(long, value_name = "TYPE")]
+    #[clap(parse(try_from_str = AnnotationType::from_str))]
+    annotation_type: Option<AnnotationType>,
+
+    /// Another SBOM for the workspace SBOM to refer to, e.g. to relate its elements to. Can be repeated.
+    #[clap(long, value_name = "PATH", multiple_occurrences = true)]
+    external_ref: Vec<PathBuf>,
+
+    /// The SBOM of the previous release, which the workspace SBOM is noted as superseding.
+    #[clap(long, value_name = "PATH")]
+    previous_sbom: Option<PathBuf>,
+
+    /// Save the information gathered about the workspace to this file, for use with --from-ir.
+    #[clap(long, value_name = "PATH")]
+    emit_ir: Option<PathBuf>,
+
+    /// Render the document from information saved with --emit-ir, instead of scanning the workspace.
+    #[clap(long, value_name = "PATH")]
+    #[clap(conflicts_with_all = &[
+        "emit-ir",
+        "licenses-dir",
+        "scan-copyrights",
+        "fetch-missing",
+        "package",
+        "features",
+        "all-features",
+        "no-default-features",
+        "no-deps",
+        "target",
+        "merged",
+        "version-from-git",
+        "exclude-dev-deps",
+        "bundle",
+    ])]
+    from_ir: Option<PathBuf>,
+
+    /// Copy the license files of each package described into this directory, with an index of them.
+    #[clap(long, value_name = "DIR")]
+    licenses_dir: Option<PathBuf>,
+
+    /// Also write a gzipped tarball of the SBOM and the license files of the packages it describes.
+    #[clap(long, value_name = "PATH")]
+    bundle: Option<PathBuf>,
+
+    /// Check each SBOM against the [policy] in the configuration file, writing violations to this SARIF file.
+    #[clap(long, value_name = "PATH")]
+    sarif: Option<PathBuf>,
+
     #[clap(subcommand)]
     pub subcommand: Option<Command>,
 }
 
-#[derive(Debug, Subcommand)]
+#[derive(Clone, Debug, Subcommand)]
 pub enum Command {
     /// Run `cargo build`, generating SBOMs for produced binaries
     #[clap(after_help = "
 The SBOMs are located alongside the binaries themselves, so if cargo produces
 target/debug/foo then the SBOM will be created at target/debug/foo.spdx[.json,.yaml]
+
+Assets embedded in a binary are mapped to the bytes holding them when listed in
+target/debug/foo.embeds.json, see the README for its contents.
     
 Example:
 $ cargo spdx -H https://foo.com build -- --release --target x86_64-unknown-linux-musl
 
 Returns an error if `--message-format` is passed as an argument")]
     Build {
+        /// Check crates.io dependencies against their published archives, noting modified sources.
+        #[clap(long)]
+        verify_downloads: bool,
+
+        /// Check the lockfile checksums of crates.io dependencies against the crates.io index.
+        #[clap(long)]
+        verify_index: bool,
+
+        /// Save the build log alongside each SBOM, listing it and its checksum in the SBOM.
+        #[clap(long)]
+        build_log: bool,
+
+        /// Leave out packages only built for the host, such as build-dependencies and proc-macros.
+        #[clap(long)]
+        exclude_build_deps: bool,
+
+        /// List packages only built for the host in a separate SBOM, e.g. foo.build-env.spdx.
+        #[clap(long, conflicts_with = "exclude-build-deps")]
+        separate_build_deps: bool,
+
         /// Arguments to pass to `cargo build`
         #[clap(multiple_values = true, takes_value = true, required = false)]
         args: Vec<OsString>,
     },
+
+    /// Print the dependency tree with each package's license and SBOM inclusion
+    #[clap(after_help = "
+Each package is marked as:
+  included  built for the target platform, so listed in `cargo spdx build` SBOMs
+  excluded  only used by tests, examples, or benchmarks
+  filtered  only used on other platforms
+
+Licenses are shown in red if they violate the [policy] in the configuration
+file, and in yellow if there is no license expression to check.")]
+    Tree {
+        /// The platform to resolve dependencies for [default: the host platform]
+        #[clap(long)]
+        target: Option<String>,
+    },
+
+    /// Check the environment cargo-spdx runs in, suggesting fixes for any problems
+    #[clap(after_help = "
+Checks cargo, the workspace and its lockfile and configuration files, the
+creator, whether crates.io can be reached, and whether the output can be written.
+cargo-spdx keeps no SPDX license list cache, so there's none to check.
+
+Exits with an error if any check fails. Warnings are for things which make
+SBOMs less complete, or which only some options need.")]
+    Doctor,
+
+    /// Convert an existing SBOM to the format given by --format
+    #[clap(after_help = "
+The converted SBOM is written alongside the input unless --output is given.
+Key-value, JSON, and YAML SBOMs following SPDX 2.x can be read.
+
+Example:
+$ cargo spdx -f kv convert foo.spdx.json")]
+    Convert {
+        /// The format of the input [default: guessed from its file extension]
+        #[clap(long)]
+        #[clap(parse(try_from_str = parse_format))]
+        from: Option<Format>,
+
+        /// Parse the input defensively, as it may be malicious, limiting its size and nesting
+        #[clap(long)]
+        untrusted: bool,
+
+        /// The SBOM to convert
+        input: PathBuf,
+    },
+
+    /// Write a corrected copy of an SBOM which amends the original
+    #[clap(after_help = "
+The amendment refers to the original with an externalDocumentRef and an AMENDS
+relationship, and each element corrected is annotated with what changed and why.
+Elements are given by SPDX ID, or as pkg:NAME or pkg:NAME@VERSION for packages.
+Fields are named as in JSON SBOMs. It's written alongside the original, e.g.
+foo.amended.spdx.json, unless --output is given.
+
+Example:
+$ cargo spdx -f json -H https://example.com/foo-2 amend --base foo.spdx.json \\
+    --set pkg:serde licenseConcluded=MIT --reason 'Reviewed by legal'")]
+    Amend {
+        /// The SBOM to amend
+        #[clap(long)]
+        base: PathBuf,
+
+        /// Correct a field of a package or file, e.g. --set pkg:serde licenseConcluded=MIT
+        #[clap(
+            long,
+            required = true,
+            multiple_occurrences = true,
+            number_of_values = 2
+        )]
+        #[clap(value_names = &["ELEMENT", "FIELD=VALUE"])]
+        set: Vec<String>,
+
+        /// Why the SBOM is being amended, recorded alongside each correction
+        #[clap(long)]
+        reason: Option<String>,
+
+        /// The format of the input [default: guessed from its file extension]
+        #[clap(long)]
+        #[clap(parse(try_from_str = parse_format))]
+        from: Option<Format>,
+
+        /// Parse the input defensively, as it may be malicious, limiting its size and nesting
+        #[clap(long)]
+        untrusted: bool,
+    },
+
+    /// Write attribution notices for the third-party packages in an SBOM
+    #[clap(after_help = "
+The notices give the license, copyright, and attribution texts of each package
+the SBOM doesn't describe, as the SBOM records them. They're written to
+THIRD-PARTY.md unless --output is given, as plain text unless it ends in .md.
+
+Example:
+$ cargo spdx -o NOTICE notices foo.spdx.json")]
+    Notices {
+        /// The format of the input [default: guessed from its file extension]
+        #[clap(long)]
+        #[clap(parse(try_from_str = parse_format))]
+        from: Option<Format>,
+
+        /// Parse the input defensively, as it may be malicious, limiting its size and nesting
+        #[clap(long)]
+        untrusted: bool,
+
+        /// The SBOM to write notices for
+        input: PathBuf,
+    },
 }
 
 /// Parse the format from the CLI input.
 fn parse_format(input: &str) -> Result<Format> {
-    let format = Format::from_str(input)?;
+    Format::from_str(input)
+}
 
-    match format {
-        Format::KeyValue | Format::Json | Format::Yaml => Ok(format),
-        Format::Rdf => return Err(anyhow!("RDF format not implemented")),
+/// Parse the SPDX version from the CLI input.
+fn parse_spdx_version(input: &str) -> Result<SpdxVersion> {
+    let version = match input.strip_prefix("SPDX-") {
+        Some(_) => SpdxVersion::from_str(input)?,
+        None => SpdxVersion::from_str(&format!("SPDX-{}", input))?,
+    };
+
+    if SpdxVersion::SUPPORTED.contains(&version).not() {
+        return Err(anyhow!("unsupported SPDX version '{}'", input));
     }
+
+    Ok(version)
 }
 
 impl Args {
+    /// Parse the arguments, rejecting options the subcommand given can't be used with.
+    pub fn try_parse_args_from<I, T>(args: I) -> Result<Args, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut command = Args::command();
+        let matches = command.try_get_matches_from_mut(args)?;
+        if let Some((_, spdx)) = matches.subcommand() {
+            if let Some(name) = spdx
+                .subcommand_name()
+                .filter(|name| WORKSPACE_SUBCOMMANDS.contains(name))
+            {
+                if let Some(id) = NOT_WITH_SUBCOMMANDS.iter().find(|id| spdx.contains_id(id)) {
+                    return Err(command.error(
+                        ErrorKind::ArgumentConflict,
+                        format!("--{} can't be used with the `{}` subcommand", id, name),
+                    ));
+                }
+            }
+        }
+        Args::from_arg_matches(&matches)
+    }
+
+    /// Fill in the settings the configuration file gives which weren't passed.
+    ///
+    /// # Arguments
+    /// * `config` - The configuration file
+    /// * `root` - The root package, if there's a workspace, which names the SBOM's host URL
+    pub fn apply_config(
+        &mut self,
+        config: &Config,
+        root: Option<&cargo_metadata::Package>,
+    ) -> Result<()> {
+        match (&self.host_url, root) {
+            (Some(template), Some(root)) => self.host_url = Some(fill_host_url(template, root)),
+            (Some(template), None)
+                if template.contains("{name}") || template.contains("{version}") =>
+            {
+                return Err(anyhow!(
+                    "--host-url names the root package, which isn't known without a workspace or with --merged; choose one with --package, or leave out `{{name}}` and `{{version}}`"
+                ));
+            }
+            (Some(_), None) => {}
+            (None, _) => self.host_url = config.host_url(root)?,
+        }
+        if self.format.is_none() {
+            self.format = config.format()?;
+        }
+        if self.default_supplier.is_none() {
+            self.default_supplier = config.default_supplier.clone();
+        }
+        Ok(())
+    }
+
     /// Get the format selected by the user.
     #[inline]
     pub fn format(&self) -> Format {
         self.format.unwrap_or_default()
     }
 
+    /// Get the JSON style selected by the user.
+    #[inline]
+    pub fn json_style(&self) -> JsonStyle {
+        self.json_style.unwrap_or_default()
+    }
+
+    /// Get the version of the SPDX specification selected by the user.
+    #[inline]
+    pub fn spdx_version(&self) -> SpdxVersion {
+        self.spdx_version.unwrap_or_default()
+    }
+
+    /// Get the version of the SPDX specification if the user selected one.
+    #[inline]
+    pub fn requested_spdx_version(&self) -> Option<SpdxVersion> {
+        self.spdx_version
+    }
+
+    /// Get the profile selected by the user.
+    #[inline]
+    pub fn profile(&self) -> Profile {
+        self.profile.unwrap_or_default()
+    }
+
+    /// Get the policy for local paths selected by the user.
+    #[inline]
+    pub fn scrub_paths(&self) -> PathPolicy {
+        self.scrub_paths.unwrap_or_default()
+    }
+
     /// Get the URL the SBOM will be hosted.
     #[inline]
     pub fn host_url(&self) -> Result<Cow<'_, str>> {
         match &self.host_url {
             Some(host_url) => Ok(Cow::Borrowed(host_url)),
             None => {
-                if self.is_interactive().not() {
-                    return Err(anyhow!(
-                        "if running non-interactively, --host-url must be specified"
-                    ));
-                }
-
-                let host_url = Input::<String>::new()
-                    .with_prompt("Where will the SBOM be hosted (must be unique)?")
-                    .interact_text()?;
+                let host_url = self.prompter().input(
+                    "Where will the SBOM be hosted (must be unique)?",
+                    "--host-url",
+                )?;
 
                 Ok(Cow::Owned(host_url))
             }
@@ -123,15 +558,331 @@ impl Args {
         self.output.as_deref()
     }
 
+    /// Get the compression selected by the user.
+    #[inline]
+    pub fn compression(&self) -> Compression {
+        self.compress.unwrap_or_default()
+    }
+
+    /// Get the algorithms to write checksum files of each SBOM with.
+    #[inline]
+    pub fn checksums(&self) -> &[ChecksumAlgorithm] {
+        &self.emit_checksum
+    }
+
     /// Whether we should forcefully overwrite prior output.
     #[inline]
     pub fn force(&self) -> bool {
         self.force
     }
 
-    /// Check if the command is running interactively.
+    /// Get the path of the configuration file, if one was given.
+    #[inline]
+    pub fn config(&self) -> Option<&Path> {
+        self.config.as_deref()
+    }
+
+    /// Get the path of the supplier mapping, if one was given.
+    #[inline]
+    pub fn suppliers(&self) -> Option<&Path> {
+        self.suppliers.as_deref()
+    }
+
+    /// Get the supplier of packages nothing else gives one, if any.
+    #[inline]
+    pub fn default_supplier(&self) -> Option<&str> {
+        self.default_supplier.as_deref()
+    }
+
+    /// Whether to only emit fields defined by the SPDX specification.
+    #[inline]
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Whether to note where the creators were looked for in each SBOM.
+    #[inline]
+    pub fn explain_creators(&self) -> bool {
+        self.explain_creators
+    }
+
+    /// The other creators to list.
     #[inline]
-    pub fn is_interactive(&self) -> bool {
-        self.no_interact.not()
+    pub fn creators(&self) -> &[Creator] {
+        &self.creator
+    }
+
+    /// Whether to leave out the person creating the SBOM.
+    #[inline]
+    pub fn no_person(&self) -> bool {
+        self.no_person
+    }
+
+    /// Whether to leave dev-dependencies out of the workspace SBOM.
+    #[inline]
+    pub fn exclude_dev_deps(&self) -> bool {
+        self.exclude_dev_deps
+    }
+
+    /// Whether to list only the workspace members.
+    #[inline]
+    pub fn no_deps(&self) -> bool {
+        self.no_deps
+    }
+
+    /// Get the patterns of the names of packages to leave out of each SBOM.
+    #[inline]
+    pub fn exclude(&self) -> &[String] {
+        &self.exclude
+    }
+
+    /// Whether to take the copyright of packages from their sources.
+    #[inline]
+    pub fn scan_copyrights(&self) -> bool {
+        self.scan_copyrights
+    }
+
+    /// Whether to add a CPE for each crate.
+    #[inline]
+    pub fn cpes(&self) -> bool {
+        self.cpes
+    }
+
+    /// Whether to fill in suppliers and originators from crates.io owners.
+    #[inline]
+    pub fn crates_io_owners(&self) -> bool {
+        self.crates_io_owners
+    }
+
+    /// Whether to download the archives whose checksums are found nowhere else.
+    #[inline]
+    pub fn fetch_missing(&self) -> bool {
+        self.fetch_missing
+    }
+
+    /// Get the workspace member to describe, if one was chosen.
+    #[inline]
+    pub fn package(&self) -> Option<&str> {
+        self.package.as_deref()
+    }
+
+    /// Get the features to resolve the dependencies with.
+    #[inline]
+    pub fn features(&self) -> &clap_cargo::Features {
+        &self.features
+    }
+
+    /// Get the platform to list the packages built for, if one was chosen.
+    #[inline]
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    /// Whether to write an SBOM for each workspace member.
+    #[inline]
+    pub fn workspace(&self) -> bool {
+        self.workspace
+    }
+
+    /// Whether to describe the whole workspace in one SBOM.
+    #[inline]
+    pub fn merged(&self) -> bool {
+        self.merged
+    }
+
+    /// Get the host URL passed on the command line, before it's filled in for a package.
+    #[inline]
+    pub fn host_url_template(&self) -> Option<&str> {
+        self.host_url.as_deref()
+    }
+
+    /// Get the arguments for describing one member with --workspace.
+    ///
+    /// The member's SBOM is written into the directory given to --output, if
+    /// any, and the license files are copied once for the whole workspace.
+    pub fn for_member(&self, member: &cargo_metadata::Package) -> Args {
+        let mut args = self.clone();
+        args.package = Some(format!("{}@{}", member.name, member.version));
+        args.workspace = false;
+        args.output = None;
+        args.licenses_dir = None;
+        args
+    }
+
+    /// Get the flags to pass to every cargo command run.
+    #[inline]
+    pub fn cargo_flags(&self) -> CargoFlags {
+        CargoFlags {
+            offline: self.offline,
+            locked: self.locked,
+            frozen: self.frozen,
+        }
+    }
+
+    /// Whether to append the git commit to the root package's version.
+    #[inline]
+    pub fn version_from_git(&self) -> bool {
+        self.version_from_git
+    }
+
+    /// Get the comments to annotate the workspace SBOM with.
+    #[inline]
+    pub fn annotations(&self) -> &[String] {
+        &self.annotate
+    }
+
+    /// Get the type of the annotations from --annotate.
+    #[inline]
+    pub fn annotation_type(&self) -> AnnotationType {
+        self.annotation_type.unwrap_or(AnnotationType::Other)
+    }
+
+    /// Get the paths to other SBOMs the workspace SBOM refers to.
+    #[inline]
+    pub fn external_refs(&self) -> &[PathBuf] {
+        &self.external_ref
+    }
+
+    /// Get the path to the SBOM the workspace SBOM supersedes, if any.
+    #[inline]
+    pub fn previous_sbom(&self) -> Option<&Path> {
+        self.previous_sbom.as_deref()
+    }
+
+    /// Get the path to save the gathered information to, if any.
+    #[inline]
+    pub fn ir_output(&self) -> Option<&Path> {
+        self.emit_ir.as_deref()
+    }
+
+    /// Get the directory to copy license files to, if any.
+    #[inline]
+    pub fn licenses_dir(&self) -> Option<&Path> {
+        self.licenses_dir.as_deref()
+    }
+
+    /// Get the path to write the bundle to, if any.
+    #[inline]
+    pub fn bundle(&self) -> Option<&Path> {
+        self.bundle.as_deref()
+    }
+
+    /// Get the path to write policy violations to, if any.
+    #[inline]
+    pub fn sarif(&self) -> Option<&Path> {
+        self.sarif.as_deref()
+    }
+
+    /// Get the path to read previously gathered information from, if any.
+    #[inline]
+    pub fn ir_input(&self) -> Option<&Path> {
+        self.from_ir.as_deref()
+    }
+
+    /// Get the prompter to ask the user for anything not given on the command line.
+    #[inline]
+    pub fn prompter(&self) -> Prompter {
+        Prompter::new(self.no_interact)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Args;
+    use crate::config::Config;
+    use clap::Parser;
+    use std::ops::Not as _;
+    use std::path::Path;
+
+    #[test]
+    fn test_for_member() {
+        let args = Args::parse_from([
+            "cargo",
+            "spdx",
+            "--workspace",
+            "-H",
+            "https://sbom.example.com/{name}/{version}",
+            "-o",
+            "sbom",
+            "--licenses-dir",
+            "licenses",
+        ]);
+        let member: cargo_metadata::Package = serde_json::from_value(serde_json::json!({
+            "name": "foo",
+            "version": "1.2.3",
+            "id": "foo",
+            "dependencies": [],
+            "targets": [],
+            "features": {},
+            "manifest_path": "/foo/Cargo.toml",
+        }))
+        .unwrap();
+
+        let mut member_args = args.for_member(&member);
+        assert_eq!(member_args.package(), Some("foo@1.2.3"));
+        assert!(member_args.workspace().not());
+        assert_eq!(member_args.output(), None);
+        assert_eq!(member_args.licenses_dir(), None);
+
+        let config = Config::load(None, Path::new("/nonexistent"), None).unwrap();
+        member_args.apply_config(&config, Some(&member)).unwrap();
+        assert_eq!(
+            member_args.host_url().unwrap(),
+            "https://sbom.example.com/foo/1.2.3"
+        );
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let conflicts = |args: &[&str]| {
+            let args = ["cargo", "spdx"].iter().chain(args);
+            Args::try_parse_args_from(args).is_err()
+        };
+        assert!(conflicts(&["--no-deps", "build"]));
+        assert!(conflicts(&["--features", "a", "tree"]));
+        assert!(conflicts(&["--from-ir", "ir.json", "--no-deps"]));
+        assert!(conflicts(&[
+            "--from-ir",
+            "ir.json",
+            "--licenses-dir",
+            "licenses"
+        ]));
+        assert!(conflicts(&["--exclude", "xtask", "build"]).not());
+        assert!(conflicts(&["--bundle", "foo.tar.gz", "convert", "foo.spdx"]).not());
+        assert!(conflicts(&["--from-ir", "ir.json", "--strict"]).not());
+    }
+
+    #[test]
+    fn test_host_url_without_root() {
+        let config = Config::load(None, Path::new("/nonexistent"), None).unwrap();
+
+        let mut args = Args::parse_from(["cargo", "spdx", "-H", "https://example.com/{name}"]);
+        assert!(args.apply_config(&config, None).is_err());
+
+        let mut args = Args::parse_from(["cargo", "spdx", "-H", "https://example.com/sbom"]);
+        args.apply_config(&config, None).unwrap();
+        assert_eq!(args.host_url().unwrap(), "https://example.com/sbom");
+    }
+
+    #[test]
+    fn test_exclude_dev_alias() {
+        let args = Args::parse_from(["cargo", "spdx", "--exclude-dev"]);
+        assert!(args.exclude_dev_deps());
+    }
+
+    #[test]
+    fn test_features() {
+        let args = Args::parse_from(["cargo", "spdx"]);
+        assert!(args.features().features.is_empty());
+
+        let args = Args::parse_from([
+            "cargo",
+            "spdx",
+            "--no-default-features",
+            "--features",
+            "a b",
+        ]);
+        assert!(args.features().no_default_features);
+        assert_eq!(args.features().features, ["a", "b"]);
     }
 }
@@ -0,0 +1,657 @@
+//! Loads the `cargo-spdx` configuration file.
+
+use crate::document::{AnnotationType, Creator, Document, Extensions, PackageAnnotation};
+use crate::format::Format;
+use crate::identity::parse_creator;
+use crate::license::License;
+use crate::policy;
+use anyhow::{anyhow, Context, Result};
+use cargo_metadata::Metadata;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::ops::Not as _;
+use std::path::{Path, PathBuf};
+
+/// The name of the configuration file looked for in the workspace root.
+pub const CONFIG_FILE_NAME: &str = "cargo-spdx.toml";
+
+/// Settings loaded from the configuration file.
+///
+/// They can also be given in `Cargo.toml`, as `[workspace.metadata.spdx]`
+/// shared by the whole workspace, which `[package.metadata.spdx]` of the member
+/// being described overrides. Those are read if there's no configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Where SBOMs are hosted, if `--host-url` isn't given. `{name}` and
+    /// `{version}` are replaced by the root package's, so each release gets
+    /// its own.
+    #[serde(default)]
+    pub host_url: Option<String>,
+
+    /// The output format, if `--format` isn't given.
+    #[serde(default)]
+    pub format: Option<String>,
+
+    /// The directory the workspace SBOM is written into, relative to the
+    /// workspace root, if `--output` isn't given.
+    #[serde(default)]
+    pub output_dir: Option<PathBuf>,
+
+    /// The supplier of packages nothing else gives one, if `--default-supplier` isn't given.
+    #[serde(default)]
+    pub default_supplier: Option<String>,
+
+    /// Workspace members to leave out of the workspace SBOM, e.g. `xtask`.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// The person or organization creating SBOMs, e.g. `Organization: Acme Corp`.
+    #[serde(default)]
+    pub creator: Option<String>,
+
+    /// More people or organizations to list as creators of every SBOM.
+    #[serde(default)]
+    pub creators: Vec<String>,
+
+    /// Whether to look for the person creating SBOMs, `true` unless set.
+    #[serde(default)]
+    pub person: Option<bool>,
+
+    /// Vendor extension fields to embed in the document.
+    #[serde(default)]
+    pub extensions: ExtensionsConfig,
+
+    /// Which licenses dependencies may use.
+    #[serde(default)]
+    pub policy: PolicyConfig,
+
+    /// Tools build scripts may run, on top of the ones detected by default.
+    #[serde(default)]
+    pub build_tools: BuildToolsConfig,
+
+    /// Notes to annotate packages with.
+    #[serde(default)]
+    pub annotations: AnnotationsConfig,
+
+    /// The fields given as `NONE` where nothing was found.
+    #[serde(default)]
+    pub none: NoneConfig,
+}
+
+impl Config {
+    /// Load the configuration of a workspace, for the `member` being described.
+    ///
+    /// Like [`Config::load`], taking the settings in `Cargo.toml` from the
+    /// workspace's metadata.
+    pub fn load_for_workspace(
+        path: Option<&Path>,
+        metadata: &Metadata,
+        member: Option<&cargo_metadata::Package>,
+    ) -> Result<Config> {
+        let mut settings = metadata.workspace_metadata.get("spdx").cloned();
+        let member = member.and_then(|member| member.metadata.get("spdx"));
+        if let Some(overrides) = member {
+            match &mut settings {
+                Some(settings) => merge_settings(settings, overrides),
+                None => settings = Some(overrides.clone()),
+            }
+        }
+        Config::load(
+            path,
+            metadata.workspace_root.as_std_path(),
+            settings.as_ref(),
+        )
+    }
+
+    /// Load the configuration.
+    ///
+    /// If no path is given, looks for the configuration file in the workspace root,
+    /// then uses the `settings` from `Cargo.toml`, falling back to the default
+    /// configuration if there are neither.
+    pub fn load(
+        path: Option<&Path>,
+        workspace_root: &Path,
+        settings: Option<&serde_json::Value>,
+    ) -> Result<Config> {
+        let path = match path {
+            Some(path) => path.to_owned(),
+            None => {
+                let path = workspace_root.join(CONFIG_FILE_NAME);
+                if path.exists().not() {
+                    return match settings {
+                        Some(settings) => {
+                            log::info!(target: "cargo_spdx", "loading configuration from Cargo.toml");
+                            let config = Config::deserialize(settings).context(
+                                "invalid [workspace.metadata.spdx] or [package.metadata.spdx]",
+                            )?;
+                            config.validate()?;
+                            Ok(config)
+                        }
+                        None => {
+                            log::info!(target: "cargo_spdx", "no configuration file found, using defaults");
+                            Ok(Config::default())
+                        }
+                    };
+                }
+                path
+            }
+        };
+
+        log::info!(target: "cargo_spdx", "loading configuration from {}", path.display());
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read configuration file {}", path.display()))?;
+        let config: Config = toml::from_str(&contents)
+            .with_context(|| format!("invalid configuration file {}", path.display()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Check the settings which can't be checked while reading them
+    fn validate(&self) -> Result<()> {
+        self.format()?;
+        self.creator()?;
+        self.creators()?;
+        self.extensions.validate()?;
+        self.none.validate()?;
+        policy::validate_required_fields(&self.policy)?;
+        Ok(())
+    }
+
+    /// Get the configured output format, if any.
+    pub fn format(&self) -> Result<Option<Format>> {
+        self.format
+            .as_deref()
+            .map(|format| format.parse().context("invalid `format` setting"))
+            .transpose()
+    }
+
+    /// Get where the SBOM of `package` is hosted, if it's configured.
+    pub fn host_url(&self, package: Option<&cargo_metadata::Package>) -> Result<Option<String>> {
+        let template = match &self.host_url {
+            Some(template) => template,
+            None => return Ok(None),
+        };
+        match package {
+            Some(package) => Ok(Some(fill_host_url(template, package))),
+            None if template.contains("{name}") || template.contains("{version}") => Err(anyhow!(
+                "the `host_url` setting names the root package, which isn't known without a workspace or with --merged; pass --host-url"
+            )),
+            None => Ok(Some(template.clone())),
+        }
+    }
+
+    /// Get the configured creator, if any.
+    pub fn creator(&self) -> Result<Option<Creator>> {
+        self.creator
+            .as_deref()
+            .map(|creator| parse_creator(creator).context("invalid `creator` setting"))
+            .transpose()
+    }
+
+    /// Get the other configured creators.
+    pub fn creators(&self) -> Result<Vec<Creator>> {
+        self.creators
+            .iter()
+            .map(|creator| parse_creator(creator).context("invalid `creators` setting"))
+            .collect()
+    }
+}
+
+// Override the settings shared by the workspace with a member's, merging tables
+// so a member can change one of their keys without repeating the others
+fn merge_settings(settings: &mut serde_json::Value, overrides: &serde_json::Value) {
+    match (settings, overrides) {
+        (serde_json::Value::Object(settings), serde_json::Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match settings.get_mut(key) {
+                    Some(setting) => merge_settings(setting, value),
+                    None => {
+                        settings.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (settings, overrides) => *settings = overrides.clone(),
+    }
+}
+
+/// Vendor extension fields, keyed by the element they're attached to.
+///
+/// ```toml
+/// [extensions.document]
+/// x-acme-tracking-id = "REL-1234"
+///
+/// [extensions.packages.serde]
+/// x-acme-review = "approved"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtensionsConfig {
+    /// Fields attached to the document itself.
+    #[serde(default)]
+    pub document: BTreeMap<String, serde_json::Value>,
+
+    /// Fields attached to packages, keyed by package name.
+    #[serde(default)]
+    pub packages: BTreeMap<String, BTreeMap<String, serde_json::Value>>,
+}
+
+impl ExtensionsConfig {
+    /// Check every configured key is in the extension namespace.
+    fn validate(&self) -> Result<()> {
+        let keys = self
+            .document
+            .keys()
+            .chain(self.packages.values().flat_map(BTreeMap::keys));
+
+        for key in keys {
+            if key.starts_with(Extensions::PREFIX).not() {
+                return Err(anyhow!(
+                    "extension field '{}' must start with '{}'",
+                    key,
+                    Extensions::PREFIX
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Attach the configured fields to the document and its packages.
+    pub fn apply(&self, doc: &mut Document) {
+        doc.extensions.0.extend(self.document.clone());
+
+        for package in doc.packages.iter_mut().flatten() {
+            if let Some(fields) = self.packages.get(&package.name) {
+                package.extensions.0.extend(fields.clone());
+            }
+        }
+    }
+}
+
+/// Notes to annotate packages with, keyed by package name, such as their
+/// review status or the exceptions made for them.
+///
+/// ```toml
+/// [[annotations.openssl-sys]]
+/// comment = "License exception approved in LEGAL-42"
+/// type = "REVIEW"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct AnnotationsConfig {
+    packages: BTreeMap<String, Vec<AnnotationConfig>>,
+}
+
+/// A note to annotate a package with.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnnotationConfig {
+    /// The note itself.
+    pub comment: String,
+
+    /// The type of the annotation, `OTHER` if not given.
+    #[serde(rename = "type")]
+    pub annotation_type: Option<AnnotationType>,
+}
+
+impl AnnotationsConfig {
+    /// Annotate the packages of the document with the configured notes, as
+    /// the document's first creator, when it was created.
+    pub fn apply(&self, doc: &mut Document) {
+        let annotator = doc.annotator();
+        let date = doc.creation_info.created.to_string();
+        for package in doc.packages.iter_mut().flatten() {
+            for annotation in self.packages.get(&package.name).into_iter().flatten() {
+                package
+                    .annotations
+                    .get_or_insert_with(Vec::new)
+                    .push(PackageAnnotation {
+                        annotation_date: date.clone(),
+                        annotation_type: annotation
+                            .annotation_type
+                            .unwrap_or(AnnotationType::Other),
+                        annotator: annotator.clone(),
+                        comment: annotation.comment.clone(),
+                    });
+            }
+        }
+    }
+}
+
+/// The fields which can be given as `NONE`, where cargo-spdx can know there's nothing.
+const NONE_FIELDS: &[&str] = &["downloadLocation", "copyrightText", "licenseInfoFromFiles"];
+
+/// The fields given as `NONE` where cargo-spdx checked and found nothing,
+/// rather than `NOASSERTION`, as saying there's nothing means more legally
+/// than making no claim.
+///
+/// Only the download location of local packages is given as `NONE` by default.
+///
+/// ```toml
+/// none = ["downloadLocation", "copyrightText", "licenseInfoFromFiles"]
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(transparent)]
+pub struct NoneConfig {
+    fields: Vec<String>,
+}
+
+impl Default for NoneConfig {
+    fn default() -> Self {
+        NoneConfig {
+            fields: vec!["downloadLocation".to_string()],
+        }
+    }
+}
+
+impl NoneConfig {
+    /// Check every configured field can be given as `NONE`.
+    fn validate(&self) -> Result<()> {
+        for field in &self.fields {
+            if NONE_FIELDS.contains(&field.as_str()).not() {
+                return Err(anyhow!(
+                    "'{}' can't be given as NONE, only {}",
+                    field,
+                    NONE_FIELDS.join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Give the fields which aren't configured as `NOASSERTION` instead of `NONE`.
+    pub fn apply(&self, doc: &mut Document) {
+        doc.retract_none(|field| self.fields.iter().any(|configured| configured == field));
+    }
+}
+
+/// The licenses dependencies are allowed to use, and the fields they must have.
+///
+/// ```toml
+/// [policy]
+/// allow = ["MIT", "Apache-2.0"]
+/// deny = ["GPL-3.0"]
+/// require = ["downloadLocation", "supplier"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// License identifiers that are allowed. If empty, anything not denied is allowed.
+    #[serde(default)]
+    pub allow: Vec<String>,
+
+    /// License identifiers that are never allowed.
+    #[serde(default)]
+    pub deny: Vec<String>,
+
+    /// SPDX fields, by their JSON names, which mustn't be `NOASSERTION`.
+    #[serde(default)]
+    pub require: Vec<String>,
+}
+
+impl PolicyConfig {
+    /// Check if a license expression can be complied with under the policy.
+    pub fn permits(&self, license: &License) -> bool {
+        license.is_satisfied_by(&|id| {
+            self.deny.iter().any(|denied| denied == id).not()
+                && (self.allow.is_empty() || self.allow.iter().any(|allowed| allowed == id))
+        })
+    }
+}
+
+/// The tools detected by default, with the build script output showing they were run.
+///
+/// The `cmake` and `nasm-rs` crates print the commands they run, and the
+/// `pkg-config` crate asks to be rerun when its environment changes.
+const DEFAULT_BUILD_TOOLS: &[(&str, &[&str])] = &[
+    (
+        "cmake",
+        &["running: \"cmake\"", "rerun-if-env-changed=CMAKE"],
+    ),
+    ("nasm", &["running: \"nasm\"", "rerun-if-env-changed=NASM"]),
+    ("pkg-config", &["rerun-if-env-changed=PKG_CONFIG"]),
+];
+
+/// The key build scripts can print to name a tool they ran, e.g.
+/// `cargo:spdx-build-tool=protoc`.
+const BUILD_TOOL_KEY: &str = "spdx-build-tool=";
+
+/// System tools build scripts run, keyed by tool name.
+///
+/// A tool is detected when any line of a build script's output contains one of
+/// its patterns. Configuring a tool detected by default replaces its patterns.
+///
+/// ```toml
+/// [build_tools.protoc]
+/// patterns = ['running: "protoc"', "rerun-if-env-changed=PROTOC"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct BuildToolsConfig {
+    tools: BTreeMap<String, BuildToolConfig>,
+}
+
+/// How to detect a tool in the output of a build script.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BuildToolConfig {
+    /// Text appearing in the output of build scripts which run the tool.
+    pub patterns: Vec<String>,
+}
+
+impl BuildToolsConfig {
+    /// Find the tools a build script ran from what it printed.
+    pub fn detect(&self, output: &str) -> BTreeSet<String> {
+        let defaults = DEFAULT_BUILD_TOOLS
+            .iter()
+            .filter(|(name, _)| self.tools.contains_key(*name).not())
+            .map(|(name, patterns)| (*name, patterns.to_vec()));
+        let configured = self.tools.iter().map(|(name, tool)| {
+            let patterns = tool.patterns.iter().map(String::as_str).collect();
+            (name.as_str(), patterns)
+        });
+        let tools: Vec<(&str, Vec<&str>)> = defaults.chain(configured).collect();
+
+        let mut detected = BTreeSet::new();
+        for line in output.lines() {
+            // Build scripts can name the tools they run themselves.
+            let marked = line
+                .strip_prefix("cargo::metadata=")
+                .or_else(|| line.strip_prefix("cargo:"))
+                .and_then(|line| line.strip_prefix(BUILD_TOOL_KEY));
+            if let Some(name) = marked {
+                detected.insert(name.trim().to_string());
+            }
+
+            for (name, patterns) in &tools {
+                if patterns.iter().any(|pattern| line.contains(pattern)) {
+                    detected.insert(name.to_string());
+                }
+            }
+        }
+        detected
+    }
+}
+
+/// Replace `{name}` and `{version}` in a host URL with those of `package`.
+pub fn fill_host_url(template: &str, package: &cargo_metadata::Package) -> String {
+    template
+        .replace("{name}", &package.name)
+        .replace("{version}", &package.version.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_settings, Config};
+    use crate::document::{
+        AnnotationType, CreationInfoBuilder, Creator, DocumentBuilder, Package, NOASSERTION, NONE,
+    };
+    use crate::format::Format;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_package_metadata() {
+        let settings = serde_json::json!({
+            "host_url": "https://sbom.example.com/{name}/{version}",
+            "format": "json",
+            "output_dir": "sbom",
+        });
+        let config = Config::load(None, Path::new("/nonexistent"), Some(&settings)).unwrap();
+        assert_eq!(config.format().unwrap(), Some(Format::Json));
+        assert_eq!(config.output_dir, Some(PathBuf::from("sbom")));
+
+        let package: cargo_metadata::Package = serde_json::from_value(serde_json::json!({
+            "name": "foo",
+            "version": "1.2.3",
+            "id": "foo",
+            "dependencies": [],
+            "targets": [],
+            "features": {},
+            "manifest_path": "/foo/Cargo.toml",
+        }))
+        .unwrap();
+        assert_eq!(
+            config.host_url(Some(&package)).unwrap().as_deref(),
+            Some("https://sbom.example.com/foo/1.2.3")
+        );
+        assert!(config.host_url(None).is_err());
+
+        let settings = serde_json::json!({ "format": "docx" });
+        assert!(Config::load(None, Path::new("/nonexistent"), Some(&settings)).is_err());
+    }
+
+    #[test]
+    fn test_merge_settings() {
+        let mut settings = serde_json::json!({
+            "creators": ["Organization: Acme Corp"],
+            "policy": { "allow": ["MIT"], "deny": ["GPL-3.0"] },
+        });
+        merge_settings(
+            &mut settings,
+            &serde_json::json!({
+                "format": "json",
+                "policy": { "allow": ["MIT", "Apache-2.0"] },
+            }),
+        );
+        assert_eq!(
+            settings,
+            serde_json::json!({
+                "creators": ["Organization: Acme Corp"],
+                "format": "json",
+                "policy": { "allow": ["MIT", "Apache-2.0"], "deny": ["GPL-3.0"] },
+            })
+        );
+    }
+
+    #[test]
+    fn test_none() {
+        let package = || {
+            let mut package = Package::build_tool("foo");
+            package.download_location = NONE.to_string();
+            package.copyright_text = Some(NONE.to_string());
+            package
+        };
+        let document = || {
+            DocumentBuilder::default()
+                .document_name("foo.spdx.json")
+                .try_document_namespace("https://example.com/foo")
+                .unwrap()
+                .creation_info(
+                    CreationInfoBuilder::default()
+                        .creators(Vec::new())
+                        .build()
+                        .unwrap(),
+                )
+                .packages(vec![package()])
+                .build()
+                .unwrap()
+        };
+
+        let mut doc = document();
+        Config::default().none.apply(&mut doc);
+        let packages = doc.packages.unwrap();
+        assert_eq!(packages[0].download_location, NONE);
+        assert_eq!(packages[0].copyright_text.as_deref(), Some(NOASSERTION));
+
+        let config: Config = toml::from_str(r#"none = ["copyrightText"]"#).unwrap();
+        let mut doc = document();
+        config.none.apply(&mut doc);
+        let packages = doc.packages.unwrap();
+        assert_eq!(packages[0].download_location, NOASSERTION);
+        assert_eq!(packages[0].copyright_text.as_deref(), Some(NONE));
+
+        let config: Config = toml::from_str(r#"none = ["licenseConcluded"]"#).unwrap();
+        assert!(config.none.validate().is_err());
+    }
+
+    #[test]
+    fn test_annotations() {
+        let config: Config = toml::from_str(
+            r#"
+            [[annotations.openssl-sys]]
+            comment = "License exception approved in LEGAL-42"
+            type = "REVIEW"
+
+            [[annotations.openssl-sys]]
+            comment = "Pinned until 0.10"
+            "#,
+        )
+        .unwrap();
+        let mut doc = DocumentBuilder::default()
+            .document_name("foo.spdx.json")
+            .try_document_namespace("https://example.com/foo")
+            .unwrap()
+            .creation_info(
+                CreationInfoBuilder::default()
+                    .creators(vec![Creator::tool("cargo-spdx 0.1.0")])
+                    .build()
+                    .unwrap(),
+            )
+            .packages(vec![
+                Package::build_tool("openssl-sys"),
+                Package::build_tool("serde"),
+            ])
+            .build()
+            .unwrap();
+
+        config.annotations.apply(&mut doc);
+        let packages = doc.packages.unwrap();
+        let annotations = packages[0].annotations.as_ref().unwrap();
+        assert_eq!(annotations.len(), 2);
+        assert!(matches!(
+            annotations[0].annotation_type,
+            AnnotationType::Review
+        ));
+        assert!(matches!(
+            annotations[1].annotation_type,
+            AnnotationType::Other
+        ));
+        assert_eq!(annotations[1].annotator, "Tool: cargo-spdx 0.1.0");
+        assert!(packages[1].annotations.is_none());
+    }
+
+    #[test]
+    fn test_detect_build_tools() {
+        let config: Config = toml::from_str(
+            r#"
+            [build_tools.protoc]
+            patterns = ["running: \"protoc\""]
+
+            [build_tools.cmake]
+            patterns = ["cmake version"]
+            "#,
+        )
+        .unwrap();
+
+        let output = "cargo:rerun-if-env-changed=PKG_CONFIG_PATH\n\
+            running: \"protoc\" \"--rust_out\"\n\
+            running: \"cmake\" \"..\"\n\
+            cargo:spdx-build-tool=bison\n";
+        let detected: Vec<_> = config.build_tools.detect(output).into_iter().collect();
+        assert_eq!(detected, ["bison", "pkg-config", "protoc"]);
+    }
+}
@@ -0,0 +1,213 @@
+//! Implements `cargo spdx convert` subcommand
+
+use crate::cli::Args;
+use crate::document::{Document, DocumentPipeline, ExternalDocumentReference};
+use crate::format::{Format, ReadLimits};
+use crate::output::{Compression, OutputManager};
+use anyhow::{anyhow, Context, Result};
+use sha1::{Digest, Sha1};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Converts an existing SBOM to another format
+///
+/// # Arguments
+/// * `input` - Path to the SBOM
+/// * `from` - The format of the SBOM, guessed from its file extension if not given
+/// * `untrusted` - Whether to parse the SBOM defensively
+/// * `args` - The output settings
+pub fn convert(input: &Path, from: Option<Format>, untrusted: bool, args: &Args) -> Result<()> {
+    if args.sarif().is_some() {
+        return Err(anyhow!(
+            "--sarif needs the policy from the workspace configuration, so can't be used with convert"
+        ));
+    }
+
+    if args.bundle().is_some() {
+        return Err(anyhow!(
+            "--bundle needs the package sources, so can't be used with convert"
+        ));
+    }
+
+    let mut doc = read_document(input, from, untrusted)?;
+
+    // The document keeps the version it was written for, unless asked otherwise.
+    let mut pipeline = DocumentPipeline::default();
+    if let Some(version) = args.requested_spdx_version() {
+        pipeline.add_pass("conform to version", move |doc| {
+            doc.spdx_version = version;
+            doc.conform_to_version();
+            Ok(())
+        });
+    }
+    let (profile, scrub_paths) = (args.profile(), args.scrub_paths());
+    let current_dir = std::env::current_dir()?;
+    pipeline
+        .add_pass("describes", |doc| {
+            doc.sync_described();
+            Ok(())
+        })
+        .add_pass("profile", move |doc| profile.apply(doc))
+        .add_pass("scrub paths", move |doc| {
+            scrub_paths.apply(doc, &current_dir);
+            Ok(())
+        })
+        .add_pass("sort", |doc| {
+            doc.sort_elements();
+            Ok(())
+        });
+    pipeline.run(&mut doc)?;
+
+    let path = match args.output() {
+        Some(path) => path.to_owned(),
+        None => output_path(input, "", args.format(), args.compression()),
+    };
+    OutputManager::new(
+        &path,
+        args.force(),
+        args.format(),
+        args.json_style(),
+        args.compression(),
+        args.checksums(),
+    )
+    .write_document(&doc)
+}
+
+/// Read an existing SBOM
+///
+/// # Arguments
+/// * `input` - Path to the SBOM
+/// * `from` - The format of the SBOM, guessed from its file extension if not given
+/// * `untrusted` - Whether to parse the SBOM defensively
+pub fn read_document(input: &Path, from: Option<Format>, untrusted: bool) -> Result<Document> {
+    let format = match from.or_else(|| detect_format(input)) {
+        Some(format) => format,
+        None => {
+            return Err(anyhow!(
+                "can't tell the format of {} from its extension, pass --from",
+                input.display()
+            ))
+        }
+    };
+
+    log::info!(target: "cargo_spdx", "reading {} as {}", input.display(), format);
+
+    let file = File::open(input).with_context(|| format!("failed to open {}", input.display()))?;
+    let reader = BufReader::new(file);
+    let doc = if untrusted {
+        Document::from_untrusted_reader(reader, format, &ReadLimits::default())
+    } else {
+        Document::from_reader(reader, format)
+    };
+    doc.with_context(|| format!("invalid SBOM in {}", input.display()))
+}
+
+/// Read an existing SBOM, with a reference other documents can refer to it by
+///
+/// SPDX requires external documents to be identified by their SHA1, so it's
+/// taken from the SBOM as read.
+pub fn read_referenced_document(
+    input: &Path,
+    from: Option<Format>,
+    untrusted: bool,
+) -> Result<(Document, ExternalDocumentReference)> {
+    let doc = read_document(input, from, untrusted)?;
+    let contents =
+        fs::read(input).with_context(|| format!("failed to read {}", input.display()))?;
+    let reference = ExternalDocumentReference::to(&doc, hex::encode(Sha1::digest(&contents)));
+    Ok((doc, reference))
+}
+
+/// Get the path to write an SBOM made from the input to, alongside it.
+///
+/// The `suffix` is added to the name of the input, before its extension.
+pub fn output_path(
+    input: &Path,
+    suffix: &str,
+    format: Format,
+    compression: Compression,
+) -> PathBuf {
+    let name = input
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let stem = [
+        ".spdx.json",
+        ".spdx.yaml",
+        ".spdx.yml",
+        ".spdx.rdf",
+        ".spdx",
+    ]
+    .iter()
+    .find_map(|extension| name.strip_suffix(extension))
+    .or_else(|| name.rsplit_once('.').map(|(stem, _)| stem))
+    .unwrap_or(&name);
+    input.with_file_name(format!(
+        "{}{}{}",
+        stem,
+        suffix,
+        format.extension(compression)
+    ))
+}
+
+/// Guess the format of a document from its file extension.
+fn detect_format(path: &Path) -> Option<Format> {
+    let format = match path.extension()?.to_str()? {
+        "spdx" => "kv",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "rdf" => "rdf",
+        _ => return None,
+    };
+    Format::from_str(format).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::output_path;
+    use crate::format::Format;
+    use crate::output::Compression;
+    use std::path::Path;
+
+    #[test]
+    fn test_output_path() {
+        assert_eq!(
+            output_path(
+                Path::new("sboms/foo.spdx.json"),
+                "",
+                Format::KeyValue,
+                Compression::None
+            ),
+            Path::new("sboms/foo.spdx")
+        );
+        assert_eq!(
+            output_path(Path::new("foo.spdx"), "", Format::Json, Compression::None),
+            Path::new("foo.spdx.json")
+        );
+        assert_eq!(
+            output_path(
+                Path::new("foo.json"),
+                "",
+                Format::KeyValue,
+                Compression::None
+            ),
+            Path::new("foo.spdx")
+        );
+        assert_eq!(
+            output_path(Path::new("foo.spdx"), "", Format::Json, Compression::Zstd),
+            Path::new("foo.spdx.json.zst")
+        );
+        assert_eq!(
+            output_path(
+                Path::new("foo.spdx.json"),
+                ".amended",
+                Format::Json,
+                Compression::None
+            ),
+            Path::new("foo.amended.spdx.json")
+        );
+    }
+}
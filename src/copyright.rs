@@ -0,0 +1,131 @@
+//! Finds the copyright statements in a package's sources.
+//!
+//! Copyright isn't declared in manifests, so it's taken from lines such as
+//! `Copyright (c) 2015 The Rust Project Developers` in the package's files,
+//! usually its license files and the headers of its source files. Reading the
+//! sources of every dependency is slow, so this only runs when asked to.
+
+use crate::document::NONE;
+use anyhow::Result;
+use std::fs;
+use std::io::Read;
+use std::ops::Not as _;
+use std::path::Path;
+
+/// Files larger than this are assumed not to be worth scanning, e.g. test data.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Collect the distinct copyright statements in the files of a package.
+///
+/// Returns `NONE` if there aren't any, as the package was checked.
+pub fn scan_package(package: &cargo_metadata::Package) -> Result<String> {
+    let root = package.manifest_path.parent().unwrap().as_std_path();
+
+    let mut statements = Vec::new();
+    scan_dir(root, root, &mut statements)?;
+    log::info!(
+        target: "cargo_spdx",
+        "found {} copyright statement(s) in {}",
+        statements.len(),
+        package.name
+    );
+
+    if statements.is_empty() {
+        return Ok(NONE.to_string());
+    }
+    Ok(statements.join("\n"))
+}
+
+// Scan the files in a directory and its subdirectories, other than those of
+// other packages, version control, and build output
+fn scan_dir(root: &Path, dir: &Path, statements: &mut Vec<String>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let skipped = name.to_string_lossy().starts_with('.')
+                || name == "target"
+                || (path != root && path.join("Cargo.toml").exists());
+            if skipped.not() {
+                scan_dir(root, &path, statements)?;
+            }
+        } else if file_type.is_file() && entry.metadata()?.len() <= MAX_FILE_SIZE {
+            let mut contents = Vec::new();
+            fs::File::open(&path)?.read_to_end(&mut contents)?;
+            // Binary files don't hold statements worth listing.
+            if contents.contains(&0) {
+                continue;
+            }
+            for statement in String::from_utf8_lossy(&contents)
+                .lines()
+                .filter_map(copyright_statement)
+            {
+                if statements.contains(&statement).not() {
+                    statements.push(statement);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Pick out the copyright statement on a line, if there is one
+fn copyright_statement(line: &str) -> Option<String> {
+    // Strip off comment markers, e.g. `//!`, `#`, `/*`, or ` * `, and any
+    // full stop, so the same statement in different files is only listed once.
+    let statement = line
+        .trim()
+        .trim_start_matches(|c: char| "/*#!;-".contains(c))
+        .trim_end_matches("*/")
+        .trim()
+        .trim_end_matches('.');
+
+    let lowercase = statement.to_lowercase();
+    let rest = if let Some(rest) = lowercase.strip_prefix("copyright") {
+        rest
+    } else if lowercase.starts_with('©') {
+        &lowercase
+    } else {
+        return None;
+    };
+
+    // Statements give a year or a copyright sign, unlike prose such as
+    // "copyright notice" or templates such as "Copyright [yyyy] [name]".
+    let asserts =
+        rest.contains("(c)") || rest.contains('©') || rest.contains(|c: char| c.is_ascii_digit());
+    asserts.then(|| statement.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::copyright_statement;
+
+    #[test]
+    fn test_copyright_statement() {
+        assert_eq!(
+            copyright_statement("Copyright (c) 2014 The Rust Project Developers").as_deref(),
+            Some("Copyright (c) 2014 The Rust Project Developers")
+        );
+        assert_eq!(
+            copyright_statement("// Copyright 2018 Developers of the Rand project.").as_deref(),
+            Some("Copyright 2018 Developers of the Rand project")
+        );
+        assert_eq!(
+            copyright_statement(" * © Acme Corp */").as_deref(),
+            Some("© Acme Corp")
+        );
+        assert_eq!(
+            copyright_statement("copyright notice and this permission notice shall be included"),
+            None
+        );
+        assert_eq!(
+            copyright_statement("   Copyright [yyyy] [name of copyright owner]"),
+            None
+        );
+        assert_eq!(copyright_statement("let copyright = 2;"), None);
+    }
+}
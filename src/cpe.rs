@@ -0,0 +1,74 @@
+//! Adds CPEs to packages, for vulnerability scanners which match by CPE.
+//!
+//! Crates have no official CPE vendor, so the crate name is used for both the
+//! vendor and the product, as the NVD does for most Rust entries, e.g.
+//! `cpe:2.3:a:hyper:hyper:0.14.10:*:*:*:*:*:*:*`. Purls identify crates more
+//! reliably, so CPEs are only added when asked for.
+
+use crate::document::{Document, ExternalRef, ReferenceCategory};
+use std::ops::Not as _;
+
+/// Add a CPE to each package from crates.io, another registry, or git.
+///
+/// Local packages, build tools, and native libraries have no cargo package
+/// URL, and no CPE a scanner could know of, so are left as they are.
+pub fn add_cpes(doc: &mut Document) {
+    for package in doc.packages.iter_mut().flatten() {
+        let is_crate = package.purl().map_or(false, |purl| purl.ty() == "cargo");
+        let version = match (&package.version_info, is_crate) {
+            (Some(version), true) => version,
+            _ => continue,
+        };
+        let locator = cpe(&package.name, version);
+        package
+            .external_refs
+            .get_or_insert_with(Vec::new)
+            .push(ExternalRef {
+                reference_category: ReferenceCategory::Security,
+                reference_type: "cpe23Type".to_string(),
+                reference_locator: locator,
+                comment: None,
+            });
+    }
+}
+
+// Build the CPE 2.3 formatted string naming a version of a crate
+fn cpe(name: &str, version: &str) -> String {
+    let name = escape(&name.to_lowercase());
+    format!(
+        "cpe:2.3:a:{}:{}:{}:*:*:*:*:*:*:*",
+        name,
+        name,
+        escape(version)
+    )
+}
+
+// Quote the characters CPE components can't hold as they are, such as the
+// `+` of semver build metadata
+fn escape(component: &str) -> String {
+    let mut escaped = String::with_capacity(component.len());
+    for c in component.chars() {
+        if (c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.').not() {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cpe;
+
+    #[test]
+    fn test_cpe() {
+        assert_eq!(
+            cpe("hyper", "0.14.10"),
+            "cpe:2.3:a:hyper:hyper:0.14.10:*:*:*:*:*:*:*"
+        );
+        assert_eq!(
+            cpe("Inflector", "0.11.4+build.1"),
+            "cpe:2.3:a:inflector:inflector:0.11.4\\+build.1:*:*:*:*:*:*:*"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Implements `cargo spdx doctor` subcommand
+
+use crate::cargo::{cargo_exec, MetadataExt};
+use crate::config::Config;
+use crate::identity::Identity;
+use crate::suppliers::Suppliers;
+use anyhow::{anyhow, Result};
+use cargo_metadata::MetadataCommand;
+use console::style;
+use std::fs;
+use std::ops::Not as _;
+use std::path::Path;
+use std::process::Command;
+
+/// The result of checking one part of the environment
+#[derive(Debug)]
+enum Outcome {
+    /// Everything is in order, with details of what was found
+    Ok(String),
+    /// cargo-spdx works, but some output may be less complete than it could be
+    Warn(String, &'static str),
+    /// cargo-spdx won't work until this is fixed
+    Fail(String, &'static str),
+}
+
+/// Checks the environment cargo-spdx runs in, printing how to fix any problems
+///
+/// # Arguments
+/// * `config` - The configuration file passed with `--config`, if any
+/// * `suppliers` - The supplier mapping passed with `--suppliers`, if any
+/// * `output` - The output file passed with `--output`, if any
+pub fn doctor(
+    config: Option<&Path>,
+    suppliers: Option<&Path>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let checks = [
+        ("cargo", check_cargo()),
+        (
+            "workspace",
+            check_workspace(Path::new("."), config, suppliers),
+        ),
+        ("creator", check_creator(config)),
+        ("crates.io", check_network()),
+        ("output", check_output(output)),
+    ];
+
+    let mut failures = 0;
+    for (name, outcome) in &checks {
+        match outcome {
+            Outcome::Ok(details) => {
+                println!("{} {}: {}", style("   ok").green(), name, details);
+            }
+            Outcome::Warn(problem, fix) => {
+                println!("{} {}: {}", style(" warn").yellow(), name, problem);
+                println!("       {}", style(fix).dim());
+            }
+            Outcome::Fail(problem, fix) => {
+                failures += 1;
+                println!("{} {}: {}", style("error").red().bold(), name, problem);
+                println!("       {}", style(fix).dim());
+            }
+        }
+    }
+
+    if failures > 0 {
+        return Err(anyhow!("{} check(s) failed", failures));
+    }
+    Ok(())
+}
+
+// Check cargo can be run
+fn check_cargo() -> Outcome {
+    match Command::new(cargo_exec()).arg("--version").output() {
+        Ok(out) if out.status.success() => {
+            Outcome::Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+        }
+        Ok(out) => Outcome::Fail(
+            String::from_utf8_lossy(&out.stderr).trim().to_string(),
+            "check `cargo --version` works, or set CARGO to the cargo to use",
+        ),
+        Err(err) => Outcome::Fail(
+            format!("failed to run {}: {}", cargo_exec(), err),
+            "install cargo with rustup, or set CARGO to the cargo to use",
+        ),
+    }
+}
+
+// Check there's a workspace with a lockfile around `dir`, and its configuration
+// files are valid
+fn check_workspace(dir: &Path, config: Option<&Path>, suppliers: Option<&Path>) -> Outcome {
+    let metadata = match MetadataCommand::new().current_dir(dir).no_deps().exec() {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            return Outcome::Fail(
+                err.to_string(),
+                "run cargo-spdx from within a crate or workspace",
+            )
+        }
+    };
+
+    if let Err(err) = Config::load_for_workspace(config, &metadata, metadata.current_member()) {
+        return Outcome::Fail(
+            format!("{:#}", err),
+            "fix the configuration file, see the README for its settings",
+        );
+    }
+    let root = metadata.workspace_root.as_std_path();
+    if let Err(err) = Suppliers::load(suppliers, root) {
+        return Outcome::Fail(
+            format!("{:#}", err),
+            "fix the supplier mapping, see the README for its format",
+        );
+    }
+
+    if root.join("Cargo.lock").exists() {
+        Outcome::Ok(format!("{}", root.display()))
+    } else {
+        Outcome::Warn(
+            format!("no Cargo.lock in {}", root.display()),
+            "run `cargo generate-lockfile` so SBOMs describe the versions actually used",
+        )
+    }
+}
+
+// Check the document creator can be found
+fn check_creator(config: Option<&Path>) -> Outcome {
+    // Problems with the workspace or configuration file are reported by its own check.
+    let config = MetadataCommand::new()
+        .no_deps()
+        .exec()
+        .ok()
+        .and_then(|metadata| {
+            Config::load_for_workspace(config, &metadata, metadata.current_member()).ok()
+        });
+
+    let identity = match Identity::configured(config.as_ref(), false, &[]) {
+        Ok(identity) => identity,
+        Err(_) => Identity::resolve(None),
+    };
+    let creators: Vec<_> = identity
+        .creators()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    if creators.is_empty() {
+        Outcome::Warn(
+            identity.to_string(),
+            "set CARGO_SPDX_CREATOR, `creator` in cargo-spdx.toml, or `git config --global user.name`, otherwise only cargo-spdx is listed as a creator",
+        )
+    } else {
+        Outcome::Ok(creators.join(", "))
+    }
+}
+
+// Check crates.io can be reached, for `build --verify-downloads`
+#[cfg(feature = "network")]
+fn check_network() -> Outcome {
+    match crate::registry::check_reachable() {
+        Ok(()) => Outcome::Ok("reachable".to_string()),
+        Err(err) => Outcome::Warn(
+            format!("{:#}", err),
+            "allow access to static.crates.io, or fill the cargo cache, to use `build --verify-downloads`",
+        ),
+    }
+}
+
+// Without the `network` feature nothing is fetched, so there's nothing to check
+#[cfg(not(feature = "network"))]
+fn check_network() -> Outcome {
+    Outcome::Ok("not used, built without the `network` feature".to_string())
+}
+
+// Check the output can be written
+fn check_output(output: Option<&Path>) -> Outcome {
+    let dir = match output.and_then(Path::parent) {
+        Some(dir) if dir.as_os_str().is_empty().not() => dir,
+        _ => Path::new("."),
+    };
+
+    let probe = dir.join(".cargo-spdx-doctor");
+    match fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            Outcome::Ok(format!("{} is writable", dir.display()))
+        }
+        Err(err) => Outcome::Fail(
+            format!("can't write to {}: {}", dir.display(), err),
+            "pass --output with a path in a writable directory",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_output, check_workspace, Outcome};
+    use std::fs;
+    use std::ops::Not as _;
+
+    #[test]
+    fn test_check_workspace_without_lockfile() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src").join("lib.rs"), "").unwrap();
+
+        match check_workspace(dir.path(), None, None) {
+            Outcome::Warn(problem, _) => assert!(problem.starts_with("no Cargo.lock")),
+            outcome => panic!("expected a warning, got {:?}", outcome),
+        }
+
+        fs::write(dir.path().join("Cargo.lock"), "version = 3\n").unwrap();
+        assert!(matches!(
+            check_workspace(dir.path(), None, None),
+            Outcome::Ok(_)
+        ));
+    }
+
+    #[test]
+    fn test_check_output() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            check_output(Some(&dir.path().join("foo.spdx"))),
+            Outcome::Ok(_)
+        ));
+        assert!(dir.path().join(".cargo-spdx-doctor").exists().not());
+
+        // A directory which doesn't exist can't be written to.
+        let missing = dir.path().join("missing").join("foo.spdx");
+        match check_output(Some(&missing)) {
+            Outcome::Fail(problem, _) => assert!(problem.starts_with("can't write to")),
+            outcome => panic!("expected a failure, got {:?}", outcome),
+        }
+    }
+}
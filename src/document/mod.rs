@@ -1,74 +1,965 @@
 //! Module for working with SPDX documents.
 
-use crate::git::get_current_user;
-use anyhow::{Context, Result};
+use crate::license::tagged_licenses;
+use crate::purl::Purl;
+use crate::suppliers::matches_pattern;
+use anyhow::{anyhow, Context, Result};
 use cargo_metadata::camino::Utf8Path;
+pub use pipeline::DocumentPipeline;
+pub use profile::Profile;
 pub use schema::*;
+pub use scrub::PathPolicy;
 use sha1::{Digest, Sha1};
 use sha2::Sha256;
-use std::{fs, io};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::Read;
+use std::ops::Not as _;
 
+mod pipeline;
+// The schema mirrors the SPDX specification, so it defines more than we
+// currently generate.
+mod profile;
+#[allow(dead_code)]
 mod schema;
+mod scrub;
+pub mod v3;
 
 pub const NOASSERTION: &str = "NOASSERTION";
 
+/// Says there's positively nothing, where `NOASSERTION` makes no claim either way.
+pub const NONE: &str = "NONE";
+
 /// Build a new SPDX document builder based on collected information.
-pub fn builder(host_url: &str, output_file_name: &str) -> Result<DocumentBuilder> {
+pub fn builder(
+    host_url: &str,
+    output_file_name: &str,
+    creation_info: &CreationInfo,
+) -> Result<DocumentBuilder> {
     log::info!(target: "cargo_spdx", "building the document");
 
     let mut builder = DocumentBuilder::default();
     builder
         .document_name(output_file_name)
         .try_document_namespace(host_url)?
-        .creation_info(get_creation_info()?);
+        .creation_info(creation_info.clone());
     Ok(builder)
 }
 
-/// Identify the creator(s) of the SBOM.
-pub fn get_creation_info() -> Result<CreationInfo> {
-    let mut creator = vec![];
+impl From<&cargo_metadata::Package> for Package {
+    fn from(package: &cargo_metadata::Package) -> Self {
+        let (download_location, source_info) = download_location(package);
+        let mut external_refs: Vec<ExternalRef> = purl(package)
+            .map(|purl| ExternalRef {
+                reference_category: ReferenceCategory::PackageManager,
+                reference_type: "purl".to_string(),
+                reference_locator: purl.to_string(),
+                comment: None,
+            })
+            .into_iter()
+            .collect();
+        if let Some(repository) = &package.repository {
+            external_refs.push(ExternalRef {
+                reference_category: ReferenceCategory::Other,
+                reference_type: "vcs".to_string(),
+                reference_locator: repository.clone(),
+                comment: None,
+            });
+        }
+        // Descriptions are usually a single line, so only longer ones are
+        // summarized, by their first line.
+        let description = package
+            .description
+            .as_deref()
+            .map(str::trim)
+            .filter(|description| description.is_empty().not());
+        let summary = description
+            .and_then(|description| description.lines().next())
+            .filter(|line| Some(*line) != description);
+        Package {
+            name: package.name.to_string(),
+            spdxid: spdx_id_safe(&format!("SPDXRef-{}-{}", package.name, package.version)),
+            version_info: Some(package.version.to_string()),
+            package_file_name: None,
+            supplier: None,
+            originator: originator(package),
+            download_location,
+            files_analyzed: None,
+            package_verification_code: None,
+            checksums: None,
+            homepage: package
+                .homepage
+                .clone()
+                .or_else(|| package.repository.clone()),
+            source_info,
+            license_concluded: Some(NOASSERTION.to_string()),
+            license_declared: Some(NOASSERTION.to_string()),
+            copyright_text: Some(NOASSERTION.to_string()),
+            description: description.map(ToOwned::to_owned),
+            comment: Some(toolchain(package)),
+            external_refs: external_refs.is_empty().not().then(|| external_refs),
+            annotations: None,
+            attribution_texts: None,
+            has_files: None,
+            license_comments: None,
+            license_info_from_files: None,
+            summary: summary.map(ToOwned::to_owned),
+            extensions: Extensions::default(),
+        }
+    }
+}
+
+// Say which edition a package is written in, and the oldest Rust it builds with, if given
+fn toolchain(package: &cargo_metadata::Package) -> String {
+    // Editions are serialized as their year, which also covers ones added later.
+    let edition = serde_json::to_value(&package.edition)
+        .ok()
+        .and_then(|edition| edition.as_str().map(ToOwned::to_owned))
+        .unwrap_or_default();
+    match &package.rust_version {
+        Some(rust_version) => format!(
+            "Rust {} edition, requiring Rust {} or later.",
+            edition,
+            rust_version.to_string().trim_start_matches('^')
+        ),
+        None => format!("Rust {} edition.", edition),
+    }
+}
+
+// Take the originator of a package from its first author, e.g. `Jane Doe <jane@example.com>`
+fn originator(package: &cargo_metadata::Package) -> Option<String> {
+    let author = package.authors.first()?.trim();
+    let (name, email) = match author.split_once('<') {
+        Some((name, email)) => (name.trim(), Some(email.trim_end_matches('>').trim())),
+        None => (author, None),
+    };
+    if name.is_empty() {
+        return None;
+    }
+    let email = email.filter(|email| email.is_empty().not());
+    Some(Creator::person(name.to_string(), email.map(ToOwned::to_owned)).to_string())
+}
+
+// Get where a package can be downloaded from, and for local packages, why there's nowhere
+fn download_location(package: &cargo_metadata::Package) -> (String, Option<String>) {
+    let source = match &package.source {
+        Some(source) => source,
+        None => {
+            return (
+                NONE.to_string(),
+                Some("Local package, built from its path rather than downloaded.".to_string()),
+            )
+        }
+    };
 
-    if let Ok(user) = get_current_user() {
-        creator.push(Creator::person(user.name, user.email));
+    if source.is_crates_io() {
+        let url = format!(
+            "https://crates.io/api/v1/crates/{}/{}/download",
+            package.name, package.version
+        );
+        return (url, None);
     }
 
-    creator.push(Creator::tool("cargo-spdx 0.1.0"));
+    // Git sources look like `git+https://host/repo?branch=main#rev`, and SPDX
+    // wants `git+https://host/repo@rev`.
+    if let Some((repository, rev)) = source
+        .repr
+        .strip_prefix("git+")
+        .and_then(|rest| rest.split_once('#'))
+    {
+        let repository = repository
+            .split_once('?')
+            .map_or(repository, |(repository, _)| repository);
+        return (format!("git+{}@{}", repository, rev), None);
+    }
 
-    Ok(CreationInfoBuilder::default().creators(creator).build()?)
+    (NOASSERTION.to_string(), None)
 }
 
-impl From<&cargo_metadata::Package> for Package {
-    fn from(package: &cargo_metadata::Package) -> Self {
+// Check whether a file is an SPDX document, e.g. `foo.spdx` or `foo.spdx.json`
+fn is_spdx_document(file_name: &str) -> bool {
+    let name = file_name.rsplit('/').next().unwrap_or(file_name);
+    name.split('.').skip(1).any(|extension| extension == "spdx")
+}
+
+/// Make an SPDX ID safe, replacing any chars other than alphanumeric chars,
+/// '.' or '-' with '-', as SPDX IDs must only contain those.
+pub(crate) fn spdx_id_safe(id: &str) -> String {
+    id.replace(
+        |c: char| !(c.is_alphanumeric() || c == '-' || c == '.'),
+        "-",
+    )
+}
+
+// Get the package URL of a package, naming the registry it's from unless
+// that's crates.io. Local packages can't be fetched, so have none.
+fn purl(package: &cargo_metadata::Package) -> Option<Purl> {
+    let source = package.source.as_ref()?;
+    let purl = Purl::cargo(&package.name, &package.version.to_string());
+    if source.is_crates_io() {
+        return Some(purl);
+    }
+    let registry = ["registry+", "sparse+"]
+        .iter()
+        .find_map(|prefix| source.repr.strip_prefix(prefix));
+    Some(match registry {
+        Some(url) => purl.with_qualifier("repository_url", url),
+        None => purl,
+    })
+}
+
+impl Package {
+    /// Get the package URL of the package, if it has a valid one.
+    pub fn purl(&self) -> Option<Purl> {
+        let locator = &self
+            .external_refs
+            .iter()
+            .flatten()
+            .find(|external_ref| external_ref.reference_type == "purl")?
+            .reference_locator;
+        match locator.parse() {
+            Ok(purl) => Some(purl),
+            Err(error) => {
+                log::warn!(target: "cargo_spdx", "ignoring the purl of {}: {}", self.name, error);
+                None
+            }
+        }
+    }
+
+    /// Tell the package apart from others of the same name and version.
+    ///
+    /// Its SPDX ID is suffixed with the start of the SHA256 of where it's from,
+    /// its source for downloaded packages and its manifest path for local ones.
+    pub fn disambiguate(&mut self, package: &cargo_metadata::Package) {
+        let origin = match &package.source {
+            Some(source) => source.repr.clone(),
+            None => package.manifest_path.to_string(),
+        };
+        let hash = hex::encode(Sha256::digest(origin.as_bytes()));
+        self.spdxid = format!("{}-{}", self.spdxid, &hash[..8]);
+    }
+
+    /// Note the features the package was built with, so it's clear which of
+    /// its conditional code was compiled. Nothing is noted for packages
+    /// without features.
+    pub fn note_features(&mut self, package: &cargo_metadata::Package, enabled: &[String]) {
+        let built_with = match enabled {
+            [] if package.features.is_empty() => return,
+            [] => "Built with no features.".to_string(),
+            [feature] => format!("Built with the `{}` feature.", feature),
+            features => format!(
+                "Built with the {} features.",
+                features
+                    .iter()
+                    .map(|feature| format!("`{}`", feature))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        self.comment = Some(match self.comment.take() {
+            Some(comment) => format!("{} {}", comment, built_with),
+            None => built_with,
+        });
+    }
+
+    /// Record the `.crate` archive the package comes from, by its SHA256.
+    pub fn set_archive(&mut self, sha256: &str) {
+        self.package_file_name = Some(format!(
+            "{}-{}.crate",
+            self.name,
+            self.version_info.as_deref().unwrap_or_default()
+        ));
+        self.checksums = Some(vec![PackageChecksum {
+            algorithm: Algorithm::Sha256,
+            checksum_value: sha256.to_string(),
+        }]);
+    }
+
+    /// Record that the package's files were analyzed, with their verification code.
+    ///
+    /// The code is the SHA1 of the sorted SHA1s of the files, per section 4.7 of
+    /// the SPDX specification. SPDX documents among the files, such as an SBOM
+    /// kept in the package, are left out, as the code can't cover itself.
+    pub fn set_verification_code(&mut self, files: &[File]) {
+        let (excluded, included): (Vec<&File>, Vec<&File>) = files
+            .iter()
+            .partition(|file| is_spdx_document(&file.file_name));
+        let mut sha1s: Vec<String> = included
+            .iter()
+            .filter_map(|file| {
+                file.checksums
+                    .iter()
+                    .flatten()
+                    .find(|checksum| matches!(checksum.algorithm, Algorithm::Sha1))
+                    .map(|checksum| checksum.checksum_value.to_lowercase())
+            })
+            .collect();
+        sha1s.sort_unstable();
+
+        let mut hasher = Sha1::new();
+        for sha1 in &sha1s {
+            hasher.update(sha1.as_bytes());
+        }
+        let excluded: Vec<String> = excluded.iter().map(|file| file.file_name.clone()).collect();
+        self.files_analyzed = Some(true);
+        self.package_verification_code = Some(PackageVerificationCode {
+            package_verification_code_excluded_files: excluded.is_empty().not().then(|| excluded),
+            package_verification_code_value: hex::encode(hasher.finalize()),
+        });
+    }
+
+    /// Describe a system tool run while building, such as `cmake`.
+    ///
+    /// Nothing more than the name is known, as the tool comes from the system
+    /// rather than from cargo.
+    pub fn build_tool(name: &str) -> Package {
+        let spdxid = spdx_id_safe(&format!("SPDXRef-BuildTool-{}", name));
         Package {
-            name: package.name.to_string(),
-            spdxid: format!("SPDXRef-{}-{}", package.name, package.version),
-            version_info: Some(package.version.to_string()),
+            name: name.to_string(),
+            spdxid,
+            version_info: None,
             package_file_name: None,
             supplier: None,
             originator: None,
             download_location: NOASSERTION.to_string(),
-            files_analyzed: None,
+            files_analyzed: Some(false),
             package_verification_code: None,
             checksums: None,
-            homepage: package.homepage.clone(),
+            homepage: None,
             source_info: None,
-            license_concluded: NOASSERTION.to_string(),
-            license_declared: NOASSERTION.to_string(),
-            copyright_text: NOASSERTION.to_string(),
+            license_concluded: Some(NOASSERTION.to_string()),
+            license_declared: Some(NOASSERTION.to_string()),
+            copyright_text: Some(NOASSERTION.to_string()),
             description: None,
-            comment: None,
-            external_refs: Some(vec![ExternalRef {
-                reference_category: ReferenceCategory::PackageManager,
-                reference_type: "purl".to_string(),
-                reference_locator: format!("pkg:cargo/{}@{}", package.name, package.version),
-                comment: None,
-            }]),
+            comment: Some(
+                "System tool run by build scripts, detected from their output.".to_string(),
+            ),
+            external_refs: None,
+            annotations: None,
+            attribution_texts: None,
+            has_files: None,
+            license_comments: None,
+            license_info_from_files: None,
+            summary: None,
+            extensions: Extensions::default(),
+        }
+    }
+
+    /// Describe the native library a crate declares it links with its `links` key.
+    ///
+    /// The library itself is never found, so its version is only known when
+    /// the crate's build script reports it.
+    pub fn native_library(
+        links: &str,
+        declared_by: &cargo_metadata::Package,
+        version: Option<String>,
+    ) -> Package {
+        let spdxid = spdx_id_safe(&format!("SPDXRef-NativeLibrary-{}", links));
+        Package {
+            name: links.to_string(),
+            spdxid,
+            version_info: version,
+            package_file_name: None,
+            supplier: None,
+            originator: None,
+            download_location: NOASSERTION.to_string(),
+            files_analyzed: Some(false),
+            package_verification_code: None,
+            checksums: None,
+            homepage: None,
+            source_info: None,
+            license_concluded: Some(NOASSERTION.to_string()),
+            license_declared: Some(NOASSERTION.to_string()),
+            copyright_text: Some(NOASSERTION.to_string()),
+            description: None,
+            comment: Some(format!(
+                "Native library declared by the `links` key of {} {}.",
+                declared_by.name, declared_by.version
+            )),
+            external_refs: None,
+            annotations: None,
+            attribution_texts: None,
+            has_files: None,
+            license_comments: None,
+            license_info_from_files: None,
+            summary: None,
+            extensions: Extensions::default(),
+        }
+    }
+
+    /// Describe a workspace as a whole, as a package containing its members.
+    ///
+    /// Workspaces aren't published, so only the name of their directory is known.
+    pub fn workspace(name: &str) -> Package {
+        let spdxid = spdx_id_safe(&format!("SPDXRef-Workspace-{}", name));
+        Package {
+            name: name.to_string(),
+            spdxid,
+            version_info: None,
+            package_file_name: None,
+            supplier: None,
+            originator: None,
+            download_location: NOASSERTION.to_string(),
+            files_analyzed: Some(false),
+            package_verification_code: None,
+            checksums: None,
+            homepage: None,
+            source_info: None,
+            license_concluded: Some(NOASSERTION.to_string()),
+            license_declared: Some(NOASSERTION.to_string()),
+            copyright_text: Some(NOASSERTION.to_string()),
+            description: None,
+            comment: Some("Cargo workspace, containing its members.".to_string()),
+            external_refs: None,
             annotations: None,
             attribution_texts: None,
             has_files: None,
             license_comments: None,
             license_info_from_files: None,
             summary: None,
+            extensions: Extensions::default(),
+        }
+    }
+}
+
+impl Document {
+    /// Sort the packages, files, snippets, and relationships by SPDX ID.
+    ///
+    /// They're gathered in whatever order cargo reports them in, which can
+    /// change between runs, so sorting them makes the same inputs produce
+    /// byte-for-byte identical documents.
+    pub fn sort_elements(&mut self) {
+        if let Some(packages) = &mut self.packages {
+            packages.sort_by(|a, b| a.spdxid.cmp(&b.spdxid));
+            for package in packages {
+                if let Some(has_files) = &mut package.has_files {
+                    has_files.sort();
+                }
+            }
+        }
+        if let Some(files) = &mut self.files {
+            files.sort_by(|a, b| a.spdxid.cmp(&b.spdxid));
+        }
+        if let Some(snippets) = &mut self.snippets {
+            snippets.sort_by(|a, b| a.spdxid.cmp(&b.spdxid));
+        }
+        if let Some(described) = &mut self.document_describes {
+            described.sort();
+        }
+        if let Some(infos) = &mut self.has_extracted_licensing_infos {
+            infos.sort_by(|a, b| a.license_id.cmp(&b.license_id));
+        }
+        if let Some(relationships) = &mut self.relationships {
+            relationships.sort_by(|a, b| {
+                (
+                    &a.spdx_element_id,
+                    &a.related_spdx_element,
+                    &a.relationship_type,
+                )
+                    .cmp(&(
+                        &b.spdx_element_id,
+                        &b.related_spdx_element,
+                        &b.relationship_type,
+                    ))
+            });
+        }
+    }
+
+    /// Record that the document supersedes an earlier one, such as the SBOM of the previous release.
+    ///
+    /// SPDX has no relationship for one document replacing another, so the
+    /// document is related to the earlier one as DESCENDANT_OF, with a comment
+    /// saying it supersedes it.
+    pub fn supersede(&mut self, previous: &Document, reference: ExternalDocumentReference) {
+        let id = self.add_external_reference(reference);
+        self.relationships
+            .get_or_insert_with(Vec::new)
+            .push(Relationship {
+                comment: Some(format!("Supersedes {}.", previous.document_name)),
+                related_spdx_element: format!("{}:{}", id, previous.spdx_identifier),
+                relationship_type: RelationshipType::DescendantOf,
+                spdx_element_id: self.spdx_identifier.to_string(),
+            });
+    }
+
+    /// Refer to another document, returning the ID it's referred to by.
+    ///
+    /// A document already referred to keeps its reference. Otherwise, if
+    /// another document is referred to by the same ID, e.g. as they have the
+    /// same name, a number is appended to the new reference's ID.
+    pub fn add_external_reference(&mut self, mut reference: ExternalDocumentReference) -> IdString {
+        let references = self
+            .external_document_references
+            .get_or_insert_with(Vec::new);
+        if let Some(existing) = references.iter().find(|existing| {
+            existing.document_uri == reference.document_uri
+                && existing.checksum.checksum_value == reference.checksum.checksum_value
+        }) {
+            return existing.id_string.clone();
+        }
+
+        let base = reference.id_string.0.clone();
+        let mut suffix = 1;
+        while references
+            .iter()
+            .any(|existing| existing.id_string == reference.id_string)
+        {
+            suffix += 1;
+            reference.id_string = IdString(format!("{}-{}", base, suffix));
+        }
+        let id = reference.id_string.clone();
+        references.push(reference);
+        id
+    }
+
+    /// The first creator of the document, who annotations it makes are by.
+    pub fn annotator(&self) -> String {
+        self.creation_info
+            .creators
+            .iter()
+            .flatten()
+            .next()
+            .map(ToString::to_string)
+            .unwrap_or_default()
+    }
+
+    /// Annotate the document as a whole, as its first creator, when it was created.
+    pub fn annotate(&mut self, annotation_type: AnnotationType, comment: &str) {
+        let annotator = self.annotator();
+        self.annotations
+            .get_or_insert_with(Vec::new)
+            .push(DocumentAnnotation {
+                annotation_date: self.creation_info.created.to_string(),
+                annotation_type,
+                annotator,
+                comment: comment.to_string(),
+            });
+    }
+
+    /// Check no two packages, files, or snippets have the same SPDX ID.
+    ///
+    /// Relationships to an element with a duplicate ID can't be told apart, so
+    /// such a document is invalid.
+    pub fn check_unique_ids(&self) -> Result<()> {
+        let ids = self
+            .packages
+            .iter()
+            .flatten()
+            .map(|package| &package.spdxid)
+            .chain(self.files.iter().flatten().map(|file| &file.spdxid))
+            .chain(
+                self.snippets
+                    .iter()
+                    .flatten()
+                    .map(|snippet| &snippet.spdxid),
+            );
+        let mut seen = BTreeSet::new();
+        for id in ids {
+            if seen.insert(id).not() {
+                return Err(anyhow!(
+                    "more than one element of the document has the SPDX ID {}, e.g. as their names only differ in chars SPDX IDs can't contain, like `foo_bar` and `foo-bar`",
+                    id
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the packages whose names match any of `patterns`, where `*`
+    /// matches any run of characters.
+    pub fn exclude_packages(&mut self, patterns: &[String]) -> Result<()> {
+        if patterns.is_empty() {
+            return Ok(());
+        }
+        let removed = self.remove_packages(|package| {
+            patterns
+                .iter()
+                .any(|pattern| matches_pattern(pattern, &package.name))
+        })?;
+        for pattern in patterns {
+            if removed
+                .iter()
+                .any(|name| matches_pattern(pattern, name))
+                .not()
+            {
+                log::warn!(target: "cargo_spdx", "no package matches the excluded pattern {}", pattern);
+            }
+        }
+        log::info!(target: "cargo_spdx", "excluded {} package(s)", removed.len());
+        Ok(())
+    }
+
+    /// Remove the packages `excluded` picks, along with the files only they
+    /// contain and any relationships to them.
+    ///
+    /// Returns the names of the packages removed. Everything the document
+    /// describes can't be removed, as it would describe nothing.
+    pub fn remove_packages(&mut self, excluded: impl Fn(&Package) -> bool) -> Result<Vec<String>> {
+        let (removed, kept): (Vec<&Package>, Vec<&Package>) = self
+            .packages
+            .iter()
+            .flatten()
+            .partition(|package| excluded(package));
+        if removed.is_empty() {
+            return Ok(Vec::new());
+        }
+        let document_id = self.spdx_identifier.to_string();
+        let described: BTreeSet<&String> = self
+            .relationships
+            .iter()
+            .flatten()
+            .filter(|relationship| {
+                relationship.relationship_type == RelationshipType::Describes
+                    && relationship.spdx_element_id == document_id
+            })
+            .map(|relationship| &relationship.related_spdx_element)
+            .chain(self.document_describes.iter().flatten())
+            .collect();
+        let removes = |spdxid: &String| removed.iter().any(|package| &package.spdxid == spdxid);
+        if described.is_empty().not() && described.iter().all(|spdxid| removes(spdxid)) {
+            let names: Vec<&str> = removed
+                .iter()
+                .filter(|package| described.contains(&package.spdxid))
+                .map(|package| package.name.as_str())
+                .collect();
+            return Err(anyhow!(
+                "can't exclude {}, as the document describes nothing else",
+                names.join(", ")
+            ));
+        }
+
+        let names = removed.iter().map(|package| package.name.clone()).collect();
+        let mut ids: BTreeSet<String> = removed
+            .iter()
+            .map(|package| package.spdxid.clone())
+            .collect();
+        let kept: BTreeSet<&str> = kept.iter().map(|package| package.spdxid.as_str()).collect();
+
+        // Files are kept while any package left still contains them.
+        let contained = |packages: &dyn Fn(&str) -> bool| -> BTreeSet<String> {
+            self.relationships
+                .iter()
+                .flatten()
+                .filter(|relationship| {
+                    relationship.relationship_type == RelationshipType::Contains
+                        && packages(&relationship.spdx_element_id)
+                })
+                .map(|relationship| relationship.related_spdx_element.clone())
+                .chain(
+                    self.packages
+                        .iter()
+                        .flatten()
+                        .filter(|package| packages(&package.spdxid))
+                        .flat_map(|package| package.has_files.iter().flatten().cloned()),
+                )
+                .collect()
+        };
+        let still_contained = contained(&|spdxid| kept.contains(spdxid));
+        let orphaned: Vec<String> = contained(&|spdxid| ids.contains(spdxid))
+            .into_iter()
+            .filter(|spdxid| still_contained.contains(spdxid).not())
+            .collect();
+        ids.extend(orphaned);
+
+        if let Some(packages) = &mut self.packages {
+            packages.retain(|package| ids.contains(&package.spdxid).not());
+        }
+        if let Some(files) = &mut self.files {
+            files.retain(|file| ids.contains(&file.spdxid).not());
+        }
+        if let Some(described) = &mut self.document_describes {
+            described.retain(|spdxid| ids.contains(spdxid).not());
+        }
+        if let Some(relationships) = &mut self.relationships {
+            relationships.retain(|relationship| {
+                ids.contains(&relationship.spdx_element_id).not()
+                    && ids.contains(&relationship.related_spdx_element).not()
+            });
+        }
+        Ok(names)
+    }
+
+    /// Keep `documentDescribes` and the DESCRIBES relationships of the document in step.
+    ///
+    /// Some tools only read one or the other, and key-value documents can only
+    /// say what they describe with relationships, so each is filled in from the other.
+    pub fn sync_described(&mut self) {
+        let document_id = self.spdx_identifier.to_string();
+        let describes = |relationship: &Relationship| match relationship.relationship_type {
+            RelationshipType::Describes if relationship.spdx_element_id == document_id => {
+                Some(relationship.related_spdx_element.clone())
+            }
+            RelationshipType::DescribedBy if relationship.related_spdx_element == document_id => {
+                Some(relationship.spdx_element_id.clone())
+            }
+            _ => None,
+        };
+
+        let mut described: Vec<String> = self
+            .relationships
+            .iter()
+            .flatten()
+            .filter_map(describes)
+            .collect();
+        for spdxid in self.document_describes.take().into_iter().flatten() {
+            if described.contains(&spdxid).not() {
+                self.relationships
+                    .get_or_insert_with(Vec::new)
+                    .push(Relationship {
+                        comment: None,
+                        related_spdx_element: spdxid.clone(),
+                        relationship_type: RelationshipType::Describes,
+                        spdx_element_id: document_id.clone(),
+                    });
+                described.push(spdxid);
+            }
+        }
+
+        if described.is_empty().not() {
+            self.document_describes = Some(described);
+        }
+    }
+
+    /// Keep the `hasFiles` of each package and its CONTAINS relationships to files in step.
+    ///
+    /// As with what the document describes, some tools only read one or the
+    /// other, so each is filled in from the other.
+    pub fn sync_has_files(&mut self) {
+        let files: BTreeSet<String> = self
+            .files
+            .iter()
+            .flatten()
+            .map(|file| file.spdxid.clone())
+            .collect();
+        let relationships = self.relationships.get_or_insert_with(Vec::new);
+
+        for package in self.packages.iter_mut().flatten() {
+            let contains = |relationship: &Relationship| match relationship.relationship_type {
+                RelationshipType::Contains if relationship.spdx_element_id == package.spdxid => {
+                    Some(relationship.related_spdx_element.clone())
+                }
+                RelationshipType::ContainedBy
+                    if relationship.related_spdx_element == package.spdxid =>
+                {
+                    Some(relationship.spdx_element_id.clone())
+                }
+                _ => None,
+            };
+
+            let mut has_files: Vec<String> = relationships
+                .iter()
+                .filter_map(contains)
+                .filter(|spdxid| files.contains(spdxid))
+                .collect();
+            for spdxid in package.has_files.take().into_iter().flatten() {
+                if has_files.contains(&spdxid).not() {
+                    relationships.push(Relationship {
+                        comment: None,
+                        related_spdx_element: spdxid.clone(),
+                        relationship_type: RelationshipType::Contains,
+                        spdx_element_id: package.spdxid.clone(),
+                    });
+                    has_files.push(spdxid);
+                }
+            }
+
+            if has_files.is_empty().not() {
+                package.has_files = Some(has_files);
+            }
+        }
+
+        if relationships.is_empty() {
+            self.relationships = None;
+        }
+    }
+
+    /// Fill in the licenses found in the files of each package from those its files list.
+    ///
+    /// Packages which already list their licenses from files are left as they
+    /// are. Those whose files were analyzed but have no license tags are given
+    /// `NONE`.
+    pub fn sync_license_info_from_files(&mut self) {
+        let files: HashMap<&str, &File> = self
+            .files
+            .iter()
+            .flatten()
+            .map(|file| (file.spdxid.as_str(), file))
+            .collect();
+
+        for package in self.packages.iter_mut().flatten() {
+            if package.license_info_from_files.is_some() {
+                continue;
+            }
+            let licenses: BTreeSet<&String> = self
+                .relationships
+                .iter()
+                .flatten()
+                .filter(|relationship| {
+                    relationship.relationship_type == RelationshipType::Contains
+                        && relationship.spdx_element_id == package.spdxid
+                })
+                .filter_map(|relationship| files.get(relationship.related_spdx_element.as_str()))
+                .flat_map(|file| file.license_info_in_files.iter().flatten())
+                .filter(|license| *license != NONE && *license != NOASSERTION)
+                .collect();
+            if licenses.is_empty().not() {
+                package.license_info_from_files = Some(licenses.into_iter().cloned().collect());
+            } else if package.files_analyzed == Some(true) {
+                package.license_info_from_files = Some(vec![NONE.to_string()]);
+            }
+        }
+    }
+
+    /// Give fields known to be empty as `NOASSERTION` rather than `NONE`, unless `asserted`.
+    ///
+    /// `NONE` is a claim there's nothing there, such as no copyright statement
+    /// in any of a package's files, which not everyone producing SBOMs wants to
+    /// make. The fields are left out instead where the SPDX version allows.
+    pub fn retract_none(&mut self, asserted: impl Fn(&str) -> bool) {
+        let optional = self.spdx_version >= SpdxVersion::V2_3;
+        for package in self.packages.iter_mut().flatten() {
+            if package.download_location == NONE && asserted("downloadLocation").not() {
+                package.download_location = NOASSERTION.to_string();
+            }
+            if package.copyright_text.as_deref() == Some(NONE) && asserted("copyrightText").not() {
+                package.copyright_text = optional.not().then(|| NOASSERTION.to_string());
+            }
+            if package.license_info_from_files.as_deref() == Some(&[NONE.to_string()][..])
+                && asserted("licenseInfoFromFiles").not()
+            {
+                package.license_info_from_files =
+                    optional.not().then(|| vec![NOASSERTION.to_string()]);
+            }
+        }
+    }
+
+    /// Adjust the document to the rules of the SPDX version it declares.
+    ///
+    /// SPDX 2.3 made the license and copyright fields of packages, files, and
+    /// snippets optional, so they're left out rather than given as `NOASSERTION`. Earlier
+    /// versions require them, and lack the relationship types, reference
+    /// categories, and checksum algorithms 2.3 added.
+    pub fn conform_to_version(&mut self) {
+        let version = self.spdx_version;
+        let required = version < SpdxVersion::V2_3;
+        let conform = |field: &mut Option<String>| {
+            if required {
+                field.get_or_insert_with(|| NOASSERTION.to_string());
+            } else if field.as_deref() == Some(NOASSERTION) {
+                *field = None;
+            }
+        };
+
+        for package in self.packages.iter_mut().flatten() {
+            conform(&mut package.license_concluded);
+            conform(&mut package.license_declared);
+            conform(&mut package.copyright_text);
+            if let Some(checksums) = &mut package.checksums {
+                checksums.retain(|checksum| checksum.algorithm.is_defined_in(version));
+            }
+            for external_ref in package.external_refs.iter_mut().flatten() {
+                if external_ref.reference_category.is_defined_in(version).not() {
+                    external_ref.reference_category = ReferenceCategory::Other;
+                }
+            }
+        }
+
+        for file in self.files.iter_mut().flatten() {
+            conform(&mut file.license_concluded);
+            conform(&mut file.copyright_text);
+            if let Some(checksums) = &mut file.checksums {
+                checksums.retain(|checksum| checksum.algorithm.is_defined_in(version));
+            }
+        }
+
+        for snippet in self.snippets.iter_mut().flatten() {
+            conform(&mut snippet.license_concluded);
+            conform(&mut snippet.copyright_text);
+        }
+
+        // Keep the meaning of newer relationships by naming them in the comment.
+        for relationship in self.relationships.iter_mut().flatten() {
+            if relationship.relationship_type.is_defined_in(version).not() {
+                let name = serde_json::to_value(&relationship.relationship_type)
+                    .ok()
+                    .and_then(|name| name.as_str().map(ToOwned::to_owned))
+                    .unwrap_or_default();
+                relationship.comment.get_or_insert(name);
+                relationship.relationship_type = RelationshipType::Other;
+            }
+        }
+    }
+}
+
+impl ExternalDocumentReference {
+    /// Refer to another document, named after it and identified by the SHA1 of its contents.
+    pub fn to(doc: &Document, sha1: String) -> ExternalDocumentReference {
+        let id = spdx_id_safe(&format!("DocumentRef-{}", doc.document_name));
+        ExternalDocumentReference {
+            id_string: IdString(id),
+            document_uri: doc.document_namespace.clone(),
+            checksum: Checksum {
+                algorithm: Algorithm::Sha1,
+                checksum_value: sha1,
+            },
+        }
+    }
+}
+
+impl FileType {
+    /// Guess the type of a file from its name.
+    ///
+    /// Files named like license and readme files, e.g. `LICENSE-MIT`, are
+    /// recognized without an extension, and anything unrecognized is `OTHER`.
+    pub fn of(path: &Utf8Path) -> FileType {
+        let name = path.file_name().unwrap_or_default();
+        if is_spdx_document(name) {
+            return FileType::Spdx;
+        }
+        let stem = name.split('.').next().unwrap_or_default().to_uppercase();
+        if [
+            "LICENSE",
+            "LICENCE",
+            "COPYING",
+            "COPYRIGHT",
+            "NOTICE",
+            "AUTHORS",
+        ]
+        .iter()
+        .any(|prefix| stem.starts_with(prefix))
+        {
+            return FileType::Text;
+        }
+        if ["README", "CHANGELOG", "CHANGES", "CONTRIBUTING"]
+            .iter()
+            .any(|prefix| stem.starts_with(prefix))
+        {
+            return FileType::Documentation;
+        }
+
+        let extension = path.extension().unwrap_or_default().to_lowercase();
+        match extension.as_str() {
+            "rs" | "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "s" | "asm" | "py" | "sh" | "js"
+            | "ts" | "go" | "java" | "proto" | "pest" | "lalrpop" | "wgsl" | "glsl" | "hlsl"
+            | "metal" | "cl" | "ld" => FileType::Source,
+            "md" | "markdown" | "rst" | "adoc" | "org" | "html" | "htm" | "tex" | "pdf" => {
+                FileType::Documentation
+            }
+            "png" | "jpg" | "jpeg" | "gif" | "svg" | "ico" | "bmp" | "webp" | "tif" | "tiff" => {
+                FileType::Image
+            }
+            "a" | "so" | "dll" | "dylib" | "lib" | "o" | "obj" | "rlib" | "exe" | "wasm" => {
+                FileType::Binary
+            }
+            "tar" | "gz" | "tgz" | "zip" | "xz" | "zst" | "bz2" | "7z" | "crate" | "jar" => {
+                FileType::Archive
+            }
+            "mp3" | "wav" | "ogg" | "flac" => FileType::Audio,
+            "mp4" | "webm" | "mov" | "avi" | "mkv" => FileType::Video,
+            "txt" | "toml" | "lock" | "json" | "yaml" | "yml" | "xml" | "csv" | "ini" | "cfg" => {
+                FileType::Text
+            }
+            _ => FileType::Other,
         }
     }
 }
@@ -82,9 +973,9 @@ impl File {
     /// * `root` - Root of the package. The file name in the SPDX entry will be relative to this
     /// * `file_type` - SPDX File type
     /// * `package_name` - Optional. If present will be included in the SPDXID for the File,
-    /// to enable unique SPDXIDs
+    ///   to enable unique SPDXIDs
     /// * `package_version` - Optional. If present will be included in the SPDXID for the File,
-    /// to enable unique SPDXIDs
+    ///   to enable unique SPDXIDs
     pub fn try_from_file(
         path: &Utf8Path,
         root: &Utf8Path,
@@ -93,32 +984,35 @@ impl File {
         package_version: Option<&str>,
     ) -> Result<File> {
         let file_name = pathdiff::diff_utf8_paths(path, root).unwrap();
-        let spdxid = format!(
+        let spdxid = spdx_id_safe(&format!(
             "SPDXRef-File-{}{}{}",
             package_name.map(|n| format!("{}-", n)).unwrap_or_default(),
             package_version
                 .map(|v| format!("{}-", v))
                 .unwrap_or_default(),
             file_name
-        )
-        // SPDX IDs must only container alphanumeric chars, '.' or '-'
-        .replace(
-            |c: char| !(c.is_alphanumeric() || c == '-' || c == '.'),
-            "-",
-        );
+        ));
+        // Only text can carry `SPDX-License-Identifier:` tags.
+        let license_info_in_files = match file_type {
+            FileType::Source | FileType::Text | FileType::Documentation => {
+                let licenses = tagged_licenses(&String::from_utf8_lossy(&fs::read(path)?));
+                licenses.is_empty().not().then(|| licenses)
+            }
+            _ => None,
+        };
         Ok(File {
             annotations: None,
             attribution_texts: None,
             checksums: Some(calculate_checksums(path)?),
             comment: None,
-            copyright_text: NOASSERTION.to_string(),
+            copyright_text: Some(NOASSERTION.to_string()),
             file_contributors: None,
             file_dependencies: None,
             file_name: file_name.to_string(),
             file_types: Some(vec![file_type]),
             license_comments: None,
-            license_concluded: NOASSERTION.to_string(),
-            license_info_in_files: None,
+            license_concluded: Some(NOASSERTION.to_string()),
+            license_info_in_files,
             notice_text: None,
             spdxid,
         })
@@ -132,20 +1026,628 @@ fn calculate_checksums(path: &Utf8Path) -> Result<Vec<FileChecksum>> {
     let mut file =
         fs::File::open(path).context(format!("Failed to calculate checksum for {}", path))?;
     let mut sha256 = Sha256::new();
-    let sha1 = Sha1::new();
-    io::copy(&mut file, &mut sha256)?;
+    let mut sha1 = Sha1::new();
+    let mut buffer = [0; 8192];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        sha1.update(&buffer[..read]);
+        sha256.update(&buffer[..read]);
+    }
     let sha256_hash = sha256.finalize();
     let sha1_hash = sha1.finalize();
     let output = vec![
         FileChecksum {
             algorithm: Algorithm::Sha1,
-            checksum_value: hex::encode(&sha1_hash),
+            checksum_value: hex::encode(sha1_hash),
         },
         FileChecksum {
             algorithm: Algorithm::Sha256,
-            checksum_value: hex::encode(&sha256_hash),
+            checksum_value: hex::encode(sha256_hash),
         },
     ];
     log::debug!("finished calculating checksums for {}", path);
     Ok(output)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CreationInfoBuilder, Creator, Document, DocumentBuilder, Extensions,
+        ExternalDocumentReference, File, FileType, Package, Relationship, RelationshipType,
+        SpdxVersion,
+    };
+    use cargo_metadata::camino::Utf8Path;
+    use serde_json::json;
+    use sha1::{Digest, Sha1};
+
+    fn document_with_extensions() -> Document {
+        let mut extensions = Extensions::default();
+        extensions
+            .0
+            .insert("x-acme-tracking-id".to_string(), json!("REL-1234"));
+
+        DocumentBuilder::default()
+            .document_name("test.spdx.json")
+            .try_document_namespace("https://example.com/test")
+            .unwrap()
+            .creation_info(
+                CreationInfoBuilder::default()
+                    .creators(vec![Creator::tool("cargo-spdx 0.1.0")])
+                    .build()
+                    .unwrap(),
+            )
+            .extensions(extensions)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_extensions_round_trip() {
+        let doc = document_with_extensions();
+
+        let json = serde_json::to_string(&doc).unwrap();
+        let parsed: Document = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            parsed.extensions.0.get("x-acme-tracking-id"),
+            Some(&json!("REL-1234"))
+        );
+
+        #[cfg(feature = "yaml")]
+        {
+            let yaml = serde_yaml::to_string(&doc).unwrap();
+            let parsed: Document = serde_yaml::from_str(&yaml).unwrap();
+            assert_eq!(
+                parsed.extensions.0.get("x-acme-tracking-id"),
+                Some(&json!("REL-1234"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_fields_are_not_extensions() {
+        let mut json = serde_json::to_value(document_with_extensions()).unwrap();
+        json["unknownField"] = json!(true);
+
+        let parsed: Document = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.extensions.0.len(), 1);
+    }
+
+    #[test]
+    fn test_set_verification_code() {
+        let cargo_toml = File::try_from_file(
+            Utf8Path::new("Cargo.toml"),
+            Utf8Path::new(""),
+            FileType::Text,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            cargo_toml.checksums.as_ref().unwrap()[0].checksum_value,
+            hex::encode(Sha1::digest(std::fs::read("Cargo.toml").unwrap()))
+        );
+
+        let file = |name: &str, sha1: &str| {
+            let mut file = cargo_toml.clone();
+            file.file_name = name.to_string();
+            file.checksums.as_mut().unwrap()[0].checksum_value = sha1.to_string();
+            file
+        };
+        let files = [
+            file("src/lib.rs", "E9D71F5EE7C92D6DC9E92FFDAD17B8BD49418F98"),
+            file("foo.spdx.json", "0000000000000000000000000000000000000000"),
+            file("Cargo.toml", "86f7e437faa5a7fce15d1ddcb9eaeaea377667b8"),
+        ];
+
+        let mut package = Package::build_tool("foo");
+        package.set_verification_code(&files);
+        assert_eq!(package.files_analyzed, Some(true));
+        let code = package.package_verification_code.unwrap();
+        assert_eq!(
+            code.package_verification_code_value,
+            "5463504435e4dbf2b93a3a8a00ca78e36ea40e24"
+        );
+        assert_eq!(
+            code.package_verification_code_excluded_files.unwrap(),
+            ["foo.spdx.json"]
+        );
+    }
+
+    #[test]
+    fn test_conform_to_version() {
+        let file = File::try_from_file(
+            Utf8Path::new("Cargo.toml"),
+            Utf8Path::new(""),
+            FileType::Text,
+            None,
+            None,
+        )
+        .unwrap();
+        let relationship = Relationship {
+            comment: None,
+            related_spdx_element: "SPDXRef-DOCUMENT".to_string(),
+            relationship_type: RelationshipType::SpecificationFor,
+            spdx_element_id: file.spdxid.clone(),
+        };
+        let mut doc = document_with_extensions();
+        doc.files = Some(vec![file]);
+        doc.relationships = Some(vec![relationship]);
+
+        doc.spdx_version = SpdxVersion::V2_3;
+        doc.conform_to_version();
+        let json = serde_json::to_value(&doc).unwrap();
+        assert!(json["files"][0].get("licenseConcluded").is_none());
+        assert_eq!(
+            json["relationships"][0]["relationshipType"],
+            "SPECIFICATION_FOR"
+        );
+
+        doc.spdx_version = SpdxVersion::V2_2;
+        doc.conform_to_version();
+        let json = serde_json::to_value(&doc).unwrap();
+        assert_eq!(json["files"][0]["licenseConcluded"], "NOASSERTION");
+        assert_eq!(json["relationships"][0]["relationshipType"], "OTHER");
+        assert_eq!(json["relationships"][0]["comment"], "SPECIFICATION_FOR");
+    }
+
+    #[test]
+    fn test_sync_described() {
+        let mut doc = document_with_extensions();
+        doc.sync_described();
+        assert!(doc.relationships.is_none());
+        assert!(doc.document_describes.is_none());
+
+        doc.relationships = Some(vec![Relationship {
+            comment: None,
+            related_spdx_element: "SPDXRef-foo".to_string(),
+            relationship_type: RelationshipType::Describes,
+            spdx_element_id: "SPDXRef-DOCUMENT".to_string(),
+        }]);
+        doc.document_describes = Some(vec!["SPDXRef-bar".to_string(), "SPDXRef-foo".to_string()]);
+        doc.sync_described();
+        assert_eq!(
+            doc.document_describes.unwrap(),
+            ["SPDXRef-foo".to_string(), "SPDXRef-bar".to_string()]
+        );
+        let relationships = doc.relationships.unwrap();
+        assert_eq!(relationships.len(), 2);
+        assert_eq!(relationships[1].related_spdx_element, "SPDXRef-bar");
+        assert_eq!(relationships[1].spdx_element_id, "SPDXRef-DOCUMENT");
+    }
+
+    #[test]
+    fn test_add_external_reference() {
+        let mut other = document_with_extensions();
+        other.document_name = "foo.spdx.json".to_string().into();
+        let reference =
+            |doc: &Document, sha1: &str| ExternalDocumentReference::to(doc, sha1.to_string());
+        let mut doc = document_with_extensions();
+
+        let first = doc.add_external_reference(reference(&other, "aa"));
+        assert_eq!(first.0, "DocumentRef-foo.spdx.json");
+        assert_eq!(doc.add_external_reference(reference(&other, "aa")), first);
+
+        // Another document of the same name.
+        other.document_namespace = "https://example.com/other".parse().unwrap();
+        let second = doc.add_external_reference(reference(&other, "bb"));
+        assert_eq!(second.0, "DocumentRef-foo.spdx.json-2");
+        assert_eq!(doc.external_document_references.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_supersede() {
+        let mut previous = document_with_extensions();
+        previous.document_name = "foo-1.0.0.spdx.json".to_string().into();
+        let mut doc = document_with_extensions();
+        doc.supersede(
+            &previous,
+            ExternalDocumentReference::to(
+                &previous,
+                "da39a3ee5e6b4b0d3255bfef95601890afd80709".to_string(),
+            ),
+        );
+
+        let reference = &doc.external_document_references.unwrap()[0];
+        assert_eq!(reference.id_string.0, "DocumentRef-foo-1.0.0.spdx.json");
+        let relationship = &doc.relationships.unwrap()[0];
+        assert_eq!(
+            relationship.relationship_type,
+            RelationshipType::DescendantOf
+        );
+        assert_eq!(
+            relationship.related_spdx_element,
+            "DocumentRef-foo-1.0.0.spdx.json:SPDXRef-DOCUMENT"
+        );
+        assert_eq!(relationship.spdx_element_id, "SPDXRef-DOCUMENT");
+        assert_eq!(
+            relationship.comment.as_deref(),
+            Some("Supersedes foo-1.0.0.spdx.json.")
+        );
+    }
+
+    #[test]
+    fn test_sync_license_info_from_files() {
+        let file = |name: &str, licenses: &[&str]| {
+            let mut file = File::try_from_file(
+                Utf8Path::new("Cargo.toml"),
+                Utf8Path::new(""),
+                FileType::Text,
+                Some(name),
+                None,
+            )
+            .unwrap();
+            assert!(file.license_info_in_files.is_none());
+            file.license_info_in_files = Some(licenses.iter().map(ToString::to_string).collect());
+            file
+        };
+        let files = vec![file("a", &["MIT", "Apache-2.0"]), file("b", &["MIT"])];
+        let mut doc = document_with_extensions();
+        let package = Package::build_tool("foo");
+        doc.relationships = Some(
+            files
+                .iter()
+                .map(|file| Relationship {
+                    comment: None,
+                    related_spdx_element: file.spdxid.clone(),
+                    relationship_type: RelationshipType::Contains,
+                    spdx_element_id: package.spdxid.clone(),
+                })
+                .collect(),
+        );
+        doc.files = Some(files);
+        doc.packages = Some(vec![package, Package::build_tool("bar")]);
+
+        doc.sync_license_info_from_files();
+        let packages = doc.packages.unwrap();
+        assert_eq!(
+            packages[0].license_info_from_files.as_deref(),
+            Some(&["Apache-2.0".to_string(), "MIT".to_string()][..])
+        );
+        assert!(packages[1].license_info_from_files.is_none());
+    }
+
+    #[test]
+    fn test_file_type_of() {
+        let of = |path: &str| FileType::of(Utf8Path::new(path));
+        assert!(matches!(of("src/lib.rs"), FileType::Source));
+        assert!(matches!(of("README.md"), FileType::Documentation));
+        assert!(matches!(of("docs/logo.PNG"), FileType::Image));
+        assert!(matches!(of("vendor/libz.a"), FileType::Binary));
+        assert!(matches!(of("tests/data.tar.gz"), FileType::Archive));
+        assert!(matches!(of("Cargo.toml"), FileType::Text));
+        assert!(matches!(of("LICENSE-MIT"), FileType::Text));
+        assert!(matches!(of("foo.spdx.json"), FileType::Spdx));
+        assert!(matches!(of("build/.keep"), FileType::Other));
+    }
+
+    #[test]
+    fn test_sync_has_files() {
+        let file = |name: &str| {
+            File::try_from_file(
+                Utf8Path::new("Cargo.toml"),
+                Utf8Path::new(""),
+                FileType::Text,
+                Some(name),
+                None,
+            )
+            .unwrap()
+        };
+        let (a, b) = (file("a"), file("b"));
+        let mut foo = Package::build_tool("foo");
+        foo.has_files = Some(vec![b.spdxid.clone()]);
+        let bar = Package::build_tool("bar");
+        let mut doc = document_with_extensions();
+        doc.relationships = Some(vec![
+            Relationship {
+                comment: None,
+                related_spdx_element: a.spdxid.clone(),
+                relationship_type: RelationshipType::Contains,
+                spdx_element_id: foo.spdxid.clone(),
+            },
+            // Packages containing packages don't list them as files.
+            Relationship {
+                comment: None,
+                related_spdx_element: bar.spdxid.clone(),
+                relationship_type: RelationshipType::Contains,
+                spdx_element_id: foo.spdxid.clone(),
+            },
+        ]);
+        doc.files = Some(vec![a.clone(), b.clone()]);
+        doc.packages = Some(vec![foo, bar]);
+
+        doc.sync_has_files();
+        let packages = doc.packages.as_ref().unwrap();
+        assert_eq!(
+            packages[0].has_files.as_deref(),
+            Some(&[a.spdxid.clone(), b.spdxid.clone()][..])
+        );
+        assert!(packages[1].has_files.is_none());
+        let relationships = doc.relationships.as_ref().unwrap();
+        assert_eq!(relationships.len(), 3);
+        assert_eq!(relationships[2].related_spdx_element, b.spdxid);
+        assert_eq!(
+            relationships[2].relationship_type,
+            RelationshipType::Contains
+        );
+    }
+
+    #[test]
+    fn test_exclude_packages() {
+        let file = |name: &str| {
+            File::try_from_file(
+                Utf8Path::new("Cargo.toml"),
+                Utf8Path::new(""),
+                FileType::Text,
+                Some(name),
+                None,
+            )
+            .unwrap()
+        };
+        let relationship = |from: &str, kind: RelationshipType, to: &str| Relationship {
+            comment: None,
+            related_spdx_element: to.to_string(),
+            relationship_type: kind,
+            spdx_element_id: from.to_string(),
+        };
+        let (shared, own) = (file("shared"), file("own"));
+        let app = Package::build_tool("app");
+        let harness = Package::build_tool("test-harness");
+        let mut doc = document_with_extensions();
+        doc.relationships = Some(vec![
+            relationship("SPDXRef-DOCUMENT", RelationshipType::Describes, &app.spdxid),
+            relationship(&app.spdxid, RelationshipType::DependsOn, &harness.spdxid),
+            relationship(&app.spdxid, RelationshipType::Contains, &shared.spdxid),
+            relationship(&harness.spdxid, RelationshipType::Contains, &shared.spdxid),
+            relationship(&harness.spdxid, RelationshipType::Contains, &own.spdxid),
+        ]);
+        doc.files = Some(vec![shared.clone(), own]);
+        doc.packages = Some(vec![app.clone(), harness]);
+
+        doc.exclude_packages(&["*-harness".to_string()]).unwrap();
+        assert_eq!(doc.packages.as_ref().unwrap().len(), 1);
+        // Files another package contains are kept.
+        assert_eq!(doc.files.as_ref().unwrap()[0].spdxid, shared.spdxid);
+        assert_eq!(doc.files.as_ref().unwrap().len(), 1);
+        assert_eq!(doc.relationships.as_ref().unwrap().len(), 2);
+
+        assert!(doc.exclude_packages(&["app".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_disambiguate() {
+        let crates_io = hex(Some(
+            "registry+https://github.com/rust-lang/crates.io-index",
+        ));
+        let fork = hex(Some(
+            "git+https://github.com/acme/rust-hex#0123456789abcdef",
+        ));
+        let mut doc = document_with_extensions();
+        doc.packages = Some(vec![Package::from(&crates_io), Package::from(&fork)]);
+        assert!(doc.check_unique_ids().is_err());
+
+        for (package, metadata) in doc.packages.iter_mut().flatten().zip([&crates_io, &fork]) {
+            package.disambiguate(metadata);
+            assert!(package.spdxid.starts_with("SPDXRef-hex-0.4.3-"));
+        }
+        assert!(doc.check_unique_ids().is_ok());
+    }
+
+    #[test]
+    fn test_package_spdxid_is_safe() {
+        let package = |name: &str, version: &str| {
+            let mut metadata = hex(None);
+            metadata.name = name.to_string();
+            metadata.version = version.parse().unwrap();
+            Package::from(&metadata)
+        };
+        assert_eq!(
+            package("wasi", "0.11.0+wasi-snapshot-preview1").spdxid,
+            "SPDXRef-wasi-0.11.0-wasi-snapshot-preview1"
+        );
+
+        // Names only told apart by chars SPDX IDs can't contain collide.
+        let mut doc = document_with_extensions();
+        doc.packages = Some(vec![
+            package("foo_bar", "1.0.0"),
+            package("foo-bar", "1.0.0"),
+        ]);
+        assert_eq!(
+            doc.packages.as_ref().unwrap()[0].spdxid,
+            "SPDXRef-foo-bar-1.0.0"
+        );
+        assert!(doc.check_unique_ids().is_err());
+    }
+
+    #[test]
+    fn test_workspace_package() {
+        let package = Package::workspace("my workspace");
+        assert_eq!(package.name, "my workspace");
+        assert_eq!(package.spdxid, "SPDXRef-Workspace-my-workspace");
+        assert_eq!(package.files_analyzed, Some(false));
+    }
+
+    #[test]
+    fn test_note_features() {
+        let mut metadata = hex(None);
+        let mut package = Package::from(&metadata);
+        package.comment = None;
+        package.note_features(&metadata, &[]);
+        assert_eq!(package.comment, None);
+
+        metadata.features.insert("std".to_string(), Vec::new());
+        metadata.features.insert("alloc".to_string(), Vec::new());
+        package.note_features(&metadata, &[]);
+        assert_eq!(package.comment.as_deref(), Some("Built with no features."));
+
+        let mut package = Package::from(&metadata);
+        package.comment = None;
+        package.note_features(&metadata, &["alloc".to_string(), "std".to_string()]);
+        assert_eq!(
+            package.comment.as_deref(),
+            Some("Built with the `alloc`, `std` features.")
+        );
+    }
+
+    #[test]
+    fn test_toolchain() {
+        let mut metadata = hex(None);
+        assert_eq!(
+            Package::from(&metadata).comment.as_deref(),
+            Some("Rust 2015 edition.")
+        );
+
+        metadata.edition = cargo_metadata::Edition::E2021;
+        metadata.rust_version = Some("1.61".parse().unwrap());
+        assert_eq!(
+            Package::from(&metadata).comment.as_deref(),
+            Some("Rust 2021 edition, requiring Rust 1.61 or later.")
+        );
+    }
+
+    // The metadata of hex 0.4.3, as if it came from the given source
+    fn hex(source: Option<&str>) -> cargo_metadata::Package {
+        serde_json::from_value(json!({
+            "name": "hex",
+            "version": "0.4.3",
+            "id": "hex",
+            "source": source,
+            "dependencies": [],
+            "targets": [],
+            "features": {},
+            "manifest_path": "/hex/Cargo.toml",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_download_location() {
+        let package = |source: Option<&str>| Package::from(&hex(source));
+        assert_eq!(
+            package(Some(
+                "registry+https://github.com/rust-lang/crates.io-index"
+            ))
+            .download_location,
+            "https://crates.io/api/v1/crates/hex/0.4.3/download"
+        );
+        assert_eq!(
+            package(Some(
+                "git+https://github.com/KokaKiwi/rust-hex?branch=main#b2b4370b5bf021b98ee7adc92233e8de3f2de792"
+            ))
+            .download_location,
+            "git+https://github.com/KokaKiwi/rust-hex@b2b4370b5bf021b98ee7adc92233e8de3f2de792"
+        );
+        assert_eq!(
+            package(Some("sparse+https://registry.example.com/index/")).download_location,
+            "NOASSERTION"
+        );
+        let local = package(None);
+        assert_eq!(local.download_location, "NONE");
+        assert!(local.source_info.is_some());
+    }
+
+    #[test]
+    fn test_package_description() {
+        let mut package = hex(None);
+        package.description =
+            Some("Encoding and decoding data into/from hexadecimal.\n\nNo std needed.".to_string());
+        package.repository = Some("https://github.com/KokaKiwi/rust-hex".to_string());
+        let package = Package::from(&package);
+        assert_eq!(
+            package.summary.as_deref(),
+            Some("Encoding and decoding data into/from hexadecimal.")
+        );
+        assert!(package.description.unwrap().ends_with("No std needed."));
+        assert_eq!(
+            package.homepage.as_deref(),
+            Some("https://github.com/KokaKiwi/rust-hex")
+        );
+        let vcs = &package.external_refs.unwrap()[0];
+        assert_eq!(vcs.reference_type, "vcs");
+        assert_eq!(
+            vcs.reference_locator,
+            "https://github.com/KokaKiwi/rust-hex"
+        );
+
+        let mut package = hex(None);
+        package.description = Some("Encoding and decoding data into/from hexadecimal.".to_string());
+        let package = Package::from(&package);
+        assert!(package.summary.is_none());
+        assert!(package.description.is_some());
+        assert!(package.external_refs.is_none());
+    }
+
+    #[test]
+    fn test_originator() {
+        let originator = |authors: &[&str]| {
+            let mut package = hex(None);
+            package.authors = authors.iter().map(ToString::to_string).collect();
+            Package::from(&package).originator
+        };
+        assert_eq!(
+            originator(&["Jane Doe <jane@example.com>", "John Doe"]).as_deref(),
+            Some("Person: Jane Doe (jane@example.com)")
+        );
+        assert_eq!(
+            originator(&["The Rust Project Developers"]).as_deref(),
+            Some("Person: The Rust Project Developers")
+        );
+        assert_eq!(originator(&["<jane@example.com>"]), None);
+        assert_eq!(originator(&[]), None);
+    }
+
+    #[test]
+    fn test_package_purl() {
+        let purl = |source: Option<&str>| {
+            Package::from(&hex(source))
+                .purl()
+                .map(|purl| purl.to_string())
+        };
+        assert_eq!(
+            purl(Some(
+                "registry+https://github.com/rust-lang/crates.io-index"
+            ))
+            .as_deref(),
+            Some("pkg:cargo/hex@0.4.3")
+        );
+        assert_eq!(
+            purl(Some("sparse+https://registry.example.com/index/")).as_deref(),
+            Some("pkg:cargo/hex@0.4.3?repository_url=https://registry.example.com/index/")
+        );
+        assert_eq!(purl(None), None);
+    }
+
+    #[test]
+    fn test_sort_elements() {
+        let relationship = |from: &str, to: &str| Relationship {
+            comment: None,
+            related_spdx_element: to.to_string(),
+            relationship_type: RelationshipType::DependsOn,
+            spdx_element_id: from.to_string(),
+        };
+        let mut doc = document_with_extensions();
+        doc.relationships = Some(vec![
+            relationship("SPDXRef-b", "SPDXRef-a"),
+            relationship("SPDXRef-a", "SPDXRef-c"),
+            relationship("SPDXRef-a", "SPDXRef-b"),
+        ]);
+
+        doc.sort_elements();
+        let order: Vec<_> = doc
+            .relationships
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.spdx_element_id, r.related_spdx_element))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                ("SPDXRef-a".to_string(), "SPDXRef-b".to_string()),
+                ("SPDXRef-a".to_string(), "SPDXRef-c".to_string()),
+                ("SPDXRef-b".to_string(), "SPDXRef-a".to_string()),
+            ]
+        );
+    }
+}
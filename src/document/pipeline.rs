@@ -0,0 +1,209 @@
+//! Post-processing passes run over a document before it's written.
+//!
+//! Everything which adjusts a document once it's been built, such as adding
+//! suppliers, restricting it to a profile, or scrubbing local paths, is a pass
+//! in a pipeline. Further passes, such as redaction or curation, can be slotted
+//! in among them without changing how documents are built or written.
+
+use super::{Document, PathPolicy, Profile};
+use crate::config::ExtensionsConfig;
+use crate::cpe;
+use crate::suppliers::Suppliers;
+use crate::telemetry;
+use anyhow::{Context, Result};
+use std::fmt::{Debug, Formatter};
+use std::path::Path;
+
+/// A pass over a document, which may fail.
+type PassFn<'a> = Box<dyn Fn(&mut Document) -> Result<()> + 'a>;
+
+/// Ordered passes run over a document before it's written.
+#[derive(Default)]
+pub struct DocumentPipeline<'a> {
+    /// The passes, with their names, in the order they're run.
+    passes: Vec<(String, PassFn<'a>)>,
+}
+
+impl<'a> DocumentPipeline<'a> {
+    /// The passes run over every document cargo-spdx generates.
+    ///
+    /// The document is conformed to its SPDX version first, so later passes
+    /// see the fields that version allows, and its elements are sorted last,
+    /// before checking no two of them have the same SPDX ID.
+    /// What the document describes, and the files of each package, are listed
+    /// in both of the ways SPDX allows, and packages list the licenses their
+    /// files are tagged with.
+    pub fn standard(
+        extensions: Option<&'a ExtensionsConfig>,
+        suppliers: &'a Suppliers,
+        profile: Profile,
+        scrub_paths: PathPolicy,
+        workspace_root: &'a Path,
+        cpes: bool,
+        crates_io_owners: bool,
+    ) -> DocumentPipeline<'a> {
+        let mut pipeline = DocumentPipeline::default();
+        pipeline
+            .add_pass("conform to version", |doc| {
+                doc.conform_to_version();
+                Ok(())
+            })
+            .add_pass("describes", |doc| {
+                doc.sync_described();
+                Ok(())
+            })
+            .add_pass("has files", |doc| {
+                doc.sync_has_files();
+                Ok(())
+            })
+            .add_pass("license info from files", |doc| {
+                doc.sync_license_info_from_files();
+                Ok(())
+            })
+            .add_pass("extensions", move |doc| {
+                if let Some(extensions) = extensions {
+                    extensions.apply(doc);
+                }
+                Ok(())
+            })
+            .add_pass("suppliers", move |doc| {
+                suppliers.apply(doc);
+                Ok(())
+            })
+            .add_pass("crates.io owners", move |doc| {
+                if crates_io_owners {
+                    fill_owners(doc)?;
+                }
+                Ok(())
+            })
+            .add_pass("default supplier", move |doc| {
+                suppliers.apply_default(doc);
+                Ok(())
+            })
+            .add_pass("cpes", move |doc| {
+                if cpes {
+                    cpe::add_cpes(doc);
+                }
+                Ok(())
+            })
+            .add_pass("profile", move |doc| profile.apply(doc))
+            .add_pass("scrub paths", move |doc| {
+                scrub_paths.apply(doc, workspace_root);
+                Ok(())
+            })
+            .add_pass("sort", |doc| {
+                doc.sort_elements();
+                Ok(())
+            })
+            .add_pass("unique ids", |doc| doc.check_unique_ids());
+        pipeline
+    }
+
+    /// Add a pass to run after those already added.
+    pub fn add_pass(
+        &mut self,
+        name: &str,
+        pass: impl Fn(&mut Document) -> Result<()> + 'a,
+    ) -> &mut Self {
+        self.passes.push((name.to_string(), Box::new(pass)));
+        self
+    }
+
+    /// Add a pass to run just before the named one, or last if there's no such pass.
+    pub fn add_pass_before(
+        &mut self,
+        before: &str,
+        name: &str,
+        pass: impl Fn(&mut Document) -> Result<()> + 'a,
+    ) -> &mut Self {
+        let index = self
+            .passes
+            .iter()
+            .position(|(existing, _)| existing == before)
+            .unwrap_or(self.passes.len());
+        self.passes
+            .insert(index, (name.to_string(), Box::new(pass)));
+        self
+    }
+
+    /// Run each pass over the document in turn, stopping at the first to fail.
+    pub fn run(&self, doc: &mut Document) -> Result<()> {
+        telemetry::in_span("enrich", &[], || {
+            for (name, pass) in &self.passes {
+                log::debug!(target: "cargo_spdx", "running the {} pass", name);
+                telemetry::in_span(name, &[], || pass(doc))
+                    .with_context(|| format!("failed to run the {} pass", name))?;
+            }
+            Ok(())
+        })
+    }
+}
+
+// Fill in suppliers and originators from the owners of crates on crates.io
+#[cfg(feature = "network")]
+fn fill_owners(doc: &mut Document) -> Result<()> {
+    crate::owners::apply(doc)
+}
+
+// Without the `network` feature there's no asking crates.io
+#[cfg(not(feature = "network"))]
+fn fill_owners(_doc: &mut Document) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "--crates-io-owners needs cargo-spdx to be built with the `network` feature"
+    ))
+}
+
+impl Debug for DocumentPipeline<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.passes.iter().map(|(name, _)| name))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DocumentPipeline;
+    use crate::document::{CreationInfoBuilder, Creator, DocumentBuilder};
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_pipeline_order() {
+        let mut doc = DocumentBuilder::default()
+            .document_name("foo.spdx")
+            .try_document_namespace("https://example.com/foo")
+            .unwrap()
+            .creation_info(
+                CreationInfoBuilder::default()
+                    .creators(vec![Creator::tool("cargo-spdx 0.1.0")])
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        let mut pipeline = DocumentPipeline::default();
+        pipeline
+            .add_pass("first", |doc| {
+                doc.document_comment = Some("first".to_string());
+                Ok(())
+            })
+            .add_pass("last", |doc| {
+                let comment = doc.document_comment.take().unwrap_or_default();
+                doc.document_comment = Some(format!("{}, last", comment));
+                Ok(())
+            })
+            .add_pass_before("last", "second", |doc| {
+                let comment = doc.document_comment.take().unwrap_or_default();
+                doc.document_comment = Some(format!("{}, second", comment));
+                Ok(())
+            });
+        assert_eq!(format!("{:?}", pipeline), r#"["first", "second", "last"]"#);
+        pipeline.run(&mut doc).unwrap();
+        assert_eq!(doc.document_comment.as_deref(), Some("first, second, last"));
+
+        pipeline.add_pass_before("first", "fail", |_| Err(anyhow!("redaction failed")));
+        let error = pipeline.run(&mut doc).unwrap_err();
+        assert_eq!(error.to_string(), "failed to run the fail pass");
+    }
+}
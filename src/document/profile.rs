@@ -0,0 +1,160 @@
+//! Profiles restricting documents to a subset of the SPDX specification.
+
+use super::{Document, Extensions, SpdxVersion, NOASSERTION};
+use anyhow::{anyhow, Error, Result};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// The subset of the specification a document is restricted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Everything cargo-spdx knows about.
+    Full,
+    /// SPDX Lite, the minimal subset defined in Annex G of the specification
+    /// for exchanging license compliance information.
+    SpdxLite,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile::Full
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Profile::Full => write!(f, "full"),
+            Profile::SpdxLite => write!(f, "spdx-lite"),
+        }
+    }
+}
+
+impl FromStr for Profile {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Profile::Full),
+            "spdx-lite" | "lite" => Ok(Profile::SpdxLite),
+            s => Err(anyhow!("unknown profile '{}'", s)),
+        }
+    }
+}
+
+impl Profile {
+    /// Check the profile is defined for the given SPDX version.
+    pub fn check_supports(self, version: SpdxVersion) -> Result<()> {
+        match self {
+            Profile::SpdxLite if version >= SpdxVersion::V3_0 => Err(anyhow!(
+                "the {} profile isn't supported for {}",
+                self,
+                version
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Restrict the document to the profile, checking everything it requires is there.
+    pub fn apply(self, doc: &mut Document) -> Result<()> {
+        match self {
+            Profile::Full => Ok(()),
+            Profile::SpdxLite => apply_spdx_lite(doc),
+        }
+    }
+}
+
+/// Keep only the SPDX Lite fields: document creation information, and the
+/// basic identification and licensing of packages.
+///
+/// Lite documents describe packages without analyzing their files, so files and
+/// the relationships to them are dropped too.
+fn apply_spdx_lite(doc: &mut Document) -> Result<()> {
+    doc.external_document_references = None;
+    doc.document_comment = None;
+    doc.creation_info.comment = None;
+    doc.annotations = None;
+    doc.creation_info.license_list_version = None;
+    doc.files = None;
+    doc.snippets = None;
+    doc.relationships = None;
+    doc.extensions = Extensions::default();
+
+    // Licenses not on the SPDX License List are kept, as their packages refer to them.
+    for info in doc.has_extracted_licensing_infos.iter_mut().flatten() {
+        info.cross_refs = None;
+        info.see_alsos = None;
+    }
+
+    for package in doc.packages.iter_mut().flatten() {
+        package.annotations = None;
+        package.attribution_texts = None;
+        package.checksums = None;
+        package.description = None;
+        package.external_refs = None;
+        package.has_files = None;
+        package.license_info_from_files = None;
+        package.originator = None;
+        package.package_verification_code = None;
+        package.source_info = None;
+        package.summary = None;
+        package.supplier = None;
+        package.extensions = Extensions::default();
+        package.files_analyzed = Some(false);
+
+        // SPDX Lite requires these even in versions where they're otherwise optional.
+        for field in [
+            &mut package.license_concluded,
+            &mut package.license_declared,
+            &mut package.copyright_text,
+        ] {
+            field.get_or_insert_with(|| NOASSERTION.to_string());
+        }
+    }
+
+    validate_spdx_lite(doc)
+}
+
+/// Check every field SPDX Lite requires has a value.
+fn validate_spdx_lite(doc: &Document) -> Result<()> {
+    let mut missing = Vec::new();
+
+    if doc.document_name.0.trim().is_empty() {
+        missing.push("DocumentName".to_string());
+    }
+    if doc
+        .creation_info
+        .creators
+        .as_ref()
+        .map_or(true, Vec::is_empty)
+    {
+        missing.push("Creator".to_string());
+    }
+
+    for package in doc.packages.iter().flatten() {
+        let required = [
+            ("PackageName", Some(&package.name)),
+            ("PackageDownloadLocation", Some(&package.download_location)),
+            (
+                "PackageLicenseConcluded",
+                package.license_concluded.as_ref(),
+            ),
+            ("PackageLicenseDeclared", package.license_declared.as_ref()),
+            ("PackageCopyrightText", package.copyright_text.as_ref()),
+        ];
+        for (field, value) in required {
+            if value.map_or(true, |value| value.trim().is_empty()) {
+                missing.push(format!("{} of {}", field, package.spdxid));
+            }
+        }
+    }
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "the SPDX Lite profile requires fields which are empty: {}",
+            missing.join(", ")
+        ))
+    }
+}
@@ -1,14 +1,16 @@
 //! Defines the SPDX document structure.
-use anyhow::Result;
+use anyhow::{anyhow, Error, Result};
 use derive_builder::Builder;
 use derive_more::{Display, From};
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
-use time::{format_description, OffsetDateTime};
+use std::str::FromStr;
+use time::{format_description, OffsetDateTime, PrimitiveDateTime};
 use url::Url;
 
 /// An SPDX SBOM document.
-#[derive(Debug, Clone, Builder, Serialize)]
+#[derive(Debug, Clone, Builder, Serialize, Deserialize)]
 pub struct Document {
     /// The version of the SPD standard.
     #[builder(setter(into))]
@@ -47,14 +49,14 @@ pub struct Document {
     #[serde(rename = "documentNamespace")]
     pub document_namespace: Url,
 
-    /// An external name for referring to the SPDX file.
+    /// Other SPDX documents which elements of this one are related to.
     #[builder(setter(strip_option))]
     #[builder(default)]
     #[serde(
         rename = "externalDocumentRefs",
         skip_serializing_if = "Option::is_none"
     )]
-    pub external_document_reference: Option<ExternalDocumentReference>,
+    pub external_document_references: Option<Vec<ExternalDocumentReference>>,
 
     /// Freeform comments about the SPDX file.
     #[builder(setter(strip_option))]
@@ -67,6 +69,25 @@ pub struct Document {
     #[serde(rename = "creationInfo")]
     pub creation_info: CreationInfo,
 
+    /// Comments on the document as a whole, such as sign-off notes.
+    #[serde(rename = "annotations", skip_serializing_if = "Option::is_none")]
+    #[builder(setter(strip_option), default)]
+    pub annotations: Option<Vec<DocumentAnnotation>>,
+
+    /// Licenses referenced in the SPDX document which aren't on the SPDX License List
+    #[serde(
+        rename = "hasExtractedLicensingInfos",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[builder(setter(strip_option), default)]
+    pub has_extracted_licensing_infos: Option<Vec<HasExtractedLicensingInfo>>,
+
+    /// SPDX IDs of the packages and files the document describes, repeating its
+    /// DESCRIBES relationships for tools which only read this.
+    #[serde(rename = "documentDescribes", skip_serializing_if = "Option::is_none")]
+    #[builder(setter(strip_option), default)]
+    pub document_describes: Option<Vec<String>>,
+
     /// Packages referenced in the SPDX document
     #[builder(setter(strip_option), default)]
     #[serde(rename = "packages")]
@@ -77,15 +98,25 @@ pub struct Document {
     #[builder(setter(strip_option), default)]
     pub files: Option<Vec<File>>,
 
+    /// Snippets of files referenced in the SPDX document
+    #[serde(rename = "snippets", skip_serializing_if = "Option::is_none")]
+    #[builder(setter(strip_option), default)]
+    pub snippets: Option<Vec<Snippet>>,
+
     /// Relationships referenced in the SPDX document
     #[serde(rename = "relationships", skip_serializing_if = "Option::is_none")]
     #[builder(setter(strip_option), default)]
     pub relationships: Option<Vec<Relationship>>,
+
+    /// Vendor extension fields attached to the document.
+    #[serde(flatten)]
+    #[builder(default)]
+    pub extensions: Extensions,
 }
 
 /// One instance is required for each SPDX file produced. It provides the necessary
 /// information for forward and backward compatibility for processing tools.
-#[derive(Debug, Clone, Builder, Serialize)]
+#[derive(Debug, Clone, Builder, Serialize, Deserialize)]
 pub struct CreationInfo {
     /// Freeform comments about the creator of the SPDX file.
     #[builder(setter(strip_option), default)]
@@ -114,7 +145,7 @@ pub struct CreationInfo {
 }
 
 /// The version of the SPDX standard being used.
-#[derive(Debug, Display, Clone, From)]
+#[derive(Debug, Display, Clone, Copy, From, PartialEq, Eq, PartialOrd, Ord)]
 #[display(fmt = "SPDX-{}.{}", major, minor)]
 pub struct SpdxVersion {
     /// The major version.
@@ -123,9 +154,24 @@ pub struct SpdxVersion {
     pub minor: u32,
 }
 
+impl SpdxVersion {
+    /// SPDX 2.2.
+    pub const V2_2: SpdxVersion = SpdxVersion { major: 2, minor: 2 };
+
+    /// SPDX 2.3.
+    pub const V2_3: SpdxVersion = SpdxVersion { major: 2, minor: 3 };
+
+    /// SPDX 3.0, which uses the model in [`v3`](super::v3).
+    pub const V3_0: SpdxVersion = SpdxVersion { major: 3, minor: 0 };
+
+    /// The versions documents can be generated for.
+    pub const SUPPORTED: &'static [SpdxVersion] =
+        &[SpdxVersion::V2_2, SpdxVersion::V2_3, SpdxVersion::V3_0];
+}
+
 impl Default for SpdxVersion {
     fn default() -> Self {
-        SpdxVersion { major: 2, minor: 2 }
+        SpdxVersion::V2_2
     }
 }
 
@@ -142,7 +188,7 @@ pub struct DataLicense;
 pub struct SpdxIdentifier;
 
 /// The name of the SPDX file itself.
-#[derive(Debug, Display, Clone, From, Serialize)]
+#[derive(Debug, Display, Clone, From, Serialize, Deserialize)]
 pub struct DocumentName(pub String);
 
 impl<'s> From<&'s str> for DocumentName {
@@ -150,25 +196,36 @@ impl<'s> From<&'s str> for DocumentName {
         DocumentName(String::from(string))
     }
 }
-/// An external name for referring to the SPDX file.
-#[derive(Debug, Display, Clone, Serialize)]
-#[display(fmt = "DocumentRef-{} {} {}", id_string, document_uri, checksum)]
+/// A reference to another SPDX document, so its elements can be related to as
+/// `DocumentRef-id:SPDXRef-element`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExternalDocumentReference {
-    /// An ID string made of letters, numbers, '.', '-', and/or '+'.
-    id_string: IdString,
+    /// `DocumentRef-` followed by letters, numbers, '.', '-', and/or '+'.
+    #[serde(rename = "externalDocumentId")]
+    pub id_string: IdString,
     /// The namespace of the document.
-    document_uri: Url,
-    /// A checksum for the external document reference.
-    checksum: Checksum,
+    #[serde(rename = "spdxDocument")]
+    pub document_uri: Url,
+    /// A checksum of the document.
+    #[serde(rename = "checksum")]
+    pub checksum: Checksum,
 }
 
-/// An ID string made of letters, numbers, '.', '-', and/or '+'.
-#[derive(Debug, Display, Clone, From, Serialize)]
+/// `DocumentRef-` followed by letters, numbers, '.', '-', and/or '+'.
+#[derive(Debug, Display, Clone, PartialEq, Eq, From, Serialize, Deserialize)]
 pub struct IdString(pub String);
 
-/// A checksum for the external document reference.
-#[derive(Debug, Display, Clone, From, Serialize)]
-pub struct Checksum(pub String);
+/// A checksum of an externally referenced document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checksum {
+    /// The algorithm used, which SPDX requires to be SHA1.
+    #[serde(rename = "algorithm")]
+    pub algorithm: Algorithm,
+
+    /// The lower case hexadecimal digest of the document.
+    #[serde(rename = "checksumValue")]
+    pub checksum_value: String,
+}
 
 /// The version of the SPDX license list used.
 #[derive(Debug, Display, Clone)]
@@ -251,6 +308,149 @@ string_serialize! {
   Created, Creator, LicenseListVersion, DataLicense, SpdxVersion, SpdxIdentifier
 }
 
+// Make serde use the FromStr implementation for the same types, so parsing
+// accepts exactly what serialization produces.
+macro_rules! string_deserialize {
+    ($($ty:ty),*) => {
+        $(impl<'de> Deserialize<'de> for $ty { fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> { String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom) }})*
+    };
+}
+
+string_deserialize! {
+  Created, Creator, LicenseListVersion, DataLicense, SpdxVersion, SpdxIdentifier
+}
+
+/// Parse a `major.minor` version pair.
+fn parse_version_pair(s: &str) -> Option<(u32, u32)> {
+    let (major, minor) = s.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+impl FromStr for SpdxVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.strip_prefix("SPDX-")
+            .and_then(parse_version_pair)
+            .map(|(major, minor)| SpdxVersion { major, minor })
+            .ok_or_else(|| anyhow!("invalid SPDX version '{}'", s))
+    }
+}
+
+impl FromStr for LicenseListVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_version_pair(s)
+            .map(|(major, minor)| LicenseListVersion { major, minor })
+            .ok_or_else(|| anyhow!("invalid license list version '{}'", s))
+    }
+}
+
+impl FromStr for DataLicense {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "CC0-1.0" => Ok(DataLicense),
+            s => Err(anyhow!("invalid data license '{}'", s)),
+        }
+    }
+}
+
+impl FromStr for SpdxIdentifier {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "SPDXRef-DOCUMENT" => Ok(SpdxIdentifier),
+            s => Err(anyhow!("invalid document identifier '{}'", s)),
+        }
+    }
+}
+
+impl FromStr for Creator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Split off an optional trailing email address, e.g. "Jane Doe (jane@example.com)".
+        fn name_and_email(s: &str) -> (String, Option<String>) {
+            match s.trim().strip_suffix(')').and_then(|s| s.rsplit_once('(')) {
+                Some((name, email)) => (name.trim().to_owned(), Some(email.trim().to_owned())),
+                None => (s.trim().to_owned(), None),
+            }
+        }
+
+        match s.split_once(':') {
+            Some(("Person", rest)) => {
+                let (name, email) = name_and_email(rest);
+                Ok(Creator::Person { name, email })
+            }
+            Some(("Organization", rest)) => {
+                let (name, email) = name_and_email(rest);
+                Ok(Creator::Organization { name, email })
+            }
+            Some(("Tool", rest)) => Ok(Creator::Tool {
+                name: rest.trim().to_owned(),
+            }),
+            _ => Err(anyhow!("invalid creator '{}'", s)),
+        }
+    }
+}
+
+impl FromStr for AnnotationType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "OTHER" => Ok(AnnotationType::Other),
+            "REVIEW" => Ok(AnnotationType::Review),
+            _ => Err(anyhow!("unknown annotation type '{}'", s)),
+        }
+    }
+}
+
+impl FromStr for Created {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let format_str = "[year]-[month]-[day]T[hour]:[minute]:[second]Z";
+        let format = format_description::parse(format_str)?;
+        let created = PrimitiveDateTime::parse(s, &format)
+            .map_err(|e| anyhow!("invalid creation date '{}': {}", s, e))?;
+        Ok(Created(created.assume_utc()))
+    }
+}
+
+/// Vendor extension fields attached to an element.
+///
+/// These are not part of the SPDX specification, so every key must start with
+/// `x-` to keep it from colliding with current or future SPDX fields. When
+/// parsing, any other unrecognized fields are ignored.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Extensions(pub BTreeMap<String, serde_json::Value>);
+
+impl Extensions {
+    /// The prefix every extension field's key must start with.
+    pub const PREFIX: &'static str = "x-";
+
+    /// Check if there are no extension fields.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<'de> Deserialize<'de> for Extensions {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut fields = BTreeMap::<String, serde_json::Value>::deserialize(deserializer)?;
+        fields.retain(|key, _| key.starts_with(Extensions::PREFIX));
+        Ok(Extensions(fields))
+    }
+}
+
 impl Display for Created {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let repr = {
@@ -391,8 +591,9 @@ pub struct Package {
     pub comment: Option<String>,
 
     /// The text of copyright declarations recited in the Package or File.
-    #[serde(rename = "copyrightText")]
-    pub copyright_text: String,
+    /// Mandatory before SPDX 2.3.
+    #[serde(rename = "copyrightText", skip_serializing_if = "Option::is_none")]
+    pub copyright_text: Option<String>,
 
     /// Provides a detailed description of the package.
     #[serde(rename = "description", skip_serializing_if = "Option::is_none")]
@@ -433,14 +634,15 @@ pub struct Package {
 
     /// License expression for licenseConcluded.  The licensing that the preparer of this SPDX
     /// document has concluded, based on the evidence, actually applies to the package.
-    #[serde(rename = "licenseConcluded")]
-    pub license_concluded: String,
+    /// Mandatory before SPDX 2.3.
+    #[serde(rename = "licenseConcluded", skip_serializing_if = "Option::is_none")]
+    pub license_concluded: Option<String>,
 
     /// License expression for licenseDeclared.  The licensing that the creators of the software
     /// in the package, or the packager, have declared. Declarations by the original software
-    /// creator should be preferred, if they exist.
-    #[serde(rename = "licenseDeclared")]
-    pub license_declared: String,
+    /// creator should be preferred, if they exist. Mandatory before SPDX 2.3.
+    #[serde(rename = "licenseDeclared", skip_serializing_if = "Option::is_none")]
+    pub license_declared: Option<String>,
 
     /// The licensing information that was discovered directly within the package. There will be
     /// an instance of this property for each distinct value of alllicenseInfoInFile properties
@@ -501,6 +703,31 @@ pub struct Package {
     /// SpdxDocument.
     #[serde(rename = "versionInfo", skip_serializing_if = "Option::is_none")]
     pub version_info: Option<String>,
+
+    /// Vendor extension fields attached to the package.
+    #[serde(flatten)]
+    pub extensions: Extensions,
+}
+
+/// An Annotation is a comment on the whole document by an agent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentAnnotation {
+    /// Identify when the comment was made. This is to be specified according to the combined
+    /// date and time in the UTC format, as specified in the ISO 8601 standard.
+    #[serde(rename = "annotationDate")]
+    pub annotation_date: String,
+
+    /// Type of the annotation.
+    #[serde(rename = "annotationType")]
+    pub annotation_type: AnnotationType,
+
+    /// This field identifies the person, organization or tool that has commented on a file,
+    /// package, or the entire document.
+    #[serde(rename = "annotator")]
+    pub annotator: String,
+
+    #[serde(rename = "comment")]
+    pub comment: String,
 }
 
 /// An Annotation is a comment on an `SpdxItem` by an agent.
@@ -589,7 +816,7 @@ pub struct PackageVerificationCode {
     pub package_verification_code_value: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Relationship {
     #[serde(rename = "comment", skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
@@ -642,8 +869,9 @@ pub struct Snippet {
     pub comment: Option<String>,
 
     /// The text of copyright declarations recited in the Package or File.
-    #[serde(rename = "copyrightText")]
-    pub copyright_text: String,
+    /// Mandatory before SPDX 2.3.
+    #[serde(rename = "copyrightText", skip_serializing_if = "Option::is_none")]
+    pub copyright_text: Option<String>,
 
     /// The licenseComments property allows the preparer of the SPDX document to describe why the
     /// licensing in spdx:licenseConcluded was chosen.
@@ -652,8 +880,9 @@ pub struct Snippet {
 
     /// License expression for licenseConcluded.  The licensing that the preparer of this SPDX
     /// document has concluded, based on the evidence, actually applies to the package.
-    #[serde(rename = "licenseConcluded")]
-    pub license_concluded: String,
+    /// Mandatory before SPDX 2.3.
+    #[serde(rename = "licenseConcluded", skip_serializing_if = "Option::is_none")]
+    pub license_concluded: Option<String>,
 
     /// Licensing information that was discovered directly in the subject snippet. This is also
     /// considered a declared license for the snippet.
@@ -743,7 +972,7 @@ pub struct StartPointer {
 }
 
 /// Type of the annotation.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum AnnotationType {
     #[serde(rename = "OTHER")]
     Other,
@@ -783,6 +1012,55 @@ pub enum Algorithm {
 
     #[serde(rename = "SHA512")]
     Sha512,
+
+    /// Added in SPDX 2.3.
+    #[serde(rename = "SHA3-256")]
+    Sha3_256,
+
+    /// Added in SPDX 2.3.
+    #[serde(rename = "SHA3-384")]
+    Sha3_384,
+
+    /// Added in SPDX 2.3.
+    #[serde(rename = "SHA3-512")]
+    Sha3_512,
+
+    /// Added in SPDX 2.3.
+    #[serde(rename = "BLAKE2b-256")]
+    Blake2b256,
+
+    /// Added in SPDX 2.3.
+    #[serde(rename = "BLAKE2b-384")]
+    Blake2b384,
+
+    /// Added in SPDX 2.3.
+    #[serde(rename = "BLAKE2b-512")]
+    Blake2b512,
+
+    /// Added in SPDX 2.3.
+    #[serde(rename = "BLAKE3")]
+    Blake3,
+
+    /// Added in SPDX 2.3.
+    #[serde(rename = "ADLER32")]
+    Adler32,
+}
+
+impl Algorithm {
+    /// Check if the algorithm is defined in the given version of the specification.
+    pub fn is_defined_in(&self, version: SpdxVersion) -> bool {
+        match self {
+            Algorithm::Sha3_256
+            | Algorithm::Sha3_384
+            | Algorithm::Sha3_512
+            | Algorithm::Blake2b256
+            | Algorithm::Blake2b384
+            | Algorithm::Blake2b512
+            | Algorithm::Blake3
+            | Algorithm::Adler32 => version >= SpdxVersion::V2_3,
+            _ => true,
+        }
+    }
 }
 
 /// The type of the file.
@@ -828,16 +1106,33 @@ pub enum ReferenceCategory {
     #[serde(rename = "OTHER")]
     Other,
 
-    #[serde(rename = "PACKAGE_MANAGER")]
+    #[serde(rename = "PACKAGE_MANAGER", alias = "PACKAGE-MANAGER")]
     PackageManager,
 
+    /// Added in SPDX 2.3.
+    #[serde(rename = "PERSISTENT_ID", alias = "PERSISTENT-ID")]
+    PersistentId,
+
     #[serde(rename = "SECURITY")]
     Security,
 }
 
+impl ReferenceCategory {
+    /// Check if the category is defined in the given version of the specification.
+    pub fn is_defined_in(&self, version: SpdxVersion) -> bool {
+        match self {
+            ReferenceCategory::PersistentId => version >= SpdxVersion::V2_3,
+            _ => true,
+        }
+    }
+}
+
 /// Describes the type of relationship between two SPDX elements.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum RelationshipType {
+    #[serde(rename = "AMENDS")]
+    Amends,
+
     #[serde(rename = "ANCESTOR_OF")]
     AncestorOf,
 
@@ -943,9 +1238,17 @@ pub enum RelationshipType {
     #[serde(rename = "PROVIDED_DEPENDENCY_OF")]
     ProvidedDependencyOf,
 
+    /// Added in SPDX 2.3.
+    #[serde(rename = "REQUIREMENT_DESCRIPTION_FOR")]
+    RequirementDescriptionFor,
+
     #[serde(rename = "RUNTIME_DEPENDENCY_OF")]
     RuntimeDependencyOf,
 
+    /// Added in SPDX 2.3.
+    #[serde(rename = "SPECIFICATION_FOR")]
+    SpecificationFor,
+
     #[serde(rename = "STATIC_LINK")]
     StaticLink,
 
@@ -965,6 +1268,18 @@ pub enum RelationshipType {
     VariantOf,
 }
 
+impl RelationshipType {
+    /// Check if the relationship type is defined in the given version of the specification.
+    pub fn is_defined_in(&self, version: SpdxVersion) -> bool {
+        match self {
+            RelationshipType::RequirementDescriptionFor | RelationshipType::SpecificationFor => {
+                version >= SpdxVersion::V2_3
+            }
+            _ => true,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct File {
     /// Provide additional information about an SpdxElement.
@@ -989,8 +1304,9 @@ pub struct File {
     pub comment: Option<String>,
 
     /// The text of copyright declarations recited in the Package or File.
-    #[serde(rename = "copyrightText")]
-    pub copyright_text: String,
+    /// Mandatory before SPDX 2.3.
+    #[serde(rename = "copyrightText", skip_serializing_if = "Option::is_none")]
+    pub copyright_text: Option<String>,
 
     /// This field provides a place for the SPDX file creator to record file contributors.
     /// Contributors could include names of copyright holders and/or authors who may not be
@@ -1016,8 +1332,9 @@ pub struct File {
 
     /// License expression for licenseConcluded.  The licensing that the preparer of this SPDX
     /// document has concluded, based on the evidence, actually applies to the package.
-    #[serde(rename = "licenseConcluded")]
-    pub license_concluded: String,
+    /// Mandatory before SPDX 2.3.
+    #[serde(rename = "licenseConcluded", skip_serializing_if = "Option::is_none")]
+    pub license_concluded: Option<String>,
 
     /// Licensing information that was discovered directly in the subject file. This is also
     /// considered a declared license for the file.
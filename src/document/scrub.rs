@@ -0,0 +1,251 @@
+//! Policies for removing local paths from documents.
+//!
+//! File names and comments can mention where things were on the machine
+//! generating the document, such as `/home/jane/.cargo/registry/...`, which
+//! gives away details of that machine when the document is published.
+
+use super::Document;
+use anyhow::{anyhow, Error, Result};
+use sha2::{Digest, Sha256};
+use std::fmt::{self, Display, Formatter};
+use std::ops::Not as _;
+use std::path::Path;
+use std::str::FromStr;
+
+/// What to do with paths outside the workspace found in a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathPolicy {
+    /// Leave paths as they are.
+    Keep,
+    /// Make paths relative to the workspace, cargo home, or home directory.
+    Relative,
+    /// Replace the directory of each path with a hash of it, keeping the file name.
+    Hash,
+    /// Replace the directory of each path with a placeholder, keeping the file name.
+    Placeholder,
+}
+
+/// The text paths outside the workspace are replaced with by [`PathPolicy::Placeholder`].
+const PLACEHOLDER: &str = "<redacted>";
+
+impl Default for PathPolicy {
+    fn default() -> Self {
+        PathPolicy::Keep
+    }
+}
+
+impl Display for PathPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PathPolicy::Keep => write!(f, "keep"),
+            PathPolicy::Relative => write!(f, "relative"),
+            PathPolicy::Hash => write!(f, "hash"),
+            PathPolicy::Placeholder => write!(f, "placeholder"),
+        }
+    }
+}
+
+impl FromStr for PathPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(PathPolicy::Keep),
+            "relative" => Ok(PathPolicy::Relative),
+            "hash" => Ok(PathPolicy::Hash),
+            "placeholder" => Ok(PathPolicy::Placeholder),
+            s => Err(anyhow!("unknown path policy '{}'", s)),
+        }
+    }
+}
+
+/// The directories paths are made relative to.
+#[derive(Debug)]
+struct Roots {
+    workspace: String,
+    cargo_home: Option<String>,
+    home: Option<String>,
+}
+
+impl Roots {
+    /// Find the directories paths are made relative to on this machine.
+    fn new(workspace_root: &Path) -> Self {
+        let home = std::env::var("HOME")
+            .or_else(|_| std::env::var("USERPROFILE"))
+            .ok();
+        let cargo_home = std::env::var("CARGO_HOME")
+            .ok()
+            .or_else(|| home.as_ref().map(|home| format!("{}/.cargo", home)));
+        Roots {
+            workspace: workspace_root.to_string_lossy().to_string(),
+            cargo_home,
+            home,
+        }
+    }
+}
+
+impl PathPolicy {
+    /// Apply the policy to the file names and comments of the document.
+    ///
+    /// Paths within the workspace are made relative to it by every policy
+    /// except [`PathPolicy::Keep`].
+    pub fn apply(self, doc: &mut Document, workspace_root: &Path) {
+        if self == PathPolicy::Keep {
+            return;
+        }
+
+        let roots = Roots::new(workspace_root);
+        let scrub = |text: &mut String| *text = self.scrub_text(text, &roots);
+        let scrub_opt = |text: &mut Option<String>| {
+            if let Some(text) = text {
+                *text = self.scrub_text(text, &roots);
+            }
+        };
+
+        scrub_opt(&mut doc.document_comment);
+        scrub_opt(&mut doc.creation_info.comment);
+        for annotation in doc.annotations.iter_mut().flatten() {
+            scrub(&mut annotation.comment);
+        }
+        for package in doc.packages.iter_mut().flatten() {
+            scrub_opt(&mut package.package_file_name);
+            scrub_opt(&mut package.source_info);
+            scrub_opt(&mut package.license_comments);
+            scrub_opt(&mut package.comment);
+            for annotation in package.annotations.iter_mut().flatten() {
+                scrub(&mut annotation.comment);
+            }
+        }
+        for file in doc.files.iter_mut().flatten() {
+            scrub(&mut file.file_name);
+            scrub_opt(&mut file.license_comments);
+            scrub_opt(&mut file.comment);
+            for annotation in file.annotations.iter_mut().flatten() {
+                scrub(&mut annotation.comment);
+            }
+        }
+        for snippet in doc.snippets.iter_mut().flatten() {
+            scrub_opt(&mut snippet.license_comments);
+            scrub_opt(&mut snippet.comment);
+        }
+        for relationship in doc.relationships.iter_mut().flatten() {
+            scrub_opt(&mut relationship.comment);
+        }
+    }
+
+    /// Scrub each path in a piece of text.
+    fn scrub_text(self, text: &str, roots: &Roots) -> String {
+        let mut scrubbed = String::with_capacity(text.len());
+        let mut token = String::new();
+        for c in text.chars() {
+            if c.is_whitespace() || "\"'`()<>,;=".contains(c) {
+                scrubbed.push_str(&self.scrub_path(&token, roots));
+                scrubbed.push(c);
+                token.clear();
+            } else {
+                token.push(c);
+            }
+        }
+        scrubbed.push_str(&self.scrub_path(&token, roots));
+        scrubbed
+    }
+
+    /// Scrub a single word of text, if it's a path outside the workspace.
+    fn scrub_path(self, token: &str, roots: &Roots) -> String {
+        // Paths climbing out of the directory they're relative to, like the
+        // names of files generated in a target directory elsewhere, name the
+        // absolute path once they reach the root.
+        let path = if self == PathPolicy::Keep {
+            return token.to_string();
+        } else if is_absolute(token) {
+            token.to_string()
+        } else if token.starts_with("../") {
+            format!("/{}", token.trim_start_matches("../"))
+        } else {
+            return token.to_string();
+        };
+
+        if let Some(relative) = strip_root(&path, &roots.workspace) {
+            return relative.to_string();
+        }
+
+        let (dir, file_name) = match path.rsplit_once(['/', '\\']) {
+            Some((dir, file_name)) if file_name.is_empty().not() => (dir, file_name),
+            _ => return token.to_string(),
+        };
+        match self {
+            PathPolicy::Keep => unreachable!("paths are kept as they are"),
+            PathPolicy::Relative => {
+                let relative = [("$CARGO_HOME", &roots.cargo_home), ("~", &roots.home)]
+                    .iter()
+                    .find_map(|(name, root)| {
+                        let relative = strip_root(&path, root.as_deref()?)?;
+                        Some(format!("{}/{}", name, relative))
+                    });
+                relative.unwrap_or_else(|| token.to_string())
+            }
+            PathPolicy::Hash => {
+                let hash = hex::encode(Sha256::digest(dir.as_bytes()));
+                format!("<sha256:{}>/{}", &hash[..12], file_name)
+            }
+            PathPolicy::Placeholder => format!("{}/{}", PLACEHOLDER, file_name),
+        }
+    }
+}
+
+/// Check if a path is absolute on Unix or Windows.
+fn is_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    (path.starts_with('/') && path.len() > 1)
+        || (bytes.len() > 2
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1] == b':'
+            && (bytes[2] == b'\\' || bytes[2] == b'/'))
+}
+
+/// Get the part of a path within a directory, if it's in it.
+fn strip_root<'p>(path: &'p str, root: &str) -> Option<&'p str> {
+    let root = root.trim_end_matches(['/', '\\']);
+    if root.is_empty() {
+        return None;
+    }
+    let relative = path.strip_prefix(root)?;
+    match relative.chars().next() {
+        None => Some("."),
+        Some('/') | Some('\\') => Some(&relative[1..]),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PathPolicy, Roots};
+
+    #[test]
+    fn test_scrub_text() {
+        let roots = Roots {
+            workspace: "/home/jane/foo".to_string(),
+            cargo_home: Some("/home/jane/.cargo".to_string()),
+            home: Some("/home/jane".to_string()),
+        };
+        let text =
+            "built /home/jane/foo/src/main.rs with /home/jane/.cargo/bin/cc (see /opt/x/y.log)";
+
+        assert_eq!(PathPolicy::Keep.scrub_text(text, &roots), text);
+        assert_eq!(
+            PathPolicy::Relative.scrub_text(text, &roots),
+            "built src/main.rs with $CARGO_HOME/bin/cc (see /opt/x/y.log)"
+        );
+        assert_eq!(
+            PathPolicy::Placeholder.scrub_text(text, &roots),
+            "built src/main.rs with <redacted>/cc (see <redacted>/y.log)"
+        );
+        assert!(PathPolicy::Hash
+            .scrub_text("../../../home/jane/tgt/gen.rs", &roots)
+            .starts_with("<sha256:"));
+        assert_eq!(
+            PathPolicy::Relative.scrub_text("../../../home/jane/tgt/gen.rs", &roots),
+            "~/tgt/gen.rs"
+        );
+    }
+}
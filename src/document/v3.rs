@@ -0,0 +1,932 @@
+//! Defines the SPDX 3.0 document structure.
+//!
+//! SPDX 3.0 replaces the single document of earlier versions with a graph of
+//! elements, each belonging to one or more profiles, serialized as JSON-LD.
+//! Documents are produced by converting the SPDX 2.x model gathered from
+//! cargo, so both versions carry the same information.
+
+use super::{
+    Algorithm, Creator, Document as DocumentV2, File as FileV2, FileType,
+    HasExtractedLicensingInfo, Package as PackageV2, RelationshipType as RelationshipTypeV2,
+    Snippet as SnippetV2, NOASSERTION,
+};
+use serde::Serialize;
+use std::ops::Not as _;
+
+/// The JSON-LD context defining the SPDX 3.0 vocabulary.
+pub const CONTEXT: &str = "https://spdx.org/rdf/3.0.1/spdx-context.jsonld";
+
+/// The exact version of the specification documents conform to.
+pub const SPEC_VERSION: &str = "3.0.1";
+
+/// The blank node identifying the creation information shared by every element.
+const CREATION_INFO_ID: &str = "_:creationinfo";
+
+/// An SPDX 3.0 document, as a graph of elements.
+#[derive(Debug, Clone, Serialize)]
+pub struct Document {
+    /// The JSON-LD context.
+    #[serde(rename = "@context")]
+    pub context: String,
+
+    /// The elements in the document.
+    #[serde(rename = "@graph")]
+    pub graph: Vec<Element>,
+}
+
+/// An element in the document graph.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum Element {
+    /// When and by whom the elements were created.
+    CreationInfo(CreationInfo),
+    /// A person who created the elements.
+    Person(Agent),
+    /// An organization which created the elements.
+    Organization(Agent),
+    /// A piece of software which created the elements without a person's involvement.
+    SoftwareAgent(Agent),
+    /// A tool used to create the elements.
+    Tool(Agent),
+    /// The document itself.
+    SpdxDocument(SpdxDocument),
+    /// A package of software.
+    #[serde(rename = "software_Package")]
+    Package(Package),
+    /// A file.
+    #[serde(rename = "software_File")]
+    File(File),
+    /// A part of a file.
+    #[serde(rename = "software_Snippet")]
+    Snippet(Snippet),
+    /// A relationship between elements.
+    Relationship(Relationship),
+    /// A license expression.
+    #[serde(rename = "simplelicensing_LicenseExpression")]
+    LicenseExpression(LicenseExpression),
+    /// The text of a license which isn't on the SPDX License List.
+    #[serde(rename = "simplelicensing_SimpleLicensingText")]
+    SimpleLicensingText(SimpleLicensingText),
+}
+
+/// When and by whom the elements were created.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreationInfo {
+    /// The blank node identifying the creation information.
+    #[serde(rename = "@id")]
+    pub id: String,
+
+    /// The version of the specification the elements conform to.
+    #[serde(rename = "specVersion")]
+    pub spec_version: String,
+
+    /// When the elements were created.
+    pub created: String,
+
+    /// The agents who created the elements.
+    #[serde(rename = "createdBy")]
+    pub created_by: Vec<String>,
+
+    /// The tools used to create the elements.
+    #[serde(rename = "createdUsing", skip_serializing_if = "Vec::is_empty")]
+    pub created_using: Vec<String>,
+
+    /// Freeform comments about the creation of the elements.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// A person, organization, or piece of software.
+#[derive(Debug, Clone, Serialize)]
+pub struct Agent {
+    /// The identifier of the agent.
+    #[serde(rename = "spdxId")]
+    pub spdx_id: String,
+
+    /// The creation information of the element.
+    #[serde(rename = "creationInfo")]
+    pub creation_info: String,
+
+    /// The name of the agent.
+    pub name: String,
+
+    /// Other ways of identifying the agent, such as an email address.
+    #[serde(rename = "externalIdentifier", skip_serializing_if = "Vec::is_empty")]
+    pub external_identifiers: Vec<ExternalIdentifier>,
+}
+
+/// The document itself.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpdxDocument {
+    /// The identifier of the document.
+    #[serde(rename = "spdxId")]
+    pub spdx_id: String,
+
+    /// The creation information of the element.
+    #[serde(rename = "creationInfo")]
+    pub creation_info: String,
+
+    /// The name of the document.
+    pub name: String,
+
+    /// Freeform comments about the document.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+
+    /// The profiles the document conforms to.
+    #[serde(rename = "profileConformance")]
+    pub profile_conformance: Vec<String>,
+
+    /// The elements the document is about.
+    #[serde(rename = "rootElement")]
+    pub root_elements: Vec<String>,
+
+    /// Every element in the document.
+    #[serde(rename = "element")]
+    pub elements: Vec<String>,
+}
+
+/// A package of software.
+#[derive(Debug, Clone, Serialize)]
+pub struct Package {
+    /// The identifier of the package.
+    #[serde(rename = "spdxId")]
+    pub spdx_id: String,
+
+    /// The creation information of the element.
+    #[serde(rename = "creationInfo")]
+    pub creation_info: String,
+
+    /// The name of the package.
+    pub name: String,
+
+    /// A short description of the package.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+
+    /// A detailed description of the package.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Freeform comments about the package.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+
+    /// The version of the package.
+    #[serde(
+        rename = "software_packageVersion",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub package_version: Option<String>,
+
+    /// Where the package can be downloaded from.
+    #[serde(
+        rename = "software_downloadLocation",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub download_location: Option<String>,
+
+    /// The package's home page.
+    #[serde(rename = "software_homePage", skip_serializing_if = "Option::is_none")]
+    pub home_page: Option<String>,
+
+    /// The package URL identifying the package.
+    #[serde(
+        rename = "software_packageUrl",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub package_url: Option<String>,
+
+    /// The text of copyright declarations recited in the package.
+    #[serde(
+        rename = "software_copyrightText",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub copyright_text: Option<String>,
+
+    /// Checksums of the package.
+    #[serde(rename = "verifiedUsing", skip_serializing_if = "Vec::is_empty")]
+    pub verified_using: Vec<Hash>,
+}
+
+/// A part of a file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Snippet {
+    /// The identifier of the snippet.
+    #[serde(rename = "spdxId")]
+    pub spdx_id: String,
+
+    /// The creation information of the element.
+    #[serde(rename = "creationInfo")]
+    pub creation_info: String,
+
+    /// The name of the snippet.
+    pub name: String,
+
+    /// Freeform comments about the snippet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+
+    /// The file the snippet is part of.
+    #[serde(rename = "software_snippetFromFile")]
+    pub snippet_from_file: String,
+
+    /// The bytes of the file the snippet covers.
+    #[serde(rename = "software_byteRange", skip_serializing_if = "Option::is_none")]
+    pub byte_range: Option<PositiveIntegerRange>,
+
+    /// The lines of the file the snippet covers.
+    #[serde(rename = "software_lineRange", skip_serializing_if = "Option::is_none")]
+    pub line_range: Option<PositiveIntegerRange>,
+
+    /// The text of copyright declarations recited in the snippet.
+    #[serde(
+        rename = "software_copyrightText",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub copyright_text: Option<String>,
+}
+
+/// An inclusive range of positions, counting from 1.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub struct PositiveIntegerRange {
+    /// The first position in the range.
+    #[serde(rename = "beginIntegerRange")]
+    pub begin: i64,
+
+    /// The last position in the range.
+    #[serde(rename = "endIntegerRange")]
+    pub end: i64,
+}
+
+/// A file.
+#[derive(Debug, Clone, Serialize)]
+pub struct File {
+    /// The identifier of the file.
+    #[serde(rename = "spdxId")]
+    pub spdx_id: String,
+
+    /// The creation information of the element.
+    #[serde(rename = "creationInfo")]
+    pub creation_info: String,
+
+    /// The path of the file.
+    pub name: String,
+
+    /// Freeform comments about the file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+
+    /// What the file is for.
+    #[serde(
+        rename = "software_primaryPurpose",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub primary_purpose: Option<String>,
+
+    /// The text of copyright declarations recited in the file.
+    #[serde(
+        rename = "software_copyrightText",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub copyright_text: Option<String>,
+
+    /// Checksums of the file.
+    #[serde(rename = "verifiedUsing", skip_serializing_if = "Vec::is_empty")]
+    pub verified_using: Vec<Hash>,
+}
+
+/// A relationship from one element to others.
+#[derive(Debug, Clone, Serialize)]
+pub struct Relationship {
+    /// The identifier of the relationship.
+    #[serde(rename = "spdxId")]
+    pub spdx_id: String,
+
+    /// The creation information of the element.
+    #[serde(rename = "creationInfo")]
+    pub creation_info: String,
+
+    /// The element the relationship is from.
+    pub from: String,
+
+    /// The type of the relationship.
+    #[serde(rename = "relationshipType")]
+    pub relationship_type: String,
+
+    /// The elements the relationship is to.
+    pub to: Vec<String>,
+
+    /// Freeform comments about the relationship.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// A license expression.
+#[derive(Debug, Clone, Serialize)]
+pub struct LicenseExpression {
+    /// The identifier of the license expression.
+    #[serde(rename = "spdxId")]
+    pub spdx_id: String,
+
+    /// The creation information of the element.
+    #[serde(rename = "creationInfo")]
+    pub creation_info: String,
+
+    /// The license expression.
+    #[serde(rename = "simplelicensing_licenseExpression")]
+    pub license_expression: String,
+
+    /// The elements holding the texts of the `LicenseRef-`s in the expression.
+    #[serde(
+        rename = "simplelicensing_customIdToUri",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub custom_id_to_uri: Vec<DictionaryEntry>,
+}
+
+/// The text of a license which isn't on the SPDX License List.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimpleLicensingText {
+    /// The identifier of the license text.
+    #[serde(rename = "spdxId")]
+    pub spdx_id: String,
+
+    /// The creation information of the element.
+    #[serde(rename = "creationInfo")]
+    pub creation_info: String,
+
+    /// The name of the license.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Freeform comments about the license.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+
+    /// The text of the license.
+    #[serde(rename = "simplelicensing_licenseText")]
+    pub license_text: String,
+}
+
+/// A key and its value.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename = "DictionaryEntry")]
+pub struct DictionaryEntry {
+    /// The key.
+    pub key: String,
+
+    /// The value.
+    pub value: String,
+}
+
+/// A checksum.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename = "Hash")]
+pub struct Hash {
+    /// The algorithm used to produce the checksum.
+    pub algorithm: String,
+
+    /// The lower case hexadecimal checksum.
+    #[serde(rename = "hashValue")]
+    pub hash_value: String,
+}
+
+/// An identifier for an element defined outside of SPDX.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename = "ExternalIdentifier")]
+pub struct ExternalIdentifier {
+    /// The kind of identifier.
+    #[serde(rename = "externalIdentifierType")]
+    pub identifier_type: String,
+
+    /// The identifier.
+    pub identifier: String,
+}
+
+impl From<&DocumentV2> for Document {
+    fn from(doc: &DocumentV2) -> Self {
+        let converter = Converter {
+            namespace: doc.document_namespace.as_str().trim_end_matches('#'),
+            document_id: doc.spdx_identifier.to_string(),
+            extracted_licenses: doc
+                .has_extracted_licensing_infos
+                .as_deref()
+                .unwrap_or_default(),
+        };
+        let mut graph = Vec::new();
+
+        // Everyone involved in creating the document.
+        let mut created_by = Vec::new();
+        let mut created_using = Vec::new();
+        for creator in doc.creation_info.creators.iter().flatten() {
+            let (name, email) = match creator {
+                Creator::Person { name, email } | Creator::Organization { name, email } => {
+                    (name, email.as_ref())
+                }
+                Creator::Tool { name } => (name, None),
+            };
+            let agent = Agent {
+                spdx_id: converter.id(&format!("SPDXRef-Agent-{}", sanitize(name))),
+                creation_info: CREATION_INFO_ID.to_string(),
+                name: name.clone(),
+                external_identifiers: email
+                    .map(|email| ExternalIdentifier {
+                        identifier_type: "email".to_string(),
+                        identifier: email.clone(),
+                    })
+                    .into_iter()
+                    .collect(),
+            };
+            match creator {
+                Creator::Person { .. } => {
+                    created_by.push(agent.spdx_id.clone());
+                    graph.push(Element::Person(agent));
+                }
+                Creator::Organization { .. } => {
+                    created_by.push(agent.spdx_id.clone());
+                    graph.push(Element::Organization(agent));
+                }
+                Creator::Tool { .. } => {
+                    created_using.push(agent.spdx_id.clone());
+                    graph.push(Element::Tool(agent));
+                }
+            }
+        }
+
+        // SPDX 3.0 needs an agent, but tools aren't agents, so fall back to
+        // describing the tools as having created the document unaided.
+        if created_by.is_empty() {
+            for tool in graph.clone() {
+                if let Element::Tool(mut agent) = tool {
+                    agent.spdx_id = format!("{}-SoftwareAgent", agent.spdx_id);
+                    created_by.push(agent.spdx_id.clone());
+                    graph.push(Element::SoftwareAgent(agent));
+                }
+            }
+        }
+
+        graph.insert(
+            0,
+            Element::CreationInfo(CreationInfo {
+                id: CREATION_INFO_ID.to_string(),
+                spec_version: SPEC_VERSION.to_string(),
+                created: doc.creation_info.created.to_string(),
+                created_by,
+                created_using,
+                comment: doc.creation_info.comment.clone(),
+            }),
+        );
+
+        for info in converter.extracted_licenses {
+            graph.push(Element::SimpleLicensingText(SimpleLicensingText {
+                spdx_id: converter.id(&info.license_id),
+                creation_info: CREATION_INFO_ID.to_string(),
+                name: info.name.clone(),
+                comment: info.comment.clone(),
+                license_text: info.extracted_text.clone(),
+            }));
+        }
+
+        for package in doc.packages.iter().flatten() {
+            graph.push(Element::Package(converter.package(package)));
+            graph.extend(converter.licenses(package));
+        }
+
+        for file in doc.files.iter().flatten() {
+            graph.push(Element::File(converter.file(file)));
+            graph.extend(converter.file_license(file));
+        }
+
+        for snippet in doc.snippets.iter().flatten() {
+            graph.push(Element::Snippet(converter.snippet(snippet)));
+            graph.extend(converter.license(
+                &snippet.spdxid,
+                "hasConcludedLicense",
+                snippet.license_concluded.as_deref(),
+            ));
+        }
+
+        // Relationships from the document itself identify what it's about.
+        let mut root_elements = Vec::new();
+        for (i, relationship) in doc.relationships.iter().flatten().enumerate() {
+            if relationship.spdx_element_id == converter.document_id
+                && matches!(
+                    relationship.relationship_type,
+                    RelationshipTypeV2::Describes
+                )
+            {
+                root_elements.push(converter.id(&relationship.related_spdx_element));
+                continue;
+            }
+
+            graph.push(Element::Relationship(converter.relationship(
+                i,
+                &relationship.spdx_element_id,
+                &relationship.relationship_type,
+                &relationship.related_spdx_element,
+                relationship.comment.as_deref(),
+            )));
+        }
+
+        // Without a DESCRIBES relationship, the document is about its packages.
+        if root_elements.is_empty() {
+            root_elements = doc
+                .packages
+                .iter()
+                .flatten()
+                .map(|package| converter.id(&package.spdxid))
+                .collect();
+        }
+
+        let elements = graph
+            .iter()
+            .filter_map(Element::spdx_id)
+            .map(ToOwned::to_owned)
+            .collect();
+        graph.insert(
+            1,
+            Element::SpdxDocument(SpdxDocument {
+                spdx_id: converter.id(&converter.document_id),
+                creation_info: CREATION_INFO_ID.to_string(),
+                name: doc.document_name.to_string(),
+                comment: doc.document_comment.clone(),
+                profile_conformance: vec![
+                    "core".to_string(),
+                    "software".to_string(),
+                    "simpleLicensing".to_string(),
+                ],
+                root_elements,
+                elements,
+            }),
+        );
+
+        Document {
+            context: CONTEXT.to_string(),
+            graph,
+        }
+    }
+}
+
+impl Element {
+    /// Get the identifier of the element, if it has one.
+    fn spdx_id(&self) -> Option<&str> {
+        match self {
+            Element::CreationInfo(_) => None,
+            Element::Person(agent)
+            | Element::Organization(agent)
+            | Element::SoftwareAgent(agent)
+            | Element::Tool(agent) => Some(&agent.spdx_id),
+            Element::SpdxDocument(doc) => Some(&doc.spdx_id),
+            Element::Package(package) => Some(&package.spdx_id),
+            Element::File(file) => Some(&file.spdx_id),
+            Element::Snippet(snippet) => Some(&snippet.spdx_id),
+            Element::Relationship(relationship) => Some(&relationship.spdx_id),
+            Element::LicenseExpression(license) => Some(&license.spdx_id),
+            Element::SimpleLicensingText(text) => Some(&text.spdx_id),
+        }
+    }
+}
+
+/// Converts SPDX 2.x elements to their SPDX 3.0 equivalents.
+struct Converter<'d> {
+    /// The namespace of the SPDX 2.x document, used as the base of element identifiers.
+    namespace: &'d str,
+    /// The identifier of the SPDX 2.x document.
+    document_id: String,
+    /// The texts of the licenses not on the SPDX License List.
+    extracted_licenses: &'d [HasExtractedLicensingInfo],
+}
+
+impl Converter<'_> {
+    /// Get the identifier of an element.
+    ///
+    /// SPDX 3.0 identifiers are IRIs, so SPDX 2.x identifiers become fragments of
+    /// the document namespace.
+    fn id(&self, spdxid: &str) -> String {
+        format!("{}#{}", self.namespace, spdxid)
+    }
+
+    /// Convert a package.
+    fn package(&self, package: &PackageV2) -> Package {
+        Package {
+            spdx_id: self.id(&package.spdxid),
+            creation_info: CREATION_INFO_ID.to_string(),
+            name: package.name.clone(),
+            summary: package.summary.clone(),
+            description: package.description.clone(),
+            comment: package.comment.clone(),
+            package_version: package.version_info.clone(),
+            download_location: Some(package.download_location.clone())
+                .filter(|location| is_assertion(location)),
+            home_page: package.homepage.clone(),
+            package_url: package.purl().map(|purl| purl.to_string()),
+            copyright_text: package
+                .copyright_text
+                .clone()
+                .filter(|text| is_assertion(text)),
+            verified_using: package
+                .checksums
+                .iter()
+                .flatten()
+                .map(|checksum| hash(&checksum.algorithm, &checksum.checksum_value))
+                .collect(),
+        }
+    }
+
+    /// Convert a file.
+    fn file(&self, file: &FileV2) -> File {
+        File {
+            spdx_id: self.id(&file.spdxid),
+            creation_info: CREATION_INFO_ID.to_string(),
+            name: file.file_name.clone(),
+            comment: file.comment.clone(),
+            primary_purpose: file
+                .file_types
+                .iter()
+                .flatten()
+                .next()
+                .map(|file_type| purpose(file_type).to_string()),
+            copyright_text: file
+                .copyright_text
+                .clone()
+                .filter(|text| is_assertion(text)),
+            verified_using: file
+                .checksums
+                .iter()
+                .flatten()
+                .map(|checksum| hash(&checksum.algorithm, &checksum.checksum_value))
+                .collect(),
+        }
+    }
+
+    /// Convert a snippet.
+    fn snippet(&self, snippet: &SnippetV2) -> Snippet {
+        let ranges = snippet.ranges.iter().flatten();
+        let byte_range = ranges.clone().find_map(|range| {
+            Some(PositiveIntegerRange {
+                begin: range.start_pointer.offset?,
+                end: range.end_pointer.offset?,
+            })
+        });
+        let line_range = ranges.clone().find_map(|range| {
+            Some(PositiveIntegerRange {
+                begin: range.start_pointer.line_number?,
+                end: range.end_pointer.line_number?,
+            })
+        });
+
+        Snippet {
+            spdx_id: self.id(&snippet.spdxid),
+            creation_info: CREATION_INFO_ID.to_string(),
+            name: snippet.name.clone(),
+            comment: snippet.comment.clone(),
+            snippet_from_file: self.id(&snippet.snippet_from_file),
+            byte_range,
+            line_range,
+            copyright_text: snippet
+                .copyright_text
+                .clone()
+                .filter(|text| is_assertion(text)),
+        }
+    }
+
+    /// Convert the licenses of a package, which are elements in their own right.
+    fn licenses(&self, package: &PackageV2) -> Vec<Element> {
+        [
+            ("hasDeclaredLicense", &package.license_declared),
+            ("hasConcludedLicense", &package.license_concluded),
+        ]
+        .into_iter()
+        .flat_map(|(relationship_type, license)| {
+            self.license(&package.spdxid, relationship_type, license.as_deref())
+        })
+        .collect()
+    }
+
+    /// Convert the concluded license of a file.
+    fn file_license(&self, file: &FileV2) -> Vec<Element> {
+        self.license(
+            &file.spdxid,
+            "hasConcludedLicense",
+            file.license_concluded.as_deref(),
+        )
+    }
+
+    /// Create a license expression and the relationship connecting it to its element.
+    fn license(
+        &self,
+        spdxid: &str,
+        relationship_type: &str,
+        license: Option<&str>,
+    ) -> Vec<Element> {
+        let license = match license.filter(|license| is_assertion(license)) {
+            Some(license) => license,
+            None => return Vec::new(),
+        };
+
+        // Point the expression at the elements holding the texts of its LicenseRefs.
+        let custom_id_to_uri = license
+            .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .filter_map(|id| {
+                self.extracted_licenses
+                    .iter()
+                    .find(|info| info.license_id == id)
+            })
+            .map(|info| DictionaryEntry {
+                key: info.license_id.clone(),
+                value: self.id(&info.license_id),
+            })
+            .collect();
+
+        let license_id = format!("{}-{}", spdxid, relationship_type);
+        vec![
+            Element::LicenseExpression(LicenseExpression {
+                spdx_id: self.id(&license_id),
+                creation_info: CREATION_INFO_ID.to_string(),
+                license_expression: license.to_string(),
+                custom_id_to_uri,
+            }),
+            Element::Relationship(Relationship {
+                spdx_id: self.id(&format!("{}-Relationship", license_id)),
+                creation_info: CREATION_INFO_ID.to_string(),
+                from: self.id(spdxid),
+                relationship_type: relationship_type.to_string(),
+                to: vec![self.id(&license_id)],
+                comment: None,
+            }),
+        ]
+    }
+
+    /// Convert a relationship.
+    ///
+    /// SPDX 3.0 dropped the inverse relationship types, so those are flipped
+    /// around, and types without an equivalent become `other`.
+    fn relationship(
+        &self,
+        index: usize,
+        from: &str,
+        relationship_type: &RelationshipTypeV2,
+        to: &str,
+        comment: Option<&str>,
+    ) -> Relationship {
+        let (from, v3_type, to) = match relationship_type {
+            RelationshipTypeV2::Contains => (from, "contains", to),
+            RelationshipTypeV2::ContainedBy => (to, "contains", from),
+            RelationshipTypeV2::DependsOn => (from, "dependsOn", to),
+            RelationshipTypeV2::DependencyOf => (to, "dependsOn", from),
+            RelationshipTypeV2::Describes => (from, "describes", to),
+            RelationshipTypeV2::DescribedBy => (to, "describes", from),
+            RelationshipTypeV2::Generates => (from, "generates", to),
+            RelationshipTypeV2::GeneratedFrom => (to, "generates", from),
+            RelationshipTypeV2::StaticLink => (from, "hasStaticLink", to),
+            RelationshipTypeV2::DynamicLink => (from, "hasDynamicLink", to),
+            _ => (from, "other", to),
+        };
+
+        // Keep the meaning of relationships without an equivalent by naming them in the comment.
+        let comment = comment.map(ToOwned::to_owned).or_else(|| {
+            let unmapped =
+                v3_type == "other" && matches!(relationship_type, RelationshipTypeV2::Other).not();
+            unmapped
+                .then(|| serde_json::to_value(relationship_type).ok())
+                .flatten()
+                .and_then(|name| name.as_str().map(ToOwned::to_owned))
+        });
+
+        Relationship {
+            spdx_id: self.id(&format!("SPDXRef-Relationship-{}", index)),
+            creation_info: CREATION_INFO_ID.to_string(),
+            from: self.id(from),
+            relationship_type: v3_type.to_string(),
+            to: vec![self.id(to)],
+            comment,
+        }
+    }
+}
+
+/// Check if a value asserts something, rather than being `NOASSERTION` or `NONE`.
+fn is_assertion(value: &str) -> bool {
+    matches!(value.trim(), NOASSERTION | "NONE" | "").not()
+}
+
+/// Make a name usable in an SPDX identifier.
+fn sanitize(name: &str) -> String {
+    name.replace(
+        |c: char| (c.is_ascii_alphanumeric() || c == '-' || c == '.').not(),
+        "-",
+    )
+}
+
+/// Convert a checksum.
+fn hash(algorithm: &Algorithm, value: &str) -> Hash {
+    let algorithm = match algorithm {
+        Algorithm::Md2 => "md2",
+        Algorithm::Md4 => "md4",
+        Algorithm::Md5 => "md5",
+        Algorithm::Md6 => "md6",
+        Algorithm::Sha1 => "sha1",
+        Algorithm::Sha224 => "sha224",
+        Algorithm::Sha256 => "sha256",
+        Algorithm::Sha384 => "sha384",
+        Algorithm::Sha512 => "sha512",
+        Algorithm::Sha3_256 => "sha3_256",
+        Algorithm::Sha3_384 => "sha3_384",
+        Algorithm::Sha3_512 => "sha3_512",
+        Algorithm::Blake2b256 => "blake2b256",
+        Algorithm::Blake2b384 => "blake2b384",
+        Algorithm::Blake2b512 => "blake2b512",
+        Algorithm::Blake3 => "blake3",
+        Algorithm::Adler32 => "adler32",
+    };
+    Hash {
+        algorithm: algorithm.to_string(),
+        hash_value: value.to_string(),
+    }
+}
+
+/// Get the purpose of a file from its type.
+fn purpose(file_type: &FileType) -> &'static str {
+    match file_type {
+        FileType::Application => "application",
+        FileType::Archive => "archive",
+        FileType::Binary => "executable",
+        FileType::Documentation => "documentation",
+        FileType::Source => "source",
+        FileType::Spdx => "bom",
+        FileType::Audio | FileType::Image | FileType::Other | FileType::Text | FileType::Video => {
+            "other"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Document, Element};
+    use crate::document::{
+        CreationInfoBuilder, Creator, DocumentBuilder, Relationship, RelationshipType,
+    };
+
+    #[test]
+    fn test_relationship_conversion() {
+        let relationship = |from: &str, relationship_type, to: &str| Relationship {
+            comment: None,
+            related_spdx_element: to.to_string(),
+            relationship_type,
+            spdx_element_id: from.to_string(),
+        };
+        let doc = DocumentBuilder::default()
+            .document_name("test.spdx.json")
+            .try_document_namespace("https://example.com/test")
+            .unwrap()
+            .creation_info(
+                CreationInfoBuilder::default()
+                    .creators(vec![Creator::tool("cargo-spdx 0.1.0")])
+                    .build()
+                    .unwrap(),
+            )
+            .relationships(vec![
+                relationship(
+                    "SPDXRef-DOCUMENT",
+                    RelationshipType::Describes,
+                    "SPDXRef-bin",
+                ),
+                relationship(
+                    "SPDXRef-bin",
+                    RelationshipType::GeneratedFrom,
+                    "SPDXRef-pkg",
+                ),
+                relationship("SPDXRef-bin", RelationshipType::TestOf, "SPDXRef-pkg"),
+            ])
+            .build()
+            .unwrap();
+
+        let doc = Document::from(&doc);
+        let spdx_document = doc.graph.iter().find_map(|element| match element {
+            Element::SpdxDocument(spdx_document) => Some(spdx_document),
+            _ => None,
+        });
+        assert_eq!(
+            spdx_document.unwrap().root_elements,
+            vec!["https://example.com/test#SPDXRef-bin"]
+        );
+
+        let relationships = doc
+            .graph
+            .iter()
+            .filter_map(|element| match element {
+                Element::Relationship(relationship) => Some(relationship),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(relationships.len(), 2);
+        assert_eq!(
+            relationships[0].from,
+            "https://example.com/test#SPDXRef-pkg"
+        );
+        assert_eq!(relationships[0].relationship_type, "generates");
+        assert_eq!(relationships[1].relationship_type, "other");
+        assert_eq!(relationships[1].comment.as_deref(), Some("TEST_OF"));
+    }
+}
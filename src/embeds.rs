@@ -0,0 +1,188 @@
+//! Maps the parts of a binary holding embedded assets.
+//!
+//! Whatever embeds assets into a binary, such as an SBOM section or data
+//! pulled in with `include_bytes!`, can record where it put them in a
+//! `{binary}.embeds.json` file alongside the binary:
+//!
+//! ```json
+//! [{ "name": "sbom", "offset": 4096, "length": 2310, "source": "foo.spdx.json" }]
+//! ```
+//!
+//! Offsets count from 0, as tools report them. Each asset becomes a snippet of
+//! the binary covering those bytes, and when its source is given the snippet
+//! is recorded as a copy of that file.
+
+use crate::document::{
+    spdx_id_safe, Algorithm, EndPointer, File, FileType, Range, Relationship, RelationshipType,
+    Snippet, StartPointer, NOASSERTION,
+};
+use anyhow::{anyhow, Context, Result};
+use cargo_metadata::camino::{Utf8Path, Utf8PathBuf};
+use serde::Deserialize;
+use std::fs;
+use std::ops::Not as _;
+
+/// An asset embedded in a binary, as recorded by the embed step.
+#[derive(Debug, Deserialize)]
+struct Embed {
+    /// What the asset is, e.g. `sbom`.
+    name: String,
+    /// Where the asset starts in the binary, counting from 0.
+    offset: u64,
+    /// The size of the asset in bytes.
+    length: u64,
+    /// The file the asset was copied from, relative to the binary's directory.
+    #[serde(default)]
+    source: Option<Utf8PathBuf>,
+    #[serde(default)]
+    comment: Option<String>,
+}
+
+/// Get the path of the file listing the assets embedded in a binary.
+fn embeds_path(binary: &Utf8Path) -> Utf8PathBuf {
+    Utf8PathBuf::from(format!("{}.embeds.json", binary))
+}
+
+/// Create a snippet for each asset recorded as embedded in the binary.
+///
+/// The source files of the assets are added to `files` if they aren't
+/// already there, and each snippet is related to the file it's a copy of.
+///
+/// # Arguments
+/// * `binary` - Path to the binary
+/// * `binary_spdxid` - SPDX ID of the binary's file entry
+/// * `files` - The files in the SBOM
+/// * `relationships` - The relationships in the SBOM
+pub fn binary_snippets(
+    binary: &Utf8Path,
+    binary_spdxid: &str,
+    files: &mut Vec<File>,
+    relationships: &mut Vec<Relationship>,
+) -> Result<Vec<Snippet>> {
+    let path = embeds_path(binary);
+    if path.exists().not() {
+        return Ok(Vec::new());
+    }
+
+    log::info!(target: "cargo_spdx", "reading embedded assets from {}", path);
+
+    let embeds: Vec<Embed> = serde_json::from_str(&fs::read_to_string(&path)?)
+        .with_context(|| format!("invalid embedded asset list {}", path))?;
+    let size = fs::metadata(binary)?.len();
+    let root = binary.parent().unwrap();
+
+    let mut snippets = Vec::new();
+    for embed in embeds {
+        let snippet = snippet(&embed, binary_spdxid, size)
+            .with_context(|| format!("invalid embedded asset '{}' in {}", embed.name, path))?;
+
+        if let Some(source) = &embed.source {
+            let source_spdxid = source_file(&root.join(source), root, files)?;
+            relationships.push(Relationship {
+                comment: None,
+                related_spdx_element: source_spdxid,
+                relationship_type: RelationshipType::CopyOf,
+                spdx_element_id: snippet.spdxid.clone(),
+            });
+        }
+
+        snippets.push(snippet);
+    }
+
+    Ok(snippets)
+}
+
+/// Create the snippet of the binary holding an asset.
+///
+/// SPDX byte ranges count from 1 and include their end.
+fn snippet(embed: &Embed, binary_spdxid: &str, size: u64) -> Result<Snippet> {
+    if embed.length == 0 {
+        return Err(anyhow!("the asset is empty"));
+    }
+    let end = embed
+        .offset
+        .checked_add(embed.length)
+        .filter(|end| *end <= size)
+        .ok_or_else(|| anyhow!("the asset extends past the end of the binary"))?;
+
+    let spdxid = spdx_id_safe(&format!("{}-Snippet-{}", binary_spdxid, embed.name));
+    Ok(Snippet {
+        annotations: None,
+        attribution_texts: None,
+        comment: embed.comment.clone(),
+        copyright_text: Some(NOASSERTION.to_string()),
+        license_comments: None,
+        license_concluded: Some(NOASSERTION.to_string()),
+        license_info_in_snippets: None,
+        name: embed.name.clone(),
+        ranges: Some(vec![Range {
+            start_pointer: StartPointer {
+                line_number: None,
+                offset: Some(i64::try_from(embed.offset + 1)?),
+                reference: binary_spdxid.to_string(),
+            },
+            end_pointer: EndPointer {
+                line_number: None,
+                offset: Some(i64::try_from(end)?),
+                reference: binary_spdxid.to_string(),
+            },
+        }]),
+        snippet_from_file: binary_spdxid.to_string(),
+        spdxid,
+    })
+}
+
+/// Get the SPDX ID of the file an asset was copied from, adding the file if
+/// it isn't already in the SBOM.
+fn source_file(path: &Utf8Path, root: &Utf8Path, files: &mut Vec<File>) -> Result<String> {
+    let file = File::try_from_file(path, root, FileType::Other, None, None)
+        .with_context(|| format!("failed to read embedded asset {}", path))?;
+
+    // Sources of the build are listed under their package, so look for the
+    // same contents rather than the same name.
+    let sha256 = |file: &File| {
+        file.checksums
+            .iter()
+            .flatten()
+            .find(|checksum| matches!(checksum.algorithm, Algorithm::Sha256))
+            .map(|checksum| checksum.checksum_value.clone())
+    };
+    let existing = files
+        .iter()
+        .find(|existing| sha256(existing).is_some() && sha256(existing) == sha256(&file));
+    if let Some(existing) = existing {
+        return Ok(existing.spdxid.clone());
+    }
+
+    let spdxid = file.spdxid.clone();
+    files.push(file);
+    Ok(spdxid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{snippet, Embed};
+
+    fn embed(offset: u64, length: u64) -> Embed {
+        Embed {
+            name: "sbom section".to_string(),
+            offset,
+            length,
+            source: None,
+            comment: None,
+        }
+    }
+
+    #[test]
+    fn test_snippet_range() {
+        let snippet = snippet(&embed(0, 16), "SPDXRef-File-foo", 16).unwrap();
+        assert_eq!(snippet.spdxid, "SPDXRef-File-foo-Snippet-sbom-section");
+        let range = &snippet.ranges.unwrap()[0];
+        assert_eq!(range.start_pointer.offset, Some(1));
+        assert_eq!(range.end_pointer.offset, Some(16));
+        assert_eq!(range.end_pointer.reference, "SPDXRef-File-foo");
+
+        assert!(super::snippet(&embed(8, 16), "SPDXRef-File-foo", 16).is_err());
+        assert!(super::snippet(&embed(8, 0), "SPDXRef-File-foo", 16).is_err());
+    }
+}
@@ -0,0 +1,508 @@
+//! Writes the document out as a CycloneDX 1.5 JSON BOM.
+//!
+//! CycloneDX models a BOM as a tree of components plus a dependency graph,
+//! so SPDX packages and files become components, `CONTAINS` relationships
+//! nest files inside their packages, and the dependency relationships become
+//! the `dependencies` graph. SPDX data without a CycloneDX equivalent is
+//! left out.
+
+use crate::document::{
+    Algorithm, Creator, Document, File, FileChecksum, Package, PackageChecksum, Relationship,
+    RelationshipType, NOASSERTION,
+};
+use crate::format::json::{self, JsonStyle};
+use anyhow::Result;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::ops::Not as _;
+
+/// The version of the CycloneDX specification written out.
+const SPEC_VERSION: &str = "1.5";
+
+/// The RFC 4122 namespace for name-based UUIDs derived from URLs.
+const URL_NAMESPACE: [u8; 16] = [
+    0x6b, 0xa7, 0xb8, 0x11, 0x9d, 0xad, 0x11, 0xd1, 0x80, 0xb4, 0x00, 0xc0, 0x4f, 0xd4, 0x30, 0xc8,
+];
+
+/// Write the document out to the provided writer.
+pub fn write<W: Write>(w: W, doc: &Document, style: JsonStyle) -> Result<()> {
+    log::info!(target: "cargo_spdx", "writing out file in CycloneDX format");
+
+    let bom = Bom::from(doc);
+    json::write(w, &bom, style)
+}
+
+/// A CycloneDX BOM.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Bom {
+    bom_format: &'static str,
+    spec_version: &'static str,
+    serial_number: String,
+    version: u32,
+    metadata: Metadata,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    components: Vec<Component>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    dependencies: Vec<Dependency>,
+}
+
+/// Information about the BOM itself.
+#[derive(Debug, Serialize)]
+struct Metadata {
+    timestamp: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Tool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<Contact>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    component: Option<Component>,
+}
+
+/// A tool used to create the BOM.
+#[derive(Debug, Serialize)]
+struct Tool {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+}
+
+/// A person or organization.
+#[derive(Debug, Serialize)]
+struct Contact {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+}
+
+/// A package or file in the BOM.
+#[derive(Debug, Clone, Serialize)]
+struct Component {
+    #[serde(rename = "type")]
+    component_type: &'static str,
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    supplier: Option<Supplier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hashes: Vec<Hash>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    licenses: Vec<LicenseChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    copyright: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpe: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    purl: Option<String>,
+    #[serde(rename = "externalReferences", skip_serializing_if = "Vec::is_empty")]
+    external_references: Vec<ExternalReference>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    components: Vec<Component>,
+}
+
+/// The supplier of a component.
+#[derive(Debug, Clone, Serialize)]
+struct Supplier {
+    name: String,
+}
+
+/// A hash of a component.
+#[derive(Debug, Clone, Serialize)]
+struct Hash {
+    alg: &'static str,
+    content: String,
+}
+
+/// Either a single license or a license expression.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+enum LicenseChoice {
+    License { license: License },
+    Expression { expression: String },
+}
+
+/// A single license, either from the SPDX License List or named.
+#[derive(Debug, Clone, Serialize)]
+struct License {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+}
+
+/// A reference to something outside the BOM.
+#[derive(Debug, Clone, Serialize)]
+struct ExternalReference {
+    #[serde(rename = "type")]
+    reference_type: &'static str,
+    url: String,
+}
+
+/// The direct dependencies of a component.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Dependency {
+    #[serde(rename = "ref")]
+    dependency_ref: String,
+    depends_on: Vec<String>,
+}
+
+impl From<&Document> for Bom {
+    fn from(doc: &Document) -> Self {
+        let packages = doc.packages.as_deref().unwrap_or_default();
+        let files = doc.files.as_deref().unwrap_or_default();
+        let relationships = doc.relationships.as_deref().unwrap_or_default();
+
+        // Files contained in a package are nested inside its component.
+        let mut contents = BTreeMap::<&str, Vec<&File>>::new();
+        let mut contained = BTreeSet::new();
+        for relationship in relationships {
+            if let RelationshipType::Contains = relationship.relationship_type {
+                if let Some(file) = files
+                    .iter()
+                    .find(|file| file.spdxid == relationship.related_spdx_element)
+                {
+                    if contained.insert(file.spdxid.as_str()) {
+                        contents
+                            .entry(relationship.spdx_element_id.as_str())
+                            .or_default()
+                            .push(file);
+                    }
+                }
+            }
+        }
+
+        let mut components = packages
+            .iter()
+            .map(|package| {
+                let mut component = Component::from(package);
+                component.components = contents
+                    .get(package.spdxid.as_str())
+                    .into_iter()
+                    .flatten()
+                    .map(|file| Component::from(*file))
+                    .collect();
+                component
+            })
+            .collect::<Vec<_>>();
+        components.extend(
+            files
+                .iter()
+                .filter(|file| contained.contains(file.spdxid.as_str()).not())
+                .map(Component::from),
+        );
+
+        // The component the document describes moves into the metadata.
+        let described = relationships
+            .iter()
+            .find(|r| {
+                matches!(r.relationship_type, RelationshipType::Describes)
+                    && r.spdx_element_id == doc.spdx_identifier.to_string()
+            })
+            .map(|r| r.related_spdx_element.as_str());
+        let component = described.and_then(|described| {
+            components
+                .iter()
+                .position(|component| component.bom_ref == described)
+                .map(|i| components.remove(i))
+        });
+
+        let mut tools = Vec::new();
+        let mut authors = Vec::new();
+        for creator in doc.creation_info.creators.iter().flatten() {
+            match creator {
+                Creator::Tool { name } => tools.push(tool(name)),
+                Creator::Person { name, email } | Creator::Organization { name, email } => authors
+                    .push(Contact {
+                        name: name.clone(),
+                        email: email.clone(),
+                    }),
+            }
+        }
+
+        Bom {
+            bom_format: "CycloneDX",
+            spec_version: SPEC_VERSION,
+            serial_number: serial_number(doc.document_namespace.as_str()),
+            version: 1,
+            metadata: Metadata {
+                timestamp: doc.creation_info.created.to_string(),
+                tools,
+                authors,
+                component,
+            },
+            components,
+            dependencies: dependencies(relationships),
+        }
+    }
+}
+
+impl From<&Package> for Component {
+    fn from(package: &Package) -> Self {
+        let mut external_references = Vec::new();
+        if let Some(homepage) = package.homepage.as_deref().filter(|h| is_assertion(h)) {
+            external_references.push(ExternalReference {
+                reference_type: "website",
+                url: homepage.to_owned(),
+            });
+        }
+        for vcs in package
+            .external_refs
+            .iter()
+            .flatten()
+            .filter(|r| r.reference_type == "vcs")
+        {
+            external_references.push(ExternalReference {
+                reference_type: "vcs",
+                url: vcs.reference_locator.clone(),
+            });
+        }
+        if is_assertion(&package.download_location) {
+            external_references.push(ExternalReference {
+                reference_type: "distribution",
+                url: package.download_location.clone(),
+            });
+        }
+
+        let external_ref = |reference_type: &str| {
+            package
+                .external_refs
+                .iter()
+                .flatten()
+                .find(|r| r.reference_type == reference_type)
+                .map(|r| r.reference_locator.clone())
+        };
+
+        Component {
+            component_type: "library",
+            bom_ref: package.spdxid.clone(),
+            supplier: package
+                .supplier
+                .as_deref()
+                .and_then(agent_name)
+                .map(|name| Supplier { name }),
+            author: package.originator.as_deref().and_then(agent_name),
+            name: package.name.clone(),
+            version: package.version_info.clone(),
+            description: package
+                .description
+                .clone()
+                .or_else(|| package.summary.clone()),
+            hashes: package
+                .checksums
+                .iter()
+                .flatten()
+                .filter_map(
+                    |PackageChecksum {
+                         algorithm,
+                         checksum_value,
+                     }| hash(algorithm, checksum_value),
+                )
+                .collect(),
+            licenses: licenses(
+                package.license_concluded.as_deref(),
+                package.license_declared.as_deref(),
+            ),
+            copyright: package.copyright_text.clone().filter(|c| is_assertion(c)),
+            cpe: external_ref("cpe23Type"),
+            purl: package.purl().map(|purl| purl.to_string()),
+            external_references,
+            components: Vec::new(),
+        }
+    }
+}
+
+impl From<&File> for Component {
+    fn from(file: &File) -> Self {
+        Component {
+            component_type: "file",
+            bom_ref: file.spdxid.clone(),
+            supplier: None,
+            author: None,
+            name: file.file_name.clone(),
+            version: None,
+            description: file.comment.clone(),
+            hashes: file
+                .checksums
+                .iter()
+                .flatten()
+                .filter_map(
+                    |FileChecksum {
+                         algorithm,
+                         checksum_value,
+                     }| hash(algorithm, checksum_value),
+                )
+                .collect(),
+            licenses: licenses(file.license_concluded.as_deref(), None),
+            copyright: file.copyright_text.clone().filter(|c| is_assertion(c)),
+            cpe: None,
+            purl: None,
+            external_references: Vec::new(),
+            components: Vec::new(),
+        }
+    }
+}
+
+/// Build the dependency graph from the SPDX relationships.
+fn dependencies(relationships: &[Relationship]) -> Vec<Dependency> {
+    let mut graph = BTreeMap::<&str, BTreeSet<&str>>::new();
+
+    for relationship in relationships {
+        let source = relationship.spdx_element_id.as_str();
+        let target = relationship.related_spdx_element.as_str();
+
+        let (dependent, dependency) = match relationship.relationship_type {
+            RelationshipType::DependsOn
+            | RelationshipType::StaticLink
+            | RelationshipType::DynamicLink
+            | RelationshipType::HasPrerequisite => (source, target),
+            RelationshipType::DependencyOf
+            | RelationshipType::BuildDependencyOf
+            | RelationshipType::DevDependencyOf
+            | RelationshipType::OptionalDependencyOf
+            | RelationshipType::ProvidedDependencyOf
+            | RelationshipType::RuntimeDependencyOf
+            | RelationshipType::TestDependencyOf
+            | RelationshipType::BuildToolOf
+            | RelationshipType::PrerequisiteFor => (target, source),
+            _ => continue,
+        };
+
+        graph.entry(dependent).or_default().insert(dependency);
+    }
+
+    graph
+        .into_iter()
+        .map(|(dependent, dependencies)| Dependency {
+            dependency_ref: dependent.to_owned(),
+            depends_on: dependencies.into_iter().map(ToOwned::to_owned).collect(),
+        })
+        .collect()
+}
+
+/// Convert a license to the CycloneDX representation.
+///
+/// The concluded license is preferred, falling back to the declared one.
+fn licenses(concluded: Option<&str>, declared: Option<&str>) -> Vec<LicenseChoice> {
+    let expression = [concluded, declared]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .find(|license| is_assertion(license));
+
+    match expression {
+        None => Vec::new(),
+        Some(expression) if expression.contains(char::is_whitespace) => {
+            vec![LicenseChoice::Expression {
+                expression: expression.to_owned(),
+            }]
+        }
+        Some(id) if id.starts_with("LicenseRef-") => vec![LicenseChoice::License {
+            license: License {
+                id: None,
+                name: Some(id.to_owned()),
+            },
+        }],
+        Some(id) => vec![LicenseChoice::License {
+            license: License {
+                id: Some(id.to_owned()),
+                name: None,
+            },
+        }],
+    }
+}
+
+/// Convert a checksum to a CycloneDX hash, if CycloneDX supports the algorithm.
+fn hash(algorithm: &Algorithm, value: &str) -> Option<Hash> {
+    let alg = match algorithm {
+        Algorithm::Md5 => "MD5",
+        Algorithm::Sha1 => "SHA-1",
+        Algorithm::Sha256 => "SHA-256",
+        Algorithm::Sha384 => "SHA-384",
+        Algorithm::Sha512 => "SHA-512",
+        _ => return None,
+    };
+
+    Some(Hash {
+        alg,
+        content: value.to_owned(),
+    })
+}
+
+/// Split a tool creator like `cargo-spdx 0.1.0` into its name and version.
+fn tool(name: &str) -> Tool {
+    match name.rsplit_once(' ') {
+        Some((name, version)) => Tool {
+            name: name.to_owned(),
+            version: Some(version.to_owned()),
+        },
+        None => Tool {
+            name: name.to_owned(),
+            version: None,
+        },
+    }
+}
+
+/// Get the name out of an SPDX agent like `Organization: Acme (contact@acme.com)`.
+fn agent_name(agent: &str) -> Option<String> {
+    match agent.parse::<Creator>().ok()? {
+        Creator::Person { name, .. }
+        | Creator::Organization { name, .. }
+        | Creator::Tool { name } => Some(name),
+    }
+}
+
+/// Check a value isn't one of the special SPDX values meaning there's no information.
+fn is_assertion(value: &str) -> bool {
+    !matches!(value.trim(), NOASSERTION | "NONE" | "")
+}
+
+/// Derive the BOM serial number from the document namespace.
+///
+/// The namespace is unique for each SPDX document, so a name-based (version 5)
+/// UUID derived from it is unique for each BOM, while staying stable when
+/// the same document is converted again.
+fn serial_number(namespace: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(URL_NAMESPACE);
+    hasher.update(namespace.as_bytes());
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(&hasher.finalize()[..16]);
+
+    // Set the version and variant bits.
+    bytes[6] = (bytes[6] & 0x0f) | 0x50;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex = hex::encode(bytes);
+    format!(
+        "urn:uuid:{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::serial_number;
+
+    #[test]
+    fn test_serial_number_is_uuid_v5() {
+        // Matches Python's `uuid.uuid5(uuid.NAMESPACE_URL, "https://example.com/sbom")`.
+        assert_eq!(
+            serial_number("https://example.com/sbom"),
+            "urn:uuid:a56f7ad0-ffa3-5fcf-9128-8a7b7348a116"
+        );
+    }
+}
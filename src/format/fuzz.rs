@@ -0,0 +1,258 @@
+//! Property tests generating malformed documents, checking the readers never
+//! panic on them.
+//!
+//! The generators build documents out of real SPDX tags, keys, and values, so
+//! the inputs get past the first field and into the parts of the readers where
+//! bugs hide, rather than being rejected straight away as noise would be.
+
+use super::{Format, ReadLimits};
+use crate::document::Document;
+use arbitrary::{Result, Unstructured};
+use serde_json::{Map, Value};
+
+/// Tags of the key-value format, including some which aren't valid.
+const TAGS: &[&str] = &[
+    "SPDXVersion",
+    "DataLicense",
+    "SPDXID",
+    "DocumentName",
+    "DocumentNamespace",
+    "ExternalDocumentRef",
+    "Creator",
+    "Created",
+    "PackageName",
+    "PackageVersion",
+    "FilesAnalyzed",
+    "PackageVerificationCode",
+    "PackageChecksum",
+    "PackageLicenseDeclared",
+    "ExternalRef",
+    "ExternalRefComment",
+    "FileName",
+    "FileChecksum",
+    "SnippetSPDXID",
+    "SnippetFromFileSPDXID",
+    "SnippetByteRange",
+    "SnippetLineRange",
+    "LicenseID",
+    "ExtractedText",
+    "LicenseName",
+    "LicenseCrossReference",
+    "LicenseComment",
+    "Relationship",
+    "RelationshipComment",
+    "Annotator",
+    "AnnotationDate",
+    "AnnotationType",
+    "AnnotationComment",
+    "SPDXREF",
+    "NotATag",
+];
+
+/// Keys of the JSON and YAML formats.
+const KEYS: &[&str] = &[
+    "spdxVersion",
+    "dataLicense",
+    "SPDXID",
+    "name",
+    "documentNamespace",
+    "creationInfo",
+    "creators",
+    "created",
+    "packages",
+    "files",
+    "snippets",
+    "hasExtractedLicensingInfos",
+    "extractedText",
+    "licenseId",
+    "relationships",
+    "annotations",
+    "checksums",
+    "algorithm",
+    "checksumValue",
+    "externalRefs",
+    "hasFiles",
+    "ranges",
+    "startPointer",
+    "endPointer",
+    "offset",
+    "reference",
+    "relationshipType",
+    "spdxElementId",
+    "relatedSpdxElement",
+    "@context",
+    "bomFormat",
+];
+
+/// Values which mean something to the readers.
+const VALUES: &[&str] = &[
+    "",
+    "SPDX-2.3",
+    "CC0-1.0",
+    "SPDXRef-DOCUMENT",
+    "SPDXRef-foo",
+    "LicenseRef-foo",
+    "NOASSERTION",
+    "https://example.com/foo",
+    "Tool: cargo-spdx",
+    "2022-01-01T00:00:00Z",
+    "SHA1: da39a3ee5e6b4b0d3255bfef95601890afd80709",
+    "PACKAGE-MANAGER purl pkg:cargo/foo@0.1.0",
+    "DocumentRef-bar https://example.com/bar SHA1: da39",
+    "SPDXRef-foo DEPENDS_ON SPDXRef-bar",
+    "SPDXRef-foo CONTAINS",
+    "1:10",
+    "10:1",
+    ":",
+    "true",
+    "nope",
+    "abc (excludes: ./a ./b)",
+    "<text>",
+    "</text>",
+    "&lt;/text&gt;",
+];
+
+/// Generate a key-value document.
+fn key_value(u: &mut Unstructured<'_>) -> Result<String> {
+    let mut contents = String::new();
+    for _ in 0..u.int_in_range(0..=64)? {
+        match u.int_in_range(0..=9)? {
+            // Lines which aren't fields.
+            0 => contents.push_str(u.arbitrary()?),
+            1 => contents.push_str("# comment"),
+            // Text which may or may not be closed.
+            2 => {
+                contents.push_str(u.choose(TAGS)?);
+                contents.push_str(": <text>");
+                contents.push_str(u.choose(VALUES)?);
+            }
+            _ => {
+                contents.push_str(u.choose(TAGS)?);
+                contents.push_str(": ");
+                contents.push_str(u.choose(VALUES)?);
+            }
+        }
+        contents.push('\n');
+    }
+    Ok(contents)
+}
+
+/// Generate a JSON value, nested no deeper than `depth`.
+fn json(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    let choice = if depth == 0 { 0..=3 } else { 0..=5 };
+    Ok(match u.int_in_range(choice)? {
+        0 => Value::Null,
+        1 => Value::Bool(u.arbitrary()?),
+        2 => Value::from(u.arbitrary::<i64>()?),
+        3 => Value::from(*u.choose(VALUES)?),
+        4 => {
+            let mut values = Vec::new();
+            for _ in 0..u.int_in_range(0..=4)? {
+                values.push(json(u, depth - 1)?);
+            }
+            Value::Array(values)
+        }
+        _ => {
+            let mut object = Map::new();
+            for _ in 0..u.int_in_range(0..=8)? {
+                object.insert(u.choose(KEYS)?.to_string(), json(u, depth - 1)?);
+            }
+            Value::Object(object)
+        }
+    })
+}
+
+/// Generate the text of a JSON document, which may be cut short.
+fn json_text(u: &mut Unstructured<'_>) -> Result<String> {
+    let mut text = json(u, 6)?.to_string();
+    if u.ratio(1, 4)? {
+        let end = u.int_in_range(0..=text.len())?;
+        let end = (0..=end).rev().find(|end| text.is_char_boundary(*end));
+        text.truncate(end.unwrap_or_default());
+    }
+    Ok(text)
+}
+
+/// Generate the text of a YAML document, which may use anchors and aliases.
+#[cfg(feature = "yaml")]
+fn yaml_text(u: &mut Unstructured<'_>) -> Result<String> {
+    let mut text = serde_yaml::to_string(&json(u, 6)?).unwrap_or_default();
+    if u.ratio(1, 4)? {
+        text.push_str("a: &a [x, y]\nb: [*a, *a]\nc: *missing\n");
+    }
+    Ok(text)
+}
+
+/// Run a check on many generated inputs, from a fixed seed so failures repeat.
+fn check_generated(check: impl Fn(&mut Unstructured<'_>) -> Result<()>) {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for _ in 0..500 {
+        let bytes: Vec<u8> = (0..1024)
+            .map(|_| {
+                // xorshift64
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        check(&mut Unstructured::new(&bytes)).unwrap();
+    }
+}
+
+#[test]
+fn test_read_generated_key_value() {
+    check_generated(|u| {
+        let _ = Document::from_reader(key_value(u)?.as_bytes(), Format::KeyValue);
+        Ok(())
+    });
+}
+
+#[test]
+fn test_read_generated_json() {
+    check_generated(|u| {
+        let _ = Document::from_reader(json_text(u)?.as_bytes(), Format::Json);
+        Ok(())
+    });
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn test_read_generated_yaml() {
+    check_generated(|u| {
+        let text = yaml_text(u)?;
+        let limits = ReadLimits::default();
+        let _ = Document::from_untrusted_reader(text.as_bytes(), Format::Yaml, &limits);
+        Ok(())
+    });
+}
+
+#[test]
+fn test_read_limits() {
+    let limits = ReadLimits {
+        max_bytes: 16,
+        max_depth: 2,
+    };
+    let read = |text: &str| Document::from_untrusted_reader(text.as_bytes(), Format::Json, &limits);
+    let error = |text: &str| read(text).unwrap_err().to_string();
+    assert!(error("{\"name\": \"0123456789\"}").contains("larger"));
+    assert!(error("[[[1]]]").contains("nests"));
+    // Brackets in strings don't count.
+    assert!(!error("[[\"[[[\"]]").contains("nests"));
+
+    let limits = ReadLimits::default();
+    let deep = format!("{}{}", "[".repeat(10_000), "]".repeat(10_000));
+    assert!(Document::from_untrusted_reader(deep.as_bytes(), Format::Json, &limits).is_err());
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn test_reject_yaml_aliases() {
+    let limits = ReadLimits::default();
+    let read = |text: &str| Document::from_untrusted_reader(text.as_bytes(), Format::Yaml, &limits);
+    let error = read("a: &a [x]\nb: [*a, *a]\n").unwrap_err();
+    assert!(error.to_string().contains("aliases"));
+    // Text which only looks like an alias is fine.
+    let error = read("name: \"*a\"\n").unwrap_err();
+    assert!(!error.to_string().contains("aliases"));
+}
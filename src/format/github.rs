@@ -0,0 +1,423 @@
+//! Writes the document out as a GitHub dependency submission snapshot.
+//!
+//! Snapshots are what GitHub's dependency submission API takes to add
+//! dependencies to a repository's dependency graph. Only packages with a
+//! package URL can be listed, so build tools and native libraries are left
+//! out, and the packages are all listed under a single `Cargo.lock` manifest.
+//!
+//! A snapshot names the commit and workflow job it was taken for, which are
+//! read from the variables GitHub Actions sets, falling back to asking `git`
+//! when run elsewhere.
+
+use crate::document::{Document, RelationshipType};
+use crate::format::json::{self, JsonStyle};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::io::Write;
+use std::ops::Not as _;
+use std::process::Command;
+
+/// The version of the snapshot format written out.
+const SNAPSHOT_VERSION: u32 = 0;
+
+/// The manifest every package is listed under.
+const MANIFEST: &str = "Cargo.lock";
+
+/// Write the document out to the provided writer.
+pub fn write<W: Write>(w: W, doc: &Document, style: JsonStyle) -> Result<()> {
+    log::info!(target: "cargo_spdx", "writing out file in GitHub dependency snapshot format");
+
+    let snapshot = Snapshot::new(doc)?;
+    json::write(w, &snapshot, style)
+}
+
+/// A snapshot of the dependencies of a commit.
+#[derive(Debug, Serialize)]
+struct Snapshot {
+    version: u32,
+    sha: String,
+    #[serde(rename = "ref")]
+    git_ref: String,
+    job: Job,
+    detector: Detector,
+    scanned: String,
+    metadata: BTreeMap<&'static str, String>,
+    manifests: BTreeMap<&'static str, Manifest>,
+}
+
+/// The workflow job the snapshot was taken in.
+#[derive(Debug, Serialize)]
+struct Job {
+    /// Snapshots with the same correlator replace each other.
+    correlator: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html_url: Option<String>,
+}
+
+/// The tool which found the dependencies.
+#[derive(Debug, Serialize)]
+struct Detector {
+    name: &'static str,
+    version: &'static str,
+    url: &'static str,
+}
+
+/// A file declaring dependencies, with what they resolved to.
+#[derive(Debug, Serialize)]
+struct Manifest {
+    name: String,
+    file: ManifestFile,
+    resolved: BTreeMap<String, Dependency>,
+}
+
+/// Where a manifest is in the repository.
+#[derive(Debug, Serialize)]
+struct ManifestFile {
+    source_location: &'static str,
+}
+
+/// A package a manifest resolved to.
+#[derive(Debug, Serialize)]
+struct Dependency {
+    package_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    relationship: Option<&'static str>,
+    scope: &'static str,
+    dependencies: Vec<String>,
+}
+
+impl Snapshot {
+    /// Map the document's packages to a snapshot of the current commit.
+    fn new(doc: &Document) -> Result<Self> {
+        let correlator = match (env::var("GITHUB_WORKFLOW"), env::var("GITHUB_JOB")) {
+            (Ok(workflow), Ok(job)) => format!("{}_{}", workflow, job),
+            _ => "cargo-spdx".to_string(),
+        };
+        let run_id = env::var("GITHUB_RUN_ID").ok();
+        let html_url = match (env::var("GITHUB_SERVER_URL"), env::var("GITHUB_REPOSITORY")) {
+            (Ok(server), Ok(repository)) => run_id
+                .as_ref()
+                .map(|run_id| format!("{}/{}/actions/runs/{}", server, repository, run_id)),
+            _ => None,
+        };
+
+        Ok(Snapshot {
+            version: SNAPSHOT_VERSION,
+            sha: env::var("GITHUB_SHA").or_else(|_| git(&["rev-parse", "HEAD"]))?,
+            git_ref: env::var("GITHUB_REF").or_else(|_| git(&["symbolic-ref", "HEAD"]))?,
+            job: Job {
+                // Each document gets its own correlator, so the snapshots for
+                // several binaries built by one job don't replace each other.
+                correlator: format!("{}_{}", correlator, doc.document_name),
+                id: run_id,
+                html_url,
+            },
+            detector: Detector {
+                name: env!("CARGO_PKG_NAME"),
+                version: env!("CARGO_PKG_VERSION"),
+                url: env!("CARGO_PKG_REPOSITORY"),
+            },
+            scanned: doc.creation_info.created.to_string(),
+            metadata: BTreeMap::from([(
+                "spdx:documentNamespace",
+                doc.document_namespace.to_string(),
+            )]),
+            manifests: BTreeMap::from([(
+                MANIFEST,
+                Manifest {
+                    name: doc.document_name.to_string(),
+                    file: ManifestFile {
+                        source_location: MANIFEST,
+                    },
+                    resolved: resolved(doc),
+                },
+            )]),
+        })
+    }
+}
+
+/// List the packages with a package URL, with what each of them depends on.
+fn resolved(doc: &Document) -> BTreeMap<String, Dependency> {
+    let packages = doc.packages.as_deref().unwrap_or_default();
+    let relationships = doc.relationships.as_deref().unwrap_or_default();
+
+    let purls = packages
+        .iter()
+        .filter_map(|package| Some((package.spdxid.as_str(), package.purl()?.to_string())))
+        .collect::<BTreeMap<_, _>>();
+
+    // The packages the document describes, or which the binaries it describes
+    // were generated from, are the project itself.
+    let document_id = doc.spdx_identifier.to_string();
+    let described: BTreeSet<&str> = relationships
+        .iter()
+        .filter(|r| {
+            matches!(r.relationship_type, RelationshipType::Describes)
+                && r.spdx_element_id == document_id
+        })
+        .map(|r| r.related_spdx_element.as_str())
+        .collect();
+    let roots: BTreeSet<&str> = relationships
+        .iter()
+        .filter(|r| {
+            matches!(r.relationship_type, RelationshipType::GeneratedFrom)
+                && described.contains(r.spdx_element_id.as_str())
+        })
+        .map(|r| r.related_spdx_element.as_str())
+        .chain(described.iter().copied())
+        .collect();
+
+    // Packages depended on by the project, or only by something other than a
+    // package, such as a binary, are direct dependencies, and the rest are indirect.
+    let mut dependencies = BTreeMap::<&str, BTreeSet<&str>>::new();
+    let mut direct = BTreeSet::new();
+    let mut indirect = BTreeSet::new();
+    let mut linked = BTreeSet::new();
+    // Each dependent and dependency, and whether it's only used in development
+    let mut edges = Vec::new();
+    for relationship in relationships {
+        let (dependent, dependency, development) = match relationship.relationship_type {
+            RelationshipType::DependsOn
+            | RelationshipType::StaticLink
+            | RelationshipType::DynamicLink => (
+                relationship.spdx_element_id.as_str(),
+                relationship.related_spdx_element.as_str(),
+                false,
+            ),
+            RelationshipType::DependencyOf
+            | RelationshipType::RuntimeDependencyOf
+            | RelationshipType::OptionalDependencyOf => (
+                relationship.related_spdx_element.as_str(),
+                relationship.spdx_element_id.as_str(),
+                false,
+            ),
+            RelationshipType::DevDependencyOf
+            | RelationshipType::BuildDependencyOf
+            | RelationshipType::BuildToolOf => (
+                relationship.related_spdx_element.as_str(),
+                relationship.spdx_element_id.as_str(),
+                true,
+            ),
+            _ => continue,
+        };
+        edges.push((dependent, dependency, development));
+        let dependency = match purls.get(dependency) {
+            Some(purl) => purl.as_str(),
+            None => continue,
+        };
+        match purls.get(dependent) {
+            Some(purl) if purl == dependency => {}
+            Some(_) => {
+                if roots.contains(dependent) {
+                    direct.insert(dependency);
+                } else {
+                    indirect.insert(dependency);
+                }
+                dependencies
+                    .entry(dependent)
+                    .or_default()
+                    .insert(dependency);
+            }
+            None => {
+                linked.insert(dependency);
+            }
+        }
+    }
+    // A binary links everything in it, but only what nothing else in the
+    // project depends on is a direct dependency.
+    direct.extend(linked.difference(&indirect));
+
+    // Packages only reachable through dev- or build-dependencies, or build
+    // tools such as proc-macros, aren't shipped, so are only used in development.
+    let runtime = reachable(
+        &edges,
+        edges
+            .iter()
+            .filter(|(dependent, _, _)| {
+                roots.contains(dependent) || purls.contains_key(dependent).not()
+            })
+            .map(|(dependent, _, _)| *dependent)
+            .collect(),
+        false,
+    );
+    let development = reachable(
+        &edges,
+        edges
+            .iter()
+            .filter(|(_, _, dev)| *dev)
+            .map(|(_, dependency, _)| *dependency)
+            .collect(),
+        true,
+    );
+
+    purls
+        .iter()
+        .map(|(spdxid, purl)| {
+            let relationship = if direct.contains(purl.as_str()) {
+                Some("direct")
+            } else if indirect.contains(purl.as_str()) {
+                Some("indirect")
+            } else {
+                None
+            };
+            let dependency = Dependency {
+                package_url: purl.clone(),
+                relationship,
+                scope: if development.contains(spdxid) && runtime.contains(spdxid).not() {
+                    "development"
+                } else {
+                    "runtime"
+                },
+                dependencies: dependencies
+                    .get(spdxid)
+                    .into_iter()
+                    .flatten()
+                    .map(ToString::to_string)
+                    .collect(),
+            };
+            (purl.clone(), dependency)
+        })
+        .collect()
+}
+
+// Find what can be reached from the `starts` along dependency edges, only
+// following dev- and build-dependencies if `development` is set
+fn reachable<'a>(
+    edges: &[(&'a str, &'a str, bool)],
+    starts: Vec<&'a str>,
+    development: bool,
+) -> BTreeSet<&'a str> {
+    let mut found: BTreeSet<&str> = starts.iter().copied().collect();
+    let mut queue = starts;
+    while let Some(id) = queue.pop() {
+        for (dependent, dependency, dev) in edges {
+            if *dependent == id && (development || dev.not()) && found.insert(dependency) {
+                queue.push(dependency);
+            }
+        }
+    }
+    found
+}
+
+/// Ask git about the current commit, for snapshots taken outside GitHub Actions.
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).output()?;
+    if output.status.success().not() {
+        return Err(anyhow!(
+            "GitHub dependency snapshots need the commit they're for, but `git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolved;
+    use crate::document::{
+        CreationInfoBuilder, Creator, DocumentBuilder, ExternalRef, Package, ReferenceCategory,
+        Relationship, RelationshipType,
+    };
+
+    fn package(name: &str) -> Package {
+        let mut package = Package::build_tool(name);
+        package.spdxid = format!("SPDXRef-{}", name);
+        package.external_refs = Some(vec![ExternalRef {
+            reference_category: ReferenceCategory::PackageManager,
+            reference_type: "purl".to_string(),
+            reference_locator: format!("pkg:cargo/{}@1.0.0", name),
+            comment: None,
+        }]);
+        package
+    }
+
+    fn relationship(from: &str, relationship_type: RelationshipType, to: &str) -> Relationship {
+        Relationship {
+            comment: None,
+            related_spdx_element: to.to_string(),
+            relationship_type,
+            spdx_element_id: from.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_resolved_relationships() {
+        let doc = DocumentBuilder::default()
+            .document_name("foo.github.json")
+            .try_document_namespace("https://example.com/foo")
+            .unwrap()
+            .creation_info(
+                CreationInfoBuilder::default()
+                    .creators(vec![Creator::tool("cargo-spdx 0.1.0")])
+                    .build()
+                    .unwrap(),
+            )
+            .packages(vec![
+                package("foo"),
+                package("bar"),
+                package("baz"),
+                package("qux"),
+                package("test"),
+                package("mock"),
+            ])
+            .relationships(vec![
+                relationship(
+                    "SPDXRef-DOCUMENT",
+                    RelationshipType::Describes,
+                    "SPDXRef-File-foo",
+                ),
+                relationship(
+                    "SPDXRef-File-foo",
+                    RelationshipType::GeneratedFrom,
+                    "SPDXRef-foo",
+                ),
+                relationship(
+                    "SPDXRef-File-foo",
+                    RelationshipType::StaticLink,
+                    "SPDXRef-qux",
+                ),
+                relationship(
+                    "SPDXRef-File-foo",
+                    RelationshipType::StaticLink,
+                    "SPDXRef-baz",
+                ),
+                relationship("SPDXRef-foo", RelationshipType::DependsOn, "SPDXRef-bar"),
+                relationship("SPDXRef-bar", RelationshipType::DependsOn, "SPDXRef-baz"),
+                relationship(
+                    "SPDXRef-test",
+                    RelationshipType::DevDependencyOf,
+                    "SPDXRef-foo",
+                ),
+                relationship("SPDXRef-test", RelationshipType::DependsOn, "SPDXRef-mock"),
+                relationship("SPDXRef-test", RelationshipType::DependsOn, "SPDXRef-baz"),
+            ])
+            .build()
+            .unwrap();
+
+        let resolved = resolved(&doc);
+        let foo = &resolved["pkg:cargo/foo@1.0.0"];
+        assert_eq!(foo.relationship, None);
+        assert_eq!(
+            foo.dependencies,
+            ["pkg:cargo/bar@1.0.0", "pkg:cargo/test@1.0.0"]
+        );
+        assert_eq!(resolved["pkg:cargo/bar@1.0.0"].relationship, Some("direct"));
+        assert_eq!(
+            resolved["pkg:cargo/baz@1.0.0"].relationship,
+            Some("indirect")
+        );
+        assert_eq!(resolved["pkg:cargo/qux@1.0.0"].relationship, Some("direct"));
+        assert_eq!(
+            resolved["pkg:cargo/test@1.0.0"].relationship,
+            Some("direct")
+        );
+        assert_eq!(resolved["pkg:cargo/test@1.0.0"].scope, "development");
+        assert_eq!(resolved["pkg:cargo/mock@1.0.0"].scope, "development");
+        assert_eq!(resolved["pkg:cargo/baz@1.0.0"].scope, "runtime");
+    }
+}
@@ -0,0 +1,83 @@
+//! Writes documents out as JSON.
+//!
+//! Documents are serialized straight into the output as they're walked, so
+//! packages and files are written one at a time rather than building up the
+//! whole document in memory first.
+
+use anyhow::{anyhow, Error, Result};
+use serde::Serialize;
+use serde_json::ser::{CompactFormatter, Formatter, PrettyFormatter, Serializer};
+use std::fmt::{self, Display};
+use std::io::Write;
+use std::str::FromStr;
+
+/// How JSON output is laid out.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JsonStyle {
+    /// Indented, one field per line.
+    Pretty,
+    /// No whitespace at all, which is smaller and faster to write.
+    Compact,
+}
+
+impl Default for JsonStyle {
+    fn default() -> Self {
+        JsonStyle::Pretty
+    }
+}
+
+impl Display for JsonStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonStyle::Pretty => write!(f, "pretty"),
+            JsonStyle::Compact => write!(f, "compact"),
+        }
+    }
+}
+
+impl FromStr for JsonStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(JsonStyle::Pretty),
+            "compact" => Ok(JsonStyle::Compact),
+            s => Err(anyhow!("unknown JSON style '{}'", s)),
+        }
+    }
+}
+
+/// Write the value out to the provided writer in the given style.
+pub fn write<W: Write, T: Serialize>(w: W, value: &T, style: JsonStyle) -> Result<()> {
+    match style {
+        JsonStyle::Pretty => write_with(w, value, PrettyFormatter::new()),
+        JsonStyle::Compact => write_with(w, value, CompactFormatter),
+    }
+}
+
+/// Serialize the value into the writer using the given formatter.
+fn write_with<W: Write, T: Serialize, F: Formatter>(w: W, value: &T, formatter: F) -> Result<()> {
+    let mut serializer = Serializer::with_formatter(w, formatter);
+    value.serialize(&mut serializer)?;
+    serializer.into_inner().flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write, JsonStyle};
+    use serde_json::json;
+
+    #[test]
+    fn test_styles() {
+        let value = json!({ "packages": [{ "name": "a" }] });
+
+        let mut compact = Vec::new();
+        write(&mut compact, &value, JsonStyle::Compact).unwrap();
+        assert_eq!(compact, br#"{"packages":[{"name":"a"}]}"#);
+
+        let mut pretty = Vec::new();
+        write(&mut pretty, &value, JsonStyle::Pretty).unwrap();
+        assert_eq!(pretty, serde_json::to_vec_pretty(&value).unwrap());
+    }
+}
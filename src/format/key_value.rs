@@ -1,8 +1,15 @@
-//! Writes the flat file format out.
+//! Reads and writes the flat file format.
 
-use crate::document::Document;
-use anyhow::Result;
-use std::io::Write;
+use super::variant_name;
+use crate::document::{
+    Document, File, HasExtractedLicensingInfo, Package, Relationship, RelationshipType, Snippet,
+};
+use anyhow::{anyhow, Context, Result};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+use std::ops::Not as _;
 
 /// Convenience macro to provide uniform field-writing syntax.
 ///
@@ -40,6 +47,61 @@ macro_rules! write_field {
             }
         }
     };
+
+    // Write out a free-form text field.
+    ( @text, $f:ident, $fmt:literal, $field:expr ) => {
+        write_field!($f, $fmt, Text(&$field))
+    };
+
+    // Write out an optional free-form text field.
+    ( @opttext, $f:ident, $fmt:literal, $field:expr ) => {
+        if let Some(field) = &$field {
+            write_field!(@text, $f, $fmt, field);
+        }
+    };
+
+    // Write out an optional iterable free-form text field.
+    ( @optalltext, $f:ident, $fmt:literal, $field:expr ) => {
+        if let Some(field) = &$field {
+            for item in field {
+                write_field!(@text, $f, $fmt, item);
+            }
+        }
+    };
+}
+
+/// A free-form text value.
+///
+/// Multi-line values, and values which could otherwise be misread as another
+/// field, are wrapped in `<text>...</text>` as the tag-value format requires.
+struct Text<'s>(&'s str);
+
+impl Text<'_> {
+    /// Check if the value has to be wrapped to be read back correctly.
+    fn needs_wrapping(&self) -> bool {
+        let text = self.0;
+        text.contains(['\n', '\r', ':'])
+            || text.starts_with(char::is_whitespace)
+            || text.ends_with(char::is_whitespace)
+            || text.contains("<text>")
+    }
+}
+
+impl Display for Text<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.needs_wrapping().not() {
+            return write!(f, "{}", self.0);
+        }
+
+        // There's no escape sequence in the tag-value format, so the closing
+        // tag is escaped the way it would be in XML to keep it from ending the
+        // text early.
+        write!(
+            f,
+            "<text>{}</text>",
+            self.0.replace("</text>", "&lt;/text&gt;")
+        )
+    }
 }
 
 /// Write the document out to the provided writer.
@@ -51,12 +113,1060 @@ pub fn write<W: Write>(mut w: W, doc: &Document) -> Result<()> {
     write_field!(w, "SPDXID: {}", doc.spdx_identifier);
     write_field!(w, "DocumentName: {}", doc.document_name);
     write_field!(w, "DocumentNamespace: {}", doc.document_namespace);
-    write_field!(@opt, w, "ExternalDocumentRef: {}", doc.external_document_reference);
+    for reference in doc.external_document_references.iter().flatten() {
+        let algorithm = variant_name(&reference.checksum.algorithm)?;
+        write_field!(
+            w,
+            "ExternalDocumentRef: {}",
+            format!(
+                "{} {} {}: {}",
+                reference.id_string,
+                reference.document_uri,
+                algorithm,
+                reference.checksum.checksum_value
+            )
+        );
+    }
     write_field!(@opt, w, "LicenseListVersion: {}", doc.creation_info.license_list_version);
     write_field!(@optall, w, "Creator: {}", doc.creation_info.creators);
     write_field!(w, "Created: {}", doc.creation_info.created);
-    write_field!(@opt, w, "CreatorComment: {}", doc.creation_info.comment);
-    write_field!(@opt, w, "DocumentComment: {}", doc.document_comment);
+    write_field!(@opttext, w, "CreatorComment: {}", doc.creation_info.comment);
+    write_field!(@opttext, w, "DocumentComment: {}", doc.document_comment);
+    for annotation in doc.annotations.iter().flatten() {
+        write_field!(w, "Annotator: {}", annotation.annotator);
+        write_field!(w, "AnnotationDate: {}", annotation.annotation_date);
+        write_field!(
+            w,
+            "AnnotationType: {}",
+            variant_name(&annotation.annotation_type)?
+        );
+        write_field!(w, "SPDXREF: {}", doc.spdx_identifier);
+        write_field!(@text, w, "AnnotationComment: {}", annotation.comment);
+    }
+
+    let files = doc.files.as_deref().unwrap_or_default();
+    let packages = doc.packages.as_deref().unwrap_or_default();
+    let relationships = doc.relationships.as_deref().unwrap_or_default();
+
+    // Files listed after a package are read as belonging to that package, so
+    // each package is followed by the files it contains, and the files which
+    // don't belong to any package are written out first.
+    let owners = file_owners(packages, relationships);
+
+    for file in files
+        .iter()
+        .filter(|file| owners.contains_key(file.spdxid.as_str()).not())
+    {
+        write_file(&mut w, file)?;
+    }
+
+    for package in packages {
+        write_package(&mut w, package)?;
+
+        for file in files
+            .iter()
+            .filter(|file| owners.get(file.spdxid.as_str()) == Some(&package.spdxid.as_str()))
+        {
+            write_file(&mut w, file)?;
+        }
+    }
+
+    for snippet in doc.snippets.iter().flatten() {
+        write_snippet(&mut w, snippet)?;
+    }
+
+    let infos = doc
+        .has_extracted_licensing_infos
+        .as_deref()
+        .unwrap_or_default();
+    if infos.is_empty().not() {
+        writeln!(w)?;
+        writeln!(w, "##### Other Licensing Information")?;
+    }
+
+    for info in infos {
+        write_extracted_license(&mut w, info)?;
+    }
+
+    if relationships.is_empty().not() {
+        writeln!(w)?;
+        writeln!(w, "##### Relationships")?;
+        writeln!(w)?;
+    }
+
+    for relationship in relationships {
+        write_relationship(&mut w, relationship)?;
+    }
+
+    Ok(())
+}
+
+/// Write out a single package section.
+fn write_package<W: Write>(mut w: W, package: &Package) -> Result<()> {
+    writeln!(w)?;
+    writeln!(w, "##### Package: {}", package.name)?;
+    writeln!(w)?;
+
+    write_field!(w, "PackageName: {}", package.name);
+    write_field!(w, "SPDXID: {}", package.spdxid);
+    write_field!(@opt, w, "PackageVersion: {}", package.version_info);
+    write_field!(@opt, w, "PackageFileName: {}", package.package_file_name);
+    write_field!(@opt, w, "PackageSupplier: {}", package.supplier);
+    write_field!(@opt, w, "PackageOriginator: {}", package.originator);
+    write_field!(w, "PackageDownloadLocation: {}", package.download_location);
+    write_field!(@opt, w, "FilesAnalyzed: {}", package.files_analyzed);
+
+    if let Some(code) = &package.package_verification_code {
+        match &code.package_verification_code_excluded_files {
+            Some(excluded) if excluded.is_empty().not() => write_field!(
+                w,
+                "PackageVerificationCode: {}",
+                format!(
+                    "{} (excludes: {})",
+                    code.package_verification_code_value,
+                    excluded.join(" ")
+                )
+            ),
+            _ => write_field!(
+                w,
+                "PackageVerificationCode: {}",
+                code.package_verification_code_value
+            ),
+        }
+    }
+
+    for checksum in package.checksums.iter().flatten() {
+        let algorithm = variant_name(&checksum.algorithm)?;
+        write_field!(
+            w,
+            "PackageChecksum: {}",
+            format!("{}: {}", algorithm, checksum.checksum_value)
+        );
+    }
+
+    write_field!(@opt, w, "PackageHomePage: {}", package.homepage);
+    write_field!(@opttext, w, "PackageSourceInfo: {}", package.source_info);
+    write_field!(@opt, w, "PackageLicenseConcluded: {}", package.license_concluded);
+    write_field!(@optall, w, "PackageLicenseInfoFromFiles: {}", package.license_info_from_files);
+    write_field!(@opt, w, "PackageLicenseDeclared: {}", package.license_declared);
+    write_field!(@opttext, w, "PackageLicenseComments: {}", package.license_comments);
+    write_field!(@opttext, w, "PackageCopyrightText: {}", package.copyright_text);
+    write_field!(@opttext, w, "PackageSummary: {}", package.summary);
+    write_field!(@opttext, w, "PackageDescription: {}", package.description);
+    write_field!(@opttext, w, "PackageComment: {}", package.comment);
+
+    for external_ref in package.external_refs.iter().flatten() {
+        // The tag-value format spells the categories with hyphens, e.g. `PACKAGE-MANAGER`.
+        let category = variant_name(&external_ref.reference_category)?.replace('_', "-");
+        write_field!(
+            w,
+            "ExternalRef: {}",
+            format!(
+                "{} {} {}",
+                category, external_ref.reference_type, external_ref.reference_locator
+            )
+        );
+        write_field!(@opttext, w, "ExternalRefComment: {}", external_ref.comment);
+    }
+
+    write_field!(@optalltext, w, "PackageAttributionText: {}", package.attribution_texts);
+
+    for annotation in package.annotations.iter().flatten() {
+        write_field!(w, "Annotator: {}", annotation.annotator);
+        write_field!(w, "AnnotationDate: {}", annotation.annotation_date);
+        write_field!(
+            w,
+            "AnnotationType: {}",
+            variant_name(&annotation.annotation_type)?
+        );
+        write_field!(w, "SPDXREF: {}", package.spdxid);
+        write_field!(@text, w, "AnnotationComment: {}", annotation.comment);
+    }
 
     Ok(())
 }
+
+/// Write out a single file section.
+fn write_file<W: Write>(mut w: W, file: &File) -> Result<()> {
+    writeln!(w)?;
+    write_field!(w, "FileName: {}", file.file_name);
+    write_field!(w, "SPDXID: {}", file.spdxid);
+
+    for file_type in file.file_types.iter().flatten() {
+        write_field!(w, "FileType: {}", variant_name(file_type)?);
+    }
+
+    for checksum in file.checksums.iter().flatten() {
+        let algorithm = variant_name(&checksum.algorithm)?;
+        write_field!(
+            w,
+            "FileChecksum: {}",
+            format!("{}: {}", algorithm, checksum.checksum_value)
+        );
+    }
+
+    write_field!(@opt, w, "LicenseConcluded: {}", file.license_concluded);
+    write_field!(@optall, w, "LicenseInfoInFile: {}", file.license_info_in_files);
+    write_field!(@opttext, w, "LicenseComments: {}", file.license_comments);
+    write_field!(@opttext, w, "FileCopyrightText: {}", file.copyright_text);
+    write_field!(@opttext, w, "FileComment: {}", file.comment);
+    write_field!(@opttext, w, "FileNotice: {}", file.notice_text);
+    write_field!(@optall, w, "FileContributor: {}", file.file_contributors);
+    write_field!(@optalltext, w, "FileAttributionText: {}", file.attribution_texts);
+
+    for annotation in file.annotations.iter().flatten() {
+        write_field!(w, "Annotator: {}", annotation.annotator);
+        write_field!(w, "AnnotationDate: {}", annotation.annotation_date);
+        write_field!(
+            w,
+            "AnnotationType: {}",
+            variant_name(&annotation.annotation_type)?
+        );
+        write_field!(w, "SPDXREF: {}", file.spdxid);
+        write_field!(@text, w, "AnnotationComment: {}", annotation.comment);
+    }
+
+    Ok(())
+}
+
+/// Write out a single snippet section.
+fn write_snippet<W: Write>(mut w: W, snippet: &Snippet) -> Result<()> {
+    writeln!(w)?;
+    write_field!(w, "SnippetSPDXID: {}", snippet.spdxid);
+    write_field!(w, "SnippetFromFileSPDXID: {}", snippet.snippet_from_file);
+
+    for range in snippet.ranges.iter().flatten() {
+        let (start, end) = (&range.start_pointer, &range.end_pointer);
+        if let (Some(start), Some(end)) = (start.offset, end.offset) {
+            write_field!(w, "SnippetByteRange: {}", format!("{}:{}", start, end));
+        }
+        if let (Some(start), Some(end)) = (start.line_number, end.line_number) {
+            write_field!(w, "SnippetLineRange: {}", format!("{}:{}", start, end));
+        }
+    }
+
+    write_field!(@opt, w, "SnippetLicenseConcluded: {}", snippet.license_concluded);
+    write_field!(@optall, w, "LicenseInfoInSnippet: {}", snippet.license_info_in_snippets);
+    write_field!(@opttext, w, "SnippetLicenseComments: {}", snippet.license_comments);
+    write_field!(@opttext, w, "SnippetCopyrightText: {}", snippet.copyright_text);
+    write_field!(@opttext, w, "SnippetComment: {}", snippet.comment);
+    write_field!(w, "SnippetName: {}", snippet.name);
+    write_field!(@optalltext, w, "SnippetAttributionText: {}", snippet.attribution_texts);
+
+    for annotation in snippet.annotations.iter().flatten() {
+        write_field!(w, "Annotator: {}", annotation.annotator);
+        write_field!(w, "AnnotationDate: {}", annotation.annotation_date);
+        write_field!(
+            w,
+            "AnnotationType: {}",
+            variant_name(&annotation.annotation_type)?
+        );
+        write_field!(w, "SPDXREF: {}", snippet.spdxid);
+        write_field!(@text, w, "AnnotationComment: {}", annotation.comment);
+    }
+
+    Ok(())
+}
+
+/// Write out a single license which isn't on the SPDX License List.
+fn write_extracted_license<W: Write>(mut w: W, info: &HasExtractedLicensingInfo) -> Result<()> {
+    writeln!(w)?;
+    write_field!(w, "LicenseID: {}", info.license_id);
+    write_field!(@text, w, "ExtractedText: {}", info.extracted_text);
+    write_field!(@opttext, w, "LicenseName: {}", info.name);
+    write_field!(@optall, w, "LicenseCrossReference: {}", info.see_alsos);
+    write_field!(@opttext, w, "LicenseComment: {}", info.comment);
+    Ok(())
+}
+
+/// Write out a single relationship.
+fn write_relationship<W: Write>(mut w: W, relationship: &Relationship) -> Result<()> {
+    write_field!(
+        w,
+        "Relationship: {}",
+        format!(
+            "{} {} {}",
+            relationship.spdx_element_id,
+            variant_name(&relationship.relationship_type)?,
+            relationship.related_spdx_element
+        )
+    );
+    write_field!(@opttext, w, "RelationshipComment: {}", relationship.comment);
+    Ok(())
+}
+
+/// Package fields holding a single value, with the JSON names they're read into.
+const PACKAGE_FIELDS: &[(&str, &str)] = &[
+    ("PackageVersion", "versionInfo"),
+    ("PackageFileName", "packageFileName"),
+    ("PackageSupplier", "supplier"),
+    ("PackageOriginator", "originator"),
+    ("PackageDownloadLocation", "downloadLocation"),
+    ("PackageHomePage", "homepage"),
+    ("PackageSourceInfo", "sourceInfo"),
+    ("PackageLicenseConcluded", "licenseConcluded"),
+    ("PackageLicenseDeclared", "licenseDeclared"),
+    ("PackageLicenseComments", "licenseComments"),
+    ("PackageCopyrightText", "copyrightText"),
+    ("PackageSummary", "summary"),
+    ("PackageDescription", "description"),
+    ("PackageComment", "comment"),
+];
+
+/// Package fields which can be repeated, with the JSON names they're read into.
+const PACKAGE_LIST_FIELDS: &[(&str, &str)] = &[
+    ("PackageLicenseInfoFromFiles", "licenseInfoFromFiles"),
+    ("PackageAttributionText", "attributionTexts"),
+];
+
+/// File fields holding a single value, with the JSON names they're read into.
+const FILE_FIELDS: &[(&str, &str)] = &[
+    ("LicenseConcluded", "licenseConcluded"),
+    ("LicenseComments", "licenseComments"),
+    ("FileCopyrightText", "copyrightText"),
+    ("FileComment", "comment"),
+    ("FileNotice", "noticeText"),
+];
+
+/// File fields which can be repeated, with the JSON names they're read into.
+const FILE_LIST_FIELDS: &[(&str, &str)] = &[
+    ("FileType", "fileTypes"),
+    ("LicenseInfoInFile", "licenseInfoInFiles"),
+    ("FileContributor", "fileContributors"),
+    ("FileAttributionText", "attributionTexts"),
+];
+
+/// Snippet fields holding a single value, with the JSON names they're read into.
+const SNIPPET_FIELDS: &[(&str, &str)] = &[
+    ("SnippetFromFileSPDXID", "snippetFromFile"),
+    ("SnippetLicenseConcluded", "licenseConcluded"),
+    ("SnippetLicenseComments", "licenseComments"),
+    ("SnippetCopyrightText", "copyrightText"),
+    ("SnippetComment", "comment"),
+    ("SnippetName", "name"),
+];
+
+/// Snippet fields which can be repeated, with the JSON names they're read into.
+const SNIPPET_LIST_FIELDS: &[(&str, &str)] = &[
+    ("LicenseInfoInSnippet", "licenseInfoInSnippets"),
+    ("SnippetAttributionText", "attributionTexts"),
+];
+
+/// Fields of licenses not on the SPDX License List holding a single value, with
+/// the JSON names they're read into.
+const LICENSE_FIELDS: &[(&str, &str)] = &[
+    ("ExtractedText", "extractedText"),
+    ("LicenseName", "name"),
+    ("LicenseComment", "comment"),
+];
+
+/// Fields of licenses not on the SPDX License List which can be repeated, with
+/// the JSON names they're read into.
+const LICENSE_LIST_FIELDS: &[(&str, &str)] = &[("LicenseCrossReference", "seeAlsos")];
+
+/// Read a document written in the flat file format.
+///
+/// The fields are gathered into the shape of a JSON document, which is then
+/// deserialized, so both formats are read by the same rules.
+pub fn read<R: Read>(mut r: R) -> Result<Document> {
+    log::info!(target: "cargo_spdx", "reading file in key-value format");
+
+    let mut contents = String::new();
+    r.read_to_string(&mut contents)?;
+
+    let mut reader = Reader::default();
+    for (line, tag, value) in fields(&contents)? {
+        reader
+            .read_field(tag, value)
+            .with_context(|| format!("invalid '{}' field on line {}", tag, line))?;
+    }
+
+    Ok(serde_json::from_value(reader.finish()?)?)
+}
+
+/// Split the contents into fields, with the line each one starts on.
+fn fields(contents: &str) -> Result<Vec<(usize, &str, String)>> {
+    let mut fields = Vec::new();
+    let mut lines = contents.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line_number = index + 1;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (tag, value) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("expected a 'Tag: value' field on line {}", line_number))?;
+
+        let value = match value.trim_start().strip_prefix("<text>") {
+            Some(text) => {
+                let mut text = text.to_string();
+                loop {
+                    if let Some(end) = text.find("</text>") {
+                        text.truncate(end);
+                        break;
+                    }
+                    let (_, next) = lines.next().ok_or_else(|| {
+                        anyhow!("unterminated <text> starting on line {}", line_number)
+                    })?;
+                    text.push('\n');
+                    text.push_str(next);
+                }
+                text.replace("&lt;/text&gt;", "</text>")
+            }
+            None => value.trim().to_string(),
+        };
+
+        fields.push((line_number, tag.trim(), value));
+    }
+
+    Ok(fields)
+}
+
+/// The element fields are currently being read into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Element {
+    Document,
+    Package,
+    File,
+    Snippet,
+    License,
+}
+
+impl Default for Element {
+    fn default() -> Self {
+        Element::Document
+    }
+}
+
+/// Gathers fields into the shape of a JSON document.
+#[derive(Debug, Default)]
+struct Reader {
+    document: Map<String, Value>,
+    creation_info: Map<String, Value>,
+    packages: Vec<Map<String, Value>>,
+    files: Vec<Map<String, Value>>,
+    snippets: Vec<Map<String, Value>>,
+    licenses: Vec<Map<String, Value>>,
+    relationships: Vec<Map<String, Value>>,
+    /// Annotations, with the SPDXID of the element each one is about.
+    annotations: Vec<(String, Map<String, Value>)>,
+    /// The element fields are being read into.
+    element: Element,
+    /// Files listed after a package, which the format reads as being in it.
+    listed_files: Vec<(usize, usize)>,
+}
+
+impl Reader {
+    /// Read a single field into the document.
+    fn read_field(&mut self, tag: &str, value: String) -> Result<()> {
+        if let Some(key) = lookup(PACKAGE_FIELDS, tag) {
+            self.package()?.insert(key.to_string(), value.into());
+            return Ok(());
+        }
+        if let Some(key) = lookup(PACKAGE_LIST_FIELDS, tag) {
+            push(self.package()?, key, value.into());
+            return Ok(());
+        }
+        if let Some(key) = lookup(FILE_FIELDS, tag) {
+            self.file()?.insert(key.to_string(), value.into());
+            return Ok(());
+        }
+        if let Some(key) = lookup(FILE_LIST_FIELDS, tag) {
+            push(self.file()?, key, value.into());
+            return Ok(());
+        }
+        if let Some(key) = lookup(SNIPPET_FIELDS, tag) {
+            self.snippet()?.insert(key.to_string(), value.into());
+            return Ok(());
+        }
+        if let Some(key) = lookup(SNIPPET_LIST_FIELDS, tag) {
+            push(self.snippet()?, key, value.into());
+            return Ok(());
+        }
+        if let Some(key) = lookup(LICENSE_FIELDS, tag) {
+            self.license()?.insert(key.to_string(), value.into());
+            return Ok(());
+        }
+        if let Some(key) = lookup(LICENSE_LIST_FIELDS, tag) {
+            push(self.license()?, key, value.into());
+            return Ok(());
+        }
+
+        match tag {
+            "SPDXVersion" => self.set_document("spdxVersion", value),
+            "DataLicense" => self.set_document("dataLicense", value),
+            "DocumentName" => self.set_document("name", value),
+            "DocumentNamespace" => self.set_document("documentNamespace", value),
+            "DocumentComment" => self.set_document("comment", value),
+            "ExternalDocumentRef" => {
+                let reference = external_document_ref(&value)?;
+                push(&mut self.document, "externalDocumentRefs", reference);
+            }
+            "LicenseListVersion" => {
+                self.creation_info
+                    .insert("licenseListVersion".to_string(), value.into());
+            }
+            "Creator" => push(&mut self.creation_info, "creators", value.into()),
+            "Created" => {
+                self.creation_info
+                    .insert("created".to_string(), value.into());
+            }
+            "CreatorComment" => {
+                self.creation_info
+                    .insert("comment".to_string(), value.into());
+            }
+            "SPDXID" => {
+                let element = match self.element {
+                    Element::Document => &mut self.document,
+                    Element::Package => self.packages.last_mut().unwrap(),
+                    Element::File => self.files.last_mut().unwrap(),
+                    Element::Snippet => self.snippets.last_mut().unwrap(),
+                    Element::License => {
+                        return Err(anyhow!("licenses are identified by LicenseID"))
+                    }
+                };
+                element.insert("SPDXID".to_string(), value.into());
+            }
+            "PackageName" => {
+                self.element = Element::Package;
+                self.packages.push(Map::new());
+                self.package()?.insert("name".to_string(), value.into());
+            }
+            "FilesAnalyzed" => {
+                let analyzed: bool = value.parse()?;
+                self.package()?
+                    .insert("filesAnalyzed".to_string(), analyzed.into());
+            }
+            "PackageVerificationCode" => {
+                let code = verification_code(&value);
+                self.package()?
+                    .insert("packageVerificationCode".to_string(), code);
+            }
+            "PackageChecksum" => {
+                let checksum = checksum(&value)?;
+                push(self.package()?, "checksums", checksum);
+            }
+            "ExternalRef" => {
+                let external_ref = external_ref(&value)?;
+                push(self.package()?, "externalRefs", external_ref);
+            }
+            "ExternalRefComment" => {
+                let external_ref = self
+                    .package()?
+                    .get_mut("externalRefs")
+                    .and_then(|refs| refs.as_array_mut()?.last_mut()?.as_object_mut())
+                    .ok_or_else(|| anyhow!("no ExternalRef to comment on"))?;
+                external_ref.insert("comment".to_string(), value.into());
+            }
+            "FileName" => {
+                if self.packages.is_empty().not() {
+                    self.listed_files
+                        .push((self.packages.len() - 1, self.files.len()));
+                }
+                self.element = Element::File;
+                self.files.push(Map::new());
+                self.file()?.insert("fileName".to_string(), value.into());
+            }
+            "FileChecksum" => {
+                let checksum = checksum(&value)?;
+                push(self.file()?, "checksums", checksum);
+            }
+            "SnippetSPDXID" => {
+                self.element = Element::Snippet;
+                self.snippets.push(Map::new());
+                self.snippet()?.insert("SPDXID".to_string(), value.into());
+            }
+            "SnippetByteRange" => {
+                let range = range("offset", &value)?;
+                push(self.snippet()?, "ranges", range);
+            }
+            "SnippetLineRange" => {
+                let range = range("lineNumber", &value)?;
+                push(self.snippet()?, "ranges", range);
+            }
+            "LicenseID" => {
+                self.element = Element::License;
+                self.licenses.push(Map::new());
+                self.license()?
+                    .insert("licenseId".to_string(), value.into());
+            }
+            "Relationship" => {
+                let relationship = relationship(&value)?;
+                self.relationships.push(relationship);
+            }
+            "RelationshipComment" => {
+                self.relationships
+                    .last_mut()
+                    .ok_or_else(|| anyhow!("no Relationship to comment on"))?
+                    .insert("comment".to_string(), value.into());
+            }
+            "Annotator" => {
+                let about = match self.element {
+                    Element::Document => self.document.get("SPDXID"),
+                    Element::Package => self.packages.last().and_then(|p| p.get("SPDXID")),
+                    Element::File => self.files.last().and_then(|f| f.get("SPDXID")),
+                    Element::Snippet => self.snippets.last().and_then(|s| s.get("SPDXID")),
+                    Element::License => None,
+                };
+                let about = about
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let mut annotation = Map::new();
+                annotation.insert("annotator".to_string(), value.into());
+                self.annotations.push((about, annotation));
+            }
+            "AnnotationDate" => {
+                let (_, annotation) = self.annotation()?;
+                annotation.insert("annotationDate".to_string(), value.into());
+            }
+            "AnnotationType" => {
+                let (_, annotation) = self.annotation()?;
+                annotation.insert("annotationType".to_string(), value.into());
+            }
+            "AnnotationComment" => {
+                let (_, annotation) = self.annotation()?;
+                annotation.insert("comment".to_string(), value.into());
+            }
+            "SPDXREF" => self.annotation()?.0 = value,
+            tag => return Err(anyhow!("unsupported tag '{}'", tag)),
+        }
+
+        Ok(())
+    }
+
+    /// Set a field of the document itself.
+    fn set_document(&mut self, key: &str, value: String) {
+        self.document.insert(key.to_string(), value.into());
+    }
+
+    /// Get the package fields are being read into.
+    fn package(&mut self) -> Result<&mut Map<String, Value>> {
+        match self.element {
+            Element::Package => Ok(self.packages.last_mut().unwrap()),
+            _ => Err(anyhow!("only valid in a package, after PackageName")),
+        }
+    }
+
+    /// Get the file fields are being read into.
+    fn file(&mut self) -> Result<&mut Map<String, Value>> {
+        match self.element {
+            Element::File => Ok(self.files.last_mut().unwrap()),
+            _ => Err(anyhow!("only valid in a file, after FileName")),
+        }
+    }
+
+    /// Get the snippet fields are being read into.
+    fn snippet(&mut self) -> Result<&mut Map<String, Value>> {
+        match self.element {
+            Element::Snippet => Ok(self.snippets.last_mut().unwrap()),
+            _ => Err(anyhow!("only valid in a snippet, after SnippetSPDXID")),
+        }
+    }
+
+    /// Get the license fields are being read into.
+    fn license(&mut self) -> Result<&mut Map<String, Value>> {
+        match self.element {
+            Element::License => Ok(self.licenses.last_mut().unwrap()),
+            _ => Err(anyhow!("only valid in a license, after LicenseID")),
+        }
+    }
+
+    /// Get the annotation fields are being read into.
+    fn annotation(&mut self) -> Result<&mut (String, Map<String, Value>)> {
+        self.annotations
+            .last_mut()
+            .ok_or_else(|| anyhow!("only valid in an annotation, after Annotator"))
+    }
+
+    /// Assemble the fields read into a JSON document.
+    fn finish(mut self) -> Result<Value> {
+        // Files listed after a package are in it, as are those it CONTAINS,
+        // which other formats list in its `hasFiles` too.
+        let file_ids: Vec<Value> = self
+            .files
+            .iter()
+            .filter_map(|file| file.get("SPDXID").cloned())
+            .collect();
+        let listed_files = std::mem::take(&mut self.listed_files);
+        for (index, package) in self.packages.iter_mut().enumerate() {
+            let package_id = package.get("SPDXID").cloned().unwrap_or_default();
+            let mut has_files: Vec<Value> = self
+                .relationships
+                .iter()
+                .filter_map(|relationship| {
+                    let is = |key: &str, value: &Value| relationship.get(key) == Some(value);
+                    if is("relationshipType", &"CONTAINS".into())
+                        && is("spdxElementId", &package_id)
+                    {
+                        relationship.get("relatedSpdxElement").cloned()
+                    } else if is("relationshipType", &"CONTAINED_BY".into())
+                        && is("relatedSpdxElement", &package_id)
+                    {
+                        relationship.get("spdxElementId").cloned()
+                    } else {
+                        None
+                    }
+                })
+                .filter(|spdxid| file_ids.contains(spdxid))
+                .collect();
+            for (_, file) in listed_files.iter().filter(|(package, _)| *package == index) {
+                let file_id = self.files[*file].get("SPDXID").cloned().unwrap_or_default();
+                if has_files.contains(&file_id).not() {
+                    has_files.push(file_id);
+                }
+            }
+            for file_id in has_files {
+                push(package, "hasFiles", file_id);
+            }
+        }
+
+        // The ends of each range point into the file the snippet is from.
+        for snippet in &mut self.snippets {
+            let file_id = snippet.get("snippetFromFile").cloned().unwrap_or_default();
+            for range in snippet
+                .get_mut("ranges")
+                .and_then(Value::as_array_mut)
+                .into_iter()
+                .flatten()
+            {
+                for pointer in ["startPointer", "endPointer"] {
+                    if let Some(pointer) = range.get_mut(pointer).and_then(Value::as_object_mut) {
+                        pointer.insert("reference".to_string(), file_id.clone());
+                    }
+                }
+            }
+        }
+
+        for (about, annotation) in std::mem::take(&mut self.annotations) {
+            if self.document.get("SPDXID").and_then(Value::as_str) == Some(&about) {
+                push(&mut self.document, "annotations", annotation.into());
+                continue;
+            }
+            let element = self
+                .packages
+                .iter_mut()
+                .chain(self.files.iter_mut())
+                .chain(self.snippets.iter_mut())
+                .find(|element| element.get("SPDXID").and_then(Value::as_str) == Some(&about))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "annotation of '{}', which isn't the document, a package, a file, or a snippet",
+                        about
+                    )
+                })?;
+            push(element, "annotations", annotation.into());
+        }
+
+        self.document
+            .insert("creationInfo".to_string(), self.creation_info.into());
+        for (key, elements) in [
+            ("packages", self.packages),
+            ("files", self.files),
+            ("snippets", self.snippets),
+            ("hasExtractedLicensingInfos", self.licenses),
+            ("relationships", self.relationships),
+        ] {
+            if elements.is_empty().not() {
+                let elements = elements.into_iter().map(Value::Object).collect();
+                self.document
+                    .insert(key.to_string(), Value::Array(elements));
+            }
+        }
+
+        Ok(Value::Object(self.document))
+    }
+}
+
+/// Find the JSON name a tag is read into.
+fn lookup(fields: &[(&str, &'static str)], tag: &str) -> Option<&'static str> {
+    fields
+        .iter()
+        .find(|(name, _)| *name == tag)
+        .map(|(_, key)| *key)
+}
+
+/// Append a value to a list field, creating it if needed.
+fn push(element: &mut Map<String, Value>, key: &str, value: Value) {
+    if let Value::Array(values) = element
+        .entry(key)
+        .or_insert_with(|| Value::Array(Vec::new()))
+    {
+        values.push(value);
+    }
+}
+
+/// Read a checksum, e.g. `SHA1: da39a3ee5e6b4b0d3255bfef95601890afd80709`.
+fn checksum(value: &str) -> Result<Value> {
+    let (algorithm, checksum) = value
+        .split_once(':')
+        .ok_or_else(|| anyhow!("expected 'ALGORITHM: value'"))?;
+    Ok(serde_json::json!({
+        "algorithm": algorithm.trim(),
+        "checksumValue": checksum.trim(),
+    }))
+}
+
+/// Read a package verification code, with the files it excludes if any.
+fn verification_code(value: &str) -> Value {
+    match value.split_once("(excludes:") {
+        Some((code, excluded)) => serde_json::json!({
+            "packageVerificationCodeValue": code.trim(),
+            "packageVerificationCodeExcludedFiles": excluded
+                .trim_end_matches(')')
+                .split_whitespace()
+                .collect::<Vec<_>>(),
+        }),
+        None => serde_json::json!({ "packageVerificationCodeValue": value }),
+    }
+}
+
+/// Read an external reference, e.g. `PACKAGE-MANAGER purl pkg:cargo/serde@1.0.0`.
+fn external_ref(value: &str) -> Result<Value> {
+    match value.split_whitespace().collect::<Vec<_>>()[..] {
+        [category, reference_type, locator] => Ok(serde_json::json!({
+            "referenceCategory": category.replace('-', "_"),
+            "referenceType": reference_type,
+            "referenceLocator": locator,
+        })),
+        _ => Err(anyhow!("expected 'CATEGORY type locator'")),
+    }
+}
+
+/// Read a reference to an external document, e.g.
+/// `DocumentRef-other https://example.com/other SHA1: da39a3ee...`.
+fn external_document_ref(value: &str) -> Result<Value> {
+    match value.splitn(3, ' ').collect::<Vec<_>>()[..] {
+        [id, uri, value] => Ok(serde_json::json!({
+            "externalDocumentId": id,
+            "spdxDocument": uri,
+            "checksum": checksum(value)?,
+        })),
+        _ => Err(anyhow!("expected 'DocumentRef-id uri SHA1: checksum'")),
+    }
+}
+
+/// Read a byte or line range, e.g. `310:420`.
+///
+/// The pointers get their `reference` once the whole snippet has been read.
+fn range(key: &str, value: &str) -> Result<Value> {
+    let (start, end) = value
+        .split_once(':')
+        .ok_or_else(|| anyhow!("expected 'start:end'"))?;
+    let pointer = |position: &str| -> Result<Value> {
+        let position: i64 = position.trim().parse()?;
+        Ok(serde_json::json!({ key: position }))
+    };
+    Ok(serde_json::json!({
+        "startPointer": pointer(start)?,
+        "endPointer": pointer(end)?,
+    }))
+}
+
+/// Read a relationship, e.g. `SPDXRef-a DEPENDS_ON SPDXRef-b`.
+fn relationship(value: &str) -> Result<Map<String, Value>> {
+    match value.split_whitespace().collect::<Vec<_>>()[..] {
+        [element, relationship_type, related] => {
+            let mut relationship = Map::new();
+            relationship.insert("spdxElementId".to_string(), element.into());
+            relationship.insert("relationshipType".to_string(), relationship_type.into());
+            relationship.insert("relatedSpdxElement".to_string(), related.into());
+            Ok(relationship)
+        }
+        _ => Err(anyhow!("expected 'SPDXRef-a TYPE SPDXRef-b'")),
+    }
+}
+
+/// Find the SPDXID of the package containing each file, keyed by file SPDXID.
+///
+/// If a file is contained in more than one package, the first one listed wins.
+fn file_owners<'d>(
+    packages: &'d [Package],
+    relationships: &'d [Relationship],
+) -> BTreeMap<&'d str, &'d str> {
+    let package_ids = packages
+        .iter()
+        .map(|package| package.spdxid.as_str())
+        .collect::<BTreeSet<_>>();
+    let mut owners = BTreeMap::new();
+
+    for package in packages {
+        for file in package.has_files.iter().flatten() {
+            owners
+                .entry(file.as_str())
+                .or_insert(package.spdxid.as_str());
+        }
+    }
+
+    for relationship in relationships {
+        let (package, file) = match relationship.relationship_type {
+            RelationshipType::Contains => (
+                &relationship.spdx_element_id,
+                &relationship.related_spdx_element,
+            ),
+            RelationshipType::ContainedBy => (
+                &relationship.related_spdx_element,
+                &relationship.spdx_element_id,
+            ),
+            _ => continue,
+        };
+
+        if package_ids.contains(package.as_str()) {
+            owners.entry(file.as_str()).or_insert(package.as_str());
+        }
+    }
+
+    owners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, write, Text};
+    use crate::document::Document;
+    use serde_json::json;
+
+    #[test]
+    fn test_text_wrapping() {
+        assert_eq!(Text("NOASSERTION").to_string(), "NOASSERTION");
+        assert_eq!(Text("a comment").to_string(), "a comment");
+        assert_eq!(
+            Text("Copyright (c) 2022\nJane Doe").to_string(),
+            "<text>Copyright (c) 2022\nJane Doe</text>"
+        );
+        assert_eq!(
+            Text("Tool: cargo-spdx").to_string(),
+            "<text>Tool: cargo-spdx</text>"
+        );
+        assert_eq!(
+            Text("first line\nends with </text>").to_string(),
+            "<text>first line\nends with &lt;/text&gt;</text>"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let json = json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": "foo.spdx",
+            "documentNamespace": "https://example.com/foo",
+            "creationInfo": {
+                "created": "2022-06-01T12:00:00Z",
+                "creators": ["Person: Jane Doe (jane@example.com)", "Tool: cargo-spdx"],
+            },
+            "annotations": [{
+                "annotator": "Person: Jane Doe (jane@example.com)",
+                "annotationDate": "2022-06-01T12:00:00Z",
+                "annotationType": "OTHER",
+                "comment": "Signed off for release",
+            }],
+            "packages": [{
+                "name": "foo",
+                "SPDXID": "SPDXRef-foo",
+                "downloadLocation": "NOASSERTION",
+                "checksums": [{ "algorithm": "SHA256", "checksumValue": "abcd" }],
+                "copyrightText": "Copyright (c) 2022\nJane Doe",
+                "externalRefs": [{
+                    "referenceCategory": "PACKAGE_MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": "pkg:cargo/foo@0.1.0",
+                    "comment": "from crates.io",
+                }],
+                "annotations": [{
+                    "annotator": "Tool: cargo-spdx",
+                    "annotationDate": "2022-06-01T12:00:00Z",
+                    "annotationType": "REVIEW",
+                    "comment": "looks good",
+                }],
+                "hasFiles": ["SPDXRef-File-src-lib.rs"],
+            }],
+            "files": [{
+                "fileName": "src/lib.rs",
+                "SPDXID": "SPDXRef-File-src-lib.rs",
+                "fileTypes": ["SOURCE"],
+                "comment": "ends with </text>",
+            }],
+            "snippets": [{
+                "SPDXID": "SPDXRef-Snippet-header",
+                "snippetFromFile": "SPDXRef-File-src-lib.rs",
+                "name": "header",
+                "ranges": [{
+                    "startPointer": { "offset": 1, "reference": "SPDXRef-File-src-lib.rs" },
+                    "endPointer": { "offset": 120, "reference": "SPDXRef-File-src-lib.rs" },
+                }],
+            }],
+            "hasExtractedLicensingInfos": [{
+                "licenseId": "LicenseRef-foo-1.0.0",
+                "extractedText": "Permission is granted:\nto nobody",
+                "name": "Foo License",
+                "seeAlsos": ["https://example.com/license"],
+                "comment": "from LICENSE",
+            }],
+            "relationships": [{
+                "spdxElementId": "SPDXRef-foo",
+                "relationshipType": "CONTAINS",
+                "relatedSpdxElement": "SPDXRef-File-src-lib.rs",
+            }],
+        });
+        let doc: Document = serde_json::from_value(json.clone()).unwrap();
+
+        let mut kv = Vec::new();
+        write(&mut kv, &doc).unwrap();
+        let read_back = read(&kv[..]).unwrap();
+        assert_eq!(serde_json::to_value(&read_back).unwrap(), json);
+    }
+
+    #[test]
+    fn test_round_trip_package_files() {
+        let package = |name: &str, files: &[&str]| {
+            json!({
+                "name": name,
+                "SPDXID": format!("SPDXRef-{}", name),
+                "downloadLocation": "NOASSERTION",
+                "hasFiles": files,
+            })
+        };
+        let file = |spdxid: &str| json!({ "fileName": spdxid, "SPDXID": spdxid });
+        let contains = |package: &str, file: &str| {
+            json!({
+                "spdxElementId": format!("SPDXRef-{}", package),
+                "relationshipType": "CONTAINS",
+                "relatedSpdxElement": file,
+            })
+        };
+        let json = json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": "foo.spdx",
+            "documentNamespace": "https://example.com/foo",
+            "creationInfo": {
+                "created": "2022-06-01T12:00:00Z",
+                "creators": ["Tool: cargo-spdx"],
+            },
+            "packages": [
+                package("foo", &["SPDXRef-File-a", "SPDXRef-File-b"]),
+                package("bar", &["SPDXRef-File-c"]),
+            ],
+            "files": [
+                file("SPDXRef-File-a"),
+                file("SPDXRef-File-b"),
+                file("SPDXRef-File-c"),
+            ],
+            "relationships": [
+                contains("foo", "SPDXRef-File-a"),
+                contains("foo", "SPDXRef-File-b"),
+                contains("bar", "SPDXRef-File-c"),
+            ],
+        });
+        let doc: Document = serde_json::from_value(json.clone()).unwrap();
+
+        let mut kv = Vec::new();
+        write(&mut kv, &doc).unwrap();
+        let read_back = read(&kv[..]).unwrap();
+        assert_eq!(serde_json::to_value(&read_back).unwrap(), json);
+    }
+}
@@ -1,9 +1,27 @@
 //! Defines the output formats supported by `cargo-spdx`.
 
+#[cfg(feature = "cyclonedx")]
+pub mod cyclonedx;
+#[cfg(test)]
+mod fuzz;
+#[cfg(feature = "github")]
+pub mod github;
+pub mod json;
 pub mod key_value;
+#[cfg(feature = "rdf")]
+pub mod rdf;
+#[cfg(feature = "report")]
+pub mod report;
 
-use anyhow::{anyhow, Error};
+use crate::document::{v3, Document, SpdxVersion};
+use crate::output::Compression;
+use anyhow::{anyhow, Error, Result};
+use json::JsonStyle;
+use serde::Serialize;
+use serde_json::Value;
 use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+use std::panic;
 use std::str::FromStr;
 
 /// The output format for the SPDX document.
@@ -14,19 +32,67 @@ pub enum Format {
     /// JSON format.
     Json,
     /// YAML format.
+    #[cfg(feature = "yaml")]
     Yaml,
     /// RDF format.
+    #[cfg(feature = "rdf")]
     Rdf,
+    /// CycloneDX JSON format.
+    #[cfg(feature = "cyclonedx")]
+    CycloneDx,
+    /// GitHub dependency submission snapshot.
+    #[cfg(feature = "github")]
+    GitHub,
+    /// Markdown report, for people rather than tools.
+    #[cfg(feature = "report")]
+    Markdown,
+    /// HTML report, for people rather than tools.
+    #[cfg(feature = "report")]
+    Html,
 }
 
 impl Format {
-    /// Get the file extension for the format.
-    pub fn extension(self) -> &'static str {
-        match self {
+    /// Get the file extension for the format, including any added by compression.
+    pub fn extension(self, compression: Compression) -> String {
+        let extension = match self {
             Format::KeyValue => ".spdx",
             Format::Json => ".spdx.json",
+            #[cfg(feature = "yaml")]
             Format::Yaml => ".spdx.yaml",
+            #[cfg(feature = "rdf")]
             Format::Rdf => ".spdx.rdf",
+            #[cfg(feature = "cyclonedx")]
+            Format::CycloneDx => ".cdx.json",
+            #[cfg(feature = "github")]
+            Format::GitHub => ".github.json",
+            #[cfg(feature = "report")]
+            Format::Markdown => ".spdx.md",
+            #[cfg(feature = "report")]
+            Format::Html => ".spdx.html",
+        };
+        format!("{}{}", extension, compression.extension())
+    }
+
+    /// Check the format can represent documents of the given SPDX version.
+    ///
+    /// SPDX 3.0 is only defined for JSON-LD, which is written for the JSON format.
+    /// Reports and GitHub snapshots aren't SPDX documents, so they can be
+    /// written for any version.
+    pub fn check_supports(self, version: SpdxVersion) -> Result<()> {
+        match self {
+            Format::Json => Ok(()),
+            #[cfg(feature = "cyclonedx")]
+            Format::CycloneDx => Ok(()),
+            #[cfg(feature = "github")]
+            Format::GitHub => Ok(()),
+            #[cfg(feature = "report")]
+            Format::Markdown | Format::Html => Ok(()),
+            _ if version >= SpdxVersion::V3_0 => Err(anyhow!(
+                "{} output isn't supported for {}, use JSON",
+                self,
+                version
+            )),
+            _ => Ok(()),
         }
     }
 }
@@ -42,8 +108,18 @@ impl Display for Format {
         match self {
             Format::KeyValue => write!(f, "Key-Value"),
             Format::Json => write!(f, "JSON"),
+            #[cfg(feature = "yaml")]
             Format::Yaml => write!(f, "YAML"),
+            #[cfg(feature = "rdf")]
             Format::Rdf => write!(f, "RDF"),
+            #[cfg(feature = "cyclonedx")]
+            Format::CycloneDx => write!(f, "CycloneDX"),
+            #[cfg(feature = "github")]
+            Format::GitHub => write!(f, "GitHub"),
+            #[cfg(feature = "report")]
+            Format::Markdown => write!(f, "Markdown"),
+            #[cfg(feature = "report")]
+            Format::Html => write!(f, "HTML"),
         }
     }
 }
@@ -55,9 +131,203 @@ impl FromStr for Format {
         match s {
             "kv" | "Key-Value" => Ok(Format::KeyValue),
             "json" | "JSON" => Ok(Format::Json),
+            #[cfg(feature = "yaml")]
             "yaml" | "YAML" => Ok(Format::Yaml),
+            #[cfg(feature = "rdf")]
             "rdf" | "RDF" => Ok(Format::Rdf),
+            #[cfg(feature = "cyclonedx")]
+            "cyclonedx" | "CycloneDX" => Ok(Format::CycloneDx),
+            #[cfg(feature = "github")]
+            "github" | "GitHub" => Ok(Format::GitHub),
+            #[cfg(feature = "report")]
+            "markdown" | "md" | "Markdown" => Ok(Format::Markdown),
+            #[cfg(feature = "report")]
+            "html" | "HTML" => Ok(Format::Html),
             s => Err(anyhow!("unknown format '{}'", s)),
         }
     }
 }
+
+impl Document {
+    /// Write the document out in the given format.
+    ///
+    /// `json_style` sets the layout of JSON, CycloneDX, and GitHub output, and is
+    /// ignored by the other formats.
+    pub fn to_writer<W: Write>(
+        &self,
+        mut w: W,
+        format: Format,
+        json_style: JsonStyle,
+    ) -> Result<()> {
+        format.check_supports(self.spdx_version)?;
+
+        match format {
+            Format::KeyValue => key_value::write(&mut w, self)?,
+            Format::Json if self.spdx_version >= SpdxVersion::V3_0 => {
+                json::write(&mut w, &v3::Document::from(self), json_style)?
+            }
+            Format::Json => json::write(&mut w, self, json_style)?,
+            #[cfg(feature = "yaml")]
+            Format::Yaml => serde_yaml::to_writer(&mut w, self)?,
+            #[cfg(feature = "rdf")]
+            Format::Rdf => rdf::write(&mut w, self)?,
+            #[cfg(feature = "cyclonedx")]
+            Format::CycloneDx => cyclonedx::write(&mut w, self, json_style)?,
+            #[cfg(feature = "github")]
+            Format::GitHub => github::write(&mut w, self, json_style)?,
+            #[cfg(feature = "report")]
+            Format::Markdown => report::write_markdown(&mut w, self)?,
+            #[cfg(feature = "report")]
+            Format::Html => report::write_html(&mut w, self)?,
+        }
+
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Read a document written in the given format.
+    ///
+    /// Only SPDX 2.x documents in the key-value, JSON, and YAML formats can be read.
+    pub fn from_reader<R: Read>(r: R, format: Format) -> Result<Document> {
+        match format {
+            Format::KeyValue => key_value::read(r),
+            Format::Json => read_json(r),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => Ok(serde_yaml::from_reader(r)?),
+            #[allow(unreachable_patterns)]
+            format => Err(anyhow!("reading {} documents isn't supported", format)),
+        }
+    }
+
+    /// Read a document from input which may be malicious, within the limits given.
+    ///
+    /// Besides enforcing the limits, YAML aliases are rejected, as a few of them
+    /// can expand into more data than fits in memory, and a parser panicking is
+    /// reported as an error.
+    pub fn from_untrusted_reader<R: Read>(
+        r: R,
+        format: Format,
+        limits: &ReadLimits,
+    ) -> Result<Document> {
+        let mut contents = Vec::new();
+        r.take(limits.max_bytes.saturating_add(1))
+            .read_to_end(&mut contents)?;
+        if contents.len() as u64 > limits.max_bytes {
+            return Err(anyhow!(
+                "the document is larger than the limit of {} bytes",
+                limits.max_bytes
+            ));
+        }
+
+        match format {
+            Format::Json => check_json_depth(&contents, limits.max_depth)?,
+            #[cfg(feature = "yaml")]
+            Format::Yaml => check_yaml_aliases(&contents)?,
+            _ => {}
+        }
+
+        panic::catch_unwind(|| Document::from_reader(contents.as_slice(), format))
+            .unwrap_or_else(|_| Err(anyhow!("the parser failed on malformed input")))
+    }
+}
+
+/// Limits on the documents read from untrusted input.
+#[derive(Debug, Clone, Copy)]
+pub struct ReadLimits {
+    /// The most bytes a document may have.
+    pub max_bytes: u64,
+    /// The deepest JSON objects and arrays may be nested.
+    pub max_depth: usize,
+}
+
+impl Default for ReadLimits {
+    /// Limits far beyond what any SBOM needs, with SPDX JSON nesting at most
+    /// five deep.
+    fn default() -> Self {
+        ReadLimits {
+            max_bytes: 256 * 1024 * 1024,
+            max_depth: 32,
+        }
+    }
+}
+
+/// Check JSON objects and arrays aren't nested deeper than the limit.
+///
+/// This is checked before parsing, so the parser never recurses that deep.
+fn check_json_depth(contents: &[u8], max_depth: usize) -> Result<()> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for byte in contents {
+        match (in_string, byte) {
+            (true, _) if escaped => escaped = false,
+            (true, b'\\') => escaped = true,
+            (true, b'"') => in_string = false,
+            (true, _) => {}
+            (false, b'"') => in_string = true,
+            (false, b'{' | b'[') => {
+                depth += 1;
+                if depth > max_depth {
+                    return Err(anyhow!(
+                        "the document nests deeper than the limit of {}",
+                        max_depth
+                    ));
+                }
+            }
+            (false, b'}' | b']') => depth = depth.saturating_sub(1),
+            (false, _) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Check a YAML document doesn't use anchors or aliases.
+///
+/// SPDX documents never need them, so they're rejected without parsing the
+/// document, just scanning through its events.
+#[cfg(feature = "yaml")]
+fn check_yaml_aliases(contents: &[u8]) -> Result<()> {
+    use yaml_rust::parser::{Event, Parser};
+
+    let contents = std::str::from_utf8(contents)?;
+    let mut parser = Parser::new(contents.chars());
+    loop {
+        let (event, marker) = parser.next()?;
+        match event {
+            Event::StreamEnd => return Ok(()),
+            Event::Alias(_)
+            | Event::Scalar(_, _, 1.., _)
+            | Event::SequenceStart(1..)
+            | Event::MappingStart(1..) => {
+                return Err(anyhow!(
+                    "YAML anchors and aliases aren't allowed, found one on line {}",
+                    marker.line()
+                ))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Read an SPDX 2.x JSON document, rejecting the other JSON documents we write.
+fn read_json<R: Read>(reader: R) -> Result<Document> {
+    let value: Value = serde_json::from_reader(reader)?;
+    if value.get("@context").is_some() {
+        return Err(anyhow!("reading SPDX 3.0 documents isn't supported"));
+    }
+    if value.get("bomFormat").is_some() {
+        return Err(anyhow!("reading CycloneDX documents isn't supported"));
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Get the name an enum variant is serialized under, e.g. `DEPENDS_ON`.
+///
+/// The SPDX enumerations are named the same way across formats, with only
+/// their casing changing, so the writers all derive them from the serde names.
+fn variant_name<T: Serialize>(value: &T) -> Result<String> {
+    match serde_json::to_value(value)? {
+        serde_json::Value::String(name) => Ok(name),
+        value => Err(anyhow!("expected a string, found '{}'", value)),
+    }
+}
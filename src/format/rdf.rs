@@ -0,0 +1,696 @@
+//! Writes the RDF/XML format out.
+
+use super::variant_name;
+use crate::document::{
+    AnnotationType, Document, File, FileChecksum, Package, Relationship, Snippet, NOASSERTION,
+};
+use crate::license::License;
+use anyhow::Result;
+use std::io::Write;
+
+/// The namespace for SPDX RDF terms.
+const SPDX_TERMS: &str = "http://spdx.org/rdf/terms#";
+
+/// The namespace for licenses on the SPDX License List.
+const SPDX_LICENSES: &str = "http://spdx.org/licenses/";
+
+/// The namespace for external reference types defined by the SPDX specification.
+const SPDX_REFERENCES: &str = "http://spdx.org/rdf/references/";
+
+/// The namespace for the pointers marking out the ranges of snippets.
+const POINTERS: &str = "http://www.w3.org/2009/pointers#";
+
+/// The SPDX value indicating a field is explicitly empty.
+const NONE: &str = "NONE";
+
+/// Write the document out to the provided writer.
+pub fn write<W: Write>(w: W, doc: &Document) -> Result<()> {
+    log::info!(target: "cargo_spdx", "writing out file in RDF format");
+
+    let mut xml = XmlWriter::new(w);
+    let namespace = doc.document_namespace.as_str();
+    let relationships = doc.relationships.as_deref().unwrap_or_default();
+
+    xml.raw(r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    xml.open(
+        "rdf:RDF",
+        &[
+            ("xmlns:rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#"),
+            ("xmlns:rdfs", "http://www.w3.org/2000/01/rdf-schema#"),
+            ("xmlns:spdx", SPDX_TERMS),
+            ("xmlns:doap", "http://usefulinc.com/ns/doap#"),
+            ("xmlns:ptr", POINTERS),
+        ],
+    )?;
+
+    let doc_about = element_uri(namespace, &doc.spdx_identifier.to_string());
+    xml.open("spdx:SpdxDocument", &[("rdf:about", &doc_about)])?;
+    xml.text("spdx:specVersion", &doc.spdx_version.to_string())?;
+    xml.resource(
+        "spdx:dataLicense",
+        &format!("{}{}", SPDX_LICENSES, doc.data_license),
+    )?;
+    xml.text("spdx:name", &doc.document_name.to_string())?;
+    if let Some(comment) = &doc.document_comment {
+        xml.text("rdfs:comment", comment)?;
+    }
+
+    xml.open("spdx:creationInfo", &[])?;
+    xml.open("spdx:CreationInfo", &[])?;
+    xml.text("spdx:created", &doc.creation_info.created.to_string())?;
+    for creator in doc.creation_info.creators.iter().flatten() {
+        xml.text("spdx:creator", &creator.to_string())?;
+    }
+    if let Some(version) = &doc.creation_info.license_list_version {
+        xml.text("spdx:licenseListVersion", &version.to_string())?;
+    }
+    if let Some(comment) = &doc.creation_info.comment {
+        xml.text("rdfs:comment", comment)?;
+    }
+    xml.close("spdx:CreationInfo")?;
+    xml.close("spdx:creationInfo")?;
+
+    for annotation in doc.annotations.iter().flatten() {
+        write_annotation(
+            &mut xml,
+            &annotation.annotation_date,
+            &annotation.annotation_type,
+            &annotation.annotator,
+            &annotation.comment,
+        )?;
+    }
+
+    for reference in doc.external_document_references.iter().flatten() {
+        xml.open("spdx:externalDocumentRef", &[])?;
+        xml.open("spdx:ExternalDocumentRef", &[])?;
+        xml.text("spdx:externalDocumentId", &reference.id_string.0)?;
+        xml.resource("spdx:spdxDocument", reference.document_uri.as_str())?;
+        write_checksum(
+            &mut xml,
+            &variant_name(&reference.checksum.algorithm)?.to_lowercase(),
+            &reference.checksum.checksum_value,
+        )?;
+        xml.close("spdx:ExternalDocumentRef")?;
+        xml.close("spdx:externalDocumentRef")?;
+    }
+
+    for info in doc.has_extracted_licensing_infos.iter().flatten() {
+        xml.open("spdx:hasExtractedLicensingInfo", &[])?;
+        xml.open(
+            "spdx:ExtractedLicensingInfo",
+            &[("rdf:about", &license_uri(namespace, &info.license_id))],
+        )?;
+        xml.text("spdx:licenseId", &info.license_id)?;
+        xml.text("spdx:extractedText", &info.extracted_text)?;
+        if let Some(name) = &info.name {
+            xml.text("spdx:name", name)?;
+        }
+        for see_also in info.see_alsos.iter().flatten() {
+            xml.text("rdfs:seeAlso", see_also)?;
+        }
+        if let Some(comment) = &info.comment {
+            xml.text("rdfs:comment", comment)?;
+        }
+        xml.close("spdx:ExtractedLicensingInfo")?;
+        xml.close("spdx:hasExtractedLicensingInfo")?;
+    }
+
+    write_relationships(
+        &mut xml,
+        namespace,
+        relationships,
+        &doc.spdx_identifier.to_string(),
+    )?;
+    xml.close("spdx:SpdxDocument")?;
+
+    for package in doc.packages.iter().flatten() {
+        write_package(&mut xml, namespace, relationships, package)?;
+    }
+
+    for file in doc.files.iter().flatten() {
+        write_file(&mut xml, namespace, relationships, file)?;
+    }
+
+    for snippet in doc.snippets.iter().flatten() {
+        write_snippet(&mut xml, namespace, relationships, snippet)?;
+    }
+
+    xml.close("rdf:RDF")?;
+    Ok(())
+}
+
+/// Write out a single package element.
+fn write_package<W: Write>(
+    xml: &mut XmlWriter<W>,
+    namespace: &str,
+    relationships: &[Relationship],
+    package: &Package,
+) -> Result<()> {
+    let about = element_uri(namespace, &package.spdxid);
+    xml.open("spdx:Package", &[("rdf:about", &about)])?;
+    xml.text("spdx:name", &package.name)?;
+    if let Some(version) = &package.version_info {
+        xml.text("spdx:versionInfo", version)?;
+    }
+    if let Some(file_name) = &package.package_file_name {
+        xml.text("spdx:packageFileName", file_name)?;
+    }
+    if let Some(supplier) = &package.supplier {
+        xml.text("spdx:supplier", supplier)?;
+    }
+    if let Some(originator) = &package.originator {
+        xml.text("spdx:originator", originator)?;
+    }
+    xml.text_or_special("spdx:downloadLocation", &package.download_location)?;
+    if let Some(files_analyzed) = package.files_analyzed {
+        xml.text("spdx:filesAnalyzed", &files_analyzed.to_string())?;
+    }
+    if let Some(code) = &package.package_verification_code {
+        xml.open("spdx:packageVerificationCode", &[])?;
+        xml.open("spdx:PackageVerificationCode", &[])?;
+        xml.text(
+            "spdx:packageVerificationCodeValue",
+            &code.package_verification_code_value,
+        )?;
+        for excluded in code
+            .package_verification_code_excluded_files
+            .iter()
+            .flatten()
+        {
+            xml.text("spdx:packageVerificationCodeExcludedFile", excluded)?;
+        }
+        xml.close("spdx:PackageVerificationCode")?;
+        xml.close("spdx:packageVerificationCode")?;
+    }
+    for checksum in package.checksums.iter().flatten() {
+        let algorithm = variant_name(&checksum.algorithm)?.to_lowercase();
+        write_checksum(xml, &algorithm, &checksum.checksum_value)?;
+    }
+    if let Some(homepage) = &package.homepage {
+        xml.text_or_special("doap:homepage", homepage)?;
+    }
+    if let Some(source_info) = &package.source_info {
+        xml.text("spdx:sourceInfo", source_info)?;
+    }
+    if let Some(license) = &package.license_concluded {
+        write_license(xml, "spdx:licenseConcluded", namespace, license)?;
+    }
+    for license in package.license_info_from_files.iter().flatten() {
+        write_license(xml, "spdx:licenseInfoFromFiles", namespace, license)?;
+    }
+    if let Some(license) = &package.license_declared {
+        write_license(xml, "spdx:licenseDeclared", namespace, license)?;
+    }
+    if let Some(comments) = &package.license_comments {
+        xml.text("spdx:licenseComments", comments)?;
+    }
+    if let Some(copyright_text) = &package.copyright_text {
+        xml.text_or_special("spdx:copyrightText", copyright_text)?;
+    }
+    if let Some(summary) = &package.summary {
+        xml.text("spdx:summary", summary)?;
+    }
+    if let Some(description) = &package.description {
+        xml.text("spdx:description", description)?;
+    }
+    if let Some(comment) = &package.comment {
+        xml.text("rdfs:comment", comment)?;
+    }
+    for external_ref in package.external_refs.iter().flatten() {
+        xml.open("spdx:externalRef", &[])?;
+        xml.open("spdx:ExternalRef", &[])?;
+        xml.resource(
+            "spdx:referenceCategory",
+            &term(
+                "referenceCategory",
+                &snake_to_camel(&variant_name(&external_ref.reference_category)?),
+            ),
+        )?;
+        xml.resource(
+            "spdx:referenceType",
+            &reference_type_uri(&external_ref.reference_type),
+        )?;
+        xml.text("spdx:referenceLocator", &external_ref.reference_locator)?;
+        if let Some(comment) = &external_ref.comment {
+            xml.text("rdfs:comment", comment)?;
+        }
+        xml.close("spdx:ExternalRef")?;
+        xml.close("spdx:externalRef")?;
+    }
+    for text in package.attribution_texts.iter().flatten() {
+        xml.text("spdx:attributionText", text)?;
+    }
+    for file in package.has_files.iter().flatten() {
+        xml.resource("spdx:hasFile", &element_uri(namespace, file))?;
+    }
+    for annotation in package.annotations.iter().flatten() {
+        write_annotation(
+            xml,
+            &annotation.annotation_date,
+            &annotation.annotation_type,
+            &annotation.annotator,
+            &annotation.comment,
+        )?;
+    }
+    write_relationships(xml, namespace, relationships, &package.spdxid)?;
+    xml.close("spdx:Package")?;
+    Ok(())
+}
+
+/// Write out a single file element.
+fn write_file<W: Write>(
+    xml: &mut XmlWriter<W>,
+    namespace: &str,
+    relationships: &[Relationship],
+    file: &File,
+) -> Result<()> {
+    let about = element_uri(namespace, &file.spdxid);
+    xml.open("spdx:File", &[("rdf:about", &about)])?;
+    xml.text("spdx:fileName", &file.file_name)?;
+    for file_type in file.file_types.iter().flatten() {
+        xml.resource(
+            "spdx:fileType",
+            &term("fileType", &variant_name(file_type)?.to_lowercase()),
+        )?;
+    }
+    for FileChecksum {
+        algorithm,
+        checksum_value,
+    } in file.checksums.iter().flatten()
+    {
+        write_checksum(
+            xml,
+            &variant_name(algorithm)?.to_lowercase(),
+            checksum_value,
+        )?;
+    }
+    if let Some(license) = &file.license_concluded {
+        write_license(xml, "spdx:licenseConcluded", namespace, license)?;
+    }
+    for license in file.license_info_in_files.iter().flatten() {
+        write_license(xml, "spdx:licenseInfoInFile", namespace, license)?;
+    }
+    if let Some(comments) = &file.license_comments {
+        xml.text("spdx:licenseComments", comments)?;
+    }
+    if let Some(copyright_text) = &file.copyright_text {
+        xml.text_or_special("spdx:copyrightText", copyright_text)?;
+    }
+    if let Some(comment) = &file.comment {
+        xml.text("rdfs:comment", comment)?;
+    }
+    if let Some(notice) = &file.notice_text {
+        xml.text("spdx:noticeText", notice)?;
+    }
+    for contributor in file.file_contributors.iter().flatten() {
+        xml.text("spdx:fileContributor", contributor)?;
+    }
+    for text in file.attribution_texts.iter().flatten() {
+        xml.text("spdx:attributionText", text)?;
+    }
+    for annotation in file.annotations.iter().flatten() {
+        write_annotation(
+            xml,
+            &annotation.annotation_date,
+            &annotation.annotation_type,
+            &annotation.annotator,
+            &annotation.comment,
+        )?;
+    }
+    write_relationships(xml, namespace, relationships, &file.spdxid)?;
+    xml.close("spdx:File")?;
+    Ok(())
+}
+
+/// Write out a single snippet element.
+fn write_snippet<W: Write>(
+    xml: &mut XmlWriter<W>,
+    namespace: &str,
+    relationships: &[Relationship],
+    snippet: &Snippet,
+) -> Result<()> {
+    let about = element_uri(namespace, &snippet.spdxid);
+    let file = element_uri(namespace, &snippet.snippet_from_file);
+    xml.open("spdx:Snippet", &[("rdf:about", &about)])?;
+    xml.text("spdx:name", &snippet.name)?;
+    xml.resource("spdx:snippetFromFile", &file)?;
+    for range in snippet.ranges.iter().flatten() {
+        let (start, end) = (&range.start_pointer, &range.end_pointer);
+        if let (Some(start), Some(end)) = (start.offset, end.offset) {
+            write_range(
+                xml,
+                &file,
+                "ptr:ByteOffsetPointer",
+                "ptr:offset",
+                start,
+                end,
+            )?;
+        }
+        if let (Some(start), Some(end)) = (start.line_number, end.line_number) {
+            write_range(
+                xml,
+                &file,
+                "ptr:LineCharPointer",
+                "ptr:lineNumber",
+                start,
+                end,
+            )?;
+        }
+    }
+    if let Some(license) = &snippet.license_concluded {
+        write_license(xml, "spdx:licenseConcluded", namespace, license)?;
+    }
+    for license in snippet.license_info_in_snippets.iter().flatten() {
+        write_license(xml, "spdx:licenseInfoInSnippet", namespace, license)?;
+    }
+    if let Some(comments) = &snippet.license_comments {
+        xml.text("spdx:licenseComments", comments)?;
+    }
+    if let Some(copyright_text) = &snippet.copyright_text {
+        xml.text_or_special("spdx:copyrightText", copyright_text)?;
+    }
+    if let Some(comment) = &snippet.comment {
+        xml.text("rdfs:comment", comment)?;
+    }
+    for text in snippet.attribution_texts.iter().flatten() {
+        xml.text("spdx:attributionText", text)?;
+    }
+    for annotation in snippet.annotations.iter().flatten() {
+        write_annotation(
+            xml,
+            &annotation.annotation_date,
+            &annotation.annotation_type,
+            &annotation.annotator,
+            &annotation.comment,
+        )?;
+    }
+    write_relationships(xml, namespace, relationships, &snippet.spdxid)?;
+    xml.close("spdx:Snippet")?;
+    Ok(())
+}
+
+/// Write out the range of a snippet, as a pair of pointers into its file.
+fn write_range<W: Write>(
+    xml: &mut XmlWriter<W>,
+    file: &str,
+    class: &str,
+    property: &str,
+    start: i64,
+    end: i64,
+) -> Result<()> {
+    xml.open("spdx:range", &[])?;
+    xml.open("ptr:StartEndPointer", &[])?;
+    for (pointer, position) in [("ptr:startPointer", start), ("ptr:endPointer", end)] {
+        xml.open(pointer, &[])?;
+        xml.open(class, &[])?;
+        xml.resource("ptr:reference", file)?;
+        xml.text(property, &position.to_string())?;
+        xml.close(class)?;
+        xml.close(pointer)?;
+    }
+    xml.close("ptr:StartEndPointer")?;
+    xml.close("spdx:range")?;
+    Ok(())
+}
+
+/// Write out an annotation on an element.
+fn write_annotation<W: Write>(
+    xml: &mut XmlWriter<W>,
+    date: &str,
+    annotation_type: &AnnotationType,
+    annotator: &str,
+    comment: &str,
+) -> Result<()> {
+    xml.open("spdx:annotation", &[])?;
+    xml.open("spdx:Annotation", &[])?;
+    xml.text("spdx:annotationDate", date)?;
+    xml.resource(
+        "spdx:annotationType",
+        &term(
+            "annotationType",
+            &variant_name(annotation_type)?.to_lowercase(),
+        ),
+    )?;
+    xml.text("spdx:annotator", annotator)?;
+    xml.text("rdfs:comment", comment)?;
+    xml.close("spdx:Annotation")?;
+    xml.close("spdx:annotation")?;
+    Ok(())
+}
+
+/// Write out a checksum. The algorithm is expected in lowercase, e.g. `sha1`.
+fn write_checksum<W: Write>(xml: &mut XmlWriter<W>, algorithm: &str, value: &str) -> Result<()> {
+    xml.open("spdx:checksum", &[])?;
+    xml.open("spdx:Checksum", &[])?;
+    xml.resource("spdx:algorithm", &term("checksumAlgorithm", algorithm))?;
+    xml.text("spdx:checksumValue", value)?;
+    xml.close("spdx:Checksum")?;
+    xml.close("spdx:checksum")?;
+    Ok(())
+}
+
+/// Write out the relationships whose subject is the given element.
+///
+/// In RDF a relationship is a property of the element it originates from,
+/// rather than a free-standing entry like in the other formats.
+fn write_relationships<W: Write>(
+    xml: &mut XmlWriter<W>,
+    namespace: &str,
+    relationships: &[Relationship],
+    spdxid: &str,
+) -> Result<()> {
+    for relationship in relationships
+        .iter()
+        .filter(|relationship| relationship.spdx_element_id == spdxid)
+    {
+        xml.open("spdx:relationship", &[])?;
+        xml.open("spdx:Relationship", &[])?;
+        xml.resource(
+            "spdx:relationshipType",
+            &term(
+                "relationshipType",
+                &snake_to_camel(&variant_name(&relationship.relationship_type)?),
+            ),
+        )?;
+        xml.resource(
+            "spdx:relatedSpdxElement",
+            &related_element_uri(namespace, &relationship.related_spdx_element),
+        )?;
+        if let Some(comment) = &relationship.comment {
+            xml.text("rdfs:comment", comment)?;
+        }
+        xml.close("spdx:Relationship")?;
+        xml.close("spdx:relationship")?;
+    }
+
+    Ok(())
+}
+
+/// Write out a license expression as the value of the named property.
+fn write_license<W: Write>(
+    xml: &mut XmlWriter<W>,
+    tag: &str,
+    namespace: &str,
+    expression: &str,
+) -> Result<()> {
+    match expression.trim() {
+        NOASSERTION => xml.resource(tag, &term("", "noassertion")),
+        NONE => xml.resource(tag, &term("", "none")),
+        expression => write_license_node(xml, tag, namespace, &License::parse(expression)?),
+    }
+}
+
+/// Write out a parsed license expression as the value of the named property.
+fn write_license_node<W: Write>(
+    xml: &mut XmlWriter<W>,
+    tag: &str,
+    namespace: &str,
+    license: &License,
+) -> Result<()> {
+    if let License::Id(id) = license {
+        return xml.resource(tag, &license_uri(namespace, id));
+    }
+
+    xml.open(tag, &[])?;
+    match license {
+        License::Id(_) => unreachable!(),
+        License::OrLater(id) => {
+            xml.open("spdx:OrLaterOperator", &[])?;
+            xml.resource("spdx:member", &license_uri(namespace, id))?;
+            xml.close("spdx:OrLaterOperator")?;
+        }
+        License::With(license, exception) => {
+            xml.open("spdx:WithExceptionOperator", &[])?;
+            write_license_node(xml, "spdx:member", namespace, license)?;
+            xml.open("spdx:licenseException", &[])?;
+            xml.open("spdx:LicenseException", &[])?;
+            xml.text("spdx:licenseExceptionId", exception)?;
+            xml.close("spdx:LicenseException")?;
+            xml.close("spdx:licenseException")?;
+            xml.close("spdx:WithExceptionOperator")?;
+        }
+        License::And(members) | License::Or(members) => {
+            let set = match license {
+                License::And(_) => "spdx:ConjunctiveLicenseSet",
+                _ => "spdx:DisjunctiveLicenseSet",
+            };
+            xml.open(set, &[])?;
+            for member in members {
+                write_license_node(xml, "spdx:member", namespace, member)?;
+            }
+            xml.close(set)?;
+        }
+    }
+    xml.close(tag)
+}
+
+/// Get the URI for a license identifier.
+fn license_uri(namespace: &str, id: &str) -> String {
+    if id.starts_with("LicenseRef-") {
+        element_uri(namespace, id)
+    } else {
+        format!("{}{}", SPDX_LICENSES, id)
+    }
+}
+
+/// Get the URI of an element defined in this document.
+fn element_uri(namespace: &str, spdxid: &str) -> String {
+    format!("{}#{}", namespace, spdxid)
+}
+
+/// Get the URI of the target of a relationship.
+fn related_element_uri(namespace: &str, spdxid: &str) -> String {
+    match spdxid {
+        NOASSERTION => term("", "noassertion"),
+        NONE => term("", "none"),
+        spdxid => element_uri(namespace, spdxid),
+    }
+}
+
+/// Get the URI for an external reference type.
+fn reference_type_uri(reference_type: &str) -> String {
+    if reference_type.contains(':') {
+        reference_type.to_owned()
+    } else {
+        format!("{}{}", SPDX_REFERENCES, reference_type)
+    }
+}
+
+/// Get the URI of a term in the SPDX vocabulary, e.g. `fileType_source`.
+fn term(class: &str, value: &str) -> String {
+    if class.is_empty() {
+        format!("{}{}", SPDX_TERMS, value)
+    } else {
+        format!("{}{}_{}", SPDX_TERMS, class, value)
+    }
+}
+
+/// Convert a `SCREAMING_SNAKE_CASE` name to `camelCase`.
+fn snake_to_camel(name: &str) -> String {
+    name.split('_')
+        .enumerate()
+        .map(|(i, word)| {
+            let word = word.to_lowercase();
+            match (i, word.chars().next()) {
+                (0, _) | (_, None) => word,
+                (_, Some(first)) => format!("{}{}", first.to_uppercase(), &word[1..]),
+            }
+        })
+        .collect()
+}
+
+/// A minimal writer for indented XML output.
+struct XmlWriter<W: Write> {
+    inner: W,
+    depth: usize,
+}
+
+impl<W: Write> XmlWriter<W> {
+    fn new(inner: W) -> Self {
+        XmlWriter { inner, depth: 0 }
+    }
+
+    /// Write out a line verbatim at the current indentation.
+    fn raw(&mut self, line: &str) -> Result<()> {
+        writeln!(
+            self.inner,
+            "{:indent$}{}",
+            "",
+            line,
+            indent = self.depth * 2
+        )?;
+        Ok(())
+    }
+
+    /// Open a new element.
+    fn open(&mut self, tag: &str, attrs: &[(&str, &str)]) -> Result<()> {
+        self.raw(&format!("<{}{}>", tag, attributes(attrs)))?;
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Close the most recently opened element.
+    fn close(&mut self, tag: &str) -> Result<()> {
+        self.depth -= 1;
+        self.raw(&format!("</{}>", tag))
+    }
+
+    /// Write out an element containing only text.
+    fn text(&mut self, tag: &str, text: &str) -> Result<()> {
+        self.raw(&format!("<{}>{}</{}>", tag, escape(text), tag))
+    }
+
+    /// Write out an element referring to another resource.
+    fn resource(&mut self, tag: &str, uri: &str) -> Result<()> {
+        self.raw(&format!(
+            "<{}{}/>",
+            tag,
+            attributes(&[("rdf:resource", uri)])
+        ))
+    }
+
+    /// Write out a text element, using the SPDX terms for `NONE` and `NOASSERTION`.
+    fn text_or_special(&mut self, tag: &str, text: &str) -> Result<()> {
+        match text {
+            NOASSERTION => self.resource(tag, &term("", "noassertion")),
+            NONE => self.resource(tag, &term("", "none")),
+            text => self.text(tag, text),
+        }
+    }
+}
+
+/// Render a list of attributes, each preceded by a space.
+fn attributes(attrs: &[(&str, &str)]) -> String {
+    attrs
+        .iter()
+        .map(|(name, value)| format!(" {}=\"{}\"", name, escape(value)))
+        .collect()
+}
+
+/// Escape the characters reserved in XML text and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::snake_to_camel;
+
+    #[test]
+    fn test_relationship_type_names() {
+        assert_eq!(snake_to_camel("DEPENDS_ON"), "dependsOn");
+        assert_eq!(snake_to_camel("CONTAINS"), "contains");
+        assert_eq!(snake_to_camel("BUILD_DEPENDENCY_OF"), "buildDependencyOf");
+    }
+}
@@ -0,0 +1,269 @@
+//! Writes human-readable Markdown and HTML reports of a document.
+//!
+//! Reports summarize the document for readers without an SPDX viewer, such as
+//! in release notes: a table of the packages, how many use each license, and
+//! how many relationships of each type there are.
+
+use super::variant_name;
+use crate::document::{Document, Package, NOASSERTION};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// The parts of the document shown in a report.
+#[derive(Debug)]
+struct Report<'d> {
+    doc: &'d Document,
+    /// Name, version, license, and package URL of each package.
+    packages: Vec<[String; 4]>,
+    /// The number of packages under each license expression.
+    licenses: BTreeMap<String, usize>,
+    /// The number of relationships of each type.
+    relationships: BTreeMap<String, usize>,
+}
+
+impl<'d> Report<'d> {
+    /// Gather the parts of the document to show.
+    fn new(doc: &'d Document) -> Result<Self> {
+        let mut packages = Vec::new();
+        let mut licenses = BTreeMap::new();
+        for package in doc.packages.iter().flatten() {
+            let license = license(package);
+            *licenses.entry(license.clone()).or_default() += 1;
+            packages.push([
+                package.name.clone(),
+                package.version_info.clone().unwrap_or_default(),
+                license,
+                package
+                    .purl()
+                    .map(|purl| purl.to_string())
+                    .unwrap_or_default(),
+            ]);
+        }
+
+        let mut relationships = BTreeMap::new();
+        for relationship in doc.relationships.iter().flatten() {
+            let name = variant_name(&relationship.relationship_type)?;
+            *relationships.entry(name).or_default() += 1;
+        }
+
+        Ok(Report {
+            doc,
+            packages,
+            licenses,
+            relationships,
+        })
+    }
+
+    /// Facts about the document itself, as label and value.
+    fn details(&self) -> Vec<(&'static str, String)> {
+        let doc = self.doc;
+        let creators = doc
+            .creation_info
+            .creators
+            .iter()
+            .flatten()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        vec![
+            ("SPDX version", doc.spdx_version.to_string()),
+            ("Namespace", doc.document_namespace.to_string()),
+            ("Created", doc.creation_info.created.to_string()),
+            ("Creators", creators.join(", ")),
+            ("Packages", self.packages.len().to_string()),
+            (
+                "Files",
+                doc.files.as_deref().unwrap_or_default().len().to_string(),
+            ),
+            (
+                "Snippets",
+                doc.snippets
+                    .as_deref()
+                    .unwrap_or_default()
+                    .len()
+                    .to_string(),
+            ),
+        ]
+    }
+}
+
+/// The license a package is known by, preferring the one it declares.
+fn license(package: &Package) -> String {
+    [&package.license_declared, &package.license_concluded]
+        .into_iter()
+        .flatten()
+        .find(|license| license.as_str() != NOASSERTION && license.as_str() != "NONE")
+        .cloned()
+        .unwrap_or_else(|| NOASSERTION.to_string())
+}
+
+/// The column headings of the package table.
+const PACKAGE_COLUMNS: [&str; 4] = ["Name", "Version", "License", "Package URL"];
+
+/// Write a Markdown report of the document out to the provided writer.
+pub fn write_markdown<W: Write>(mut w: W, doc: &Document) -> Result<()> {
+    log::info!(target: "cargo_spdx", "writing out file in Markdown format");
+
+    let report = Report::new(doc)?;
+
+    writeln!(w, "# {}", markdown(&doc.document_name.to_string()))?;
+    writeln!(w)?;
+    for (label, value) in report.details() {
+        writeln!(w, "- **{}:** {}", label, markdown(&value))?;
+    }
+    if let Some(comment) = &doc.document_comment {
+        writeln!(w)?;
+        writeln!(w, "{}", markdown(comment))?;
+    }
+
+    writeln!(w)?;
+    writeln!(w, "## Packages")?;
+    writeln!(w)?;
+    writeln!(w, "| {} |", PACKAGE_COLUMNS.join(" | "))?;
+    writeln!(w, "|{}", " --- |".repeat(PACKAGE_COLUMNS.len()))?;
+    for row in &report.packages {
+        let cells = row.iter().map(|cell| markdown(cell)).collect::<Vec<_>>();
+        writeln!(w, "| {} |", cells.join(" | "))?;
+    }
+
+    writeln!(w)?;
+    writeln!(w, "## Licenses")?;
+    writeln!(w)?;
+    writeln!(w, "| License | Packages |")?;
+    writeln!(w, "| --- | --- |")?;
+    for (license, count) in &report.licenses {
+        writeln!(w, "| {} | {} |", markdown(license), count)?;
+    }
+
+    writeln!(w)?;
+    writeln!(w, "## Relationships")?;
+    writeln!(w)?;
+    writeln!(w, "| Type | Count |")?;
+    writeln!(w, "| --- | --- |")?;
+    for (relationship_type, count) in &report.relationships {
+        writeln!(w, "| `{}` | {} |", relationship_type, count)?;
+    }
+
+    w.flush()?;
+    Ok(())
+}
+
+/// Write an HTML report of the document out to the provided writer.
+pub fn write_html<W: Write>(mut w: W, doc: &Document) -> Result<()> {
+    log::info!(target: "cargo_spdx", "writing out file in HTML format");
+
+    let report = Report::new(doc)?;
+    let title = html(&doc.document_name.to_string());
+
+    writeln!(w, "<!DOCTYPE html>")?;
+    writeln!(w, "<html>")?;
+    writeln!(w, "<head>")?;
+    writeln!(w, "<meta charset=\"utf-8\">")?;
+    writeln!(w, "<title>{}</title>", title)?;
+    writeln!(w, "</head>")?;
+    writeln!(w, "<body>")?;
+    writeln!(w, "<h1>{}</h1>", title)?;
+    writeln!(w, "<dl>")?;
+    for (label, value) in report.details() {
+        writeln!(w, "<dt>{}</dt><dd>{}</dd>", label, html(&value))?;
+    }
+    writeln!(w, "</dl>")?;
+    if let Some(comment) = &doc.document_comment {
+        writeln!(w, "<p>{}</p>", html(comment))?;
+    }
+
+    writeln!(w, "<h2>Packages</h2>")?;
+    writeln!(w, "<table>")?;
+    writeln!(
+        w,
+        "<tr>{}</tr>",
+        PACKAGE_COLUMNS
+            .iter()
+            .map(|column| format!("<th>{}</th>", column))
+            .collect::<String>()
+    )?;
+    for row in &report.packages {
+        writeln!(
+            w,
+            "<tr>{}</tr>",
+            row.iter()
+                .map(|cell| format!("<td>{}</td>", html(cell)))
+                .collect::<String>()
+        )?;
+    }
+    writeln!(w, "</table>")?;
+
+    writeln!(w, "<h2>Licenses</h2>")?;
+    writeln!(w, "<table>")?;
+    writeln!(w, "<tr><th>License</th><th>Packages</th></tr>")?;
+    for (license, count) in &report.licenses {
+        writeln!(w, "<tr><td>{}</td><td>{}</td></tr>", html(license), count)?;
+    }
+    writeln!(w, "</table>")?;
+
+    writeln!(w, "<h2>Relationships</h2>")?;
+    writeln!(w, "<table>")?;
+    writeln!(w, "<tr><th>Type</th><th>Count</th></tr>")?;
+    for (relationship_type, count) in &report.relationships {
+        writeln!(
+            w,
+            "<tr><td><code>{}</code></td><td>{}</td></tr>",
+            relationship_type, count
+        )?;
+    }
+    writeln!(w, "</table>")?;
+    writeln!(w, "</body>")?;
+    writeln!(w, "</html>")?;
+
+    w.flush()?;
+    Ok(())
+}
+
+/// Escape text for a Markdown table cell or list item.
+fn markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '|' | '*' | '_' | '`' | '[' | ']' | '<' | '>' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape text for HTML.
+fn html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{html, markdown};
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(markdown("MIT | Apache-2.0"), "MIT \\| Apache-2.0");
+        assert_eq!(
+            markdown("Jane <jane@example.com>"),
+            "Jane \\<jane@example.com\\>"
+        );
+        assert_eq!(
+            html("Jane <jane@example.com> & co"),
+            "Jane &lt;jane@example.com&gt; &amp; co"
+        );
+    }
+}
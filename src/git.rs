@@ -1,16 +1,25 @@
 //! Functions for getting git metadata.
 
 use anyhow::Result;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
 
 /// Get the current Git user.
 ///
 /// This requires that the name is specified, but permits the
 /// email to be missing.
+#[cfg(feature = "git")]
 pub fn get_current_user() -> Result<User> {
     log::info!(target: "cargo_spdx", "loading default git configuration");
 
-    let git_config = git2::Config::open_default()?.snapshot()?;
-    let name = git_config.get_str("user.name")?.to_owned();
+    // git2's messages are clear enough without its error class and code.
+    let git_config = git2::Config::open_default()
+        .and_then(|mut config| config.snapshot())
+        .map_err(|err| anyhow::anyhow!("can't open the git configuration: {}", err.message()))?;
+    let name = git_config
+        .get_str("user.name")
+        .map_err(|err| anyhow::anyhow!("{}", err.message()))?
+        .to_owned();
     let email = git_config.get_str("user.email").ok().map(ToOwned::to_owned);
 
     log::info!(target: "cargo_spdx", "detected git username: {}", name);
@@ -22,6 +31,99 @@ pub fn get_current_user() -> Result<User> {
     Ok(User { name, email })
 }
 
+/// Get the current Git user, which can't be done without the `git` feature.
+#[cfg(not(feature = "git"))]
+pub fn get_current_user() -> Result<User> {
+    Err(anyhow::anyhow!(
+        "cargo-spdx was built without the `git` feature"
+    ))
+}
+
+/// Get the state of the git repository holding a path.
+///
+/// Untracked files count as uncommitted changes, since they may be built or
+/// packaged, but ignored files don't.
+#[cfg(feature = "git")]
+pub fn get_source_state(path: &Path) -> Result<SourceState> {
+    let message = |err: git2::Error| anyhow::anyhow!("{}", err.message());
+
+    let repo = git2::Repository::discover(path).map_err(message)?;
+    let head = repo.head().map_err(message)?;
+    let commit = head.peel_to_commit().map_err(message)?.id().to_string();
+    let branch = head
+        .is_branch()
+        .then(|| head.shorthand().map(ToOwned::to_owned))
+        .flatten();
+
+    let mut options = git2::StatusOptions::new();
+    options
+        .include_untracked(true)
+        .include_ignored(false)
+        .exclude_submodules(true);
+    let dirty = !repo
+        .statuses(Some(&mut options))
+        .map_err(message)?
+        .is_empty();
+
+    log::info!(target: "cargo_spdx", "detected git commit: {}", commit);
+
+    Ok(SourceState {
+        commit,
+        branch,
+        dirty,
+    })
+}
+
+/// Get the state of the git repository holding a path, which can't be done without the `git` feature.
+#[cfg(not(feature = "git"))]
+pub fn get_source_state(_path: &Path) -> Result<SourceState> {
+    Err(anyhow::anyhow!(
+        "cargo-spdx was built without the `git` feature"
+    ))
+}
+
+/// Which commit of a git repository is checked out, and whether it's been changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceState {
+    /// The hash of the commit checked out.
+    pub commit: String,
+    /// The branch checked out, unless the head is detached.
+    pub branch: Option<String>,
+    /// Whether there are changes which haven't been committed.
+    pub dirty: bool,
+}
+
+impl SourceState {
+    /// The number of digits of the commit hash put in versions, as `git` abbreviates it.
+    const SHORT_COMMIT_LEN: usize = 7;
+
+    /// A version marked as built from this commit, e.g. `1.0.0+g1a2b3c4`.
+    ///
+    /// The commit goes in the build metadata, after any already there, so the
+    /// version still compares equal to the release it's built from.
+    pub fn version_of(&self, version: &cargo_metadata::semver::Version) -> String {
+        let commit = &self.commit[..Self::SHORT_COMMIT_LEN.min(self.commit.len())];
+        if version.build.is_empty() {
+            format!("{}+g{}", version, commit)
+        } else {
+            format!("{}.g{}", version, commit)
+        }
+    }
+}
+
+impl Display for SourceState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Sources at git commit {}", self.commit)?;
+        if let Some(branch) = &self.branch {
+            write!(f, " on branch {}", branch)?;
+        }
+        if self.dirty {
+            write!(f, ", with uncommitted changes")?;
+        }
+        write!(f, ".")
+    }
+}
+
 /// A user pulled from the Git config.
 #[derive(Debug)]
 pub struct User {
@@ -30,3 +132,36 @@ pub struct User {
     /// The user's email, if specified.
     pub email: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::SourceState;
+
+    #[test]
+    fn test_source_state() {
+        let mut state = SourceState {
+            commit: "eb21981d6d3c0c3b2f6b9a0c2d0d4f4c9b7f5a11".to_string(),
+            branch: Some("main".to_string()),
+            dirty: false,
+        };
+        assert_eq!(
+            state.to_string(),
+            "Sources at git commit eb21981d6d3c0c3b2f6b9a0c2d0d4f4c9b7f5a11 on branch main."
+        );
+        state.branch = None;
+        state.dirty = true;
+        assert_eq!(
+            state.to_string(),
+            "Sources at git commit eb21981d6d3c0c3b2f6b9a0c2d0d4f4c9b7f5a11, with uncommitted changes."
+        );
+
+        assert_eq!(
+            state.version_of(&cargo_metadata::semver::Version::parse("1.0.0").unwrap()),
+            "1.0.0+geb21981"
+        );
+        assert_eq!(
+            state.version_of(&cargo_metadata::semver::Version::parse("1.0.0-rc.1+linux").unwrap()),
+            "1.0.0-rc.1+linux.geb21981"
+        );
+    }
+}
@@ -0,0 +1,323 @@
+//! Works out who is creating an SBOM, to list alongside cargo-spdx.
+//!
+//! Each source is tried in turn until one names someone: the
+//! `CARGO_SPDX_CREATOR` environment variable, the `creator` setting of the
+//! configuration file, then the git configuration. Why each source was passed
+//! over is kept, so a missing creator can be explained rather than silently
+//! dropped. More creators, such as the organization an SBOM is made for, can be
+//! listed alongside, and the lookup can be skipped so only they're named.
+
+use crate::config::Config;
+use crate::document::{CreationInfo, CreationInfoBuilder, Creator};
+use crate::git::get_current_user;
+use anyhow::{anyhow, Result};
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::ops::Not as _;
+
+/// The environment variable naming the creator.
+pub const CREATOR_VAR: &str = "CARGO_SPDX_CREATOR";
+
+/// Looks for the creator in one source, giving why it was passed over if it's not there.
+type FindFn<'a> = &'a dyn Fn() -> Result<Creator, String>;
+
+/// What one source of the creator's identity gave.
+#[derive(Debug, Clone)]
+struct Attempt {
+    /// Where the identity was looked for.
+    source: &'static str,
+    /// The creator found, or why the source was passed over.
+    result: Result<Creator, String>,
+}
+
+/// The creator of an SBOM, with how it was found.
+#[derive(Debug, Clone, Default)]
+pub struct Identity {
+    /// Each source tried, in order, ending with the one which named a creator, if any.
+    attempts: Vec<Attempt>,
+    /// Whether the person creating the SBOM was left out, so no source was tried.
+    suppressed: bool,
+    /// Creators listed alongside the one found, such as an organization.
+    additional: Vec<Creator>,
+}
+
+impl Identity {
+    /// Look for the creator in each source in turn.
+    ///
+    /// # Arguments
+    /// * `configured` - The creator set in the configuration file, if any
+    pub fn resolve(configured: Option<&Creator>) -> Identity {
+        let mut identity = Identity::default();
+        let sources: [(&'static str, FindFn<'_>); 3] = [
+            (CREATOR_VAR, &from_env),
+            ("configuration file", &|| {
+                configured
+                    .cloned()
+                    .ok_or_else(|| "no `creator` set".to_string())
+            }),
+            ("git configuration", &from_git),
+        ];
+        for (source, find) in sources {
+            let result = find();
+            let found = result.is_ok();
+            identity.attempts.push(Attempt { source, result });
+            if found {
+                break;
+            }
+        }
+        identity
+    }
+
+    /// Name only the `additional` creators, leaving out the person creating the SBOM.
+    pub fn suppressed() -> Identity {
+        Identity {
+            suppressed: true,
+            ..Identity::default()
+        }
+    }
+
+    /// Work out the creators from the configuration file, if there is one, and
+    /// the command line.
+    ///
+    /// # Arguments
+    /// * `config` - The configuration file, giving `creator`, `creators`, and `person`
+    /// * `no_person` - Whether to leave out the person creating the SBOM
+    /// * `creators` - More creators given on the command line
+    pub fn configured(
+        config: Option<&Config>,
+        no_person: bool,
+        creators: &[Creator],
+    ) -> Result<Identity> {
+        let mut identity =
+            if no_person || config.map_or(false, |config| config.person == Some(false)) {
+                Identity::suppressed()
+            } else {
+                Identity::resolve(config.map(Config::creator).transpose()?.flatten().as_ref())
+            };
+        if let Some(config) = config {
+            identity.add_creators(config.creators()?);
+        }
+        identity.add_creators(creators.iter().cloned());
+        Ok(identity)
+    }
+
+    /// List more creators alongside the one found, skipping any already listed.
+    pub fn add_creators(&mut self, creators: impl IntoIterator<Item = Creator>) {
+        for creator in creators {
+            let listed = self
+                .creators()
+                .iter()
+                .any(|listed| listed.to_string() == creator.to_string());
+            if listed.not() {
+                self.additional.push(creator);
+            }
+        }
+    }
+
+    /// The creator found, if any.
+    pub fn creator(&self) -> Option<&Creator> {
+        self.attempts
+            .iter()
+            .find_map(|attempt| attempt.result.as_ref().ok())
+    }
+
+    /// Every creator to list besides cargo-spdx: the one found, then the others.
+    pub fn creators(&self) -> Vec<&Creator> {
+        self.creator().into_iter().chain(&self.additional).collect()
+    }
+
+    /// Log where the creator was found, or warn why none was.
+    pub fn report(&self) {
+        for attempt in &self.attempts {
+            match &attempt.result {
+                Ok(creator) => {
+                    log::info!(target: "cargo_spdx", "creator from {}: {}", attempt.source, creator)
+                }
+                Err(why) => {
+                    log::info!(target: "cargo_spdx", "no creator from {}: {}", attempt.source, why)
+                }
+            }
+        }
+        if self.suppressed {
+            log::info!(target: "cargo_spdx", "not looking for the person creating the SBOM, as asked");
+        }
+        if self.creators().is_empty() && self.suppressed {
+            log::warn!(
+                target: "cargo_spdx",
+                "only cargo-spdx is listed as a creator, as the person creating the SBOM is left out; name an organization with --creator or `creators` in the configuration file"
+            );
+        } else if self.creators().is_empty() {
+            log::warn!(
+                target: "cargo_spdx",
+                "only cargo-spdx is listed as a creator ({}); set {}, `creator` in the configuration file, or `git config user.name`",
+                self,
+                CREATOR_VAR
+            );
+        }
+    }
+
+    /// Build the creation info naming the creator found and cargo-spdx.
+    ///
+    /// # Arguments
+    /// * `explain` - Whether to note the sources tried in the creation info comment
+    pub fn creation_info(&self, explain: bool) -> Result<CreationInfo> {
+        let mut creators: Vec<Creator> = self.creators().into_iter().cloned().collect();
+        creators.push(Creator::tool(&format!(
+            "cargo-spdx {}",
+            env!("CARGO_PKG_VERSION")
+        )));
+
+        let mut builder = CreationInfoBuilder::default();
+        builder.creators(creators);
+        if explain {
+            builder.comment(format!("Creators were looked for in: {}.", self));
+        }
+        Ok(builder.build()?)
+    }
+}
+
+impl Display for Identity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.suppressed {
+            write!(f, "nowhere, as the person creating it was left out")?;
+        }
+        for (i, attempt) in self.attempts.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            match &attempt.result {
+                Ok(creator) => write!(f, "{} gave {}", attempt.source, creator)?,
+                Err(why) => write!(f, "{} skipped, {}", attempt.source, why)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Read the creator from the environment, where a bare name is taken to be a person
+fn from_env() -> Result<Creator, String> {
+    match env::var(CREATOR_VAR) {
+        Ok(value) if value.trim().is_empty() => Err("it's empty".to_string()),
+        Ok(value) => parse_creator(&value).map_err(|err| err.to_string()),
+        Err(env::VarError::NotPresent) => Err("it isn't set".to_string()),
+        Err(env::VarError::NotUnicode(_)) => Err("it isn't valid UTF-8".to_string()),
+    }
+}
+
+// Read the creator from the git configuration
+fn from_git() -> Result<Creator, String> {
+    get_current_user()
+        .map(|user| Creator::person(user.name, user.email))
+        .map_err(|err| format!("{:#}", err))
+}
+
+/// Parse a person or organization, in the form SPDX lists creators.
+///
+/// A value without a `Person:` or `Organization:` prefix is taken to be a person.
+pub fn parse_creator(s: &str) -> Result<Creator> {
+    if s.starts_with("Tool:") {
+        return Err(anyhow!(
+            "'{}' is a tool, but the creator must be a person or organization",
+            s
+        ));
+    }
+    if s.starts_with("Person:") || s.starts_with("Organization:") {
+        s.parse()
+    } else {
+        format!("Person: {}", s).parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_creator, Attempt, Identity};
+    use crate::config::Config;
+    use crate::document::Creator;
+
+    #[test]
+    fn test_identity_report() {
+        let identity = Identity {
+            attempts: vec![
+                Attempt {
+                    source: "CARGO_SPDX_CREATOR",
+                    result: Err("it isn't set".to_string()),
+                },
+                Attempt {
+                    source: "configuration file",
+                    result: Ok(parse_creator("Organization: Acme Corp").unwrap()),
+                },
+            ],
+            ..Identity::default()
+        };
+        assert_eq!(
+            identity.creator().map(ToString::to_string).as_deref(),
+            Some("Organization: Acme Corp")
+        );
+        assert_eq!(
+            identity.to_string(),
+            "CARGO_SPDX_CREATOR skipped, it isn't set; configuration file gave Organization: Acme Corp"
+        );
+
+        let info = identity.creation_info(true).unwrap();
+        assert_eq!(info.creators.as_ref().unwrap().len(), 2);
+        assert_eq!(
+            info.comment.as_deref(),
+            Some("Creators were looked for in: CARGO_SPDX_CREATOR skipped, it isn't set; configuration file gave Organization: Acme Corp.")
+        );
+        assert!(Identity::default()
+            .creation_info(false)
+            .unwrap()
+            .comment
+            .is_none());
+    }
+
+    #[test]
+    fn test_configured_creators() {
+        let config: Config = toml::from_str(
+            r#"
+            creators = ["Organization: Acme Corp", "Jane Doe (jane@example.com)"]
+            person = false
+            "#,
+        )
+        .unwrap();
+        let identity = Identity::configured(
+            Some(&config),
+            false,
+            &[
+                parse_creator("John Doe").unwrap(),
+                parse_creator("Organization: Acme Corp").unwrap(),
+            ],
+        )
+        .unwrap();
+        let creators: Vec<_> = identity
+            .creation_info(false)
+            .unwrap()
+            .creators
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            creators,
+            [
+                "Organization: Acme Corp".to_string(),
+                "Person: Jane Doe (jane@example.com)".to_string(),
+                "Person: John Doe".to_string(),
+                format!("Tool: cargo-spdx {}", env!("CARGO_PKG_VERSION")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_creator() {
+        assert!(matches!(
+            parse_creator("Jane Doe (jane@example.com)").unwrap(),
+            Creator::Person { name, email: Some(email) } if name == "Jane Doe" && email == "jane@example.com"
+        ));
+        assert!(matches!(
+            parse_creator("Organization: Acme Corp").unwrap(),
+            Creator::Organization { .. }
+        ));
+        assert!(parse_creator("Tool: foo").is_err());
+    }
+}
@@ -0,0 +1,391 @@
+//! The intermediate representation of the information gathered about a workspace.
+//!
+//! Gathering runs `cargo` and hashes every file, so it's the slow part of
+//! producing an SBOM. Saving what was gathered lets any number of documents be
+//! rendered from it later without scanning the workspace again.
+
+use crate::cargo::{cargo_exec, dependency_edges, CargoFlags, MetadataExt};
+use crate::checksums::ArchiveChecksums;
+use crate::copyright;
+use crate::document::{
+    self, File, FileType, HasExtractedLicensingInfo, Package, Relationship, SpdxIdentifier,
+};
+use crate::git::get_source_state;
+use crate::license::DeclaredLicense;
+use anyhow::{anyhow, Context, Result};
+use cargo_metadata::camino::Utf8PathBuf;
+use cargo_metadata::Metadata;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::BufRead;
+use std::path::Path;
+use std::process::Command;
+
+/// The version of the IR format, bumped whenever it changes incompatibly.
+pub const IR_VERSION: u32 = 1;
+
+/// Everything gathered about a workspace that goes into its SBOM.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ir {
+    /// The version of the IR format.
+    pub version: u32,
+
+    /// The name of the root package, which names the output by default.
+    pub name: String,
+
+    /// The packages in the workspace.
+    pub packages: Vec<Package>,
+
+    /// The files in the packages.
+    pub files: Vec<File>,
+
+    /// The relationships between the packages and files.
+    pub relationships: Vec<Relationship>,
+
+    /// The texts of licenses the packages declare which aren't on the SPDX License List.
+    #[serde(default)]
+    pub extracted_licenses: Vec<HasExtractedLicensingInfo>,
+}
+
+/// What to gather about a workspace
+#[derive(Debug, Default)]
+pub struct GatherOptions<'a> {
+    /// Whether to list dev-dependencies
+    pub include_dev: bool,
+    /// Whether to list only the members, leaving out what they depend on
+    pub no_deps: bool,
+    /// Whether to take the copyright of packages from their sources
+    pub scan_copyrights: bool,
+    /// Whether to download and hash the archives of registry packages whose
+    /// checksums are found nowhere else
+    pub fetch_missing: bool,
+    /// Whether to append the commit checked out to the root package's version
+    pub version_from_git: bool,
+    /// Members to leave out, other than the one described, unless something
+    /// listed depends on them
+    pub exclude: &'a [String],
+    /// The member to describe, as a package spec, rather than the resolve's root
+    pub package: Option<&'a str>,
+    /// Whether to describe the whole workspace, as a package containing its members
+    pub merged: bool,
+    /// Flags to pass to cargo when listing files
+    pub cargo_flags: CargoFlags,
+}
+
+impl Ir {
+    /// Gather the files, packages, and relationships for each member of the workspace.
+    ///
+    /// Everything the members depend on is listed too, but without its files,
+    /// unless `no_deps`, when only the dependencies among the members are.
+    /// When a `package` is chosen, it's described along with only the members
+    /// it depends on. When `merged`, the document describes a package for the
+    /// workspace itself instead, which contains every member.
+    pub fn gather(metadata: &Metadata, options: &GatherOptions<'_>) -> Result<Ir> {
+        let GatherOptions {
+            include_dev,
+            no_deps,
+            scan_copyrights,
+            fetch_missing,
+            version_from_git,
+            exclude,
+            package,
+            merged,
+            cargo_flags,
+        } = *options;
+        let root = match package {
+            Some(spec) => Some(metadata.find_member(spec)?),
+            None if merged => None,
+            None => Some(metadata.root()?),
+        };
+        for name in exclude {
+            if !metadata
+                .workspace_packages()
+                .iter()
+                .any(|package| &package.name == name)
+            {
+                return Err(anyhow!(
+                    "can't exclude {}, as it isn't a workspace member",
+                    name
+                ));
+            }
+        }
+        let mut members: Vec<_> = match (package, root) {
+            (Some(_), Some(root)) => {
+                let mut members = vec![&root.id];
+                for edge in dependency_edges(metadata, &[&root.id], include_dev) {
+                    if metadata.workspace_members.contains(edge.to) && !members.contains(&edge.to) {
+                        members.push(edge.to);
+                    }
+                }
+                members
+            }
+            _ => metadata.workspace_members.iter().collect(),
+        };
+        members.retain(|member| {
+            root.map_or(false, |root| *member == &root.id)
+                || !exclude.contains(&metadata[*member].name)
+        });
+
+        let mut archives = ArchiveChecksums::new(
+            metadata.workspace_root.as_std_path(),
+            metadata
+                .target_directory
+                .join("cargo-spdx")
+                .join("downloads")
+                .as_std_path(),
+            cargo_flags.is_offline(),
+            fetch_missing,
+        )?;
+        let mut packages = Vec::new();
+        let mut files = Vec::new();
+        let mut relationships = Vec::new();
+        let mut extracted_licenses = Vec::new();
+        let mut spdxids = HashMap::new();
+        for &member in &members {
+            let package = &metadata[member];
+            // List files in package
+            let out = Command::new(cargo_exec())
+                .args([
+                    "package",
+                    "--list",
+                    "--allow-dirty",
+                    "--manifest-path",
+                    package.manifest_path.as_str(),
+                ])
+                .args(cargo_flags.args())
+                .output()?;
+            let root = package.manifest_path.parent().unwrap();
+            let mut source_files = out
+                .stdout
+                .lines()
+                .map_while(Result::ok)
+                // `cargo package --list` includes the normalized Cargo.toml.orig
+                // but this won't be present locally (`cargo package` fails if it is)
+                // cargo package always lists Cargo.lock too, which may not be present.
+                // So just filter out any entries which can't be found locally
+                .filter_map(|path| {
+                    // Path is relative to crate root, so we need to add
+                    // the crate root in order to find it locally.
+                    let mut abs_path = Utf8PathBuf::from(root);
+                    abs_path.push(path);
+                    if abs_path.exists() {
+                        Some(abs_path)
+                    } else {
+                        None
+                    }
+                })
+                .map(|path| -> Result<File, anyhow::Error> {
+                    File::try_from_file(
+                        &path,
+                        root,
+                        FileType::of(&path),
+                        Some(&package.name),
+                        Some(&package.version.to_string()),
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut spdx_package: Package = package.into();
+            if metadata.is_ambiguous(package) {
+                spdx_package.disambiguate(package);
+            }
+            spdx_package.note_features(package, metadata.enabled_features(member));
+            spdx_package.set_verification_code(&source_files);
+            let license = DeclaredLicense::of(package)?;
+            spdx_package.license_declared = Some(license.expression);
+            extracted_licenses.extend(license.extracted);
+            if scan_copyrights {
+                spdx_package.copyright_text = Some(copyright::scan_package(package)?);
+            }
+            for file in &source_files {
+                relationships.push(Relationship {
+                    comment: None,
+                    related_spdx_element: file.spdxid.clone(),
+                    relationship_type: document::RelationshipType::Contains,
+                    spdx_element_id: spdx_package.spdxid.clone(),
+                });
+            }
+            // Without running the build script it's unknown how the library is
+            // linked, so assume rustc's default of linking it dynamically.
+            if let Some(links) = &package.links {
+                let native_library = Package::native_library(links, package, None);
+                relationships.push(Relationship {
+                    comment: Some("Linkage assumed, as the build script wasn't run.".to_string()),
+                    related_spdx_element: native_library.spdxid.clone(),
+                    relationship_type: document::RelationshipType::DynamicLink,
+                    spdx_element_id: spdx_package.spdxid.clone(),
+                });
+                packages.push(native_library);
+            }
+            spdxids.insert(member, spdx_package.spdxid.clone());
+            packages.push(spdx_package);
+            files.append(&mut source_files);
+        }
+
+        // The document is the SBOM of the root package, or of the workspace as
+        // a whole, which contains the members.
+        let (described, described_dir, name) = match root {
+            Some(root) => (
+                spdxids[&root.id].clone(),
+                root.manifest_path.parent().unwrap(),
+                root.name.clone(),
+            ),
+            None => {
+                let name = metadata
+                    .workspace_root
+                    .file_name()
+                    .unwrap_or("workspace")
+                    .to_string();
+                let workspace = Package::workspace(&name);
+                for &member in &members {
+                    relationships.push(Relationship {
+                        comment: None,
+                        related_spdx_element: spdxids[member].clone(),
+                        relationship_type: document::RelationshipType::Contains,
+                        spdx_element_id: workspace.spdxid.clone(),
+                    });
+                }
+                let described = workspace.spdxid.clone();
+                packages.push(workspace);
+                (described, metadata.workspace_root.as_path(), name)
+            }
+        };
+
+        // Note which commit the described package was built from, if it's in a
+        // git repository, so the SBOM can be traced back to its sources.
+        match get_source_state(described_dir.as_std_path()) {
+            Ok(state) => {
+                if let Some(package) = packages
+                    .iter_mut()
+                    .find(|package| package.spdxid == described)
+                {
+                    package.source_info = Some(match package.source_info.take() {
+                        Some(info) => format!("{} {}", info, state),
+                        None => state.to_string(),
+                    });
+                    if let (true, Some(root)) = (version_from_git, root) {
+                        package.version_info = Some(state.version_of(&root.version));
+                    }
+                }
+            }
+            Err(err) if version_from_git => {
+                return Err(err.context(format!(
+                "--version-from-git needs {} to be in a git repository with a commit checked out",
+                name
+            )))
+            }
+            Err(err) => {
+                log::info!(target: "cargo_spdx", "not recording the git commit of {}: {:#}", name, err)
+            }
+        }
+        relationships.push(Relationship {
+            comment: None,
+            related_spdx_element: described.clone(),
+            relationship_type: document::RelationshipType::Describes,
+            spdx_element_id: SpdxIdentifier.to_string(),
+        });
+
+        // Relate the workspace's manifest and lockfile to the described package,
+        // so the SBOM can be checked against the ones it was made from. Those in
+        // a member at the workspace root are among its files already.
+        let owner = root.or_else(|| {
+            members
+                .iter()
+                .map(|member| &metadata[*member])
+                .find(|member| member.manifest_path.parent() == Some(&metadata.workspace_root))
+        });
+        for name in ["Cargo.toml", "Cargo.lock"] {
+            let path = metadata.workspace_root.join(name);
+            if !path.exists() {
+                continue;
+            }
+            let file = match owner {
+                Some(owner) if path.starts_with(owner.manifest_path.parent().unwrap()) => {
+                    File::try_from_file(
+                        &path,
+                        owner.manifest_path.parent().unwrap(),
+                        FileType::of(&path),
+                        Some(&owner.name),
+                        Some(&owner.version.to_string()),
+                    )?
+                }
+                _ => File::try_from_file(
+                    &path,
+                    &metadata.workspace_root,
+                    FileType::of(&path),
+                    None,
+                    None,
+                )?,
+            };
+            relationships.push(Relationship {
+                comment: None,
+                related_spdx_element: described.clone(),
+                relationship_type: document::RelationshipType::DependencyManifestOf,
+                spdx_element_id: file.spdxid.clone(),
+            });
+            if !files.iter().any(|listed| listed.spdxid == file.spdxid) {
+                files.push(file);
+            }
+        }
+
+        for edge in dependency_edges(metadata, &members, include_dev) {
+            if no_deps && !(spdxids.contains_key(edge.from) && spdxids.contains_key(edge.to)) {
+                continue;
+            }
+            if !spdxids.contains_key(edge.to) {
+                let package = &metadata[edge.to];
+                let mut spdx_package: Package = package.into();
+                if metadata.is_ambiguous(package) {
+                    spdx_package.disambiguate(package);
+                }
+                spdx_package.note_features(package, metadata.enabled_features(edge.to));
+                let license = DeclaredLicense::of(package)?;
+                spdx_package.license_declared = Some(license.expression);
+                extracted_licenses.extend(license.extracted);
+                if scan_copyrights {
+                    spdx_package.copyright_text = Some(copyright::scan_package(package)?);
+                }
+                if let Some(checksum) = archives.checksum(package)? {
+                    spdx_package.set_archive(&checksum);
+                }
+                spdxids.insert(edge.to, spdx_package.spdxid.clone());
+                packages.push(spdx_package);
+            }
+            relationships.push(edge.relationship(&spdxids[edge.from], &spdxids[edge.to]));
+        }
+
+        Ok(Ir {
+            version: IR_VERSION,
+            name,
+            packages,
+            files,
+            relationships,
+            extracted_licenses,
+        })
+    }
+
+    /// Read an IR saved by [`Ir::save`].
+    pub fn load(path: &Path) -> Result<Ir> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read IR from {}", path.display()))?;
+        let ir: Ir = serde_json::from_str(&contents)
+            .with_context(|| format!("invalid IR in {}", path.display()))?;
+        if ir.version != IR_VERSION {
+            return Err(anyhow!(
+                "IR in {} is version {}, but this version of cargo-spdx reads version {}",
+                path.display(),
+                ir.version,
+                IR_VERSION
+            ));
+        }
+        Ok(ir)
+    }
+
+    /// Save the IR so documents can be rendered from it later.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        log::info!(target: "cargo_spdx", "writing IR to {}", path.display());
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents)
+            .with_context(|| format!("failed to write IR to {}", path.display()))
+    }
+}
@@ -0,0 +1,511 @@
+//! Parses SPDX license expressions.
+
+use crate::document::{spdx_id_safe, HasExtractedLicensingInfo, NOASSERTION};
+use crate::licenses::license_files;
+use anyhow::{anyhow, Result};
+use cargo_metadata::camino::Utf8Path;
+use std::fmt::{Display, Formatter};
+use std::fs;
+
+mod list;
+
+/// A parsed SPDX license expression.
+#[derive(Debug, PartialEq, Eq)]
+pub enum License {
+    /// A single license identifier.
+    Id(String),
+    /// A license identifier followed by `+`.
+    OrLater(String),
+    /// A license with an exception.
+    With(Box<License>, String),
+    /// A conjunction of licenses.
+    And(Vec<License>),
+    /// A disjunction of licenses.
+    Or(Vec<License>),
+}
+
+impl License {
+    /// Parse a license expression.
+    pub fn parse(expression: &str) -> Result<License> {
+        let tokens = tokenize_license(expression);
+        let mut parser = LicenseParser {
+            tokens: &tokens,
+            pos: 0,
+        };
+        let parsed = parser.parse_or()?;
+        if parser.pos != tokens.len() {
+            return Err(anyhow!("malformed license expression '{}'", expression));
+        }
+        Ok(parsed)
+    }
+
+    /// Parse the `license` field of a Cargo manifest.
+    ///
+    /// Cargo still accepts the deprecated `/` separator in place of `OR`.
+    pub fn parse_manifest(expression: &str) -> Result<License> {
+        License::parse(&expression.replace('/', " OR "))
+    }
+
+    /// Check if a license identifier is on the SPDX License List.
+    pub fn is_listed(id: &str) -> bool {
+        list::LICENSE_IDS.binary_search(&id).is_ok()
+    }
+
+    /// Replace each license identifier, leaving the structure of the expression as it is.
+    pub fn map_ids(self, f: &mut impl FnMut(String) -> String) -> License {
+        match self {
+            License::Id(id) => License::Id(f(id)),
+            License::OrLater(id) => License::OrLater(f(id)),
+            License::With(license, exception) => {
+                License::With(Box::new(license.map_ids(f)), exception)
+            }
+            License::And(members) => {
+                License::And(members.into_iter().map(|m| m.map_ids(f)).collect())
+            }
+            License::Or(members) => {
+                License::Or(members.into_iter().map(|m| m.map_ids(f)).collect())
+            }
+        }
+    }
+
+    /// List the licenses in the expression, without their exceptions, each once.
+    pub fn simple_licenses(&self) -> Vec<String> {
+        let mut licenses = Vec::new();
+        self.collect_simple_licenses(&mut licenses);
+        licenses
+    }
+
+    // Add each license in the expression to `licenses`, if it isn't there already
+    fn collect_simple_licenses(&self, licenses: &mut Vec<String>) {
+        let license = match self {
+            License::Id(id) => id.clone(),
+            License::OrLater(id) => format!("{}+", id),
+            License::With(license, _) => return license.collect_simple_licenses(licenses),
+            License::And(members) | License::Or(members) => {
+                for member in members {
+                    member.collect_simple_licenses(licenses);
+                }
+                return;
+            }
+        };
+        if !licenses.contains(&license) {
+            licenses.push(license);
+        }
+    }
+
+    /// Check if the license terms can be met using only the licenses `permitted` accepts.
+    ///
+    /// Exceptions only grant extra permissions, so a license with an exception is
+    /// acceptable whenever the license itself is.
+    pub fn is_satisfied_by(&self, permitted: &impl Fn(&str) -> bool) -> bool {
+        match self {
+            License::Id(id) | License::OrLater(id) => permitted(id),
+            License::With(license, _) => license.is_satisfied_by(permitted),
+            License::And(members) => members.iter().all(|m| m.is_satisfied_by(permitted)),
+            License::Or(members) => members.iter().any(|m| m.is_satisfied_by(permitted)),
+        }
+    }
+}
+
+impl Display for License {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Parenthesize members which bind more loosely than their parent.
+        let write_member = |f: &mut Formatter<'_>, member: &License, loose: bool| {
+            if loose {
+                write!(f, "({})", member)
+            } else {
+                write!(f, "{}", member)
+            }
+        };
+
+        match self {
+            License::Id(id) => write!(f, "{}", id),
+            License::OrLater(id) => write!(f, "{}+", id),
+            License::With(license, exception) => {
+                let loose = matches!(**license, License::And(_) | License::Or(_));
+                write_member(f, license, loose)?;
+                write!(f, " WITH {}", exception)
+            }
+            License::And(members) | License::Or(members) => {
+                let operator = match self {
+                    License::And(_) => " AND ",
+                    _ => " OR ",
+                };
+                for (i, member) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{}", operator)?;
+                    }
+                    let loose = matches!(self, License::And(_)) && matches!(member, License::Or(_));
+                    write_member(f, member, loose)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The license a package declares, as an SPDX license expression.
+#[derive(Debug)]
+pub struct DeclaredLicense {
+    /// The license expression, or `NOASSERTION` if the package declares none.
+    pub expression: String,
+    /// The texts of the `LicenseRef-`s in the expression.
+    pub extracted: Vec<HasExtractedLicensingInfo>,
+}
+
+impl DeclaredLicense {
+    /// Find the license a package declares in its manifest.
+    ///
+    /// Identifiers not on the SPDX License List, and licenses only given by a
+    /// `license-file`, would make the expression invalid, so they're replaced
+    /// with `LicenseRef-`s named after the package, whose texts are taken from
+    /// its license files.
+    pub fn of(package: &cargo_metadata::Package) -> Result<DeclaredLicense> {
+        let root = package.manifest_path.parent().unwrap();
+        let license_file_text = package
+            .license_file
+            .as_ref()
+            .and_then(|file| read_text(&root.join(file)));
+        let prefix = format!("{} {}", package.name, package.version);
+
+        let expression = match (&package.license, &package.license_file) {
+            (Some(expression), _) => expression,
+            (None, Some(file)) => {
+                return Ok(DeclaredLicense::license_ref(
+                    license_ref(package, None),
+                    license_file_text,
+                    None,
+                    format!("The license-file of {}, {}.", prefix, file),
+                ))
+            }
+            (None, None) => {
+                return Ok(DeclaredLicense {
+                    expression: NOASSERTION.to_string(),
+                    extracted: Vec::new(),
+                })
+            }
+        };
+
+        let license = match License::parse_manifest(expression) {
+            Ok(license) => license,
+            // Keep what was declared, even if it can't be read as an expression.
+            Err(_) => {
+                return Ok(DeclaredLicense::license_ref(
+                    license_ref(package, None),
+                    license_file_text,
+                    Some(expression.clone()),
+                    format!(
+                        "The license of {}, which isn't a valid SPDX license expression.",
+                        prefix
+                    ),
+                ))
+            }
+        };
+
+        let files = license_files(package, root)?;
+        let mut extracted: Vec<HasExtractedLicensingInfo> = Vec::new();
+        let license = license.map_ids(&mut |id| {
+            if License::is_listed(&id) {
+                return id;
+            }
+
+            let license_id = license_ref(package, Some(&id));
+            if extracted.iter().all(|info| info.license_id != license_id) {
+                // Prefer a license file named after the license, e.g. LICENSE-ACME for Acme-1.0.
+                let lowercase_id = id.to_ascii_lowercase();
+                let text = files
+                    .iter()
+                    .find(|file| {
+                        let stem = file.file_stem().unwrap_or_default().to_ascii_lowercase();
+                        match stem.split_once(['-', '_']) {
+                            Some((_, name)) => lowercase_id.starts_with(name),
+                            None => false,
+                        }
+                    })
+                    .and_then(|file| read_text(file))
+                    .or_else(|| license_file_text.clone());
+                let comment = format!("Declared by {}, but not on the SPDX License List.", prefix);
+                extracted.extend(
+                    DeclaredLicense::license_ref(license_id.clone(), text, Some(id), comment)
+                        .extracted,
+                );
+            }
+            license_id
+        });
+
+        Ok(DeclaredLicense {
+            expression: license.to_string(),
+            extracted,
+        })
+    }
+
+    // A license made up of one LicenseRef, with the given text
+    fn license_ref(
+        license_id: String,
+        text: Option<String>,
+        name: Option<String>,
+        comment: String,
+    ) -> DeclaredLicense {
+        let comment = if text.is_some() {
+            comment
+        } else {
+            format!("{} No license text was found in the package.", comment)
+        };
+        DeclaredLicense {
+            expression: license_id.clone(),
+            extracted: vec![HasExtractedLicensingInfo {
+                comment: Some(comment),
+                cross_refs: None,
+                extracted_text: text.unwrap_or_else(|| NOASSERTION.to_string()),
+                license_id,
+                name,
+                see_alsos: None,
+            }],
+        }
+    }
+}
+
+/// Find the licenses a file's `SPDX-License-Identifier:` tags give.
+///
+/// Licenses which aren't on the SPDX License List, other than `LicenseRef-`s,
+/// are left out, as they can't be listed as they are.
+pub fn tagged_licenses(text: &str) -> Vec<String> {
+    const TAG: &str = "SPDX-License-Identifier:";
+
+    let mut licenses = Vec::new();
+    for line in text.lines() {
+        let expression = match line.find(TAG) {
+            Some(start) => &line[start + TAG.len()..],
+            None => continue,
+        };
+        // Drop whatever closes the comment the tag is in, e.g. `*/` or `-->`.
+        let expression = expression
+            .trim()
+            .trim_end_matches("*/")
+            .trim_end_matches("-->")
+            .trim();
+        let license = match License::parse(expression) {
+            Ok(license) => license,
+            Err(_) => {
+                log::debug!(target: "cargo_spdx", "ignoring malformed license tag '{}'", expression);
+                continue;
+            }
+        };
+        for license in license.simple_licenses() {
+            let id = license.trim_end_matches('+');
+            if (License::is_listed(id) || id.starts_with("LicenseRef-"))
+                && !licenses.contains(&license)
+            {
+                licenses.push(license);
+            }
+        }
+    }
+    licenses
+}
+
+// The ID of a license declared by a package, optionally named after an unlisted license ID
+fn license_ref(package: &cargo_metadata::Package, id: Option<&str>) -> String {
+    let license_ref = match id {
+        Some(id) => format!("LicenseRef-{}-{}-{}", package.name, package.version, id),
+        None => format!("LicenseRef-{}-{}", package.name, package.version),
+    };
+    spdx_id_safe(&license_ref)
+}
+
+// Read a license text, which may not be valid UTF-8
+fn read_text(path: &Utf8Path) -> Option<String> {
+    fs::read(path)
+        .ok()
+        .map(|text| String::from_utf8_lossy(&text).into_owned())
+}
+
+/// Split a license expression into its tokens.
+fn tokenize_license(expression: &str) -> Vec<String> {
+    expression
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// A recursive-descent parser for SPDX license expressions.
+///
+/// `WITH` binds tighter than `AND`, which binds tighter than `OR`.
+struct LicenseParser<'t> {
+    tokens: &'t [String],
+    pos: usize,
+}
+
+impl<'t> LicenseParser<'t> {
+    fn peek(&self) -> Option<&'t str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Result<&'t str> {
+        let token = self
+            .peek()
+            .ok_or_else(|| anyhow!("unexpected end of license expression"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn parse_or(&mut self) -> Result<License> {
+        let mut members = vec![self.parse_and()?];
+        while self.peek() == Some("OR") {
+            self.pos += 1;
+            members.push(self.parse_and()?);
+        }
+        Ok(flatten(members, License::Or))
+    }
+
+    fn parse_and(&mut self) -> Result<License> {
+        let mut members = vec![self.parse_with()?];
+        while self.peek() == Some("AND") {
+            self.pos += 1;
+            members.push(self.parse_with()?);
+        }
+        Ok(flatten(members, License::And))
+    }
+
+    fn parse_with(&mut self) -> Result<License> {
+        let license = self.parse_atom()?;
+        if self.peek() == Some("WITH") {
+            self.pos += 1;
+            let exception = self.next()?;
+            return Ok(License::With(Box::new(license), exception.to_owned()));
+        }
+        Ok(license)
+    }
+
+    fn parse_atom(&mut self) -> Result<License> {
+        match self.next()? {
+            "(" => {
+                let inner = self.parse_or()?;
+                match self.next()? {
+                    ")" => Ok(inner),
+                    token => Err(anyhow!("expected ')' but found '{}'", token)),
+                }
+            }
+            token @ (")" | "AND" | "OR" | "WITH") => {
+                Err(anyhow!("unexpected '{}' in license expression", token))
+            }
+            token => match token.strip_suffix('+') {
+                Some(id) => Ok(License::OrLater(id.to_owned())),
+                None => Ok(License::Id(token.to_owned())),
+            },
+        }
+    }
+}
+
+/// Collapse a single-member set into the member itself.
+fn flatten(mut members: Vec<License>, set: fn(Vec<License>) -> License) -> License {
+    if members.len() == 1 {
+        members.remove(0)
+    } else {
+        set(members)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tagged_licenses, DeclaredLicense, License};
+    use serde_json::json;
+
+    #[test]
+    fn test_license_expression_precedence() {
+        assert_eq!(
+            License::parse("MIT OR Apache-2.0 WITH LLVM-exception AND (ISC OR GPL-2.0+)").unwrap(),
+            License::Or(vec![
+                License::Id("MIT".to_string()),
+                License::And(vec![
+                    License::With(
+                        Box::new(License::Id("Apache-2.0".to_string())),
+                        "LLVM-exception".to_string()
+                    ),
+                    License::Or(vec![
+                        License::Id("ISC".to_string()),
+                        License::OrLater("GPL-2.0".to_string()),
+                    ]),
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_license_display() {
+        for expression in [
+            "MIT",
+            "GPL-2.0+ WITH Classpath-exception-2.0",
+            "(MIT OR Apache-2.0) AND ISC",
+            "MIT OR Apache-2.0 AND ISC",
+            "(MIT AND ISC) WITH LLVM-exception",
+        ] {
+            let license = License::parse(expression).unwrap();
+            assert_eq!(license.to_string(), expression);
+            assert_eq!(License::parse(&license.to_string()).unwrap(), license);
+        }
+    }
+
+    #[test]
+    fn test_declared_license() {
+        let package = |license: &str| -> cargo_metadata::Package {
+            serde_json::from_value(json!({
+                "name": "foo",
+                "version": "1.0.0",
+                "id": "foo 1.0.0",
+                "license": license,
+                "dependencies": [],
+                "targets": [],
+                "features": {},
+                "manifest_path": concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"),
+            }))
+            .unwrap()
+        };
+
+        let declared = DeclaredLicense::of(&package("MIT OR Apache-2.0")).unwrap();
+        assert_eq!(declared.expression, "MIT OR Apache-2.0");
+        assert!(declared.extracted.is_empty());
+
+        let declared = DeclaredLicense::of(&package("MIT/Acme-Proprietary")).unwrap();
+        assert_eq!(
+            declared.expression,
+            "MIT OR LicenseRef-foo-1.0.0-Acme-Proprietary"
+        );
+        assert_eq!(declared.extracted.len(), 1);
+        assert_eq!(
+            declared.extracted[0].name.as_deref(),
+            Some("Acme-Proprietary")
+        );
+
+        let declared = DeclaredLicense::of(&package("MIT AND")).unwrap();
+        assert_eq!(declared.expression, "LicenseRef-foo-1.0.0");
+        assert_eq!(declared.extracted[0].name.as_deref(), Some("MIT AND"));
+    }
+
+    #[test]
+    fn test_license_satisfaction() {
+        let permitted = |id: &str| id != "GPL-3.0";
+        assert!(License::parse_manifest("MIT/GPL-3.0")
+            .unwrap()
+            .is_satisfied_by(&permitted));
+        assert!(!License::parse("MIT AND GPL-3.0")
+            .unwrap()
+            .is_satisfied_by(&permitted));
+    }
+
+    #[test]
+    fn test_tagged_licenses() {
+        let text = "// SPDX-License-Identifier: MIT OR Apache-2.0\n\
+                    /* SPDX-License-Identifier: GPL-2.0+ WITH Linux-syscall-note */\n\
+                    <!-- SPDX-License-Identifier: LicenseRef-Acme -->\n\
+                    # SPDX-License-Identifier: MIT AND Made-Up\n\
+                    // SPDX-License-Identifier: (MIT\n\
+                    let tag = 1;";
+        assert_eq!(
+            tagged_licenses(text),
+            ["MIT", "Apache-2.0", "GPL-2.0+", "LicenseRef-Acme"]
+        );
+        assert!(tagged_licenses("fn main() {}").is_empty());
+    }
+}
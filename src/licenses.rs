@@ -0,0 +1,175 @@
+//! Collects the license files of packages into a directory.
+//!
+//! Some distributions must ship the license texts of everything they include
+//! verbatim, which the SBOM only names. The directory holds a subdirectory of
+//! license files for each package, and an `index.json` tying each one back to
+//! the package's SPDX ID.
+
+use crate::document::Package;
+use anyhow::{Context, Result};
+use cargo_metadata::camino::{Utf8Path, Utf8PathBuf};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the index file written into the directory.
+const INDEX_FILE: &str = "index.json";
+
+/// The prefixes of files conventionally holding license texts.
+const LICENSE_PREFIXES: &[&str] = &["license", "licence", "copying", "notice", "unlicense"];
+
+/// An entry in the index, describing the license files of one package.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexEntry {
+    #[serde(rename = "SPDXID")]
+    spdxid: String,
+    name: String,
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<String>,
+    /// Paths of the license files, relative to the directory.
+    files: Vec<String>,
+}
+
+/// The license files found for a package.
+#[derive(Debug)]
+pub struct PackageLicenses {
+    /// The SPDX ID of the package.
+    pub spdxid: String,
+    /// The name of the package.
+    pub name: String,
+    /// The version of the package.
+    pub version: String,
+    /// The license expression of the package, if it has one.
+    pub license: Option<String>,
+    /// The directory the package's license files are collected into, e.g. `serde-1.0.137`.
+    pub subdir: String,
+    /// The license files themselves.
+    pub files: BTreeSet<Utf8PathBuf>,
+}
+
+impl PackageLicenses {
+    /// Get the paths the license files are collected to, relative to the
+    /// collection, along with the files themselves.
+    pub fn collected_paths(&self) -> impl Iterator<Item = (String, &Utf8Path)> + '_ {
+        self.files.iter().map(move |source| {
+            (
+                format!("{}/{}", self.subdir, source.file_name().unwrap()),
+                source.as_path(),
+            )
+        })
+    }
+}
+
+/// Find the license files of each package.
+pub fn find_licenses<'a>(
+    packages: impl IntoIterator<Item = &'a cargo_metadata::Package>,
+) -> Result<Vec<PackageLicenses>> {
+    let mut licenses = Vec::new();
+    for package in packages {
+        let subdir = format!("{}-{}", package.name, package.version);
+        let root = package.manifest_path.parent().unwrap();
+        let files = license_files(package, root)?;
+
+        if files.is_empty() {
+            log::warn!(target: "cargo_spdx", "no license files found for {}", subdir);
+        }
+
+        licenses.push(PackageLicenses {
+            spdxid: Package::from(package).spdxid,
+            name: package.name.clone(),
+            version: package.version.to_string(),
+            license: package.license.clone(),
+            subdir,
+            files,
+        });
+    }
+    Ok(licenses)
+}
+
+/// Copy the license files of each package into `dir`, and index them.
+pub fn write_licenses_dir<'a>(
+    dir: &Path,
+    packages: impl IntoIterator<Item = &'a cargo_metadata::Package>,
+) -> Result<()> {
+    log::info!(target: "cargo_spdx", "writing license files to {}", dir.display());
+
+    let mut index = Vec::new();
+    for licenses in find_licenses(packages)? {
+        let mut files = Vec::new();
+        for (path, source) in licenses.collected_paths() {
+            let to = dir.join(&path);
+            fs::create_dir_all(to.parent().unwrap())?;
+            fs::copy(source, &to)
+                .with_context(|| format!("failed to copy license file {}", source))?;
+            files.push(path);
+        }
+
+        index.push(IndexEntry {
+            spdxid: licenses.spdxid,
+            name: licenses.name,
+            version: licenses.version,
+            license: licenses.license,
+            files,
+        });
+    }
+
+    index.sort_by(|a, b| a.spdxid.cmp(&b.spdxid));
+    fs::create_dir_all(dir)?;
+    fs::write(index_path(dir), serde_json::to_string_pretty(&index)?)?;
+    Ok(())
+}
+
+/// Get the path of the index written into `dir`.
+pub fn index_path(dir: &Path) -> PathBuf {
+    dir.join(INDEX_FILE)
+}
+
+/// Find the license files of a package: the `license-file` from its manifest,
+/// and any conventionally named files in its root.
+pub fn license_files(
+    package: &cargo_metadata::Package,
+    root: &Utf8Path,
+) -> Result<BTreeSet<Utf8PathBuf>> {
+    let mut files = BTreeSet::new();
+
+    if let Some(license_file) = &package.license_file {
+        let path = root.join(license_file);
+        if path.is_file() {
+            files.insert(path);
+        }
+    }
+
+    for entry in root.read_dir_utf8()? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() && is_license_file_name(entry.file_name()) {
+            files.insert(entry.path().to_owned());
+        }
+    }
+
+    Ok(files)
+}
+
+/// Check whether a file name is conventionally used for license texts.
+fn is_license_file_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    LICENSE_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_license_file_name;
+
+    #[test]
+    fn test_is_license_file_name() {
+        assert!(is_license_file_name("LICENSE-MIT"));
+        assert!(is_license_file_name("License.txt"));
+        assert!(is_license_file_name("COPYING"));
+        assert!(!is_license_file_name("README.md"));
+        assert!(!is_license_file_name("Cargo.toml"));
+    }
+}
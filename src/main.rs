@@ -4,130 +4,522 @@
 #![deny(missing_copy_implementations)]
 #![deny(missing_docs)]
 
+use crate::amend::amend;
 use crate::cargo::MetadataExt;
 use crate::cli::Args;
+use crate::config::Config;
+use crate::convert::{convert, read_referenced_document};
+use crate::doctor::doctor;
+use crate::document::DocumentPipeline;
 use crate::format::Format;
-use crate::output::OutputManager;
-use anyhow::Result;
-use build::build;
-use cargo::cargo_exec;
-use cargo_metadata::camino::Utf8PathBuf;
-use cargo_metadata::MetadataCommand;
-use clap::Parser;
-use document::{get_creation_info, DocumentBuilder, File, FileType, Package, Relationship};
-use std::io::BufRead;
-use std::path::PathBuf;
-use std::process::Command;
+use crate::identity::Identity;
+use crate::ir::{GatherOptions, Ir};
+use crate::licenses::write_licenses_dir;
+use crate::notices::notices;
+use crate::output::{OutputManager, OutputPlan};
+use crate::suppliers::Suppliers;
+use anyhow::{anyhow, Context, Result};
+use build::{build, BuildDeps, SbomOptions};
+use cargo_metadata::{Metadata, MetadataCommand};
+use std::ffi::OsString;
+use std::fs;
+use std::ops::Not as _;
+use std::path::{Path, PathBuf};
+use tree::tree;
 
+mod amend;
 mod build;
+#[cfg(feature = "bundle")]
+mod bundle;
 mod cargo;
+mod checksums;
 mod cli;
+mod config;
+mod convert;
+mod copyright;
+mod cpe;
+mod doctor;
 mod document;
+mod embeds;
 mod format;
 mod git;
+mod identity;
+mod ir;
+mod license;
+mod licenses;
+mod notices;
 mod output;
+#[cfg(feature = "network")]
+mod owners;
+mod policy;
+mod prompt;
+mod purl;
+#[cfg(feature = "network")]
+mod registry;
+mod suppliers;
+mod telemetry;
+mod toolchain;
+mod tree;
 
 /// Program entrypoint, only inits the system, calls `run` and reports errors.
 fn main() -> Result<()> {
     // Start the environment logger.
     env_logger::init();
-    let args = Args::parse();
+    let mut args = Args::try_parse_args_from(std::env::args_os()).unwrap_or_else(|e| e.exit());
+
+    let result = telemetry::in_span("cargo-spdx", &[], || run(&mut args));
+    telemetry::export(args.cargo_flags().is_offline());
+    result
+}
+
+/// Produce the SBOMs, or run the subcommand, the arguments ask for.
+fn run(args: &mut Args) -> Result<()> {
+    match &args.subcommand {
+        // The doctor checks everything below can work, so it mustn't depend on it.
+        Some(cli::Command::Doctor) => {
+            return doctor(args.config(), args.suppliers(), args.output())
+        }
+        // Converting only needs the SBOM being converted, not a workspace.
+        Some(cli::Command::Convert {
+            from,
+            untrusted,
+            input,
+        }) => return convert(input, *from, *untrusted, args),
+        // So does amending an SBOM.
+        Some(cli::Command::Amend {
+            base,
+            set,
+            reason,
+            from,
+            untrusted,
+        }) => return amend(base, set, reason.as_deref(), *from, *untrusted, args),
+        // Notices come from an existing SBOM too.
+        Some(cli::Command::Notices {
+            from,
+            untrusted,
+            input,
+        }) => return notices(input, *from, *untrusted, args),
+        _ => {}
+    }
+
+    if args.crates_io_owners() && cfg!(feature = "network").not() {
+        return Err(anyhow!(
+            "--crates-io-owners needs cargo-spdx to be built with the `network` feature"
+        ));
+    }
+    // Keep cargo-spdx off the network too when cargo is.
+    if args.cargo_flags().is_offline() && args.crates_io_owners() {
+        return Err(anyhow!(
+            "--crates-io-owners needs the network, so can't be used with --offline or --frozen"
+        ));
+    }
+    if args.cargo_flags().is_offline() && args.fetch_missing() {
+        return Err(anyhow!(
+            "--fetch-missing needs the network, so can't be used with --offline or --frozen"
+        ));
+    }
+
+    // Rendering from an IR doesn't need a workspace, so look for the configuration
+    // file alongside the IR instead.
+    let workspace = match args.ir_input() {
+        Some(_) => None,
+        None => Some(
+            MetadataCommand::new()
+                .no_deps()
+                .other_options(args.cargo_flags().args())
+                .exec()?,
+        ),
+    };
+    let config_root = match (args.ir_input(), &workspace) {
+        (Some(path), _) => path.parent().map(ToOwned::to_owned).unwrap_or_default(),
+        (None, Some(workspace)) => workspace.workspace_root.clone().into_std_path_buf(),
+        (None, None) => unreachable!("the workspace is read unless rendering from an IR"),
+    };
+    if args.workspace() {
+        let workspace = workspace
+            .as_ref()
+            .expect("--workspace can't be used with --from-ir");
+        return describe_members(args, workspace, &config_root);
+    }
+    describe(args, workspace.as_ref(), &config_root, None, None)
+}
+
+// Write an SBOM for each member of the workspace, for --workspace
+fn describe_members(args: &Args, workspace: &Metadata, config_root: &Path) -> Result<()> {
+    // Each SBOM needs its own namespace, so the host URL has to tell them apart.
+    let config = Config::load_for_workspace(args.config(), workspace, None)?;
+    let template = args.host_url_template().or(config.host_url.as_deref());
+    if template
+        .map_or(false, |template| template.contains("{name}"))
+        .not()
+    {
+        return Err(anyhow!(
+            "--workspace writes an SBOM for each member, so needs a --host-url or `host_url` setting with `{{name}}` in it"
+        ));
+    }
+    if let Some(dir) = args.output() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create the output directory {}", dir.display()))?;
+    }
+
+    // Resolve the dependencies once, for every member.
+    let metadata = resolve(args)?;
+    if let Some(dir) = args.licenses_dir() {
+        write_licenses_dir(dir, metadata.workspace_packages())?;
+    }
+    for member in workspace.workspace_packages() {
+        if config.exclude.contains(&member.name) {
+            log::info!(target: "cargo_spdx", "skipping {}, as it's excluded", member.name);
+            continue;
+        }
+        log::info!(target: "cargo_spdx", "describing {}", member.name);
+        describe(
+            &mut args.for_member(member),
+            Some(workspace),
+            config_root,
+            Some(&metadata),
+            args.output(),
+        )?;
+    }
+    Ok(())
+}
+
+// Resolve the dependencies of the workspace, with the features and platform chosen
+fn resolve(args: &Args) -> Result<Metadata> {
+    let mut options = args.cargo_flags().args();
+    if let Some(target) = args.target() {
+        options.extend(["--filter-platform".to_string(), target.to_string()]);
+    }
+    telemetry::in_span("resolve metadata", &[], || {
+        Ok(args
+            .features()
+            .forward_metadata(&mut MetadataCommand::new())
+            .other_options(options)
+            .exec()?)
+    })
+}
+
+// Produce the SBOM, or run the subcommand, for the member the arguments choose.
+//
+// `metadata` is the resolved workspace, if it was already, and `output_dir` is
+// where to write the SBOM unless --output names the file.
+fn describe(
+    args: &mut Args,
+    workspace: Option<&Metadata>,
+    config_root: &Path,
+    metadata: Option<&Metadata>,
+    output_dir: Option<&Path>,
+) -> Result<()> {
+    // The member being described, unless it's the whole workspace, whose settings
+    // override the workspace's
+    let root = match (workspace, args.package()) {
+        (Some(workspace), Some(spec)) => Some(workspace.find_member(spec)?),
+        (Some(_), None) if args.merged() => None,
+        (Some(workspace), None) => workspace.current_member(),
+        (None, _) => None,
+    };
+    let config = match workspace {
+        Some(workspace) => Config::load_for_workspace(args.config(), workspace, root)?,
+        None => Config::load(args.config(), config_root, None)?,
+    };
+    // Settings passed on the command line take precedence over the configuration.
+    args.apply_config(&config, root)?;
+    let args: &Args = args;
+    let mut suppliers = Suppliers::load(args.suppliers(), config_root)?;
+    if let Some(supplier) = args.default_supplier() {
+        suppliers
+            .set_default(supplier)
+            .context("invalid --default-supplier or `default_supplier` setting")?;
+    }
+
+    // Fail before doing any work if the output can't be written.
+    if args.bundle().is_some() && !cfg!(feature = "bundle") {
+        return Err(anyhow!(
+            "--bundle needs cargo-spdx to be built with the `bundle` feature"
+        ));
+    }
+    args.format().check_supports(args.spdx_version())?;
+    args.profile().check_supports(args.spdx_version())?;
+
+    // Vendor extensions aren't part of the SPDX specification, so leave them out
+    // when the user asks for strict conformance.
+    let extensions = args.strict().not().then(|| &config.extensions);
+
+    // Only look for the creator when an SBOM is actually being written.
+    let creation_info = || -> Result<_> {
+        let identity = Identity::configured(Some(&config), args.no_person(), args.creators())?;
+        identity.report();
+        identity.creation_info(args.explain_creators())
+    };
 
     // Invoke build subcommand if specified to run `cargo build` with added SBOMs
     if let Some(cmd) = &args.subcommand {
         match cmd {
-            cli::Command::Build { args: build_args } => {
-                build(build_args, args.host_url()?.as_ref(), args.format())?;
+            cli::Command::Build {
+                verify_downloads,
+                verify_index,
+                build_log,
+                exclude_build_deps,
+                separate_build_deps,
+                args: build_args,
+            } => {
+                let build_deps = match (exclude_build_deps, separate_build_deps) {
+                    (true, _) => BuildDeps::Exclude,
+                    (_, true) => BuildDeps::Separate,
+                    _ => BuildDeps::Include,
+                };
+                let host_url = args.host_url()?;
+                let creation_info = creation_info()?;
+                let options = SbomOptions {
+                    host_url: host_url.as_ref(),
+                    creation_info: &creation_info,
+                    format: args.format(),
+                    json_style: args.json_style(),
+                    compression: args.compression(),
+                    checksums: args.checksums(),
+                    spdx_version: args.spdx_version(),
+                    profile: args.profile(),
+                    scrub_paths: args.scrub_paths(),
+                    extensions,
+                    annotations: &config.annotations,
+                    none: &config.none,
+                    suppliers: &suppliers,
+                    scan_copyrights: args.scan_copyrights(),
+                    cpes: args.cpes(),
+                    crates_io_owners: args.crates_io_owners(),
+                    build_deps,
+                    build_tools: &config.build_tools,
+                    policy: &config.policy,
+                    sarif: args.sarif(),
+                    exclude: args.exclude(),
+                };
+                if args.cargo_flags().is_offline() && (*verify_downloads || *verify_index) {
+                    return Err(anyhow!(
+                        "--verify-downloads and --verify-index need the network, so can't be used with --offline or --frozen"
+                    ));
+                }
+                let build_args: Vec<OsString> = args
+                    .cargo_flags()
+                    .args()
+                    .into_iter()
+                    .map(OsString::from)
+                    .chain(build_args.iter().cloned())
+                    .collect();
+                build(
+                    &build_args,
+                    &options,
+                    *verify_downloads,
+                    *verify_index,
+                    args.fetch_missing(),
+                    *build_log,
+                    args.licenses_dir(),
+                )?;
+            }
+            cli::Command::Tree { target } => {
+                tree(target.as_deref(), &config.policy, args.cargo_flags())?;
+            }
+            cli::Command::Doctor
+            | cli::Command::Convert { .. }
+            | cli::Command::Amend { .. }
+            | cli::Command::Notices { .. } => {
+                unreachable!("handled before anything else")
             }
         };
     }
     // Otherwise create an SBOM for the current workspace
     else {
-        let metadata = MetadataCommand::new().exec()?;
+        // Read the other SBOMs referred to first, so a bad one fails before the slow scan.
+        let previous = args
+            .previous_sbom()
+            .map(|path| read_referenced_document(path, None, false))
+            .transpose()?;
+        let external_refs = args
+            .external_refs()
+            .iter()
+            .map(|path| read_referenced_document(path, None, false))
+            .collect::<Result<Vec<_>>>()?;
 
-        // Figure out where the SPDX file will be written, setting up a manager to ensure we only write when conditions are met.
-        let output_manager = if let Some(output) = args.output() {
-            // User specified a path, use that
-            OutputManager::new(output, args.force(), args.format())
-        } else {
-            // Determine path from metadata
-            let path = PathBuf::from(format!(
-                "{}{}",
-                &metadata.root()?.name,
-                args.format().extension()
-            ));
-            OutputManager::new(&path, args.force(), args.format())
+        // Gather everything that goes into the document, unless that was done earlier.
+        let resolved;
+        let (ir, metadata) = match args.ir_input() {
+            Some(path) => (Ir::load(path)?, None),
+            None => {
+                let metadata = match metadata {
+                    Some(metadata) => metadata,
+                    None => {
+                        resolved = resolve(args)?;
+                        &resolved
+                    }
+                };
+                let ir = telemetry::in_span("hash files", &[], || {
+                    Ir::gather(
+                        metadata,
+                        &GatherOptions {
+                            include_dev: args.exclude_dev_deps().not(),
+                            no_deps: args.no_deps(),
+                            scan_copyrights: args.scan_copyrights(),
+                            fetch_missing: args.fetch_missing(),
+                            version_from_git: args.version_from_git(),
+                            exclude: &config.exclude,
+                            package: args.package(),
+                            merged: args.merged(),
+                            cargo_flags: args.cargo_flags(),
+                        },
+                    )
+                })?;
+                (ir, Some(metadata))
+            }
         };
 
-        // Determine the files, package, and relationships for each
-        // member of the workspace
-        let mut packages = Vec::new();
-        let mut files = Vec::new();
-        let mut relationships = Vec::new();
-        for member in &metadata.workspace_members {
-            let package = &metadata[member];
-            // List files in package
-            let out = Command::new(&cargo_exec())
-                .args([
-                    "package",
-                    "--list",
-                    "--allow-dirty",
-                    "--manifest-path",
-                    package.manifest_path.as_str(),
-                ])
-                .output()?;
-            let root = package.manifest_path.parent().unwrap();
-            let mut source_files = out
-                .stdout
-                .lines()
-                .filter_map(Result::ok)
-                // `cargo package --list` includes the normalized Cargo.toml.orig
-                // but this won't be present locally (`cargo package` fails if it is)
-                // cargo package always lists Cargo.lock too, which may not be present.
-                // So just filter out any entries which can't be found locally
-                .filter_map(|path| {
-                    // Path is relative to crate root, so we need to add
-                    // the crate root in order to find it locally.
-                    let mut abs_path = Utf8PathBuf::from(root);
-                    abs_path.push(path);
-                    if abs_path.exists() {
-                        Some(abs_path)
-                    } else {
-                        None
+        // Figure out where the SPDX file will be written.
+        let path = match args.output() {
+            // User specified a path, use that
+            Some(output) => output.to_owned(),
+            // Determine path from the root package, in the configured directory if any
+            None => {
+                let name = format!("{}{}", ir.name, args.format().extension(args.compression()));
+                match (output_dir, &config.output_dir) {
+                    (Some(dir), _) => dir.join(name),
+                    (None, Some(dir)) => {
+                        let dir = config_root.join(dir);
+                        fs::create_dir_all(&dir).with_context(|| {
+                            format!("failed to create the output directory {}", dir.display())
+                        })?;
+                        dir.join(name)
                     }
-                })
-                .map(|path| -> Result<File, anyhow::Error> {
-                    File::try_from_file(
-                        &path,
-                        root,
-                        FileType::Source,
-                        Some(&package.name),
-                        Some(&package.version.to_string()),
-                    )
-                })
-                .collect::<Result<Vec<_>, _>>()?;
-            let spdx_package: Package = package.into();
-            for file in &source_files {
-                relationships.push(Relationship {
-                    comment: None,
-                    related_spdx_element: file.spdxid.clone(),
-                    relationship_type: document::RelationshipType::Contains,
-                    spdx_element_id: spdx_package.spdxid.clone(),
-                });
+                    (None, None) => PathBuf::from(name),
+                }
             }
-            packages.push(spdx_package);
-            files.append(&mut source_files);
+        };
+
+        // Fail before writing anything if outputs would replace each other.
+        let mut plan = OutputPlan::default();
+        plan.add(
+            &path,
+            "workspace SBOM".to_string(),
+            "pass a different path to --output".to_string(),
+        );
+        for algorithm in args.checksums() {
+            plan.add(
+                &output::checksum_path(&path, *algorithm),
+                format!("{} checksum of the workspace SBOM", algorithm),
+                "pass a different path to --output".to_string(),
+            );
+        }
+        if let Some(ir_path) = args.ir_output() {
+            plan.add(
+                ir_path,
+                "information gathered for --emit-ir".to_string(),
+                "pass a different path to --emit-ir".to_string(),
+            );
+        }
+        if let Some(dir) = args.licenses_dir() {
+            plan.add(
+                &licenses::index_path(dir),
+                "license file index".to_string(),
+                "pass a different directory to --licenses-dir".to_string(),
+            );
+        }
+        if let Some(bundle_path) = args.bundle() {
+            plan.add(
+                bundle_path,
+                "bundle for --bundle".to_string(),
+                "pass a different path to --bundle".to_string(),
+            );
+        }
+        if let Some(sarif_path) = args.sarif() {
+            plan.add(
+                sarif_path,
+                "policy violations for --sarif".to_string(),
+                "pass a different path to --sarif".to_string(),
+            );
         }
+        plan.check()?;
 
-        let doc = DocumentBuilder::default()
-            .document_name(output_manager.output_file_name())
-            .try_document_namespace(args.host_url()?.as_ref())?
-            .creation_info(get_creation_info()?)
-            .files(files)
-            .packages(packages)
-            .relationships(relationships)
-            .build()?;
+        if let (Some(dir), Some(metadata)) = (args.licenses_dir(), metadata) {
+            write_licenses_dir(dir, metadata.workspace_packages())?;
+        }
+        if let Some(path) = args.ir_output() {
+            ir.save(path)?;
+        }
+
+        // Set up a manager to ensure we only write when conditions are met.
+        let output_manager = OutputManager::new(
+            &path,
+            args.force(),
+            args.format(),
+            args.json_style(),
+            args.compression(),
+            args.checksums(),
+        );
+
+        let mut doc = document::builder(
+            args.host_url()?.as_ref(),
+            &output_manager.output_file_name(),
+            &creation_info()?,
+        )?
+        .spdx_version(args.spdx_version())
+        .files(ir.files)
+        .packages(ir.packages)
+        .relationships(ir.relationships)
+        .build()?;
+        if !ir.extracted_licenses.is_empty() {
+            doc.has_extracted_licensing_infos = Some(ir.extracted_licenses);
+        }
+        if let Some((previous, reference)) = previous {
+            doc.supersede(&previous, reference);
+        }
+        for (_, reference) in external_refs {
+            let id = doc.add_external_reference(reference);
+            log::info!(target: "cargo_spdx", "referring to another SBOM as {}", id);
+        }
+        for comment in args.annotations() {
+            doc.annotate(args.annotation_type(), comment);
+        }
+        // The bundle's license files are listed in the SBOM it holds.
+        #[cfg(feature = "bundle")]
+        let bundle_licenses = match (args.bundle(), metadata) {
+            (Some(_), Some(metadata)) => {
+                let licenses = bundle::find_described_licenses(&doc, metadata)?;
+                bundle::add_license_files(&mut doc, &licenses)?;
+                licenses
+            }
+            _ => Vec::new(),
+        };
+        DocumentPipeline::standard(
+            extensions,
+            &suppliers,
+            args.profile(),
+            args.scrub_paths(),
+            config_root,
+            args.cpes(),
+            args.crates_io_owners(),
+        )
+        // Added once the licenses from files are known, which may be `NONE`.
+        .add_pass_before("describes", "exclude", |doc| {
+            doc.exclude_packages(args.exclude())
+        })
+        .add_pass_before("extensions", "none", |doc| {
+            config.none.apply(doc);
+            Ok(())
+        })
+        // Added before the profile is applied, so it restricts them too.
+        .add_pass_before("profile", "annotations", |doc| {
+            config.annotations.apply(doc);
+            Ok(())
+        })
+        .run(&mut doc)?;
         output_manager.write_document(&doc)?;
+
+        #[cfg(feature = "bundle")]
+        if let Some(bundle_path) = args.bundle() {
+            bundle::write_bundle(bundle_path, args.force(), &path, &bundle_licenses)?;
+        }
+
+        if let Some(sarif_path) = args.sarif() {
+            let violations = policy::check(&doc, &path, &config.policy);
+            policy::write_sarif(sarif_path, &violations, config_root)?;
+        }
     }
     Ok(())
 }
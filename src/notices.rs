@@ -0,0 +1,245 @@
+//! Implements `cargo spdx notices` subcommand
+//!
+//! Legal teams usually want a notices file shipped alongside a release, giving
+//! the license, copyright, and attribution text of each third-party package.
+//! Everything in it comes from the SBOM, so it says no more than the SBOM does.
+
+use crate::cli::Args;
+use crate::convert::read_document;
+use crate::document::{Document, Package, RelationshipType, NOASSERTION};
+use crate::format::Format;
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Not as _;
+use std::path::{Path, PathBuf};
+
+/// The file notices are written to, unless --output is given.
+const DEFAULT_PATH: &str = "THIRD-PARTY.md";
+
+/// Writes attribution notices for the third-party packages in an SBOM
+///
+/// The notices are Markdown if written to a `.md` file, and plain text otherwise,
+/// such as for a `NOTICE` file.
+///
+/// # Arguments
+/// * `input` - Path to the SBOM
+/// * `from` - The format of the SBOM, guessed from its file extension if not given
+/// * `untrusted` - Whether to parse the SBOM defensively
+/// * `args` - The output settings
+pub fn notices(input: &Path, from: Option<Format>, untrusted: bool, args: &Args) -> Result<()> {
+    let doc = read_document(input, from, untrusted)?;
+
+    let path = args
+        .output()
+        .map(ToOwned::to_owned)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_PATH));
+    if args.force().not() && path.exists() {
+        return Err(anyhow!("notices already exist: {}", path.display()));
+    }
+
+    log::info!(target: "cargo_spdx", "writing notices to {}", path.display());
+
+    let file = File::create(&path)
+        .with_context(|| format!("failed to create notices {}", path.display()))?;
+    let markdown = path
+        .extension()
+        .map_or(false, |extension| extension == "md");
+    write_notices(BufWriter::new(file), &doc, markdown)
+}
+
+/// The attribution details of a package.
+#[derive(Debug)]
+struct Notice<'d> {
+    package: &'d Package,
+    license: Option<&'d str>,
+    copyright: Option<&'d str>,
+}
+
+impl<'d> Notice<'d> {
+    // Gather the details of a package worth showing
+    fn new(package: &'d Package) -> Self {
+        let known = |field: &'d Option<String>| {
+            field
+                .as_deref()
+                .filter(|value| *value != NOASSERTION && *value != "NONE")
+        };
+        Notice {
+            package,
+            license: known(&package.license_declared).or_else(|| known(&package.license_concluded)),
+            copyright: known(&package.copyright_text),
+        }
+    }
+
+    // The heading of the package's notice
+    fn title(&self) -> String {
+        match &self.package.version_info {
+            Some(version) => format!("{} {}", self.package.name, version),
+            None => self.package.name.clone(),
+        }
+    }
+
+    // The line giving the package's license
+    fn license_line(&self) -> String {
+        format!("License: {}", self.license.unwrap_or("not known"))
+    }
+
+    // The copyright and attribution texts of the package, as given
+    fn texts(&self) -> impl Iterator<Item = &'d str> {
+        self.copyright.into_iter().chain(
+            self.package
+                .attribution_texts
+                .iter()
+                .flatten()
+                .map(String::as_str),
+        )
+    }
+}
+
+/// Find the notices for each package the document doesn't describe.
+///
+/// The packages a document describes are what it's the SBOM of, so everything
+/// else is third-party.
+fn third_party_notices(doc: &Document) -> Vec<Notice<'_>> {
+    let described: HashSet<&str> = doc
+        .relationships
+        .iter()
+        .flatten()
+        .filter_map(|relationship| match relationship.relationship_type {
+            RelationshipType::Describes => Some(relationship.related_spdx_element.as_str()),
+            RelationshipType::DescribedBy => Some(relationship.spdx_element_id.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    let mut notices: Vec<Notice<'_>> = doc
+        .packages
+        .iter()
+        .flatten()
+        .filter(|package| described.contains(package.spdxid.as_str()).not())
+        .map(Notice::new)
+        .collect();
+    notices.sort_by(|a, b| {
+        (&a.package.name, &a.package.version_info).cmp(&(&b.package.name, &b.package.version_info))
+    });
+
+    let unlicensed = notices
+        .iter()
+        .filter(|notice| notice.license.is_none())
+        .count();
+    if unlicensed > 0 {
+        log::warn!(
+            target: "cargo_spdx",
+            "{} package(s) have no license in the SBOM, so their notices are incomplete",
+            unlicensed
+        );
+    }
+
+    notices
+}
+
+/// Write the notices out to the provided writer.
+fn write_notices<W: Write>(mut w: W, doc: &Document, markdown: bool) -> Result<()> {
+    let notices = third_party_notices(doc);
+    let intro = format!(
+        "{} includes the following third-party packages.",
+        doc.document_name
+    );
+
+    if markdown {
+        writeln!(w, "# Third-party notices")?;
+        writeln!(w)?;
+        writeln!(w, "{}", intro)?;
+        for notice in &notices {
+            writeln!(w)?;
+            writeln!(w, "## {}", notice.title())?;
+            writeln!(w)?;
+            writeln!(w, "{}", notice.license_line())?;
+            for text in notice.texts() {
+                writeln!(w)?;
+                // Quoting keeps the texts from being read as Markdown.
+                for line in text.lines() {
+                    writeln!(w, "> {}", line)?;
+                }
+            }
+        }
+    } else {
+        writeln!(w, "THIRD-PARTY NOTICES")?;
+        writeln!(w)?;
+        writeln!(w, "{}", intro)?;
+        for notice in &notices {
+            writeln!(w)?;
+            writeln!(w, "{}", "-".repeat(72))?;
+            writeln!(w, "{}", notice.title())?;
+            writeln!(w, "{}", notice.license_line())?;
+            for text in notice.texts() {
+                writeln!(w)?;
+                writeln!(w, "{}", text)?;
+            }
+        }
+    }
+
+    w.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_notices;
+    use crate::document::{
+        CreationInfoBuilder, Creator, DocumentBuilder, Package, Relationship, RelationshipType,
+    };
+
+    #[test]
+    fn test_write_notices() {
+        let root = Package::build_tool("foo");
+        let mut serde = Package::build_tool("serde");
+        serde.version_info = Some("1.0.0".to_string());
+        serde.license_declared = Some("MIT OR Apache-2.0".to_string());
+        serde.copyright_text = Some("Copyright (c) David Tolnay".to_string());
+        serde.attribution_texts = Some(vec!["Includes code\nfrom elsewhere".to_string()]);
+        let unlicensed = Package::build_tool("unlicensed");
+        let doc = DocumentBuilder::default()
+            .document_name("foo.spdx")
+            .try_document_namespace("https://example.com/foo")
+            .unwrap()
+            .creation_info(
+                CreationInfoBuilder::default()
+                    .creators(vec![Creator::tool("cargo-spdx 0.1.0")])
+                    .build()
+                    .unwrap(),
+            )
+            .relationships(vec![Relationship {
+                comment: None,
+                related_spdx_element: root.spdxid.clone(),
+                relationship_type: RelationshipType::Describes,
+                spdx_element_id: "SPDXRef-DOCUMENT".to_string(),
+            }])
+            .packages(vec![unlicensed, root, serde])
+            .build()
+            .unwrap();
+
+        let mut out = Vec::new();
+        write_notices(&mut out, &doc, true).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "# Third-party notices\n\
+            \n\
+            foo.spdx includes the following third-party packages.\n\
+            \n\
+            ## serde 1.0.0\n\
+            \n\
+            License: MIT OR Apache-2.0\n\
+            \n\
+            > Copyright (c) David Tolnay\n\
+            \n\
+            > Includes code\n\
+            > from elsewhere\n\
+            \n\
+            ## unlicensed\n\
+            \n\
+            License: not known\n"
+        );
+    }
+}
@@ -1,13 +1,18 @@
 //! Handle outputting the document to the user.
 
 use crate::document::Document;
-use crate::{format, Format};
-use anyhow::{anyhow, Result};
-use std::ffi::OsStr;
-use std::fs::File;
-use std::io::{BufWriter, Write};
+use crate::format::json::JsonStyle;
+use crate::telemetry;
+use crate::Format;
+use anyhow::{anyhow, Error, Result};
+use sha2::{Digest, Sha256, Sha512};
+use std::ffi::{OsStr, OsString};
+use std::fmt::{self, Display, Formatter};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
 use std::ops::Not as _;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
 
 /// Handles writing to the correct path.
 #[derive(Debug)]
@@ -18,13 +23,40 @@ pub struct OutputManager {
     format: Format,
     /// Whether output is being forced.
     force: bool,
+    /// How to lay out JSON output.
+    json_style: JsonStyle,
+    /// How to compress the output.
+    compression: Compression,
+    /// The checksums to write alongside the output.
+    checksums: Vec<ChecksumAlgorithm>,
 }
 
 impl OutputManager {
     /// Get a new output manager based on CLI args and package info.
-    pub fn new(path: &Path, force: bool, format: Format) -> Self {
+    ///
+    /// If no compression is asked for, the output is still compressed when the
+    /// path ends in `.gz` or `.zst`.
+    pub fn new(
+        path: &Path,
+        force: bool,
+        format: Format,
+        json_style: JsonStyle,
+        compression: Compression,
+        checksums: &[ChecksumAlgorithm],
+    ) -> Self {
         let to = path.to_owned();
-        OutputManager { to, format, force }
+        let compression = match compression {
+            Compression::None => Compression::from_path(&to),
+            compression => compression,
+        };
+        OutputManager {
+            to,
+            format,
+            force,
+            json_style,
+            compression,
+            checksums: checksums.to_vec(),
+        }
     }
 
     /// Get the name of the output file.
@@ -43,6 +75,17 @@ impl OutputManager {
     /// Write the document to the output file in the specified format.
     #[inline]
     pub fn write_document(&self, doc: &Document) -> Result<()> {
+        let path = self.to.display().to_string();
+        let format = self.format.to_string();
+        let attributes = [
+            ("spdx.path", path.as_str()),
+            ("spdx.format", format.as_str()),
+        ];
+        telemetry::in_span("write", &attributes, || self.write_document_untraced(doc))
+    }
+
+    // Write the document, checksumming it if asked to
+    fn write_document_untraced(&self, doc: &Document) -> Result<()> {
         // Check the output file has a file name and isn't a directory.
         if self.to.file_name().is_none() {
             return Err(anyhow!("missing output file name"));
@@ -52,22 +95,60 @@ impl OutputManager {
             return Err(anyhow!("output can't be a directory"));
         }
 
-        // Get the writer to the output file.
-        let mut writer = self.get_writer()?;
+        // Fail before creating the output file if the format can't represent the document.
+        self.format.check_supports(doc.spdx_version)?;
+
+        // Write the document out in the requested format, compressing it on the way.
+        let w = self.get_writer()?;
+        self.write_compressed(w, doc)?;
+
+        for algorithm in &self.checksums {
+            self.write_checksum(*algorithm)?;
+        }
+        Ok(())
+    }
 
-        // Write the document out in the requested format.
-        match self.format {
-            Format::KeyValue => Ok(format::key_value::write(&mut writer, doc)?),
-            Format::Json => Ok(serde_json::to_writer_pretty(writer, doc)?),
-            Format::Yaml => Ok(serde_yaml::to_writer(writer, doc)?),
-            Format::Rdf => Err(anyhow!("{} format not yet implemented", self.format)),
+    /// Write the document to the writer, compressed if asked to.
+    fn write_compressed(&self, w: Box<dyn Write>, doc: &Document) -> Result<()> {
+        match self.compression {
+            Compression::None => doc.to_writer(w, self.format, self.json_style),
+            #[cfg(feature = "compress")]
+            Compression::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(w, flate2::Compression::default());
+                doc.to_writer(&mut encoder, self.format, self.json_style)?;
+                encoder.finish()?.flush()?;
+                Ok(())
+            }
+            #[cfg(feature = "compress")]
+            Compression::Zstd => {
+                let mut encoder = zstd::Encoder::new(w, 0)?;
+                doc.to_writer(&mut encoder, self.format, self.json_style)?;
+                encoder.finish()?.flush()?;
+                Ok(())
+            }
+            #[cfg(not(feature = "compress"))]
+            compression => Err(anyhow!(
+                "{} output needs cargo-spdx to be built with the `compress` feature",
+                compression
+            )),
         }
     }
 
+    /// Write a checksum of the output file alongside it, e.g. `foo.spdx.json.sha256`.
+    ///
+    /// The checksum file is laid out like the output of `sha256sum`, so it can be
+    /// checked with `sha256sum -c`.
+    fn write_checksum(&self, algorithm: ChecksumAlgorithm) -> Result<()> {
+        let digest = file_digest(&self.to, algorithm)?;
+        let path = checksum_path(&self.to, algorithm);
+        fs::write(path, format!("{}  {}\n", digest, self.output_file_name()))?;
+        Ok(())
+    }
+
     /// Get a writer to the output file.
     ///
-    /// Returns an error if the output file already exists and the user hasn't set output
-    /// to be forced.
+    /// Returns an error if the output file, or any checksum file written alongside it,
+    /// already exists and the user hasn't set output to be forced.
     fn get_writer(&self) -> Result<Box<dyn Write>> {
         // A little truth table making clear this conditional is the right one.
         //
@@ -81,6 +162,342 @@ impl OutputManager {
             return Err(anyhow!("output file already exists: {}", self.to.display()));
         }
 
+        for algorithm in &self.checksums {
+            let path = checksum_path(&self.to, *algorithm);
+            if self.force.not() && path.exists() {
+                return Err(anyhow!("checksum file already exists: {}", path.display()));
+            }
+        }
+
         Ok(Box::new(BufWriter::new(File::create(&self.to)?)))
     }
 }
+
+/// How the output file is compressed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    /// Written as is.
+    None,
+    /// Gzip, for a `.gz` file.
+    Gzip,
+    /// Zstandard, for a `.zst` file.
+    Zstd,
+}
+
+impl Compression {
+    /// Get the file extension added for the compression, if any.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Compression::None => "",
+            Compression::Gzip => ".gz",
+            Compression::Zstd => ".zst",
+        }
+    }
+
+    /// Get the compression implied by the extension of a path.
+    pub fn from_path(path: &Path) -> Compression {
+        match path.extension().and_then(OsStr::to_str) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::None
+    }
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Compression::None => write!(f, "uncompressed"),
+            Compression::Gzip => write!(f, "gzip"),
+            Compression::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Compression::None),
+            "gzip" | "gz" => Ok(Compression::Gzip),
+            "zstd" | "zst" => Ok(Compression::Zstd),
+            s => Err(anyhow!("unknown compression '{}'", s)),
+        }
+    }
+}
+
+/// An algorithm to write a checksum of the output with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    /// SHA-256, written to a `.sha256` file.
+    Sha256,
+    /// SHA-512, written to a `.sha512` file.
+    Sha512,
+}
+
+impl ChecksumAlgorithm {
+    /// Get the file extension of checksum files for the algorithm.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => ".sha256",
+            ChecksumAlgorithm::Sha512 => ".sha512",
+        }
+    }
+}
+
+impl Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ChecksumAlgorithm::Sha256 => write!(f, "sha256"),
+            ChecksumAlgorithm::Sha512 => write!(f, "sha512"),
+        }
+    }
+}
+
+impl FromStr for ChecksumAlgorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(ChecksumAlgorithm::Sha256),
+            "sha512" => Ok(ChecksumAlgorithm::Sha512),
+            s => Err(anyhow!("unknown checksum algorithm '{}'", s)),
+        }
+    }
+}
+
+/// Get the hex encoded checksum of a file.
+pub fn file_digest(path: &Path, algorithm: ChecksumAlgorithm) -> Result<String> {
+    let mut file = File::open(path)?;
+    Ok(match algorithm {
+        ChecksumAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            io::copy(&mut file, &mut hasher)?;
+            hex::encode(hasher.finalize())
+        }
+        ChecksumAlgorithm::Sha512 => {
+            let mut hasher = Sha512::new();
+            io::copy(&mut file, &mut hasher)?;
+            hex::encode(hasher.finalize())
+        }
+    })
+}
+
+/// Get the path of the checksum file written alongside an output file.
+pub fn checksum_path(path: &Path, algorithm: ChecksumAlgorithm) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(algorithm.extension());
+    PathBuf::from(path)
+}
+
+/// The files a run will write, checked for clashes before any are written.
+///
+/// A workspace with two binaries of the same name, or options pointing at the
+/// same file, would otherwise have one output silently replace another. The
+/// namespaces of the documents are checked too, as SPDX requires each document
+/// to have its own.
+#[derive(Debug, Default)]
+pub struct OutputPlan {
+    /// Each file to be written, with what it is and how to move it elsewhere.
+    outputs: Vec<PlannedOutput>,
+    /// The namespace of each document to be written, with what it's for and
+    /// how to give it another.
+    namespaces: Vec<PlannedOutput>,
+}
+
+/// A file a run will write, or the namespace of a document it will write.
+#[derive(Debug)]
+struct PlannedOutput {
+    /// The path, made absolute so different spellings of it compare equal, or
+    /// the namespace.
+    key: String,
+    /// What the file holds.
+    description: String,
+    /// How the user can give the file a different path.
+    suggestion: String,
+}
+
+impl OutputPlan {
+    /// Plan to write a file.
+    ///
+    /// # Arguments
+    /// * `path` - Where the file will be written
+    /// * `description` - What the file holds, e.g. `SBOM for target/debug/foo`
+    /// * `suggestion` - How to write the file somewhere else instead
+    pub fn add(&mut self, path: &Path, description: String, suggestion: String) {
+        self.outputs.push(PlannedOutput {
+            key: absolute(path).display().to_string(),
+            description,
+            suggestion,
+        });
+    }
+
+    /// Plan to write a document with the given namespace.
+    ///
+    /// # Arguments
+    /// * `namespace` - The namespace of the document
+    /// * `description` - What the document is, e.g. `SBOM for target/debug/foo`
+    /// * `suggestion` - How to give the document a different namespace
+    pub fn add_namespace(&mut self, namespace: &str, description: String, suggestion: String) {
+        self.namespaces.push(PlannedOutput {
+            key: namespace.to_string(),
+            description,
+            suggestion,
+        });
+    }
+
+    /// Check no two planned files have the same path, and no two documents
+    /// the same namespace.
+    pub fn check(&self) -> Result<()> {
+        check_unique(
+            &self.outputs,
+            "output path(s) would be written more than once",
+        )?;
+        check_unique(
+            &self.namespaces,
+            "document namespace(s) would be used more than once",
+        )
+    }
+}
+
+// Check no two planned outputs have the same path or namespace, describing any which do
+fn check_unique(planned: &[PlannedOutput], problem: &str) -> Result<()> {
+    let mut conflicts: Vec<(&str, Vec<&PlannedOutput>)> = Vec::new();
+    for output in planned {
+        match conflicts.iter_mut().find(|(key, _)| *key == output.key) {
+            Some((_, outputs)) => outputs.push(output),
+            None => conflicts.push((&output.key, vec![output])),
+        }
+    }
+    conflicts.retain(|(_, outputs)| outputs.len() > 1);
+
+    if conflicts.is_empty() {
+        return Ok(());
+    }
+
+    let mut message = format!("{} {}:", conflicts.len(), problem);
+    let mut suggestions = Vec::new();
+    for (key, outputs) in &conflicts {
+        message.push_str(&format!("\n  {}", key));
+        for output in outputs {
+            message.push_str(&format!("\n    - {}", output.description));
+            if suggestions.contains(&&output.suggestion).not() {
+                suggestions.push(&output.suggestion);
+            }
+        }
+    }
+    message.push_str("\nTo fix this:");
+    for suggestion in suggestions {
+        message.push_str(&format!("\n  - {}", suggestion));
+    }
+    Err(anyhow!(message))
+}
+
+/// Make a path absolute and remove any `.` and `..` components, without
+/// touching the filesystem, as the file may not exist yet.
+fn absolute(path: &Path) -> PathBuf {
+    let path = match std::env::current_dir() {
+        Ok(dir) => dir.join(path),
+        Err(_) => path.to_owned(),
+    };
+
+    let mut absolute = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            component => absolute.push(component),
+        }
+    }
+    absolute
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checksum_path, ChecksumAlgorithm, Compression, OutputPlan};
+    use std::path::Path;
+
+    #[test]
+    fn test_checksum_path() {
+        assert_eq!(
+            checksum_path(Path::new("out/foo.spdx.json"), ChecksumAlgorithm::Sha512),
+            Path::new("out/foo.spdx.json.sha512")
+        );
+    }
+
+    #[test]
+    fn test_compression_from_path() {
+        assert_eq!(
+            Compression::from_path(Path::new("foo.spdx.json.gz")),
+            Compression::Gzip
+        );
+        assert_eq!(
+            Compression::from_path(Path::new("foo.spdx.zst")),
+            Compression::Zstd
+        );
+        assert_eq!(
+            Compression::from_path(Path::new("foo.spdx")),
+            Compression::None
+        );
+    }
+
+    #[test]
+    fn test_output_plan_conflicts() {
+        let mut plan = OutputPlan::default();
+        plan.add(
+            Path::new("foo.spdx"),
+            "SBOM".to_string(),
+            "use --output".to_string(),
+        );
+        plan.add(
+            Path::new("ir.json"),
+            "IR".to_string(),
+            "use --emit-ir".to_string(),
+        );
+        assert!(plan.check().is_ok());
+
+        plan.add(
+            Path::new("./sub/../foo.spdx"),
+            "IR".to_string(),
+            "use --emit-ir".to_string(),
+        );
+        let message = plan.check().unwrap_err().to_string();
+        assert!(message.starts_with("1 output path(s) would be written more than once"));
+        assert!(message.contains("    - SBOM\n    - IR\n"));
+        assert!(message.ends_with("  - use --output\n  - use --emit-ir"));
+    }
+
+    #[test]
+    fn test_output_plan_namespaces() {
+        let mut plan = OutputPlan::default();
+        plan.add_namespace(
+            "https://x/m/m.spdx",
+            "SBOM for m".to_string(),
+            "use {binary}".to_string(),
+        );
+        plan.add_namespace(
+            "https://x/m/libm.so.spdx",
+            "SBOM for libm.so".to_string(),
+            "use {binary}".to_string(),
+        );
+        assert!(plan.check().is_ok());
+
+        plan.add_namespace(
+            "https://x/m/m.spdx",
+            "SBOM for another m".to_string(),
+            "use {binary}".to_string(),
+        );
+        let message = plan.check().unwrap_err().to_string();
+        assert!(message.starts_with("1 document namespace(s) would be used more than once"));
+        assert!(message.contains("  https://x/m/m.spdx\n    - SBOM for m\n"));
+    }
+}
@@ -0,0 +1,165 @@
+//! Fills in the suppliers and originators of crates.io packages from their owners.
+//!
+//! Cargo doesn't record who supplies a crate, but crates.io knows who may
+//! publish it: its owners, which are people or GitHub teams. A team's GitHub
+//! organization is taken as the supplier, as it publishes on the organization's
+//! behalf, and otherwise the first person listed. The first person listed is
+//! also taken as the originator. Packages given a supplier or originator by
+//! other means, such as `suppliers.toml`, keep it.
+
+use crate::document::{Document, NOASSERTION};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::ops::Not as _;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Where the crates.io API is served from.
+const API_URL: &str = "https://crates.io/api/v1";
+
+/// Where crates.io packages are downloaded from, which only they have.
+const DOWNLOAD_URL: &str = "https://crates.io/api/v1/crates/";
+
+/// The minimum time between requests to crates.io, per the crawler policy.
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// An owner of a crate on crates.io.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct Owner {
+    /// `user` or `team`.
+    kind: String,
+    /// The GitHub login of a user, or `github:ORG:TEAM` for a team.
+    login: String,
+    /// The display name of a user, or the name of a team.
+    name: Option<String>,
+}
+
+/// The owners of a crate, as the crates.io API lists them.
+#[derive(Debug, Deserialize)]
+struct OwnersResponse {
+    /// Each owner, people and teams alike.
+    users: Vec<Owner>,
+}
+
+/// Fill in the supplier and originator of each crates.io package from its owners.
+///
+/// Crates are looked up one at a time, at most one a second, so this is slow
+/// for large dependency graphs.
+pub fn apply(doc: &mut Document) -> Result<()> {
+    let agent = ureq::AgentBuilder::new()
+        .user_agent(&format!(
+            "cargo-spdx/{} (+{})",
+            env!("CARGO_PKG_VERSION"),
+            env!("CARGO_PKG_REPOSITORY")
+        ))
+        .timeout(Duration::from_secs(30))
+        .build();
+    let mut last_request: Option<Instant> = None;
+    // Several versions of a crate have the same owners.
+    let mut owners: HashMap<String, Vec<Owner>> = HashMap::new();
+
+    for package in doc.packages.iter_mut().flatten() {
+        let unset = |field: &Option<String>| field.as_deref().map_or(true, |v| v == NOASSERTION);
+        if package.download_location.starts_with(DOWNLOAD_URL).not()
+            || (unset(&package.supplier) || unset(&package.originator)).not()
+        {
+            continue;
+        }
+
+        if owners.contains_key(&package.name).not() {
+            if let Some(last_request) = last_request {
+                let elapsed = last_request.elapsed();
+                if elapsed < REQUEST_INTERVAL {
+                    thread::sleep(REQUEST_INTERVAL - elapsed);
+                }
+            }
+            last_request = Some(Instant::now());
+            let found = fetch_owners(&agent, &package.name)?;
+            owners.insert(package.name.clone(), found);
+        }
+
+        let (supplier, originator) = parties(&owners[&package.name]);
+        if unset(&package.supplier) {
+            if let Some(supplier) = supplier {
+                package.supplier = Some(supplier);
+            }
+        }
+        if unset(&package.originator) {
+            if let Some(originator) = originator {
+                package.originator = Some(originator);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Ask crates.io who owns a crate
+fn fetch_owners(agent: &ureq::Agent, name: &str) -> Result<Vec<Owner>> {
+    let url = format!("{}/crates/{}/owners", API_URL, name);
+    log::debug!(target: "cargo_spdx", "fetching {}", url);
+    match agent.get(&url).call() {
+        Ok(response) => {
+            let response: OwnersResponse = serde_json::from_str(&response.into_string()?)
+                .with_context(|| format!("invalid owners list {}", url))?;
+            Ok(response.users)
+        }
+        // The crate isn't published, so has no owners.
+        Err(ureq::Error::Status(404, _)) => Ok(Vec::new()),
+        Err(err) => Err(anyhow!(err).context(format!("failed to fetch {}", url))),
+    }
+}
+
+// Pick the supplier and originator of a crate from its owners
+fn parties(owners: &[Owner]) -> (Option<String>, Option<String>) {
+    let person = owners
+        .iter()
+        .find(|owner| owner.kind == "user")
+        .map(|user| {
+            format!(
+                "Person: {}",
+                user.name
+                    .as_deref()
+                    .filter(|name| name.trim().is_empty().not())
+                    .unwrap_or(&user.login)
+            )
+        });
+    let organization = owners
+        .iter()
+        .filter(|owner| owner.kind == "team")
+        .find_map(|team| team.login.split(':').nth(1))
+        .map(|org| format!("Organization: {}", org));
+    (organization.or_else(|| person.clone()), person)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parties, Owner};
+
+    #[test]
+    fn test_parties() {
+        let owner = |kind: &str, login: &str, name: Option<&str>| Owner {
+            kind: kind.to_string(),
+            login: login.to_string(),
+            name: name.map(ToString::to_string),
+        };
+        let jane = owner("user", "jdoe", Some("Jane Doe"));
+        let team = owner("team", "github:rust-lang:libs", Some("libs"));
+
+        assert_eq!(
+            parties(&[jane, team]),
+            (
+                Some("Organization: rust-lang".to_string()),
+                Some("Person: Jane Doe".to_string())
+            )
+        );
+        assert_eq!(
+            parties(&[owner("user", "jdoe", None)]),
+            (
+                Some("Person: jdoe".to_string()),
+                Some("Person: jdoe".to_string())
+            )
+        );
+        assert_eq!(parties(&[]), (None, None));
+    }
+}
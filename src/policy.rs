@@ -0,0 +1,459 @@
+//! Checks documents against the license policy, reporting violations as SARIF.
+//!
+//! SARIF is what code scanning tools such as GitHub's take as input, so the
+//! problems found in an SBOM can be shown alongside the ones found in code.
+
+use crate::config::PolicyConfig;
+use crate::document::{Document, File, Package, NOASSERTION};
+use crate::format::json::{self, JsonStyle};
+use crate::license::License;
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::ops::Not as _;
+use std::path::{Path, PathBuf};
+
+/// The fields which can be required not to be `NOASSERTION` by the policy.
+pub const REQUIRABLE_FIELDS: &[&str] = &[
+    "downloadLocation",
+    "supplier",
+    "originator",
+    "licenseConcluded",
+    "licenseDeclared",
+    "copyrightText",
+];
+
+/// Check every field the policy requires is one which can be checked.
+pub fn validate_required_fields(policy: &PolicyConfig) -> Result<()> {
+    for field in &policy.require {
+        if REQUIRABLE_FIELDS.contains(&field.as_str()).not() {
+            return Err(anyhow!(
+                "unknown required field '{}' in [policy], expected one of: {}",
+                field,
+                REQUIRABLE_FIELDS.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The ways a document can violate the policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// A package has no license.
+    MissingLicense,
+    /// A package or file has a license the policy doesn't permit.
+    DeniedLicense,
+    /// A package or file has no value for a field the policy requires.
+    NoAssertion,
+}
+
+impl Rule {
+    /// Every rule, in the order they're listed in SARIF output.
+    const ALL: [Rule; 3] = [Rule::MissingLicense, Rule::DeniedLicense, Rule::NoAssertion];
+
+    /// The ID of the rule in SARIF output.
+    fn id(self) -> &'static str {
+        match self {
+            Rule::MissingLicense => "missing-license",
+            Rule::DeniedLicense => "denied-license",
+            Rule::NoAssertion => "noassertion",
+        }
+    }
+
+    /// A one-line description of the rule.
+    fn description(self) -> &'static str {
+        match self {
+            Rule::MissingLicense => "Package has neither a declared nor a concluded license",
+            Rule::DeniedLicense => "License isn't permitted by the license policy",
+            Rule::NoAssertion => "Field required by the license policy is NOASSERTION",
+        }
+    }
+
+    /// How serious a violation of the rule is.
+    fn level(self) -> &'static str {
+        match self {
+            Rule::MissingLicense | Rule::NoAssertion => "warning",
+            Rule::DeniedLicense => "error",
+        }
+    }
+}
+
+/// A violation of the policy by an element of a document.
+#[derive(Debug, Clone)]
+pub struct Violation {
+    /// The rule violated.
+    pub rule: Rule,
+    /// The SBOM the element is in.
+    pub sbom: PathBuf,
+    /// The SPDX ID of the offending element.
+    pub spdxid: String,
+    /// What kind of element it is, `package` or `file`.
+    pub kind: &'static str,
+    /// What's wrong, for people to read.
+    pub message: String,
+}
+
+/// Check a document against the policy.
+///
+/// # Arguments
+/// * `doc` - The document to check
+/// * `sbom` - Path the document is written to, which violations are reported against
+/// * `policy` - The license policy
+pub fn check(doc: &Document, sbom: &Path, policy: &PolicyConfig) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut violation = |rule, spdxid: &str, kind, message| {
+        violations.push(Violation {
+            rule,
+            sbom: sbom.to_owned(),
+            spdxid: spdxid.to_owned(),
+            kind,
+            message,
+        })
+    };
+
+    for package in doc.packages.iter().flatten() {
+        let name = format!(
+            "{} {}",
+            package.name,
+            package.version_info.as_deref().unwrap_or_default()
+        );
+        let license = [&package.license_declared, &package.license_concluded]
+            .into_iter()
+            .flatten()
+            .find(|license| is_assertion(license));
+        match license {
+            None => violation(
+                Rule::MissingLicense,
+                &package.spdxid,
+                "package",
+                format!("{} has no license", name.trim()),
+            ),
+            Some(license) if permits(policy, license).not() => violation(
+                Rule::DeniedLicense,
+                &package.spdxid,
+                "package",
+                format!(
+                    "{} is licensed under {}, which the policy doesn't permit",
+                    name.trim(),
+                    license
+                ),
+            ),
+            Some(_) => {}
+        }
+
+        for field in &policy.require {
+            if package_field(package, field).map_or(true, |value| is_assertion(value).not()) {
+                violation(
+                    Rule::NoAssertion,
+                    &package.spdxid,
+                    "package",
+                    format!("{} has no {}", name.trim(), field),
+                );
+            }
+        }
+    }
+
+    for file in doc.files.iter().flatten() {
+        if let Some(license) = file
+            .license_concluded
+            .as_deref()
+            .filter(|l| is_assertion(l))
+        {
+            if permits(policy, license).not() {
+                violation(
+                    Rule::DeniedLicense,
+                    &file.spdxid,
+                    "file",
+                    format!(
+                        "{} is licensed under {}, which the policy doesn't permit",
+                        file.file_name, license
+                    ),
+                );
+            }
+        }
+
+        for field in &policy.require {
+            // Files don't have the package-only fields, so they can't lack them
+            let value = match file_field(file, field) {
+                Some(value) => value,
+                None => continue,
+            };
+            if value.map_or(true, |value| is_assertion(value).not()) {
+                violation(
+                    Rule::NoAssertion,
+                    &file.spdxid,
+                    "file",
+                    format!("{} has no {}", file.file_name, field),
+                );
+            }
+        }
+    }
+
+    violations
+}
+
+/// Check a license expression is permitted by the policy.
+///
+/// Expressions which can't be parsed can't be checked, so are let through.
+fn permits(policy: &PolicyConfig, license: &str) -> bool {
+    match License::parse(license) {
+        Ok(license) => policy.permits(&license),
+        Err(err) => {
+            log::debug!(target: "cargo_spdx", "can't check license '{}': {}", license, err);
+            true
+        }
+    }
+}
+
+/// Check a field has a value, rather than `NOASSERTION` or `NONE`.
+fn is_assertion(value: &str) -> bool {
+    value != NOASSERTION && value != "NONE"
+}
+
+/// Get a field of a package by its SPDX JSON name.
+fn package_field<'p>(package: &'p Package, field: &str) -> Option<&'p str> {
+    match field {
+        "downloadLocation" => Some(&package.download_location),
+        "supplier" => package.supplier.as_deref(),
+        "originator" => package.originator.as_deref(),
+        "licenseConcluded" => package.license_concluded.as_deref(),
+        "licenseDeclared" => package.license_declared.as_deref(),
+        "copyrightText" => package.copyright_text.as_deref(),
+        _ => None,
+    }
+}
+
+/// Get a field of a file by its SPDX JSON name, if files have that field.
+fn file_field<'f>(file: &'f File, field: &str) -> Option<Option<&'f str>> {
+    match field {
+        "licenseConcluded" => Some(file.license_concluded.as_deref()),
+        "copyrightText" => Some(file.copyright_text.as_deref()),
+        _ => None,
+    }
+}
+
+/// Write the violations out as a SARIF log.
+///
+/// # Arguments
+/// * `path` - Path to write the SARIF log to
+/// * `violations` - The violations found in every SBOM written
+/// * `workspace_root` - Root of the workspace, which the SBOM paths are made relative to
+pub fn write_sarif(path: &Path, violations: &[Violation], workspace_root: &Path) -> Result<()> {
+    log::info!(target: "cargo_spdx", "writing {} policy violation(s) to {}", violations.len(), path.display());
+    if violations.is_empty().not() {
+        log::warn!(
+            target: "cargo_spdx",
+            "{} policy violation(s) found, see {}",
+            violations.len(),
+            path.display()
+        );
+    }
+
+    let log = SarifLog {
+        schema: "https://json.schemastore.org/sarif-2.1.0.json",
+        version: "2.1.0",
+        runs: vec![Run {
+            tool: Tool {
+                driver: Driver {
+                    name: env!("CARGO_PKG_NAME"),
+                    version: env!("CARGO_PKG_VERSION"),
+                    information_uri: env!("CARGO_PKG_REPOSITORY"),
+                    rules: Rule::ALL
+                        .iter()
+                        .map(|rule| RuleDescriptor {
+                            id: rule.id(),
+                            short_description: Message {
+                                text: rule.description().to_string(),
+                            },
+                            default_configuration: RuleConfiguration {
+                                level: rule.level(),
+                            },
+                        })
+                        .collect(),
+                },
+            },
+            results: violations
+                .iter()
+                .map(|violation| SarifResult {
+                    rule_id: violation.rule.id(),
+                    level: violation.rule.level(),
+                    message: Message {
+                        text: violation.message.clone(),
+                    },
+                    locations: vec![Location {
+                        physical_location: PhysicalLocation {
+                            artifact_location: ArtifactLocation {
+                                uri: uri(&violation.sbom, workspace_root),
+                            },
+                        },
+                        logical_locations: vec![LogicalLocation {
+                            name: violation.spdxid.clone(),
+                            kind: violation.kind,
+                        }],
+                    }],
+                })
+                .collect(),
+        }],
+    };
+
+    let mut contents = Vec::new();
+    json::write(&mut contents, &log, JsonStyle::Pretty)?;
+    fs::write(path, contents)
+        .with_context(|| format!("failed to write SARIF log to {}", path.display()))
+}
+
+/// Get the URI of an SBOM, relative to the workspace root where possible.
+fn uri(sbom: &Path, workspace_root: &Path) -> String {
+    let absolute = match std::env::current_dir() {
+        Ok(dir) => dir.join(sbom),
+        Err(_) => sbom.to_owned(),
+    };
+    let path = absolute.strip_prefix(workspace_root).unwrap_or(sbom);
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A SARIF log, holding the results of each run of an analysis tool.
+#[derive(Debug, Serialize)]
+struct SarifLog {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+/// A single run of cargo-spdx.
+#[derive(Debug, Serialize)]
+struct Run {
+    tool: Tool,
+    results: Vec<SarifResult>,
+}
+
+/// The tool which ran.
+#[derive(Debug, Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+/// The tool which ran, with the rules it checks.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    information_uri: &'static str,
+    rules: Vec<RuleDescriptor>,
+}
+
+/// A rule the tool checks.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RuleDescriptor {
+    id: &'static str,
+    short_description: Message,
+    default_configuration: RuleConfiguration,
+}
+
+/// How serious violations of a rule are.
+#[derive(Debug, Serialize)]
+struct RuleConfiguration {
+    level: &'static str,
+}
+
+/// Text for people to read.
+#[derive(Debug, Serialize)]
+struct Message {
+    text: String,
+}
+
+/// A violation of a rule.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: &'static str,
+    level: &'static str,
+    message: Message,
+    locations: Vec<Location>,
+}
+
+/// Where a violation is.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+    logical_locations: Vec<LogicalLocation>,
+}
+
+/// The file a violation is in.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+}
+
+/// The path to a file.
+#[derive(Debug, Serialize)]
+struct ArtifactLocation {
+    uri: String,
+}
+
+/// The element of the SBOM a violation is in.
+#[derive(Debug, Serialize)]
+struct LogicalLocation {
+    name: String,
+    kind: &'static str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, Rule};
+    use crate::config::PolicyConfig;
+    use crate::document::{CreationInfoBuilder, Creator, DocumentBuilder, Package};
+    use std::path::Path;
+
+    #[test]
+    fn test_check() {
+        let mut permitted = Package::build_tool("permitted");
+        permitted.license_declared = Some("MIT OR GPL-3.0".to_string());
+        let mut denied = Package::build_tool("denied");
+        denied.license_declared = Some("GPL-3.0".to_string());
+        let unlicensed = Package::build_tool("unlicensed");
+        let doc = DocumentBuilder::default()
+            .document_name("foo.spdx")
+            .try_document_namespace("https://example.com/foo")
+            .unwrap()
+            .creation_info(
+                CreationInfoBuilder::default()
+                    .creators(vec![Creator::tool("cargo-spdx 0.1.0")])
+                    .build()
+                    .unwrap(),
+            )
+            .packages(vec![permitted, denied, unlicensed])
+            .build()
+            .unwrap();
+        let policy = PolicyConfig {
+            allow: Vec::new(),
+            deny: vec!["GPL-3.0".to_string()],
+            require: vec!["downloadLocation".to_string()],
+        };
+
+        let violations = check(&doc, Path::new("foo.spdx"), &policy);
+        let found = violations
+            .iter()
+            .map(|violation| (violation.rule, violation.spdxid.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (Rule::NoAssertion, "SPDXRef-BuildTool-permitted"),
+                (Rule::DeniedLicense, "SPDXRef-BuildTool-denied"),
+                (Rule::NoAssertion, "SPDXRef-BuildTool-denied"),
+                (Rule::MissingLicense, "SPDXRef-BuildTool-unlicensed"),
+                (Rule::NoAssertion, "SPDXRef-BuildTool-unlicensed"),
+            ]
+        );
+    }
+}
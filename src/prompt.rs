@@ -0,0 +1,89 @@
+//! Asks the user for input, when there's a user around to ask.
+
+use anyhow::{anyhow, Result};
+use console::Term;
+#[cfg(feature = "interactive")]
+use dialoguer::Input;
+use std::ffi::OsString;
+use std::ops::Not as _;
+
+/// Decides whether the user can be prompted, and prompts them.
+///
+/// Every prompt goes through here, so none of them can block waiting for
+/// input that will never come.
+#[derive(Debug, Clone, Copy)]
+pub struct Prompter {
+    /// Why prompting isn't possible, if it isn't.
+    unavailable: Option<&'static str>,
+}
+
+impl Prompter {
+    /// Create a prompter, disabled if `--no-interact` was passed, when running
+    /// in CI, when there's no terminal to prompt on, or when built without the
+    /// `interactive` feature.
+    pub fn new(no_interact: bool) -> Prompter {
+        let unavailable = if cfg!(feature = "interactive").not() {
+            Some("built without the `interactive` feature")
+        } else if no_interact {
+            Some("--no-interact was passed")
+        } else if is_ci(std::env::var_os("CI")) {
+            Some("running in CI")
+        } else if Term::stdout().is_term().not() || Term::stderr().is_term().not() {
+            Some("not running in a terminal")
+        } else {
+            None
+        };
+
+        Prompter { unavailable }
+    }
+
+    /// Ask the user for some text.
+    ///
+    /// `flag` is the option which provides the value without prompting, and is
+    /// suggested when prompting isn't possible.
+    pub fn input(&self, prompt: &str, flag: &str) -> Result<String> {
+        if let Some(reason) = self.unavailable {
+            return Err(anyhow!(
+                "if running non-interactively, {} must be specified ({})",
+                flag,
+                reason
+            ));
+        }
+
+        read_input(prompt)
+    }
+}
+
+/// Read a line of text from the terminal.
+#[cfg(feature = "interactive")]
+fn read_input(prompt: &str) -> Result<String> {
+    Ok(Input::<String>::new().with_prompt(prompt).interact_text()?)
+}
+
+/// Without the `interactive` feature the prompter is never available, so this
+/// is never reached.
+#[cfg(not(feature = "interactive"))]
+fn read_input(_prompt: &str) -> Result<String> {
+    unreachable!("prompting without the `interactive` feature")
+}
+
+/// Check the `CI` environment variable, which CI services set to `true`.
+fn is_ci(value: Option<OsString>) -> bool {
+    match value {
+        Some(value) => matches!(value.to_str(), Some("" | "0" | "false")).not(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_ci;
+
+    #[test]
+    fn test_is_ci() {
+        assert!(is_ci(Some("true".into())));
+        assert!(is_ci(Some("1".into())));
+        assert!(!is_ci(Some("false".into())));
+        assert!(!is_ci(None));
+    }
+}
@@ -0,0 +1,244 @@
+//! Builds and parses package URLs (purls).
+//!
+//! Purls identify packages across ecosystems, e.g. `pkg:cargo/serde@1.0.137`.
+//! Names, versions, and qualifiers may need percent-encoding, such as the `+`
+//! of semver build metadata, so purls are always built and compared through
+//! [`Purl`], which writes them out in their canonical form.
+//!
+//! See the [specification](https://github.com/package-url/purl-spec).
+
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::ops::Not as _;
+use std::str::FromStr;
+
+/// A package URL.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Purl {
+    /// The ecosystem of the package, e.g. `cargo`.
+    ty: String,
+    /// The segments of the namespace, such as a GitHub organization.
+    namespace: Vec<String>,
+    /// The name of the package.
+    name: String,
+    /// The version of the package.
+    version: Option<String>,
+    /// Extra details, such as where the package was downloaded from.
+    qualifiers: BTreeMap<String, String>,
+    /// A path within the package.
+    subpath: Option<String>,
+}
+
+impl Purl {
+    /// Construct a purl for a package in the given ecosystem.
+    pub fn new(ty: &str, name: &str) -> Result<Purl> {
+        let ty = ty.to_ascii_lowercase();
+        if ty.is_empty()
+            || ty.starts_with(|c: char| c.is_ascii_digit())
+            || ty.contains(|c: char| {
+                !(c.is_ascii_alphanumeric() || c == '.' || c == '+' || c == '-')
+            })
+        {
+            return Err(anyhow!("invalid purl type '{}'", ty));
+        }
+        if name.is_empty() {
+            return Err(anyhow!("purls must have a name"));
+        }
+
+        Ok(Purl {
+            ty,
+            namespace: Vec::new(),
+            name: name.to_string(),
+            version: None,
+            qualifiers: BTreeMap::new(),
+            subpath: None,
+        })
+    }
+
+    /// Construct a purl for a crate.
+    pub fn cargo(name: &str, version: &str) -> Purl {
+        Purl {
+            ty: "cargo".to_string(),
+            namespace: Vec::new(),
+            name: name.to_string(),
+            version: Some(version.to_string()),
+            qualifiers: BTreeMap::new(),
+            subpath: None,
+        }
+    }
+
+    /// The ecosystem of the package, e.g. `cargo`.
+    pub fn ty(&self) -> &str {
+        &self.ty
+    }
+
+    /// Set the version.
+    pub fn with_version(mut self, version: &str) -> Purl {
+        self.version = Some(version.to_string()).filter(|version| version.is_empty().not());
+        self
+    }
+
+    /// Add a qualifier. Qualifiers without a value are left out.
+    pub fn with_qualifier(mut self, key: &str, value: &str) -> Purl {
+        if value.is_empty().not() {
+            self.qualifiers
+                .insert(key.to_ascii_lowercase(), value.to_string());
+        }
+        self
+    }
+}
+
+impl FromStr for Purl {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Purl> {
+        let invalid = || anyhow!("invalid purl '{}'", s);
+
+        // Each part is split off the end in turn, as the specification describes.
+        let (rest, subpath) = match s.rsplit_once('#') {
+            Some((rest, subpath)) => (rest, Some(subpath)),
+            None => (s, None),
+        };
+        let (rest, qualifiers) = match rest.rsplit_once('?') {
+            Some((rest, qualifiers)) => (rest, Some(qualifiers)),
+            None => (rest, None),
+        };
+        let (scheme, rest) = rest.split_once(':').ok_or_else(invalid)?;
+        if scheme.eq_ignore_ascii_case("pkg").not() {
+            return Err(invalid());
+        }
+        let (ty, rest) = rest
+            .trim_start_matches('/')
+            .split_once('/')
+            .ok_or_else(invalid)?;
+        let rest = rest.trim_end_matches('/');
+        let (rest, version) = match rest.rsplit_once('@') {
+            Some((rest, version)) => (rest, Some(decode(version)?)),
+            None => (rest, None),
+        };
+        let (namespace, name) = match rest.rsplit_once('/') {
+            Some((namespace, name)) => (Some(namespace), name),
+            None => (None, rest),
+        };
+
+        let mut purl = Purl::new(ty, &decode(name)?).map_err(|error| error.context(invalid()))?;
+        for segment in namespace.into_iter().flat_map(segments) {
+            purl.namespace.push(decode(segment)?);
+        }
+        if let Some(version) = version {
+            purl = purl.with_version(&version);
+        }
+        for qualifier in qualifiers
+            .into_iter()
+            .flat_map(|qualifiers| qualifiers.split('&'))
+            .filter(|qualifier| qualifier.is_empty().not())
+        {
+            let (key, value) = qualifier.split_once('=').ok_or_else(invalid)?;
+            purl = purl.with_qualifier(key, &decode(value)?);
+        }
+        if let Some(subpath) = subpath {
+            let subpath = segments(subpath)
+                .filter(|segment| *segment != "." && *segment != "..")
+                .map(decode)
+                .collect::<Result<Vec<_>>>()?;
+            purl.subpath = Some(subpath.join("/")).filter(|subpath| subpath.is_empty().not());
+        }
+        Ok(purl)
+    }
+}
+
+impl Display for Purl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "pkg:{}/", self.ty)?;
+        for segment in &self.namespace {
+            write!(f, "{}/", encode(segment, ""))?;
+        }
+        write!(f, "{}", encode(&self.name, ""))?;
+        if let Some(version) = &self.version {
+            write!(f, "@{}", encode(version, ""))?;
+        }
+        for (i, (key, value)) in self.qualifiers.iter().enumerate() {
+            let separator = if i == 0 { '?' } else { '&' };
+            write!(f, "{}{}={}", separator, key, encode(value, "/"))?;
+        }
+        if let Some(subpath) = &self.subpath {
+            write!(f, "#{}", encode(subpath, "/"))?;
+        }
+        Ok(())
+    }
+}
+
+// Split a path into its non-empty segments
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| segment.is_empty().not())
+}
+
+// Percent-encode everything but unreserved characters, colons, and any `safe` characters
+fn encode(s: &str, safe: &str) -> String {
+    let mut encoded = String::new();
+    for byte in s.bytes() {
+        let c = byte as char;
+        if c.is_ascii_alphanumeric() || "-._~:".contains(c) || safe.contains(c) {
+            encoded.push(c);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+// Decode percent-encoded characters
+fn decode(s: &str) -> Result<String> {
+    let mut bytes = Vec::new();
+    let mut rest = s.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| anyhow!("invalid percent-encoding in '{}'", s))?;
+            bytes.push(hex);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| anyhow!("'{}' doesn't decode to UTF-8", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Purl;
+
+    #[test]
+    fn test_purl_round_trip() {
+        for purl in [
+            "pkg:cargo/serde@1.0.137",
+            "pkg:cargo/foo@1.0.0%2Bbuild.5",
+            "pkg:github/rust-lang/cargo@0.62.0",
+            "pkg:cargo/foo@1.0.0?repository_url=https://example.com/index&vcs_url=git%2Bhttps://example.com/foo.git",
+            "pkg:generic/openssl@3.0.5#lib/ssl",
+        ] {
+            assert_eq!(purl.parse::<Purl>().unwrap().to_string(), purl);
+        }
+    }
+
+    #[test]
+    fn test_purl_normalization() {
+        let purl: Purl = "PKG:Cargo/serde@1.0.137?B=2&a=1&empty=#/src/./lib//"
+            .parse()
+            .unwrap();
+        assert_eq!(purl.to_string(), "pkg:cargo/serde@1.0.137?a=1&b=2#src/lib");
+
+        assert_eq!(
+            Purl::cargo("foo", "1.0.0+build.5").to_string(),
+            "pkg:cargo/foo@1.0.0%2Bbuild.5"
+        );
+        assert!("cargo/serde".parse::<Purl>().is_err());
+        assert!("pkg:cargo".parse::<Purl>().is_err());
+        assert!("pkg:cargo/serde@%zz".parse::<Purl>().is_err());
+    }
+}
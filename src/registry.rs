@@ -0,0 +1,348 @@
+//! Fetches and verifies `.crate` archives published on crates.io.
+//!
+//! The checksums crates.io publishes for each version are read from its sparse
+//! index, so lockfile checksums can be cross-checked without any downloads.
+
+use crate::checksums::{registry_cache_dirs, sha256_file};
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::ops::Not as _;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Where crates.io serves `.crate` archives from.
+const DOWNLOAD_URL: &str = "https://static.crates.io/crates";
+
+/// Where the crates.io sparse index is served from.
+pub const INDEX_URL: &str = "https://index.crates.io";
+
+/// The minimum time between requests to crates.io, per the crawler policy.
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many times to try a download before giving up.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Check crates.io can be reached to download archives from.
+///
+/// Any response counts, as only whether the connection can be made matters.
+pub fn check_reachable() -> Result<()> {
+    let result = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .head(DOWNLOAD_URL)
+        .call();
+    match result {
+        Ok(_) | Err(ureq::Error::Status(..)) => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Fetches `.crate` archives, preferring ones already in the local cargo cache.
+#[derive(Debug)]
+pub struct Downloader {
+    /// Cargo's registry caches, checked before downloading anything.
+    cache_dirs: Vec<PathBuf>,
+    /// Where downloaded archives are stored.
+    download_dir: PathBuf,
+    /// The HTTP agent used for downloads.
+    agent: ureq::Agent,
+    /// When the last request to crates.io was made.
+    last_request: Option<Instant>,
+}
+
+impl Downloader {
+    /// Create a downloader storing archives it fetches in `download_dir`.
+    pub fn new(download_dir: &Path) -> Result<Downloader> {
+        fs::create_dir_all(download_dir)
+            .with_context(|| format!("failed to create {}", download_dir.display()))?;
+
+        let agent = ureq::AgentBuilder::new()
+            .user_agent(&format!(
+                "cargo-spdx/{} (+{})",
+                env!("CARGO_PKG_VERSION"),
+                env!("CARGO_PKG_REPOSITORY")
+            ))
+            .timeout_read(Duration::from_secs(60))
+            .build();
+
+        Ok(Downloader {
+            cache_dirs: registry_cache_dirs(),
+            download_dir: download_dir.to_owned(),
+            agent,
+            last_request: None,
+        })
+    }
+
+    /// Get the path of a verified `.crate` archive for the given package.
+    ///
+    /// Archives are only returned once their SHA256 matches `checksum`, so they
+    /// are safe to unpack.
+    pub fn fetch(&mut self, name: &str, version: &str, checksum: &str) -> Result<PathBuf> {
+        let file_name = format!("{}-{}.crate", name, version);
+
+        for cache_dir in &self.cache_dirs {
+            let cached = cache_dir.join(&file_name);
+            if cached.is_file() {
+                if sha256_file(&cached)? == checksum {
+                    log::debug!(target: "cargo_spdx", "using cached archive {}", cached.display());
+                    return Ok(cached);
+                }
+
+                log::warn!(
+                    target: "cargo_spdx",
+                    "cached archive {} doesn't match the lockfile checksum, ignoring it",
+                    cached.display()
+                );
+            }
+        }
+
+        let archive = self.download_dir.join(&file_name);
+        if archive.is_file() && sha256_file(&archive)? == checksum {
+            return Ok(archive);
+        }
+
+        let url = format!("{}/{}/{}", DOWNLOAD_URL, name, file_name);
+        let partial = self.download_dir.join(format!("{}.part", file_name));
+        self.download(&url, &partial)?;
+
+        let actual = sha256_file(&partial)?;
+        if actual != checksum {
+            fs::remove_file(&partial)?;
+            return Err(anyhow!(
+                "checksum mismatch for {}: the lockfile has {} but crates.io served {}",
+                file_name,
+                checksum,
+                actual
+            ));
+        }
+
+        fs::rename(&partial, &archive)?;
+        Ok(archive)
+    }
+
+    /// Get the path of the `.crate` archive for the given package, downloading
+    /// it if it hasn't been already.
+    ///
+    /// Its checksum isn't known, so the archive can't be verified, and mustn't
+    /// be unpacked; it's only for finding out its checksum.
+    pub fn fetch_unverified(&mut self, name: &str, version: &str) -> Result<PathBuf> {
+        let file_name = format!("{}-{}.crate", name, version);
+        let archive = self.download_dir.join(&file_name);
+        if archive.is_file() {
+            return Ok(archive);
+        }
+
+        let url = format!("{}/{}/{}", DOWNLOAD_URL, name, file_name);
+        let partial = self.download_dir.join(format!("{}.part", file_name));
+        self.download(&url, &partial)?;
+        fs::rename(&partial, &archive)?;
+        Ok(archive)
+    }
+
+    /// Download `url` to `dest`, resuming from whatever is already there.
+    fn download(&mut self, url: &str, dest: &Path) -> Result<()> {
+        let mut attempt = 1;
+        loop {
+            match self.try_download(url, dest) {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt < MAX_ATTEMPTS => {
+                    log::warn!(
+                        target: "cargo_spdx",
+                        "failed to download {} (attempt {} of {}): {}",
+                        url,
+                        attempt,
+                        MAX_ATTEMPTS,
+                        err
+                    );
+                    attempt += 1;
+                }
+                Err(err) => return Err(err.context(format!("failed to download {}", url))),
+            }
+        }
+    }
+
+    /// Make a single request for `url`, appending to `dest` if the server honours the range.
+    fn try_download(&mut self, url: &str, dest: &Path) -> Result<()> {
+        self.wait_turn();
+
+        let existing = fs::metadata(dest).map(|m| m.len()).unwrap_or(0);
+        let mut request = self.agent.get(url);
+        if existing > 0 {
+            request = request.set("Range", &format!("bytes={}-", existing));
+        }
+
+        log::info!(target: "cargo_spdx", "downloading {}", url);
+        let response = match request.call() {
+            Ok(response) => response,
+            // The partial download is already complete.
+            Err(ureq::Error::Status(416, _)) => return Ok(()),
+            Err(ureq::Error::Status(429, response)) => {
+                let delay = response
+                    .header("Retry-After")
+                    .and_then(|secs| secs.parse().ok())
+                    .map_or(REQUEST_INTERVAL, Duration::from_secs);
+                thread::sleep(delay);
+                return Err(anyhow!("rate limited by crates.io"));
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        // A 206 continues the partial download, anything else starts it over.
+        let resume = response.status() == 206;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resume)
+            .truncate(resume.not())
+            .open(dest)?;
+        io::copy(&mut response.into_reader(), &mut file)?;
+        Ok(())
+    }
+
+    /// Wait until it's polite to make another request.
+    fn wait_turn(&mut self) {
+        if let Some(last_request) = self.last_request {
+            let elapsed = last_request.elapsed();
+            if elapsed < REQUEST_INTERVAL {
+                thread::sleep(REQUEST_INTERVAL - elapsed);
+            }
+        }
+        self.last_request = Some(Instant::now());
+    }
+}
+
+/// Reads the checksums of published crates from a registry's sparse index,
+/// crates.io's by default.
+#[derive(Debug)]
+pub struct SparseIndex {
+    /// Where the index is served from.
+    url: String,
+    /// The HTTP agent used for index requests.
+    agent: ureq::Agent,
+    /// The checksums of each version of the crates looked up so far.
+    crates: HashMap<String, HashMap<String, String>>,
+}
+
+impl Default for SparseIndex {
+    fn default() -> SparseIndex {
+        SparseIndex::new(INDEX_URL)
+    }
+}
+
+impl SparseIndex {
+    /// Read the sparse index served from `url`, e.g. `https://index.crates.io`.
+    pub fn new(url: &str) -> SparseIndex {
+        let agent = ureq::AgentBuilder::new()
+            .user_agent(&format!(
+                "cargo-spdx/{} (+{})",
+                env!("CARGO_PKG_VERSION"),
+                env!("CARGO_PKG_REPOSITORY")
+            ))
+            .timeout(Duration::from_secs(30))
+            .build();
+
+        SparseIndex {
+            url: url.trim_end_matches('/').to_string(),
+            agent,
+            crates: HashMap::new(),
+        }
+    }
+
+    /// Get the checksum the registry publishes for a version of a crate.
+    ///
+    /// Returns `None` if the index doesn't list that version.
+    pub fn checksum(&mut self, name: &str, version: &str) -> Result<Option<&str>> {
+        if self.crates.contains_key(name).not() {
+            let url = format!("{}/{}", self.url, index_path(name));
+            log::debug!(target: "cargo_spdx", "fetching {}", url);
+            let entries = match self.agent.get(&url).call() {
+                Ok(response) => parse_index_checksums(&response.into_string()?)
+                    .with_context(|| format!("invalid index entry {}", url))?,
+                // The crate isn't published, so has no versions to check.
+                Err(ureq::Error::Status(404, _)) => HashMap::new(),
+                Err(err) => {
+                    return Err(anyhow::Error::from(err).context(format!("failed to fetch {}", url)))
+                }
+            };
+            self.crates.insert(name.to_string(), entries);
+        }
+
+        Ok(self.crates[name].get(version).map(String::as_str))
+    }
+}
+
+/// Get the path of a crate's entry in the index, e.g. `se/rd/serde`.
+fn index_path(name: &str) -> String {
+    let name = name.to_ascii_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+    }
+}
+
+/// Parse the checksum of each version out of an index entry, one JSON object per line.
+fn parse_index_checksums(contents: &str) -> Result<HashMap<String, String>> {
+    #[derive(serde::Deserialize)]
+    struct IndexVersion {
+        vers: String,
+        cksum: String,
+    }
+
+    contents
+        .lines()
+        .filter(|line| line.trim().is_empty().not())
+        .map(|line| {
+            let version: IndexVersion = serde_json::from_str(line)?;
+            Ok((version.vers, version.cksum))
+        })
+        .collect()
+}
+
+/// Unpack a verified `.crate` archive into `into`, returning the package's root directory.
+pub fn unpack(archive: &Path, name: &str, version: &str, into: &Path) -> Result<PathBuf> {
+    let root = into.join(format!("{}-{}", name, version));
+    if root.exists() {
+        fs::remove_dir_all(&root)?;
+    }
+    fs::create_dir_all(into)?;
+
+    let file = fs::File::open(archive)?;
+    let mut archive_reader = tar::Archive::new(GzDecoder::new(file));
+    for entry in archive_reader.entries()? {
+        // `unpack_in` refuses to write outside of `into`.
+        entry?.unpack_in(into)?;
+    }
+
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{index_path, parse_index_checksums};
+
+    #[test]
+    fn test_index_checksums() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("cc"), "2/cc");
+        assert_eq!(index_path("syn"), "3/s/syn");
+        assert_eq!(index_path("Serde_JSON"), "se/rd/serde_json");
+
+        let checksums = parse_index_checksums(
+            r#"{"name":"anyhow","vers":"1.0.56","deps":[],"cksum":"4361135be9122e0870de935d7c439aef945b9f9ddd4199a553b5270b49c82a27","features":{},"yanked":false}
+{"name":"anyhow","vers":"1.0.57","deps":[],"cksum":"08f9b8508dccb7687a1d6c4ce66b2b0ecef467c94667de27d8d7fe1f8d2a9cdc","features":{},"yanked":false}
+"#,
+        )
+        .unwrap();
+        assert_eq!(checksums.len(), 2);
+        assert_eq!(
+            checksums["1.0.57"],
+            "08f9b8508dccb7687a1d6c4ce66b2b0ecef467c94667de27d8d7fe1f8d2a9cdc"
+        );
+    }
+}
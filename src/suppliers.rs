@@ -0,0 +1,326 @@
+//! Maps packages to their suppliers, from a `suppliers.toml` file.
+//!
+//! Cargo doesn't record who supplies a crate, so organizations which keep
+//! their own vendor data can list it here:
+//!
+//! ```toml
+//! # Used for packages no rule matches, otherwise they're left as NOASSERTION.
+//! default = "Organization: Acme Corp"
+//!
+//! [[rule]]
+//! crates = ["tokio", "tokio-*"]
+//! supplier = "Organization: Tokio Contributors"
+//!
+//! [[rule]]
+//! purls = ["pkg:github/rust-lang/*"]
+//! supplier = "Organization: The Rust Project Developers"
+//! ```
+//!
+//! Rules are tried in order, and the first one matching a package wins. Patterns
+//! may use `*` to match any run of characters.
+
+use crate::document::{Document, NOASSERTION};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::ops::Not as _;
+use std::path::Path;
+
+/// The name of the supplier mapping looked for in the workspace root.
+pub const SUPPLIERS_FILE_NAME: &str = "suppliers.toml";
+
+/// The kinds of supplier SPDX allows.
+const SUPPLIER_PREFIXES: &[&str] = &["Person: ", "Organization: "];
+
+/// The suppliers of packages, matched by crate name or purl.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suppliers {
+    /// The supplier of packages no rule matches.
+    #[serde(default)]
+    default: Option<String>,
+
+    /// The rules matching packages to suppliers, in the order they're tried.
+    #[serde(default, rename = "rule")]
+    rules: Vec<SupplierRule>,
+}
+
+/// A supplier, and the packages it supplies.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SupplierRule {
+    /// Patterns matching the names of crates.
+    #[serde(default)]
+    crates: Vec<String>,
+
+    /// Patterns matching the purls of packages.
+    #[serde(default)]
+    purls: Vec<String>,
+
+    /// The supplier, e.g. `Organization: Tokio Contributors`.
+    supplier: String,
+}
+
+impl Suppliers {
+    /// Load the supplier mapping.
+    ///
+    /// If no path is given, looks for the mapping in the workspace root, mapping
+    /// nothing if there isn't one.
+    pub fn load(path: Option<&Path>, workspace_root: &Path) -> Result<Suppliers> {
+        let path = match path {
+            Some(path) => path.to_owned(),
+            None => {
+                let path = workspace_root.join(SUPPLIERS_FILE_NAME);
+                if path.exists().not() {
+                    return Ok(Suppliers::default());
+                }
+                path
+            }
+        };
+
+        log::info!(target: "cargo_spdx", "loading suppliers from {}", path.display());
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read supplier mapping {}", path.display()))?;
+        let suppliers: Suppliers = toml::from_str(&contents)
+            .with_context(|| format!("invalid supplier mapping {}", path.display()))?;
+        suppliers.validate()?;
+        Ok(suppliers)
+    }
+
+    /// Give packages nothing else supplies the given supplier, in place of any mapped default.
+    pub fn set_default(&mut self, supplier: &str) -> Result<()> {
+        self.default = Some(supplier.to_string());
+        self.validate()
+    }
+
+    /// Check every supplier is one SPDX allows, and every rule matches something.
+    fn validate(&self) -> Result<()> {
+        let suppliers = self
+            .default
+            .iter()
+            .chain(self.rules.iter().map(|rule| &rule.supplier));
+        for supplier in suppliers {
+            if SUPPLIER_PREFIXES
+                .iter()
+                .any(|prefix| supplier.starts_with(prefix))
+                .not()
+            {
+                return Err(anyhow!(
+                    "supplier '{}' must start with 'Person: ' or 'Organization: '",
+                    supplier
+                ));
+            }
+        }
+
+        if let Some(rule) = self
+            .rules
+            .iter()
+            .find(|rule| rule.crates.is_empty() && rule.purls.is_empty())
+        {
+            return Err(anyhow!(
+                "the rule for '{}' must list some crates or purls",
+                rule.supplier
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Whether there's anything to map.
+    fn is_empty(&self) -> bool {
+        self.default.is_none() && self.rules.is_empty()
+    }
+
+    /// Set the supplier of each package a rule matches.
+    ///
+    /// Packages no rule matches are left for other sources of suppliers, then
+    /// for [`Suppliers::apply_default`].
+    pub fn apply(&self, doc: &mut Document) {
+        for package in doc.packages.iter_mut().flatten() {
+            let purl = package.purl().map(|purl| purl.to_string());
+            let rule = self.rules.iter().find(|rule| {
+                rule.crates
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, &package.name))
+                    || rule
+                        .purls
+                        .iter()
+                        .any(|pattern| purl.iter().any(|purl| matches_pattern(pattern, purl)))
+            });
+
+            if let Some(rule) = rule {
+                package.supplier = Some(rule.supplier.clone());
+            }
+        }
+    }
+
+    /// Give the default supplier to each package still without one.
+    ///
+    /// The packages are logged, so the mapping can be filled in.
+    pub fn apply_default(&self, doc: &mut Document) {
+        if self.is_empty() {
+            return;
+        }
+
+        let mut unmatched = Vec::new();
+        for package in doc.packages.iter_mut().flatten() {
+            if package
+                .supplier
+                .as_deref()
+                .map_or(true, |supplier| supplier == NOASSERTION)
+            {
+                unmatched.push(package.name.clone());
+                package.supplier = self.default.clone();
+            }
+        }
+
+        if unmatched.is_empty().not() {
+            unmatched.sort();
+            unmatched.dedup();
+            log::warn!(
+                target: "cargo_spdx",
+                "no supplier is known for {}, so {}",
+                unmatched.join(", "),
+                match &self.default {
+                    Some(default) => format!("using '{}'", default),
+                    None => "leaving them as NOASSERTION".to_string(),
+                }
+            );
+        }
+    }
+}
+
+/// Check if a pattern, where `*` matches any run of characters, matches the text.
+pub fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // There's always a first part, even if it's empty.
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match text.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let (last, middle) = match parts.split_last() {
+        Some(split) => split,
+        // No `*`, so the whole text must match.
+        None => return rest.is_empty(),
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches_pattern, Suppliers};
+    use crate::document::{
+        CreationInfoBuilder, Creator, Document, DocumentBuilder, ExternalRef, Package,
+        ReferenceCategory,
+    };
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("tokio", "tokio"));
+        assert!(!matches_pattern("tokio", "tokio-util"));
+        assert!(matches_pattern("tokio-*", "tokio-util"));
+        assert!(!matches_pattern("tokio-*", "tokio"));
+        assert!(matches_pattern("*-sys", "openssl-sys"));
+        assert!(matches_pattern(
+            "pkg:github/*/*",
+            "pkg:github/rust-lang/libc"
+        ));
+        assert!(!matches_pattern("a*a", "a"));
+        assert!(matches_pattern("*", ""));
+    }
+
+    #[test]
+    fn test_apply_suppliers() {
+        let suppliers: Suppliers = toml::from_str(
+            r#"
+            default = "Organization: Acme Corp"
+
+            [[rule]]
+            crates = ["tokio*"]
+            supplier = "Organization: Tokio Contributors"
+
+            [[rule]]
+            purls = ["pkg:cargo/serde@*"]
+            supplier = "Person: David Tolnay"
+            "#,
+        )
+        .unwrap();
+        suppliers.validate().unwrap();
+
+        let package = |name: &str| {
+            let mut package = Package::build_tool(name);
+            package.external_refs = Some(vec![ExternalRef {
+                reference_category: ReferenceCategory::PackageManager,
+                reference_type: "purl".to_string(),
+                reference_locator: format!("pkg:cargo/{}@1.0.0", name),
+                comment: None,
+            }]);
+            package
+        };
+        let mut doc = DocumentBuilder::default()
+            .document_name("foo.spdx")
+            .try_document_namespace("https://example.com/foo")
+            .unwrap()
+            .creation_info(
+                CreationInfoBuilder::default()
+                    .creators(vec![Creator::tool("cargo-spdx 0.1.0")])
+                    .build()
+                    .unwrap(),
+            )
+            .packages(vec![
+                package("tokio-util"),
+                package("serde"),
+                package("libc"),
+            ])
+            .build()
+            .unwrap();
+        suppliers.apply(&mut doc);
+
+        let supplied = |doc: &Document| -> Vec<_> {
+            doc.packages
+                .iter()
+                .flatten()
+                .map(|package| package.supplier.clone())
+                .collect()
+        };
+        assert_eq!(
+            supplied(&doc),
+            [
+                Some("Organization: Tokio Contributors".to_string()),
+                Some("Person: David Tolnay".to_string()),
+                None
+            ]
+        );
+
+        suppliers.apply_default(&mut doc);
+        assert_eq!(
+            supplied(&doc)[2].as_deref(),
+            Some("Organization: Acme Corp")
+        );
+    }
+
+    #[test]
+    fn test_invalid_supplier() {
+        let suppliers: Suppliers = toml::from_str(
+            r#"
+            default = "Acme Corp"
+            "#,
+        )
+        .unwrap();
+        assert!(suppliers.validate().is_err());
+        assert!(Suppliers::default().set_default("crates.io").is_err());
+        assert!(Suppliers::default()
+            .set_default("Organization: crates.io")
+            .is_ok());
+    }
+}
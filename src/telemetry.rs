@@ -0,0 +1,380 @@
+//! Traces the steps of producing SBOMs with OpenTelemetry.
+//!
+//! SBOMs are often generated inside a larger release pipeline which is traced,
+//! so slow steps are worth showing there. When cargo-spdx is built with the
+//! `otel` feature and `OTEL_EXPORTER_OTLP_ENDPOINT` or
+//! `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` is set, each step is recorded as a span,
+//! and the spans are sent to the endpoint with OTLP over HTTP, encoded as JSON,
+//! before cargo-spdx exits, unless it's kept off the network. A W3C
+//! `TRACEPARENT` in the environment makes the spans part of the pipeline's
+//! own trace.
+
+use anyhow::Result;
+#[cfg(feature = "otel")]
+use serde_json::{json, Value};
+#[cfg(feature = "otel")]
+use std::cell::RefCell;
+use std::env;
+#[cfg(feature = "otel")]
+use std::ops::Not as _;
+#[cfg(feature = "otel")]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The variables which turn tracing on, giving where spans are sent.
+#[cfg(not(feature = "otel"))]
+const ENDPOINT_VARS: &[&str] = &[
+    "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+    "OTEL_EXPORTER_OTLP_ENDPOINT",
+];
+
+/// Run a step of producing an SBOM, recording it as a span if tracing is on.
+///
+/// Spans started while the step runs are its children, and the span is marked
+/// as failed if the step fails.
+#[cfg(feature = "otel")]
+pub fn in_span<T>(
+    name: &str,
+    attributes: &[(&str, &str)],
+    step: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    let started = TRACER.with(|tracer| tracer.borrow_mut().as_mut().map(Tracer::start));
+    let result = step();
+    if let Some(started) = started {
+        let error = result.as_ref().err().map(|error| format!("{:#}", error));
+        TRACER.with(|tracer| {
+            if let Some(tracer) = tracer.borrow_mut().as_mut() {
+                tracer.end(started, name, attributes, error);
+            }
+        });
+    }
+    result
+}
+
+/// Run a step of producing an SBOM.
+#[cfg(not(feature = "otel"))]
+pub fn in_span<T>(
+    _name: &str,
+    _attributes: &[(&str, &str)],
+    step: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    step()
+}
+
+/// Send the spans recorded so far to the OTLP endpoint, unless `offline`.
+///
+/// Failing to send them is logged rather than failing cargo-spdx, as the SBOMs
+/// have been written by then.
+#[cfg(feature = "otel")]
+pub fn export(offline: bool) {
+    let tracer = TRACER.with(|tracer| tracer.borrow_mut().take());
+    let tracer = match tracer {
+        Some(tracer) if tracer.spans.is_empty().not() => tracer,
+        _ => return,
+    };
+    if offline {
+        log::warn!(
+            target: "cargo_spdx",
+            "not sending spans to {}, as --offline or --frozen keeps cargo-spdx off the network",
+            tracer.endpoint
+        );
+        return;
+    }
+
+    log::info!(target: "cargo_spdx", "sending {} spans to {}", tracer.spans.len(), tracer.endpoint);
+
+    let mut request = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .post(&tracer.endpoint)
+        .set("Content-Type", "application/json");
+    for (name, value) in &tracer.headers {
+        request = request.set(name, value);
+    }
+    if let Err(error) = request.send_string(&tracer.to_otlp().to_string()) {
+        log::warn!(target: "cargo_spdx", "failed to send spans to {}: {}", tracer.endpoint, error);
+    }
+}
+
+/// Warn that tracing was asked for but isn't available.
+#[cfg(not(feature = "otel"))]
+pub fn export(_offline: bool) {
+    if let Some(var) = ENDPOINT_VARS.iter().find(|var| env::var_os(var).is_some()) {
+        log::warn!(
+            target: "cargo_spdx",
+            "{} is set, but tracing needs cargo-spdx to be built with the `otel` feature",
+            var
+        );
+    }
+}
+
+#[cfg(feature = "otel")]
+thread_local! {
+    /// The tracer, if tracing is on. SBOMs are produced on the main thread, so
+    /// that's the only one spans are recorded on.
+    static TRACER: RefCell<Option<Tracer>> = RefCell::new(Tracer::from_env());
+}
+
+/// Records spans, and where to send them.
+#[cfg(feature = "otel")]
+#[derive(Debug)]
+struct Tracer {
+    /// Where to send the spans.
+    endpoint: String,
+    /// Headers to send the spans with, such as for authentication.
+    headers: Vec<(String, String)>,
+    /// The name of the service the spans are from.
+    service_name: String,
+    /// The trace the spans are part of.
+    trace_id: [u8; 16],
+    /// The spans which are running, innermost last, starting with the span
+    /// from `TRACEPARENT` if there is one.
+    stack: Vec<[u8; 8]>,
+    /// The spans which have ended.
+    spans: Vec<Span>,
+    /// How many IDs have been generated, so each one differs.
+    ids_generated: u64,
+}
+
+/// A span which has started, but not yet ended.
+#[cfg(feature = "otel")]
+#[derive(Debug, Clone, Copy)]
+struct Started {
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    start: u128,
+}
+
+/// A span which has ended.
+#[cfg(feature = "otel")]
+#[derive(Debug)]
+struct Span {
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    name: String,
+    attributes: Vec<(String, String)>,
+    start: u128,
+    end: u128,
+    /// The error the step failed with, if it did.
+    error: Option<String>,
+}
+
+#[cfg(feature = "otel")]
+impl Tracer {
+    /// Set up tracing from the standard OpenTelemetry variables, if they turn it on.
+    fn from_env() -> Option<Tracer> {
+        let endpoint = match env::var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT") {
+            Ok(endpoint) => endpoint,
+            Err(_) => format!(
+                "{}/v1/traces",
+                env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                    .ok()?
+                    .trim_end_matches('/')
+            ),
+        };
+        let headers = env::var("OTEL_EXPORTER_OTLP_HEADERS")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|header| header.split_once('='))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect();
+        let service_name =
+            env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| env!("CARGO_PKG_NAME").to_string());
+
+        let mut tracer = Tracer {
+            endpoint,
+            headers,
+            service_name,
+            trace_id: [0; 16],
+            stack: Vec::new(),
+            spans: Vec::new(),
+            ids_generated: 0,
+        };
+        match env::var("TRACEPARENT")
+            .ok()
+            .as_deref()
+            .and_then(parse_traceparent)
+        {
+            Some((trace_id, parent_span_id)) => {
+                tracer.trace_id = trace_id;
+                tracer.stack.push(parent_span_id);
+            }
+            None => {
+                let (high, low) = (tracer.generate_id(), tracer.generate_id());
+                tracer.trace_id[..8].copy_from_slice(&high);
+                tracer.trace_id[8..].copy_from_slice(&low);
+            }
+        }
+        Some(tracer)
+    }
+
+    // Start a span as a child of the innermost running span
+    fn start(&mut self) -> Started {
+        let started = Started {
+            span_id: self.generate_id(),
+            parent_span_id: self.stack.last().copied(),
+            start: now(),
+        };
+        self.stack.push(started.span_id);
+        started
+    }
+
+    // End a span, recording it for sending
+    fn end(
+        &mut self,
+        started: Started,
+        name: &str,
+        attributes: &[(&str, &str)],
+        error: Option<String>,
+    ) {
+        self.stack.retain(|id| *id != started.span_id);
+        self.spans.push(Span {
+            span_id: started.span_id,
+            parent_span_id: started.parent_span_id,
+            name: name.to_string(),
+            attributes: attributes
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            start: started.start,
+            end: now(),
+            error,
+        });
+    }
+
+    // Generate an ID which is unlikely to have been used by anything else in the trace
+    fn generate_id(&mut self) -> [u8; 8] {
+        use sha2::{Digest, Sha256};
+
+        self.ids_generated += 1;
+        let seed = format!("{}-{}-{}", now(), std::process::id(), self.ids_generated);
+        let mut id = [0; 8];
+        id.copy_from_slice(&Sha256::digest(seed.as_bytes())[..8]);
+        id
+    }
+
+    /// Encode the spans as an OTLP `ExportTraceServiceRequest`.
+    fn to_otlp(&self) -> Value {
+        let attribute =
+            |key: &str, value: &str| json!({ "key": key, "value": { "stringValue": value } });
+        let spans: Vec<Value> = self
+            .spans
+            .iter()
+            .map(|span| {
+                let mut value = json!({
+                    "traceId": hex::encode(self.trace_id),
+                    "spanId": hex::encode(span.span_id),
+                    "name": span.name,
+                    // SPAN_KIND_INTERNAL
+                    "kind": 1,
+                    "startTimeUnixNano": span.start.to_string(),
+                    "endTimeUnixNano": span.end.to_string(),
+                    "attributes": span
+                        .attributes
+                        .iter()
+                        .map(|(key, value)| attribute(key, value))
+                        .collect::<Vec<_>>(),
+                    // STATUS_CODE_OK or STATUS_CODE_ERROR
+                    "status": match &span.error {
+                        Some(error) => json!({ "code": 2, "message": error }),
+                        None => json!({ "code": 1 }),
+                    },
+                });
+                if let Some(parent_span_id) = span.parent_span_id {
+                    value["parentSpanId"] = hex::encode(parent_span_id).into();
+                }
+                value
+            })
+            .collect();
+
+        json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [attribute("service.name", &self.service_name)],
+                },
+                "scopeSpans": [{
+                    "scope": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                    "spans": spans,
+                }],
+            }],
+        })
+    }
+}
+
+/// The current time, in nanoseconds since the Unix epoch.
+#[cfg(feature = "otel")]
+fn now() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
+
+/// Parse a W3C `traceparent`, giving the trace ID and the ID of the parent span.
+#[cfg(feature = "otel")]
+fn parse_traceparent(traceparent: &str) -> Option<([u8; 16], [u8; 8])> {
+    let mut parts = traceparent.trim().split('-');
+    let (version, trace_id, parent_id) = (parts.next()?, parts.next()?, parts.next()?);
+    if version.len() != 2 {
+        return None;
+    }
+
+    let mut trace = [0; 16];
+    let mut parent = [0; 8];
+    hex::decode_to_slice(trace_id, &mut trace).ok()?;
+    hex::decode_to_slice(parent_id, &mut parent).ok()?;
+    // All-zero IDs are invalid.
+    if trace == [0; 16] || parent == [0; 8] {
+        return None;
+    }
+    Some((trace, parent))
+}
+
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    use super::{parse_traceparent, Tracer};
+
+    #[test]
+    fn test_parse_traceparent() {
+        let (trace_id, parent_id) =
+            parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").unwrap();
+        assert_eq!(hex::encode(trace_id), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(hex::encode(parent_id), "00f067aa0ba902b7");
+        assert!(
+            parse_traceparent("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none()
+        );
+        assert!(parse_traceparent("00-4bf92f35-00f067aa0ba902b7-01").is_none());
+        assert!(parse_traceparent("").is_none());
+    }
+
+    #[test]
+    fn test_nested_spans() {
+        let mut tracer = Tracer {
+            endpoint: "http://localhost:4318/v1/traces".to_string(),
+            headers: Vec::new(),
+            service_name: "cargo-spdx".to_string(),
+            trace_id: [1; 16],
+            stack: vec![[2; 8]],
+            spans: Vec::new(),
+            ids_generated: 0,
+        };
+        let outer = tracer.start();
+        let inner = tracer.start();
+        tracer.end(inner, "write", &[("spdx.path", "foo.spdx")], None);
+        tracer.end(outer, "produce sbom", &[], Some("failed".to_string()));
+
+        let otlp = tracer.to_otlp();
+        let spans = &otlp["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        assert_eq!(spans[0]["name"], "write");
+        assert_eq!(spans[0]["parentSpanId"], spans[1]["spanId"]);
+        assert_eq!(
+            spans[0]["attributes"][0]["value"]["stringValue"],
+            "foo.spdx"
+        );
+        assert_eq!(spans[1]["parentSpanId"], "0202020202020202");
+        assert_eq!(spans[1]["status"]["code"], 2);
+        assert_ne!(spans[0]["spanId"], spans[1]["spanId"]);
+    }
+}
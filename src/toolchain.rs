@@ -0,0 +1,138 @@
+//! Functions for identifying the Rust toolchain which builds a workspace.
+
+use crate::document::Package;
+use anyhow::{anyhow, Result};
+use std::ops::Not as _;
+use std::path::Path;
+use std::process::Command;
+
+/// The compiler which cargo builds with, as reported by `rustc --version --verbose`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toolchain {
+    /// The first line of the report, e.g. `rustc 1.75.0 (82e1608df 2023-12-21)`.
+    pub version: String,
+    /// The release, e.g. `1.75.0` or `1.77.0-nightly`.
+    pub release: String,
+    /// The commit rustc was built from, unless it was built outside of git.
+    pub commit_hash: Option<String>,
+    /// The platform rustc runs on.
+    pub host: String,
+    /// The rustup toolchain selected, if rustup is in use.
+    pub rustup_toolchain: Option<String>,
+}
+
+/// Find the compiler cargo builds with.
+pub fn rustc_exec() -> String {
+    // cargo builds with this if it's set, so ask the same compiler
+    std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string())
+}
+
+/// Ask rustc which toolchain builds the workspace at `dir`.
+///
+/// rustc is run from `dir`, so rustup picks the toolchain it would for a
+/// build there, e.g. from a `rust-toolchain.toml` file.
+pub fn detect(dir: &Path) -> Result<Toolchain> {
+    let output = Command::new(rustc_exec())
+        .args(["--version", "--verbose"])
+        .current_dir(dir)
+        .output()?;
+    if output.status.success().not() {
+        return Err(anyhow!(
+            "rustc --version --verbose failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let mut toolchain = parse(&String::from_utf8(output.stdout)?)?;
+    // rustup tells the tools it runs which toolchain it picked
+    toolchain.rustup_toolchain = std::env::var("RUSTUP_TOOLCHAIN").ok();
+    log::info!(target: "cargo_spdx", "detected toolchain: {}", toolchain.version);
+    Ok(toolchain)
+}
+
+// Read the output of `rustc --version --verbose`
+fn parse(output: &str) -> Result<Toolchain> {
+    let field = |name: &str| {
+        output.lines().find_map(|line| {
+            line.strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix(": "))
+                .map(ToOwned::to_owned)
+        })
+    };
+    let missing = |name: &str| anyhow!("rustc didn't report its {}", name);
+    Ok(Toolchain {
+        version: output
+            .lines()
+            .next()
+            .filter(|line| line.starts_with("rustc "))
+            .ok_or_else(|| missing("version"))?
+            .to_owned(),
+        release: field("release").ok_or_else(|| missing("release"))?,
+        // rustc built outside of git reports its commit as `unknown`
+        commit_hash: field("commit-hash").filter(|hash| hash != "unknown"),
+        host: field("host").ok_or_else(|| missing("host"))?,
+        rustup_toolchain: None,
+    })
+}
+
+impl Toolchain {
+    /// Describe the compiler as a package, to be related to what it builds.
+    pub fn package(&self) -> Package {
+        let mut package = Package::build_tool("rustc");
+        package.spdxid = "SPDXRef-Toolchain-rustc".to_string();
+        package.version_info = Some(self.release.clone());
+        package.homepage = Some("https://www.rust-lang.org".to_string());
+        package.license_declared = Some("MIT OR Apache-2.0".to_string());
+        package.source_info = Some(match &self.commit_hash {
+            Some(hash) => format!("{}, built from commit {}.", self.version, hash),
+            None => format!("{}.", self.version),
+        });
+        package.comment = Some(match &self.rustup_toolchain {
+            Some(toolchain) => format!(
+                "Rust compiler for {}, from the {} rustup toolchain.",
+                self.host, toolchain
+            ),
+            None => format!("Rust compiler for {}.", self.host),
+        });
+        package
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn test_parse() {
+        let mut toolchain = parse(
+            "rustc 1.75.0 (82e1608df 2023-12-21)\n\
+             binary: rustc\n\
+             commit-hash: 82e1608dfa6e0b5569232559e3d385fea5a93112\n\
+             commit-date: 2023-12-21\n\
+             host: x86_64-unknown-linux-gnu\n\
+             release: 1.75.0\n\
+             LLVM version: 17.0.6\n",
+        )
+        .unwrap();
+        assert_eq!(toolchain.release, "1.75.0");
+        assert_eq!(toolchain.host, "x86_64-unknown-linux-gnu");
+
+        toolchain.rustup_toolchain = Some("stable-x86_64-unknown-linux-gnu".to_string());
+        let package = toolchain.package();
+        assert_eq!(package.version_info.as_deref(), Some("1.75.0"));
+        assert_eq!(
+            package.source_info.as_deref(),
+            Some("rustc 1.75.0 (82e1608df 2023-12-21), built from commit 82e1608dfa6e0b5569232559e3d385fea5a93112.")
+        );
+        assert_eq!(
+            package.comment.as_deref(),
+            Some("Rust compiler for x86_64-unknown-linux-gnu, from the stable-x86_64-unknown-linux-gnu rustup toolchain.")
+        );
+
+        let toolchain = parse(
+            "rustc 1.75.0\ncommit-hash: unknown\nhost: x86_64-unknown-linux-gnu\nrelease: 1.75.0\n",
+        )
+        .unwrap();
+        assert_eq!(toolchain.commit_hash, None);
+        assert!(parse("error: no such toolchain\n").is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! Implements `cargo spdx tree` subcommand
+
+use crate::cargo::CargoFlags;
+use crate::config::PolicyConfig;
+use crate::license::License;
+use crate::toolchain::rustc_exec;
+use anyhow::{anyhow, Result};
+use cargo_metadata::{DependencyKind, Metadata, MetadataCommand, Node, PackageId};
+use console::{style, StyledObject};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+use std::ops::Not as _;
+use std::process::Command;
+
+/// Whether a package ends up in the SBOMs produced by `cargo spdx build`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    /// Built for the target platform
+    Included,
+    /// Only used by tests, examples and benchmarks
+    Excluded,
+    /// Only used on other platforms
+    Filtered,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Included => write!(f, "included"),
+            Status::Excluded => write!(f, "excluded"),
+            Status::Filtered => write!(f, "filtered"),
+        }
+    }
+}
+
+/// How a package's declared license fares against the license policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compliance {
+    /// The license can be complied with
+    Permitted,
+    /// The license can't be complied with
+    Violation,
+    /// There's no license expression to check
+    Unknown,
+}
+
+/// Prints the resolved dependency tree of the workspace
+///
+/// # Arguments
+/// * `target` - The platform to resolve for, defaulting to the host
+/// * `policy` - The license policy packages are checked against
+/// * `cargo_flags` - Flags to pass to `cargo metadata`, such as `--locked`
+pub fn tree(target: Option<&str>, policy: &PolicyConfig, cargo_flags: CargoFlags) -> Result<()> {
+    let metadata = MetadataCommand::new()
+        .other_options(cargo_flags.args())
+        .exec()?;
+    let target = match target {
+        Some(target) => target.to_string(),
+        None => host_target()?,
+    };
+    let mut options = cargo_flags.args();
+    options.extend(["--filter-platform".to_string(), target]);
+    let filtered = MetadataCommand::new().other_options(options).exec()?;
+
+    let printer = TreePrinter {
+        metadata: &metadata,
+        nodes: nodes(&metadata)?,
+        statuses: statuses(&metadata, &filtered)?,
+        policy,
+    };
+
+    let mut seen = HashSet::new();
+    let mut violations = 0;
+    for member in &metadata.workspace_members {
+        printer.print(member, None, "", "", &mut seen, &mut violations)?;
+        println!();
+    }
+
+    if violations > 0 {
+        println!(
+            "{}",
+            style(format!(
+                "{} included package(s) violate the license policy",
+                violations
+            ))
+            .red()
+            .bold()
+        );
+    }
+
+    Ok(())
+}
+
+// Holds what's needed to print the tree
+struct TreePrinter<'m> {
+    metadata: &'m Metadata,
+    nodes: HashMap<&'m PackageId, &'m Node>,
+    statuses: HashMap<&'m PackageId, Status>,
+    policy: &'m PolicyConfig,
+}
+
+impl<'m> TreePrinter<'m> {
+    // Print a package and, the first time it's seen, its dependencies
+    fn print(
+        &self,
+        id: &'m PackageId,
+        kind: Option<DependencyKind>,
+        prefix: &str,
+        child_prefix: &str,
+        seen: &mut HashSet<&'m PackageId>,
+        violations: &mut usize,
+    ) -> Result<()> {
+        let package = &self.metadata[id];
+        let status = self.statuses.get(id).copied().unwrap_or(Status::Filtered);
+        let first_visit = seen.insert(id);
+
+        let license = package.license.as_deref();
+        let compliance = match license.map(License::parse_manifest) {
+            Some(Ok(license)) if self.policy.permits(&license) => Compliance::Permitted,
+            Some(Ok(_)) => Compliance::Violation,
+            Some(Err(_)) | None => Compliance::Unknown,
+        };
+        if first_visit && status == Status::Included && compliance == Compliance::Violation {
+            *violations += 1;
+        }
+
+        let license = license
+            .map(ToOwned::to_owned)
+            .or_else(|| package.license_file.as_ref().map(|f| format!("see {}", f)))
+            .unwrap_or_else(|| "no license".to_string());
+        let license = match compliance {
+            Compliance::Permitted => style(license).green(),
+            Compliance::Violation => style(license).red().bold(),
+            Compliance::Unknown => style(license).yellow(),
+        };
+
+        let kind = match kind {
+            Some(DependencyKind::Development) => " (dev)",
+            Some(DependencyKind::Build) => " (build)",
+            _ => "",
+        };
+        println!(
+            "{}{} v{}{} ({}) {}{}",
+            prefix,
+            package.name,
+            package.version,
+            kind,
+            license,
+            dim_unless_included(format!("[{}]", status), status),
+            if first_visit { "" } else { " (*)" }
+        );
+
+        if first_visit.not() {
+            return Ok(());
+        }
+
+        let node = self
+            .nodes
+            .get(id)
+            .ok_or_else(|| anyhow!("{} is missing from the dependency graph", id))?;
+        let count = node.deps.len();
+        for (i, dep) in node.deps.iter().enumerate() {
+            let (branch, indent) = if i + 1 == count {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            // Dependencies can be used in several ways at once, so show the one that
+            // matters most for what gets built.
+            let kind = dep
+                .dep_kinds
+                .iter()
+                .map(|k| k.kind)
+                .min_by_key(|kind| match kind {
+                    DependencyKind::Normal => 0,
+                    DependencyKind::Build => 1,
+                    _ => 2,
+                });
+            self.print(
+                &dep.pkg,
+                kind,
+                &format!("{}{}", child_prefix, branch),
+                &format!("{}{}", child_prefix, indent),
+                seen,
+                violations,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+// Dim the text of packages that won't be in the SBOM
+fn dim_unless_included(text: String, status: Status) -> StyledObject<String> {
+    match status {
+        Status::Included => style(text),
+        Status::Excluded | Status::Filtered => style(text).dim(),
+    }
+}
+
+// Index the nodes of the resolved dependency graph by package
+fn nodes(metadata: &Metadata) -> Result<HashMap<&PackageId, &Node>> {
+    let resolve = metadata
+        .resolve
+        .as_ref()
+        .ok_or_else(|| anyhow!("no dependency graph found"))?;
+    Ok(resolve.nodes.iter().map(|node| (&node.id, node)).collect())
+}
+
+// Work out whether each package would be built for the target platform
+//
+// `filtered` only has the dependencies used on the target platform, so anything
+// reachable from the workspace through its non-dev dependencies gets built.
+fn statuses<'m>(
+    metadata: &'m Metadata,
+    filtered: &Metadata,
+) -> Result<HashMap<&'m PackageId, Status>> {
+    let filtered_nodes = nodes(filtered)?;
+
+    let mut included = HashSet::new();
+    let mut queue: Vec<&PackageId> = filtered.workspace_members.iter().collect();
+    while let Some(id) = queue.pop() {
+        if included.insert(id.clone()) {
+            if let Some(node) = filtered_nodes.get(id) {
+                queue.extend(
+                    node.deps
+                        .iter()
+                        .filter(|dep| {
+                            dep.dep_kinds
+                                .iter()
+                                .any(|k| k.kind != DependencyKind::Development)
+                        })
+                        .map(|dep| &dep.pkg),
+                );
+            }
+        }
+    }
+
+    Ok(metadata
+        .packages
+        .iter()
+        .map(|package| {
+            let status = if included.contains(&package.id) {
+                Status::Included
+            } else if filtered_nodes.contains_key(&package.id) {
+                Status::Excluded
+            } else {
+                Status::Filtered
+            };
+            (&package.id, status)
+        })
+        .collect())
+}
+
+// Ask rustc which platform it builds for by default
+fn host_target() -> Result<String> {
+    let output = Command::new(rustc_exec()).arg("-vV").output()?;
+    String::from_utf8(output.stdout)?
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(ToOwned::to_owned)
+        .ok_or_else(|| anyhow!("couldn't determine the host platform, pass --target instead"))
+}